@@ -0,0 +1,186 @@
+#![no_std]
+#![no_main]
+
+use core::{cell::RefCell, fmt::Write};
+
+use defmt::{error, info, warn};
+use defmt_rtt as _;
+use embassy_embedded_hal::shared_bus::blocking::spi::SpiDeviceWithConfig;
+use embassy_executor::Spawner;
+use embassy_stm32::{
+    Config,
+    gpio::{Level, Output, Speed},
+    rcc::{Pll, PllDiv, PllMul, PllPreDiv, PllSource, Sysclk, VoltageScale, mux},
+    spi::{self, Spi},
+    time::Hertz,
+};
+use embassy_sync::blocking_mutex::{Mutex, raw::NoopRawMutex};
+use embassy_time::{Delay, Duration, Ticker};
+use embedded_graphics::{
+    draw_target::DrawTarget,
+    pixelcolor::Rgb565,
+    prelude::{Point, RgbColor, Size},
+};
+use mipidsi::{
+    interface::SpiInterface,
+    models::ST7735s,
+    options::{Orientation, Rotation},
+};
+use panic_probe as _;
+
+use lab05::{
+    display::console::{self, Console},
+    mpu6500::{
+        AccelDlpf, AccelScale, Config as Mpu6500Config, GyroDlpf, GyroScale,
+        device_blocking::Mpu6500,
+    },
+};
+
+/// The acceleration above which the board is considered shaken
+const SHAKE_THRESHOLD_G: f32 = 1.5;
+
+#[embassy_executor::main]
+async fn main(_spawner: Spawner) {
+    // Increase the frequency of the microcontroller to make the
+    // display transfer faster, see `ex5.rs`.
+    let mut config = Config::default();
+    config.rcc.hsi = true;
+    config.rcc.pll1 = Some(Pll {
+        source: PllSource::HSI, // 16 MHz
+        prediv: PllPreDiv::DIV1,
+        mul: PllMul::MUL10,
+        divp: None,
+        divq: None,
+        divr: Some(PllDiv::DIV1), // 160 MHz
+    });
+    config.rcc.sys = Sysclk::PLL1_R;
+    config.rcc.voltage_range = VoltageScale::RANGE1;
+    config.rcc.mux.iclksel = mux::Iclksel::HSI48; // USB uses ICLK
+
+    let peripherals = embassy_stm32::init(config);
+    info!("Device started");
+
+    // screen reset is D2 (PC8)
+    let screen_rst = Output::new(peripherals.PC8, Level::Low, Speed::Low);
+    // screen dc is D3 (PB3)
+    let screen_dc = Output::new(peripherals.PB3, Level::Low, Speed::Low);
+
+    // SPI1 is exposed by the Arduino header using pins:
+    // - MISO - D12 (PA6)
+    // - MOSI - D11 (PA7)
+    // - CLK - D13 (PA5)
+    let spi = Spi::new_blocking(
+        peripherals.SPI1,
+        peripherals.PA5,
+        peripherals.PA7,
+        peripherals.PA6,
+        spi::Config::default(),
+    );
+    let spi_bus_mutex: Mutex<NoopRawMutex, _> = Mutex::new(RefCell::new(spi));
+
+    // The display uses D4 (PB5) as CS
+    let mut screen_spi_config = spi::Config::default();
+    screen_spi_config.frequency = Hertz(3_000_000);
+    let screen_cs = Output::new(peripherals.PB5, Level::High, Speed::Low);
+    let display_spi = SpiDeviceWithConfig::new(&spi_bus_mutex, screen_cs, screen_spi_config);
+
+    let mut screen_buffer = [0; 4096];
+    let di = SpiInterface::new(display_spi, screen_dc, &mut screen_buffer);
+    let mut screen = mipidsi::Builder::new(ST7735s, di)
+        .reset_pin(screen_rst)
+        .orientation(Orientation::new().rotate(Rotation::Deg180))
+        .init(&mut Delay)
+        .unwrap();
+    screen.clear(Rgb565::BLACK).unwrap();
+
+    // The console fills the screen, 21 x 16 characters with the
+    // default font, and keeps 32 lines
+    let mut console = Console::<32, 21>::new(
+        Point::zero(),
+        Size::new(128, 160),
+        console::Config::default(),
+    );
+
+    // The important messages are sent to `defmt` and mirrored on the screen
+    console.log(console::Level::Info, "Device started");
+
+    // The MPU6500 sensor uses D7 (PA8) as CS
+    let mut mpu6500_spi_config = spi::Config::default();
+    mpu6500_spi_config.frequency = Hertz(1_000_000);
+    let mpu6500_cs_pin = Output::new(peripherals.PA8, Level::High, Speed::Low);
+    let mut mpu6500_spi_device =
+        SpiDeviceWithConfig::new(&spi_bus_mutex, mpu6500_cs_pin, mpu6500_spi_config);
+    let mut mpu6500 = Mpu6500::new(&mut mpu6500_spi_device);
+
+    if let Err(error) = mpu6500.init() {
+        error!("MPU6500 sensor is not available: {}", error);
+        console.log(console::Level::Error, "MPU6500 sensor is not available");
+        console.draw(&mut screen).unwrap();
+        return;
+    }
+
+    let mpu6500_config = Mpu6500Config::default()
+        .accel_scale(AccelScale::G2)
+        .gyro_scale(GyroScale::Gs1000)
+        .gyro_dlpf(GyroDlpf::Hz41)
+        .accel_dlpf(AccelDlpf::Hz45)
+        .sample_rate_divider(9);
+    mpu6500
+        .configure(mpu6500_config)
+        .expect("Failed to configure the sensor");
+    info!("Sample rate {} Hz", mpu6500_config.sample_rate());
+    core::writeln!(
+        console.writer(console::Level::Info),
+        "Sample rate {} Hz",
+        mpu6500_config.sample_rate()
+    )
+    .unwrap();
+
+    // Read the sensor every 100 ms, log the readings every second
+    let mut ticker = Ticker::every(Duration::from_millis(100));
+    let mut count = 0;
+    loop {
+        ticker.next().await;
+
+        let acceleration = match mpu6500.read_acceleration() {
+            Ok(acceleration) => acceleration,
+            Err(error) => {
+                error!("Failed to read the sensor: {}", error);
+                console.log(console::Level::Error, "Failed to read the sensor");
+                console.draw(&mut screen).unwrap();
+                continue;
+            }
+        };
+
+        let magnitude = libm::sqrtf(
+            acceleration.x * acceleration.x
+                + acceleration.y * acceleration.y
+                + acceleration.z * acceleration.z,
+        );
+        if magnitude > SHAKE_THRESHOLD_G {
+            warn!("Shaken, {} g", magnitude);
+            core::writeln!(
+                console.writer(console::Level::Warn),
+                "Shaken, {:.2} g",
+                magnitude
+            )
+            .unwrap();
+        }
+
+        count += 1;
+        if count == 10 {
+            count = 0;
+            core::writeln!(
+                console.writer(console::Level::Debug),
+                "A {:.2} {:.2} {:.2}",
+                acceleration.x,
+                acceleration.y,
+                acceleration.z
+            )
+            .unwrap();
+        }
+
+        // Only the rows that changed are sent to the display
+        console.draw(&mut screen).unwrap();
+    }
+}
@@ -0,0 +1,202 @@
+#![no_std]
+#![no_main]
+
+use defmt::{error, info, warn};
+use defmt_rtt as _;
+use embassy_embedded_hal::shared_bus::asynch::spi::SpiDeviceWithConfig;
+use embassy_executor::Spawner;
+use embassy_stm32::{
+    Config,
+    exti::ExtiInput,
+    gpio::{Level, Output, Pull, Speed},
+    mode::Async,
+    rcc::{Pll, PllDiv, PllMul, PllPreDiv, PllSource, Sysclk, VoltageScale, mux},
+    spi::{self, Spi},
+    time::Hertz,
+};
+use embassy_sync::{blocking_mutex::raw::ThreadModeRawMutex, channel::Channel, mutex::Mutex};
+use embassy_time::{Delay, Duration, Instant};
+use embedded_graphics::{
+    pixelcolor::Rgb565,
+    prelude::{Point, RgbColor},
+};
+use mipidsi::options::{Orientation, Rotation};
+use panic_probe as _;
+
+use lab05::{
+    display::{
+        plot::{self, Plot, Series},
+        st7735s::{self, FrameBuffer, SpiInterface, St7735s},
+    },
+    mpu6500::{
+        AccelDlpf, AccelScale, Acceleration, Config as Mpu6500Config, GyroDlpf, InterruptPin,
+        device::Mpu6500,
+    },
+};
+
+/// SPI1, shared by the display and the sensor
+type SpiBus = Mutex<ThreadModeRawMutex, Spi<'static, Async>>;
+
+/// An SPI device on SPI1
+type SpiDevice =
+    SpiDeviceWithConfig<'static, ThreadModeRawMutex, Spi<'static, Async>, Output<'static>>;
+
+/// The width of the ST7735s memory, with the `mipidsi`
+/// defaults used by the other examples
+const WIDTH: usize = st7735s::CONTROLLER_SIZE.0 as usize;
+
+/// The height of the ST7735s memory
+const HEIGHT: usize = st7735s::CONTROLLER_SIZE.1 as usize;
+
+// The IMU task sends the acceleration samples to the display task
+static SAMPLES: Channel<ThreadModeRawMutex, Acceleration, 16> = Channel::new();
+
+#[embassy_executor::task]
+async fn imu_task(mut spi: SpiDevice, mut int: ExtiInput<'static>) {
+    let mut mpu6500 = Mpu6500::new(&mut spi);
+    if let Err(error) = mpu6500.init().await {
+        error!("MPU6500 sensor is not available: {}", error);
+        return;
+    }
+
+    // Sample at 100 Hz (1 kHz / (1 + 9)), the gyro filter sets
+    // the 1 kHz rate, the sensor ignores the divider at 8 kHz
+    let mpu6500_config = Mpu6500Config::default()
+        .accel_scale(AccelScale::G2)
+        .accel_dlpf(AccelDlpf::Hz21)
+        .gyro_dlpf(GyroDlpf::Hz41)
+        .sample_rate_divider(9);
+    mpu6500
+        .configure(mpu6500_config)
+        .await
+        .expect("Failed to configure the sensor");
+    mpu6500
+        .enable_data_ready(InterruptPin::default())
+        .await
+        .expect("Failed to enable the data ready interrupt");
+
+    let mut last = Instant::now();
+    loop {
+        // The reads wait for the bus only while the display
+        // sends one transfer, not the whole frame
+        mpu6500.wait_for_data(&mut int).await.unwrap();
+        let acceleration = mpu6500.read_acceleration().await.unwrap();
+
+        let now = Instant::now();
+        if now - last > Duration::from_millis(15) {
+            warn!(
+                "Late sample, {} ms since the last one",
+                (now - last).as_millis()
+            );
+        }
+        last = now;
+
+        if SAMPLES.try_send(acceleration).is_err() {
+            warn!("The display is too slow, sample dropped");
+        }
+    }
+}
+
+#[embassy_executor::task]
+async fn display_task(spi: SpiDevice, dc: Output<'static>, rst: Output<'static>) {
+    let mut display = St7735s::new(
+        SpiInterface::new(spi, dc),
+        rst,
+        st7735s::Config::default().orientation(Orientation::new().rotate(Rotation::Deg180)),
+    );
+    display.init(&mut Delay).await.unwrap();
+
+    let mut frame = FrameBuffer::<WIDTH, HEIGHT>::new(Rgb565::BLACK);
+    let mut plot = Plot::<3, 100>::new(
+        Point::new(0, 20),
+        120,
+        [
+            Series::new("X", Rgb565::RED),
+            Series::new("Y", Rgb565::GREEN),
+            Series::new("Z", Rgb565::CYAN),
+        ],
+        plot::Config::default(),
+    );
+
+    loop {
+        // Add the samples received while the last frame was sent
+        // and send a single frame with all of them
+        plot.push(acceleration_values(SAMPLES.receive().await));
+        while let Ok(acceleration) = SAMPLES.try_receive() {
+            plot.push(acceleration_values(acceleration));
+        }
+        plot.draw(&mut frame).unwrap();
+        display.flush(&mut frame).await.unwrap();
+    }
+}
+
+fn acceleration_values(acceleration: Acceleration) -> [f32; 3] {
+    [acceleration.x, acceleration.y, acceleration.z]
+}
+
+#[embassy_executor::main]
+async fn main(spawner: Spawner) {
+    // Increase the frequency of the microcontroller to make the
+    // display transfer faster, see `ex5.rs`.
+    let mut config = Config::default();
+    config.rcc.hsi = true;
+    config.rcc.pll1 = Some(Pll {
+        source: PllSource::HSI, // 16 MHz
+        prediv: PllPreDiv::DIV1,
+        mul: PllMul::MUL10,
+        divp: None,
+        divq: None,
+        divr: Some(PllDiv::DIV1), // 160 MHz
+    });
+    config.rcc.sys = Sysclk::PLL1_R;
+    config.rcc.voltage_range = VoltageScale::RANGE1;
+    config.rcc.mux.iclksel = mux::Iclksel::HSI48; // USB uses ICLK
+
+    let peripherals = embassy_stm32::init(config);
+    info!("Device started");
+
+    // screen reset is D2 (PC8)
+    let screen_rst = Output::new(peripherals.PC8, Level::Low, Speed::Low);
+    // screen dc is D3 (PB3)
+    let screen_dc = Output::new(peripherals.PB3, Level::Low, Speed::Low);
+
+    // SPI1 is exposed by the Arduino header using pins:
+    // - MISO - D12 (PA6)
+    // - MOSI - D11 (PA7)
+    // - CLK - D13 (PA5)
+    //
+    // We use the asynchronous API and we need two free
+    // DMA channels. We use GPDMA1_CH0 and GPDMA1_CH1
+    let spi = Spi::new(
+        peripherals.SPI1,
+        peripherals.PA5,
+        peripherals.PA7,
+        peripherals.PA6,
+        peripherals.GPDMA1_CH0,
+        peripherals.GPDMA1_CH1,
+        spi::Config::default(),
+    );
+    // The tasks use the bus for the whole program, the
+    // Mutex has to live forever (`'static`)
+    let spi_bus: &'static SpiBus = cortex_m::singleton!(: SpiBus = Mutex::new(spi)).unwrap();
+
+    // The display uses D4 (PB5) as CS
+    let mut screen_spi_config = spi::Config::default();
+    screen_spi_config.frequency = Hertz(3_000_000);
+    let screen_cs = Output::new(peripherals.PB5, Level::High, Speed::Low);
+    let display_spi = SpiDeviceWithConfig::new(spi_bus, screen_cs, screen_spi_config);
+
+    // The MPU6500 sensor uses D7 (PA8) as CS
+    let mut mpu6500_spi_config = spi::Config::default();
+    mpu6500_spi_config.frequency = Hertz(1_000_000);
+    let mpu6500_cs_pin = Output::new(peripherals.PA8, Level::High, Speed::Low);
+    let mpu6500_spi = SpiDeviceWithConfig::new(spi_bus, mpu6500_cs_pin, mpu6500_spi_config);
+
+    // The INT pin of the MPU6500 sensor is connected to D6 (PB10).
+    let mpu6500_int = ExtiInput::new(peripherals.PB10, peripherals.EXTI10, Pull::None);
+
+    spawner.spawn(imu_task(mpu6500_spi, mpu6500_int)).unwrap();
+    spawner
+        .spawn(display_task(display_spi, screen_dc, screen_rst))
+        .unwrap();
+}
@@ -41,24 +41,24 @@ const ACCEL_SCALE_2G: u8 = 0b00;
 /// The gyro scale value for ±1000 deg/s
 const GYRO_SCALE_1000: u8 = 0b10;
 
-/// Converts the `u16` acceleration value to m/s^2 using
+/// Converts the `i16` acceleration value to g using
 /// the configured acceleration scale.
 fn convert_to_g(value: i16, scale: AccelScale) -> f32 {
-    // i16::MAX ...... scale.value() (2, 4, 8 or 16 x g)
-    // value ......... acceleration
+    // scale.sensitivity() ...... 1 g (16384, 8192, 4096 or 2048 LSB)
+    // value .................... acceleration
     //
-    // acceleration = (value x scale.value()) / i16::MAX
-    (value as f32 * scale.value()) / i16::MAX as f32
+    // acceleration = value / scale.sensitivity()
+    value as f32 / scale.sensitivity()
 }
 
-/// Converts the `u16` acceleration value to deg/s using
+/// Converts the `i16` gyro value to deg/s using
 /// the configured gyro scale.
 fn convert_to_deg_s(value: i16, scale: GyroScale) -> f32 {
-    // i16::MAX ...... scale.value() (250, 500, 1000 or 2000 deg/s)
-    // value ......... gyro
+    // scale.sensitivity() ...... 1 deg/s (131, 65.5, 32.8 or 16.4 LSB)
+    // value .................... gyro
     //
-    // acceleration = (value x scale.value()) / i16::MAX
-    (value as f32 * scale.value()) / i16::MAX as f32
+    // gyro = value / scale.sensitivity()
+    value as f32 / scale.sensitivity()
 }
 
 #[embassy_executor::main]
@@ -280,7 +280,7 @@ async fn main(_spawner: Spawner) {
         // End the SPI transmission by setting the CS line HIGH.
         mpu6500_cs_pin.set_high();
 
-        // Convert the raw values into `i16` m/s^2
+        // Convert the raw `i16` values into g
         //
         // We know that we have set the acceleration scale to 2G
         info!(
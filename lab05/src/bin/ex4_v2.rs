@@ -39,24 +39,24 @@ const GYRO_XOUT_H: u8 = 0x43;
 /// WHO_AM_I Register Value for the MPU6500 sensor
 const WHO_AM_I_VALUE: u8 = 0x70;
 
-/// Converts the `u16` acceleration value to m/s^2 using
+/// Converts the `i16` acceleration value to g using
 /// the configured acceleration scale.
 fn convert_to_g(value: i16, scale: AccelScale) -> f32 {
-    // i16::MAX ...... scale.value() (2, 4, 8 or 16 x g)
-    // value ......... acceleration
+    // scale.sensitivity() ...... 1 g (16384, 8192, 4096 or 2048 LSB)
+    // value .................... acceleration
     //
-    // acceleration = (value x scale.value()) / i16::MAX
-    (value as f32 * scale.value()) / i16::MAX as f32
+    // acceleration = value / scale.sensitivity()
+    value as f32 / scale.sensitivity()
 }
 
-/// Converts the `u16` acceleration value to deg/s using
+/// Converts the `i16` gyro value to deg/s using
 /// the configured gyro scale.
 fn convert_to_deg_s(value: i16, scale: GyroScale) -> f32 {
-    // i16::MAX ...... scale.value() (250, 500, 1000 or 2000 deg/s)
-    // value ......... gyro
+    // scale.sensitivity() ...... 1 deg/s (131, 65.5, 32.8 or 16.4 LSB)
+    // value .................... gyro
     //
-    // acceleration = (value x scale.value()) / i16::MAX
-    (value as f32 * scale.value()) / i16::MAX as f32
+    // gyro = value / scale.sensitivity()
+    value as f32 / scale.sensitivity()
 }
 
 /// Set the gyro scale
@@ -246,7 +246,7 @@ async fn read_acceleration(
     match res {
         // The transmission was successful, extract and return the acceleration
         //
-        // We have to convert the `i16` value to m/s^2. We know that we have set the
+        // We have to convert the `i16` value to g. We know that we have set the
         // acceleration scale to AccelScale::G2.
         Ok(()) => Ok(Acceleration {
             x: convert_to_g(i16::from_be_bytes([rx[1], rx[2]]), AccelScale::G2),
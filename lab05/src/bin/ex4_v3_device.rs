@@ -1,7 +1,7 @@
 #![no_std]
 #![no_main]
 
-use defmt::{info, warn};
+use defmt::{error, info};
 use defmt_rtt as _;
 use embassy_embedded_hal::shared_bus::asynch::spi::SpiDevice;
 use embassy_executor::Spawner;
@@ -58,8 +58,13 @@ async fn main(_spawner: Spawner) {
     // Create an instance of the MPU6500 driver
     let mut mpu6500 = Mpu6500::new(&mut spi_device);
 
-    // Verify that the MPU 6500 sensor is connected.
-    if mpu6500.is_connected().await {
+    // Verify that the MPU 6500 sensor is connected and initialise the driver.
+    //
+    // If the sensor is not available, `init` tells us why: a bus error,
+    // an unexpected WHO_AM_I value (another or no sensor) and so on.
+    if let Err(error) = mpu6500.init().await {
+        error!("MPU6500 sensor is not available: {}", error);
+    } else {
         // Set the acceleration scale
         mpu6500
             .set_accel_scale(AccelScale::G2)
@@ -96,7 +101,5 @@ async fn main(_spawner: Spawner) {
 
             Timer::after_millis(100).await;
         }
-    } else {
-        warn!("MPU6500 sensor is not connected.");
     }
 }
@@ -3,19 +3,20 @@
 
 use core::{cell::RefCell, fmt::Write};
 
-use defmt::{debug, info, warn};
+use defmt::{debug, error, info};
 use defmt_rtt as _;
 use embassy_embedded_hal::shared_bus::blocking::spi::SpiDeviceWithConfig;
 use embassy_executor::Spawner;
 use embassy_stm32::{
     Config,
-    gpio::{Level, Output, Speed},
+    exti::ExtiInput,
+    gpio::{Level, Output, Pull, Speed},
     rcc::{Pll, PllDiv, PllMul, PllPreDiv, PllSource, Sysclk, VoltageScale, mux},
     spi::{self, Spi},
     time::Hertz,
 };
 use embassy_sync::blocking_mutex::{Mutex, raw::NoopRawMutex};
-use embassy_time::{Delay, Timer};
+use embassy_time::Delay;
 use embedded_graphics::{
     Drawable,
     draw_target::DrawTarget,
@@ -32,7 +33,10 @@ use mipidsi::{
 use panic_probe as _;
 
 // We use the MPU6500 driver that requires a blocking SPI device
-use lab05::mpu6500::{AccelScale, GyroScale, device_blocking::Mpu6500};
+use lab05::mpu6500::{
+    AccelDlpf, AccelScale, Config as Mpu6500Config, GyroDlpf, GyroScale, InterruptPin,
+    device_blocking::Mpu6500,
+};
 
 #[embassy_executor::main]
 async fn main(_spawner: Spawner) {
@@ -125,6 +129,11 @@ async fn main(_spawner: Spawner) {
     // Create an instance of the MPU6500 driver
     let mut mpu6500 = Mpu6500::new(&mut mpu6500_spi_device);
 
+    // The INT pin of the MPU6500 sensor is connected to D6 (PB10).
+    //
+    // The sensor drives the pin (push pull), so no pull resistor is needed.
+    let mut mpu6500_int = ExtiInput::new(peripherals.PB10, peripherals.EXTI10, Pull::None);
+
     screen.clear(Rgb565::BLACK).unwrap();
     let mut style = MonoTextStyle::new(&FONT_6X10, Rgb565::WHITE);
 
@@ -133,15 +142,42 @@ async fn main(_spawner: Spawner) {
     // on top of the old text, making it unreadable.
     style.set_background_color(Some(Rgb565::BLACK));
 
-    if mpu6500.is_connected() {
+    // Verify that the MPU 6500 sensor is connected and initialise the driver.
+    //
+    // If the sensor is not available, `init` tells us why: a bus error,
+    // an unexpected WHO_AM_I value (another or no sensor) and so on.
+    if let Err(error) = mpu6500.init() {
+        error!("MPU6500 sensor is not available: {}", error);
+    } else {
+        // The power on configuration samples the sensor at 8 kHz without
+        // filtering, which is too noisy for displaying the values.
+        // We enable the low pass filters and sample at 100 Hz
+        // (1 kHz / (1 + 9)).
+        let mpu6500_config = Mpu6500Config::default()
+            .accel_scale(AccelScale::G2)
+            .gyro_scale(GyroScale::Gs1000)
+            .gyro_dlpf(GyroDlpf::Hz41)
+            .accel_dlpf(AccelDlpf::Hz45)
+            .sample_rate_divider(9);
         mpu6500
-            .set_accel_scale(AccelScale::G2)
-            .expect("Failed to set the acceleration scale");
+            .configure(mpu6500_config)
+            .expect("Failed to configure the sensor");
+
+        // Instead of reading the sensor every 100 ms, we wait for the
+        // sensor to tell us that it has a new sample. This way we
+        // never read the same sample twice.
         mpu6500
-            .set_gyro_scale(GyroScale::Gs1000)
-            .expect("Failed to set the gyro scale");
+            .enable_data_ready(InterruptPin::default())
+            .expect("Failed to enable the data ready interrupt");
 
         loop {
+            // Wait for the INT pin, the task sleeps until the sample is available.
+            //
+            // Drawing the values takes longer than 10 ms, so some samples
+            // are skipped. The interrupt is latched, so the next sample
+            // is never missed.
+            mpu6500.wait_for_data(&mut mpu6500_int).await.unwrap();
+
             let acceleration = mpu6500.read_acceleration().unwrap();
             let gyro = mpu6500.read_gyro().unwrap();
 
@@ -179,9 +215,6 @@ async fn main(_spawner: Spawner) {
                 .unwrap();
 
             info!("Gyro: X {}, Y {}, Z {}", gyro.x, gyro.y, gyro.z);
-            Timer::after_millis(100).await;
         }
-    } else {
-        warn!("MPU6500 sensor is not connected.");
     }
 }
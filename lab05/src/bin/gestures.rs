@@ -0,0 +1,92 @@
+#![no_std]
+#![no_main]
+
+use defmt::{error, info};
+use defmt_rtt as _;
+use embassy_embedded_hal::shared_bus::asynch::spi::SpiDevice;
+use embassy_executor::Spawner;
+use embassy_futures::join::join;
+use embassy_stm32::{
+    gpio::{Level, Output, Speed},
+    spi::{self, Spi},
+    time::Hertz,
+};
+use embassy_sync::{blocking_mutex::raw::ThreadModeRawMutex, channel::Channel, mutex::Mutex};
+use embassy_time::Duration;
+use panic_probe as _;
+
+use lab05::{
+    gesture::{Config, Detector, Event, Position, run},
+    mpu6500::{AccelScale, device::Mpu6500},
+};
+
+// The detector sends the gestures through this channel
+static EVENTS: Channel<ThreadModeRawMutex, Event, 8> = Channel::new();
+
+#[embassy_executor::main]
+async fn main(_spawner: Spawner) {
+    let peripherals = embassy_stm32::init(Default::default());
+    info!("Device started");
+
+    // Create the SPI bus configuration
+    let mut config = spi::Config::default();
+    // Set the SPI frequency to 1 MHz
+    config.frequency = Hertz(1_000_000);
+
+    // SPI1 is exposed by the Arduino header using pins:
+    // - MISO - D12 (PA6)
+    // - MOSI - D11 (PA7)
+    // - CLK - D13 (PA5)
+    let spi = Spi::new(
+        peripherals.SPI1,
+        peripherals.PA5,
+        peripherals.PA7,
+        peripherals.PA6,
+        peripherals.GPDMA1_CH0,
+        peripherals.GPDMA1_CH1,
+        config,
+    );
+
+    // We use the D7 (PA8) pin as CS
+    let mpu6500_cs_pin = Output::new(peripherals.PA8, Level::High, Speed::Low);
+
+    let spi_mutex = Mutex::<ThreadModeRawMutex, _>::new(spi);
+    let mut spi_device = SpiDevice::new(&spi_mutex, mpu6500_cs_pin);
+    let mut mpu6500 = Mpu6500::new(&mut spi_device);
+
+    if let Err(error) = mpu6500.init().await {
+        error!("MPU6500 sensor is not available: {}", error);
+        return;
+    }
+    // Taps and shakes can go above 2 g
+    mpu6500
+        .set_accel_scale(AccelScale::G8)
+        .await
+        .expect("Failed to set the acceleration scale");
+
+    let mut detector = Detector::new(Config::default());
+
+    // The detector reads the sensor, while the second future
+    // reacts to the gestures
+    let (result, _) = join(
+        // Taps are very short, so we read the sensor at 200 Hz
+        run(
+            &mut mpu6500,
+            &mut detector,
+            Duration::from_millis(5),
+            EVENTS.sender(),
+        ),
+        async {
+            loop {
+                match EVENTS.receive().await {
+                    Event::Shake => info!("Shake to reset"),
+                    Event::Position(Position::FaceDown) => info!("Flip to mute"),
+                    event => info!("Gesture: {}", event),
+                }
+            }
+        },
+    )
+    .await;
+    let Err(error) = result;
+    error!("Failed to read the sensor: {}", error);
+}
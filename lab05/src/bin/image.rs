@@ -0,0 +1,129 @@
+#![no_std]
+#![no_main]
+
+use core::cell::RefCell;
+
+use defmt::{error, info};
+use defmt_rtt as _;
+use embassy_embedded_hal::shared_bus::blocking::spi::SpiDeviceWithConfig;
+use embassy_executor::Spawner;
+use embassy_stm32::{
+    Config,
+    gpio::{Level, Output, Speed},
+    rcc::{Pll, PllDiv, PllMul, PllPreDiv, PllSource, Sysclk, VoltageScale, mux},
+    spi::{self, Spi},
+    time::Hertz,
+};
+use embassy_sync::blocking_mutex::{Mutex, raw::NoopRawMutex};
+use embassy_time::{Delay, Duration, Ticker};
+use embedded_graphics::{
+    Drawable,
+    draw_target::DrawTarget,
+    image::Image,
+    pixelcolor::Rgb565,
+    prelude::{Point, RgbColor, Size},
+};
+use mipidsi::{
+    interface::SpiInterface,
+    models::ST7735s,
+    options::{Orientation, Rotation},
+};
+use panic_probe as _;
+
+use lab05::display::image::{Animation, Qoi, SpriteSheet};
+
+/// A 48 x 48 logo, without transparent pixels
+static LOGO: &[u8] = include_bytes!("../../assets/logo.qoi");
+
+/// The 8 frames of a spinner, 16 x 16 pixels each,
+/// with a transparent background
+static SPINNER: &[u8] = include_bytes!("../../assets/spinner.qoi");
+
+#[embassy_executor::main]
+async fn main(_spawner: Spawner) {
+    // Increase the frequency of the microcontroller to make the
+    // display transfer faster, see `ex5.rs`.
+    let mut config = Config::default();
+    config.rcc.hsi = true;
+    config.rcc.pll1 = Some(Pll {
+        source: PllSource::HSI, // 16 MHz
+        prediv: PllPreDiv::DIV1,
+        mul: PllMul::MUL10,
+        divp: None,
+        divq: None,
+        divr: Some(PllDiv::DIV1), // 160 MHz
+    });
+    config.rcc.sys = Sysclk::PLL1_R;
+    config.rcc.voltage_range = VoltageScale::RANGE1;
+    config.rcc.mux.iclksel = mux::Iclksel::HSI48; // USB uses ICLK
+
+    let peripherals = embassy_stm32::init(config);
+    info!("Device started");
+
+    // screen reset is D2 (PC8)
+    let screen_rst = Output::new(peripherals.PC8, Level::Low, Speed::Low);
+    // screen dc is D3 (PB3)
+    let screen_dc = Output::new(peripherals.PB3, Level::Low, Speed::Low);
+
+    // SPI1 is exposed by the Arduino header using pins:
+    // - MISO - D12 (PA6)
+    // - MOSI - D11 (PA7)
+    // - CLK - D13 (PA5)
+    let spi = Spi::new_blocking(
+        peripherals.SPI1,
+        peripherals.PA5,
+        peripherals.PA7,
+        peripherals.PA6,
+        spi::Config::default(),
+    );
+    let spi_bus_mutex: Mutex<NoopRawMutex, _> = Mutex::new(RefCell::new(spi));
+
+    // The display uses D4 (PB5) as CS
+    let mut screen_spi_config = spi::Config::default();
+    screen_spi_config.frequency = Hertz(3_000_000);
+    let screen_cs = Output::new(peripherals.PB5, Level::High, Speed::Low);
+    let display_spi = SpiDeviceWithConfig::new(&spi_bus_mutex, screen_cs, screen_spi_config);
+
+    let mut screen_buffer = [0; 4096];
+    let di = SpiInterface::new(display_spi, screen_dc, &mut screen_buffer);
+    let mut screen = mipidsi::Builder::new(ST7735s, di)
+        .reset_pin(screen_rst)
+        .orientation(Orientation::new().rotate(Rotation::Deg180))
+        .init(&mut Delay)
+        .unwrap();
+    screen.clear(Rgb565::BLACK).unwrap();
+
+    // The images are checked once, a corrupted image is reported
+    // here and not while drawing
+    let logo = match Qoi::new(LOGO) {
+        Ok(logo) => logo,
+        Err(error) => {
+            error!("The logo is not a valid image: {}", error);
+            return;
+        }
+    };
+    let spinner =
+        match Qoi::new(SPINNER).and_then(|image| SpriteSheet::new(image, Size::new(16, 16))) {
+            Ok(spinner) => spinner,
+            Err(error) => {
+                error!("The spinner is not a valid sprite sheet: {}", error);
+                return;
+            }
+        };
+
+    // The logo is decoded while it is sent to the display,
+    // it is never stored in RAM
+    Image::new(&logo, Point::new(40, 40))
+        .draw(&mut screen)
+        .unwrap();
+
+    // The transparent pixels of the spinner are drawn black,
+    // which erases the previous frame
+    let mut animation = Animation::new(spinner, Point::new(56, 110), Some(Rgb565::BLACK));
+    let mut ticker = Ticker::every(Duration::from_millis(100));
+    loop {
+        animation.draw(&mut screen).unwrap();
+        ticker.next().await;
+        animation.next_frame();
+    }
+}
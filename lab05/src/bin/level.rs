@@ -0,0 +1,231 @@
+#![no_std]
+#![no_main]
+
+use core::{cell::RefCell, fmt::Write};
+
+use async_debounce::Debouncer;
+use defmt::{error, info};
+use defmt_rtt as _;
+use embassy_embedded_hal::shared_bus::blocking::spi::SpiDeviceWithConfig;
+use embassy_executor::Spawner;
+use embassy_futures::select::{Either, select};
+use embassy_stm32::{
+    Config,
+    exti::ExtiInput,
+    gpio::{Level, Output, OutputType, Pull, Speed},
+    peripherals::TIM3,
+    rcc::{Pll, PllDiv, PllMul, PllPreDiv, PllSource, Sysclk, VoltageScale, mux},
+    spi::{self, Spi},
+    time::{Hertz, hz},
+    timer::{
+        Ch1,
+        simple_pwm::{PwmPin, SimplePwm},
+    },
+};
+use embassy_sync::blocking_mutex::{Mutex, raw::NoopRawMutex};
+use embassy_time::{Delay, Duration, Instant, Ticker};
+use embedded_graphics::{
+    Drawable,
+    draw_target::DrawTarget,
+    mono_font::{MonoTextStyle, ascii::FONT_6X10},
+    pixelcolor::Rgb565,
+    prelude::{Point, RgbColor},
+    text::{Baseline, Text, renderer::CharacterStyle},
+};
+use embedded_hal_async::digital::Wait;
+use mipidsi::{
+    interface::SpiInterface,
+    models::ST7735s,
+    options::{Orientation, Rotation},
+};
+use panic_probe as _;
+
+use lab05::{
+    display::bubble::{self, Bubble},
+    inclinometer::{self, Inclinometer},
+    mpu6500::{AccelDlpf, AccelScale, Config as Mpu6500Config, device_blocking::Mpu6500},
+};
+
+const DEBOUNCE_STABLE_PERIOD: Duration = Duration::from_millis(100);
+
+/// The time between two samples
+const SAMPLE_PERIOD: Duration = Duration::from_millis(20);
+
+/// How long the buzzer beeps when the board becomes level
+const BEEP_DURATION: Duration = Duration::from_millis(150);
+
+#[embassy_executor::main]
+async fn main(_spawner: Spawner) {
+    // Increase the frequency of the microcontroller to make the
+    // display transfer faster, see `ex5.rs`.
+    let mut config = Config::default();
+    config.rcc.hsi = true;
+    config.rcc.pll1 = Some(Pll {
+        source: PllSource::HSI, // 16 MHz
+        prediv: PllPreDiv::DIV1,
+        mul: PllMul::MUL10,
+        divp: None,
+        divq: None,
+        divr: Some(PllDiv::DIV1), // 160 MHz
+    });
+    config.rcc.sys = Sysclk::PLL1_R;
+    config.rcc.voltage_range = VoltageScale::RANGE1;
+    config.rcc.mux.iclksel = mux::Iclksel::HSI48; // USB uses ICLK
+
+    let peripherals = embassy_stm32::init(config);
+    info!("Device started");
+
+    // screen reset is D2 (PC8)
+    let screen_rst = Output::new(peripherals.PC8, Level::Low, Speed::Low);
+    // screen dc is D3 (PB3)
+    let screen_dc = Output::new(peripherals.PB3, Level::Low, Speed::Low);
+
+    // SPI1 is exposed by the Arduino header using pins:
+    // - MISO - D12 (PA6)
+    // - MOSI - D11 (PA7)
+    // - CLK - D13 (PA5)
+    let spi = Spi::new_blocking(
+        peripherals.SPI1,
+        peripherals.PA5,
+        peripherals.PA7,
+        peripherals.PA6,
+        spi::Config::default(),
+    );
+    let spi_bus_mutex: Mutex<NoopRawMutex, _> = Mutex::new(RefCell::new(spi));
+
+    // The display uses D4 (PB5) as CS
+    let mut screen_spi_config = spi::Config::default();
+    screen_spi_config.frequency = Hertz(3_000_000);
+    let screen_cs = Output::new(peripherals.PB5, Level::High, Speed::Low);
+    let display_spi = SpiDeviceWithConfig::new(&spi_bus_mutex, screen_cs, screen_spi_config);
+
+    let mut screen_buffer = [0; 4096];
+    let di = SpiInterface::new(display_spi, screen_dc, &mut screen_buffer);
+    let mut screen = mipidsi::Builder::new(ST7735s, di)
+        .reset_pin(screen_rst)
+        .orientation(Orientation::new().rotate(Rotation::Deg180))
+        .init(&mut Delay)
+        .unwrap();
+    screen.clear(Rgb565::BLACK).unwrap();
+
+    // The MPU6500 sensor uses D7 (PA8) as CS
+    let mut mpu6500_spi_config = spi::Config::default();
+    mpu6500_spi_config.frequency = Hertz(1_000_000);
+    let mpu6500_cs_pin = Output::new(peripherals.PA8, Level::High, Speed::Low);
+    let mut mpu6500_spi_device =
+        SpiDeviceWithConfig::new(&spi_bus_mutex, mpu6500_cs_pin, mpu6500_spi_config);
+    let mut mpu6500 = Mpu6500::new(&mut mpu6500_spi_device);
+
+    if let Err(error) = mpu6500.init() {
+        error!("MPU6500 sensor is not available: {}", error);
+        return;
+    }
+    // The inclinometer smooths the angles, the sensor's
+    // filter removes only the faster vibrations
+    mpu6500
+        .configure(
+            Mpu6500Config::default()
+                .accel_scale(AccelScale::G2)
+                .accel_dlpf(AccelDlpf::Hz21),
+        )
+        .expect("Failed to configure the sensor");
+
+    // The buttons on the lab board have an external pull up resistor,
+    // they are LOW when pressed, see lab04.
+    //
+    // In lab04 S1 is connected to D7 (PA8), which is the sensor's
+    // CS in this lab, so S1 (calibrate) is connected to D5 (PB4).
+    let mut button_calibrate = Debouncer::new(
+        ExtiInput::new(peripherals.PB4, peripherals.EXTI4, Pull::None),
+        DEBOUNCE_STABLE_PERIOD,
+    );
+
+    // In lab04 the buzzer is connected to D3 (PB3), which is the
+    // screen's DC in this lab, so the buzzer is connected to D9 (PC6).
+    //
+    // PC6 can be connected for PWM to Channel 1 of TIM 3.
+    let buzzer_pin: PwmPin<'_, TIM3, Ch1> = PwmPin::new(peripherals.PC6, OutputType::PushPull);
+    let mut buzzer_pwm = SimplePwm::new(
+        peripherals.TIM3,   // Timer 3 peripheral
+        Some(buzzer_pin),   // Channel 1 output (PC6)
+        None,               // Channel 2 not used
+        None,               // Channel 3 not used
+        None,               // Channel 4 not used
+        hz(2000),           // The frequency of the beep
+        Default::default(), // Default configuration
+    );
+    let mut buzzer = buzzer_pwm.ch1();
+    buzzer.set_duty_cycle_percent(50);
+
+    let mut inclinometer = Inclinometer::new(inclinometer::Config::default());
+    let mut bubble = Bubble::new(Point::new(64, 72), 50, bubble::Config::default());
+
+    let mut style = MonoTextStyle::new(&FONT_6X10, Rgb565::WHITE);
+    style.set_background_color(Some(Rgb565::BLACK));
+    let mut drawn_angles = None;
+
+    // The time when the beep stops, `None` if the buzzer is silent
+    let mut beep_until: Option<Instant> = None;
+
+    let mut ticker = Ticker::every(SAMPLE_PERIOD);
+    loop {
+        let acceleration = mpu6500.read_acceleration().unwrap();
+        let now = Instant::now();
+        let was_level = inclinometer.is_level();
+        let tilt = inclinometer.update(acceleration, now);
+
+        if inclinometer.is_level() && !was_level {
+            info!("Level");
+            buzzer.enable();
+            beep_until = Some(now + BEEP_DURATION);
+        }
+        if let Some(until) = beep_until
+            && now >= until
+        {
+            buzzer.disable();
+            beep_until = None;
+        }
+
+        // The roll tilts the board around the screen's Y axis and the
+        // pitch around its X axis, the bubble moves up, to the higher
+        // side. If the sensor is mounted the other way around, change
+        // the signs.
+        bubble.set_position(tilt.roll, -tilt.pitch, inclinometer.is_level());
+        bubble.draw(&mut screen).unwrap();
+
+        // The angles are displayed with a decimal, they are
+        // sent to the display only when they change
+        let angles = (
+            libm::roundf(tilt.pitch * 10.0) as i32,
+            libm::roundf(tilt.roll * 10.0) as i32,
+        );
+        if drawn_angles != Some(angles) {
+            let mut text = heapless::String::<32>::new();
+            core::write!(
+                &mut text,
+                "P {:6.1} R {:6.1}",
+                angles.0 as f32 / 10.0,
+                angles.1 as f32 / 10.0
+            )
+            .unwrap();
+            Text::with_baseline(&text, Point::new(2, 2), style, Baseline::Top)
+                .draw(&mut screen)
+                .unwrap();
+            drawn_angles = Some(angles);
+        }
+
+        // Pressing S1 takes the current position as level,
+        // the board has to be still
+        if let Either::First(_) =
+            select(button_calibrate.wait_for_falling_edge(), ticker.next()).await
+        {
+            inclinometer.calibrate();
+            let offset = inclinometer.offset();
+            info!(
+                "Calibrated, offset pitch {} roll {}",
+                offset.pitch, offset.roll
+            );
+            ticker.reset();
+        }
+    }
+}
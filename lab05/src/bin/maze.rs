@@ -0,0 +1,184 @@
+#![no_std]
+#![no_main]
+
+use core::{cell::RefCell, fmt::Write};
+
+use defmt::{error, info};
+use defmt_rtt as _;
+use embassy_embedded_hal::shared_bus::blocking::spi::SpiDeviceWithConfig;
+use embassy_executor::Spawner;
+use embassy_stm32::{
+    Config,
+    gpio::{Level, Output, Speed},
+    rcc::{Pll, PllDiv, PllMul, PllPreDiv, PllSource, Sysclk, VoltageScale, mux},
+    spi::{self, Spi},
+    time::Hertz,
+};
+use embassy_sync::blocking_mutex::{Mutex, raw::NoopRawMutex};
+use embassy_time::{Delay, Instant, Ticker};
+use embedded_graphics::{
+    Drawable,
+    draw_target::DrawTarget,
+    mono_font::{MonoTextStyle, ascii::FONT_6X10},
+    pixelcolor::Rgb565,
+    prelude::{Point, RgbColor},
+    text::{Baseline, Text, renderer::CharacterStyle},
+};
+use mipidsi::{
+    interface::SpiInterface,
+    models::ST7735s,
+    options::{Orientation, Rotation},
+};
+use panic_probe as _;
+
+use lab05::{
+    maze::{self, Event, FixedStep, Game, View},
+    mpu6500::{AccelDlpf, AccelScale, Config as Mpu6500Config, device_blocking::Mpu6500},
+};
+
+/// The maze, 16 x 18 cells of 8 pixels, see `lab05::maze`
+const MAZE: &[&str] = &[
+    "################",
+    "#o.....#......*#",
+    "#.####.#.####..#",
+    "#.#*........#..#",
+    "#.#.######..#..#",
+    "#.#......#..#*.#",
+    "#.####.#.#..####",
+    "#......#.#.....#",
+    "####.###.####..#",
+    "#*.#.....#..#..#",
+    "#..#.###.#..#..#",
+    "#..#...#....#*.#",
+    "#..###.######..#",
+    "#..............#",
+    "#.####.#.#####.#",
+    "#.#*...#.....#.#",
+    "#.#....#*....#.#",
+    "################",
+];
+
+/// The maze is displayed below the score
+const MAZE_TOP: i32 = 14;
+
+#[embassy_executor::main]
+async fn main(_spawner: Spawner) {
+    // Increase the frequency of the microcontroller to make the
+    // display transfer faster, see `ex5.rs`.
+    let mut config = Config::default();
+    config.rcc.hsi = true;
+    config.rcc.pll1 = Some(Pll {
+        source: PllSource::HSI, // 16 MHz
+        prediv: PllPreDiv::DIV1,
+        mul: PllMul::MUL10,
+        divp: None,
+        divq: None,
+        divr: Some(PllDiv::DIV1), // 160 MHz
+    });
+    config.rcc.sys = Sysclk::PLL1_R;
+    config.rcc.voltage_range = VoltageScale::RANGE1;
+    config.rcc.mux.iclksel = mux::Iclksel::HSI48; // USB uses ICLK
+
+    let peripherals = embassy_stm32::init(config);
+    info!("Device started");
+
+    // screen reset is D2 (PC8)
+    let screen_rst = Output::new(peripherals.PC8, Level::Low, Speed::Low);
+    // screen dc is D3 (PB3)
+    let screen_dc = Output::new(peripherals.PB3, Level::Low, Speed::Low);
+
+    // SPI1 is exposed by the Arduino header using pins:
+    // - MISO - D12 (PA6)
+    // - MOSI - D11 (PA7)
+    // - CLK - D13 (PA5)
+    let spi = Spi::new_blocking(
+        peripherals.SPI1,
+        peripherals.PA5,
+        peripherals.PA7,
+        peripherals.PA6,
+        spi::Config::default(),
+    );
+    let spi_bus_mutex: Mutex<NoopRawMutex, _> = Mutex::new(RefCell::new(spi));
+
+    // The display uses D4 (PB5) as CS
+    let mut screen_spi_config = spi::Config::default();
+    screen_spi_config.frequency = Hertz(3_000_000);
+    let screen_cs = Output::new(peripherals.PB5, Level::High, Speed::Low);
+    let display_spi = SpiDeviceWithConfig::new(&spi_bus_mutex, screen_cs, screen_spi_config);
+
+    let mut screen_buffer = [0; 4096];
+    let di = SpiInterface::new(display_spi, screen_dc, &mut screen_buffer);
+    let mut screen = mipidsi::Builder::new(ST7735s, di)
+        .reset_pin(screen_rst)
+        .orientation(Orientation::new().rotate(Rotation::Deg180))
+        .init(&mut Delay)
+        .unwrap();
+    screen.clear(Rgb565::BLACK).unwrap();
+
+    // The MPU6500 sensor uses D7 (PA8) as CS
+    let mut mpu6500_spi_config = spi::Config::default();
+    mpu6500_spi_config.frequency = Hertz(1_000_000);
+    let mpu6500_cs_pin = Output::new(peripherals.PA8, Level::High, Speed::Low);
+    let mut mpu6500_spi_device =
+        SpiDeviceWithConfig::new(&spi_bus_mutex, mpu6500_cs_pin, mpu6500_spi_config);
+    let mut mpu6500 = Mpu6500::new(&mut mpu6500_spi_device);
+
+    if let Err(error) = mpu6500.init() {
+        error!("MPU6500 sensor is not available: {}", error);
+        return;
+    }
+    // The filter removes the vibrations, only the tilt moves the ball
+    mpu6500
+        .configure(
+            Mpu6500Config::default()
+                .accel_scale(AccelScale::G2)
+                .accel_dlpf(AccelDlpf::Hz21),
+        )
+        .expect("Failed to configure the sensor");
+
+    let config = maze::Config::default();
+    let mut game = Game::new(MAZE, config);
+    let mut view = View::new(Point::new(0, MAZE_TOP), config);
+    let mut clock = FixedStep::new(config.time_step);
+
+    let mut style = MonoTextStyle::new(&FONT_6X10, Rgb565::WHITE);
+    style.set_background_color(Some(Rgb565::BLACK));
+    let mut drawn_score = None;
+
+    // The screen is redrawn at most every game step, a frame that
+    // takes longer is followed by several steps
+    let mut ticker = Ticker::every(config.time_step);
+    loop {
+        let acceleration = mpu6500.read_acceleration().unwrap();
+
+        // The accelerometer measures the tilt on the axes of the
+        // sensor, X points to the right of the screen and Y to its top.
+        // Tilting the right side down makes X negative, the ball
+        // rolls to the right. If the sensor is mounted the other way
+        // around, change the signs.
+        let tilt = [-acceleration.x, acceleration.y];
+        for _ in 0..clock.steps(Instant::now()) {
+            for event in game.step(tilt) {
+                match event {
+                    Event::Wall => {}
+                    Event::Coin => info!("Coin, score {}", game.score()),
+                    Event::Cleared => info!("All the coins collected"),
+                }
+            }
+        }
+
+        // Only the ball, the collected coins and the
+        // score are sent to the display
+        view.draw(&game, &mut screen).unwrap();
+        if drawn_score != Some(game.score()) {
+            let mut text = heapless::String::<16>::new();
+            core::write!(&mut text, "Score {}", game.score()).unwrap();
+            Text::with_baseline(&text, Point::new(2, 2), style, Baseline::Top)
+                .draw(&mut screen)
+                .unwrap();
+            drawn_score = Some(game.score());
+        }
+
+        ticker.next().await;
+    }
+}
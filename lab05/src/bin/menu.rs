@@ -0,0 +1,289 @@
+#![no_std]
+#![no_main]
+
+use core::{cell::RefCell, fmt::Write};
+
+use async_debounce::Debouncer;
+use defmt::{error, info};
+use defmt_rtt as _;
+use embassy_embedded_hal::shared_bus::blocking::spi::SpiDeviceWithConfig;
+use embassy_executor::Spawner;
+use embassy_futures::select::{Either, Either4, select, select4};
+use embassy_stm32::{
+    Config,
+    exti::ExtiInput,
+    gpio::{Level, Output, Pull, Speed},
+    rcc::{Pll, PllDiv, PllMul, PllPreDiv, PllSource, Sysclk, VoltageScale, mux},
+    spi::{self, Spi},
+    time::Hertz,
+};
+use embassy_sync::blocking_mutex::{Mutex, raw::NoopRawMutex};
+use embassy_time::{Delay, Duration, Ticker};
+use embedded_graphics::{
+    Drawable,
+    draw_target::DrawTarget,
+    mono_font::{MonoTextStyle, ascii::FONT_6X10},
+    pixelcolor::Rgb565,
+    prelude::{Point, RgbColor, Size},
+    text::{Text, renderer::CharacterStyle},
+};
+use embedded_hal_async::digital::Wait;
+use mipidsi::{
+    interface::SpiInterface,
+    models::ST7735s,
+    options::{Orientation, Rotation},
+};
+use panic_probe as _;
+
+use lab05::{
+    display::menu::{self, Button, Change, Item, Menu},
+    mpu6500::{AccelScale, Config as Mpu6500Config, GyroDlpf, GyroScale, device_blocking::Mpu6500},
+};
+
+/// The period in which a button's value has to stay stable
+/// to be considered pressed or released, see lab04.
+const DEBOUNCE_STABLE_PERIOD: Duration = Duration::from_millis(100);
+
+// The numbers of the settings
+const ACCEL_SCALE: usize = 0;
+const GYRO_SCALE: usize = 1;
+const SAMPLE_RATE: usize = 2;
+const ROTATION: usize = 3;
+const MIRROR: usize = 4;
+
+const SENSOR: &[Item] = &[
+    Item::choice("Accel", ACCEL_SCALE, &["2g", "4g", "8g", "16g"]),
+    Item::choice("Gyro", GYRO_SCALE, &["250", "500", "1000", "2000"]),
+    Item::spinner("Rate", SAMPLE_RATE, 10, 200, 10, "Hz"),
+    Item::back("Back"),
+];
+
+const DISPLAY: &[Item] = &[
+    Item::choice("Rotation", ROTATION, &["0", "90", "180", "270"]),
+    Item::toggle("Mirror", MIRROR),
+    Item::back("Back"),
+];
+
+const SETTINGS: &[Item] = &[
+    Item::submenu("Sensor", SENSOR),
+    Item::submenu("Display", DISPLAY),
+];
+
+const ACCEL_SCALES: [AccelScale; 4] = [
+    AccelScale::G2,
+    AccelScale::G4,
+    AccelScale::G8,
+    AccelScale::G16,
+];
+
+const GYRO_SCALES: [GyroScale; 4] = [
+    GyroScale::Gs250,
+    GyroScale::Gs500,
+    GyroScale::Gs1000,
+    GyroScale::Gs2000,
+];
+
+const ROTATIONS: [Rotation; 4] = [
+    Rotation::Deg0,
+    Rotation::Deg90,
+    Rotation::Deg180,
+    Rotation::Deg270,
+];
+
+/// Returns the sensor configuration for the menu's settings
+fn sensor_config(values: [i32; 5]) -> Mpu6500Config {
+    // The filter samples at 1 kHz, the divider
+    // gives the closest rate to the setting
+    let divider = (1000 / values[SAMPLE_RATE] - 1).clamp(0, 255) as u8;
+    Mpu6500Config::default()
+        .accel_scale(ACCEL_SCALES[values[ACCEL_SCALE] as usize])
+        .gyro_scale(GYRO_SCALES[values[GYRO_SCALE] as usize])
+        .gyro_dlpf(GyroDlpf::Hz41)
+        .sample_rate_divider(divider)
+}
+
+/// Returns the display orientation for the menu's settings
+fn orientation(values: [i32; 5]) -> Orientation {
+    let orientation = Orientation::new().rotate(ROTATIONS[values[ROTATION] as usize]);
+    if values[MIRROR] != 0 {
+        orientation.flip_horizontal()
+    } else {
+        orientation
+    }
+}
+
+#[embassy_executor::main]
+async fn main(_spawner: Spawner) {
+    // Increase the frequency of the microcontroller to make the
+    // display transfer faster, see `ex5.rs`.
+    let mut config = Config::default();
+    config.rcc.hsi = true;
+    config.rcc.pll1 = Some(Pll {
+        source: PllSource::HSI, // 16 MHz
+        prediv: PllPreDiv::DIV1,
+        mul: PllMul::MUL10,
+        divp: None,
+        divq: None,
+        divr: Some(PllDiv::DIV1), // 160 MHz
+    });
+    config.rcc.sys = Sysclk::PLL1_R;
+    config.rcc.voltage_range = VoltageScale::RANGE1;
+    config.rcc.mux.iclksel = mux::Iclksel::HSI48; // USB uses ICLK
+
+    let peripherals = embassy_stm32::init(config);
+    info!("Device started");
+
+    // The buttons on the lab board have an external pull up resistor,
+    // they are LOW when pressed, see lab04.
+    //
+    // In lab04 S1 is connected to D7 (PA8), which is the sensor's
+    // CS in this lab, so the buttons are connected to free pins:
+    // - S1 (up) - D5 (PB4)
+    // - S2 (down) - D8 (PC7)
+    // - S3 (select) - D9 (PC6)
+    // - S4 (back) - D10 (PC9)
+    let mut button_up = Debouncer::new(
+        ExtiInput::new(peripherals.PB4, peripherals.EXTI4, Pull::None),
+        DEBOUNCE_STABLE_PERIOD,
+    );
+    let mut button_down = Debouncer::new(
+        ExtiInput::new(peripherals.PC7, peripherals.EXTI7, Pull::None),
+        DEBOUNCE_STABLE_PERIOD,
+    );
+    let mut button_select = Debouncer::new(
+        ExtiInput::new(peripherals.PC6, peripherals.EXTI6, Pull::None),
+        DEBOUNCE_STABLE_PERIOD,
+    );
+    let mut button_back = Debouncer::new(
+        ExtiInput::new(peripherals.PC9, peripherals.EXTI9, Pull::None),
+        DEBOUNCE_STABLE_PERIOD,
+    );
+
+    // screen reset is D2 (PC8)
+    let screen_rst = Output::new(peripherals.PC8, Level::Low, Speed::Low);
+    // screen dc is D3 (PB3)
+    let screen_dc = Output::new(peripherals.PB3, Level::Low, Speed::Low);
+
+    // SPI1 is exposed by the Arduino header using pins:
+    // - MISO - D12 (PA6)
+    // - MOSI - D11 (PA7)
+    // - CLK - D13 (PA5)
+    let spi = Spi::new_blocking(
+        peripherals.SPI1,
+        peripherals.PA5,
+        peripherals.PA7,
+        peripherals.PA6,
+        spi::Config::default(),
+    );
+    let spi_bus_mutex: Mutex<NoopRawMutex, _> = Mutex::new(RefCell::new(spi));
+
+    // The display uses D4 (PB5) as CS
+    let mut screen_spi_config = spi::Config::default();
+    screen_spi_config.frequency = Hertz(3_000_000);
+    let screen_cs = Output::new(peripherals.PB5, Level::High, Speed::Low);
+    let display_spi = SpiDeviceWithConfig::new(&spi_bus_mutex, screen_cs, screen_spi_config);
+
+    // The initial settings: 2g, 250 deg/s, 100 Hz, rotated by 180 degrees
+    let values = [0, 0, 100, 2, 0];
+
+    let mut screen_buffer = [0; 4096];
+    let di = SpiInterface::new(display_spi, screen_dc, &mut screen_buffer);
+    let mut screen = mipidsi::Builder::new(ST7735s, di)
+        .reset_pin(screen_rst)
+        .orientation(orientation(values))
+        .init(&mut Delay)
+        .unwrap();
+
+    // The MPU6500 sensor uses D7 (PA8) as CS
+    let mut mpu6500_spi_config = spi::Config::default();
+    mpu6500_spi_config.frequency = Hertz(1_000_000);
+    let mpu6500_cs_pin = Output::new(peripherals.PA8, Level::High, Speed::Low);
+    let mut mpu6500_spi_device =
+        SpiDeviceWithConfig::new(&spi_bus_mutex, mpu6500_cs_pin, mpu6500_spi_config);
+    let mut mpu6500 = Mpu6500::new(&mut mpu6500_spi_device);
+
+    screen.clear(Rgb565::BLACK).unwrap();
+    let mut style = MonoTextStyle::new(&FONT_6X10, Rgb565::WHITE);
+    style.set_background_color(Some(Rgb565::BLACK));
+
+    if let Err(error) = mpu6500.init() {
+        error!("MPU6500 sensor is not available: {}", error);
+        return;
+    }
+    mpu6500
+        .configure(sensor_config(values))
+        .expect("Failed to configure the sensor");
+
+    // The menu fits the screen in all the orientations (128 x 128),
+    // the readings are displayed below it
+    let mut menu = Menu::new(
+        Point::zero(),
+        Size::new(128, 85),
+        "Settings",
+        SETTINGS,
+        values,
+        menu::Config::default(),
+    );
+
+    let mut ticker = Ticker::every(Duration::from_millis(200));
+    loop {
+        menu.draw(&mut screen).unwrap();
+
+        let buttons = select4(
+            button_up.wait_for_falling_edge(),
+            button_down.wait_for_falling_edge(),
+            button_select.wait_for_falling_edge(),
+            button_back.wait_for_falling_edge(),
+        );
+        match select(buttons, ticker.next()).await {
+            Either::First(pressed) => {
+                let button = match pressed {
+                    Either4::First(_) => Button::Up,
+                    Either4::Second(_) => Button::Down,
+                    Either4::Third(_) => Button::Select,
+                    Either4::Fourth(_) => Button::Back,
+                };
+                let Some(Change { setting, value }) = menu.press(button) else {
+                    continue;
+                };
+                info!("Setting {} changed to {}", setting, value);
+
+                // Apply the change without reflashing
+                match setting {
+                    ROTATION | MIRROR => {
+                        screen.set_orientation(orientation(menu.values())).unwrap();
+                        screen.clear(Rgb565::BLACK).unwrap();
+                        menu.invalidate();
+                    }
+                    _ => {
+                        let config = sensor_config(menu.values());
+                        mpu6500
+                            .configure(config)
+                            .expect("Failed to configure the sensor");
+                        info!("Sample rate {} Hz", config.sample_rate());
+                    }
+                }
+            }
+            Either::Second(_) => {
+                let acceleration = mpu6500.read_acceleration().unwrap();
+                let gyro = mpu6500.read_gyro().unwrap();
+
+                let mut text = heapless::String::<64>::new();
+                core::write!(
+                    &mut text,
+                    "A {:5.2} {:5.2} {:5.2}\nG {:5.0} {:5.0} {:5.0}",
+                    acceleration.x,
+                    acceleration.y,
+                    acceleration.z,
+                    gyro.x,
+                    gyro.y,
+                    gyro.z
+                )
+                .unwrap();
+                Text::new(&text, Point::new(0, 100), style)
+                    .draw(&mut screen)
+                    .unwrap();
+            }
+        }
+    }
+}
@@ -0,0 +1,97 @@
+#![no_std]
+#![no_main]
+
+use defmt::{error, info};
+use defmt_rtt as _;
+use embassy_embedded_hal::shared_bus::asynch::spi::SpiDevice;
+use embassy_executor::Spawner;
+use embassy_stm32::{
+    gpio::{Level, Output, Speed},
+    spi::{self, Spi},
+    time::Hertz,
+};
+use embassy_sync::{blocking_mutex::raw::ThreadModeRawMutex, mutex::Mutex};
+use embassy_time::{Duration, Instant, Ticker};
+use panic_probe as _;
+
+use lab05::{
+    mpu6500::{AccelScale, GyroScale, device::Mpu6500},
+    orientation::{ComplementaryFilter, DEFAULT_BETA, Filter, Madgwick, Sample},
+};
+
+#[embassy_executor::main]
+async fn main(_spawner: Spawner) {
+    let peripherals = embassy_stm32::init(Default::default());
+    info!("Device started");
+
+    // Create the SPI bus configuration
+    let mut config = spi::Config::default();
+    // Set the SPI frequency to 1 MHz
+    config.frequency = Hertz(1_000_000);
+
+    // SPI1 is exposed by the Arduino header using pins:
+    // - MISO - D12 (PA6)
+    // - MOSI - D11 (PA7)
+    // - CLK - D13 (PA5)
+    let spi = Spi::new(
+        peripherals.SPI1,
+        peripherals.PA5,
+        peripherals.PA7,
+        peripherals.PA6,
+        peripherals.GPDMA1_CH0,
+        peripherals.GPDMA1_CH1,
+        config,
+    );
+
+    // We use the D7 (PA8) pin as CS
+    let mpu6500_cs_pin = Output::new(peripherals.PA8, Level::High, Speed::Low);
+
+    let spi_mutex = Mutex::<ThreadModeRawMutex, _>::new(spi);
+    let mut spi_device = SpiDevice::new(&spi_mutex, mpu6500_cs_pin);
+    let mut mpu6500 = Mpu6500::new(&mut spi_device);
+
+    if let Err(error) = mpu6500.init().await {
+        error!("MPU6500 sensor is not available: {}", error);
+        return;
+    }
+    mpu6500
+        .set_accel_scale(AccelScale::G2)
+        .await
+        .expect("Failed to set the acceleration scale");
+    mpu6500
+        .set_gyro_scale(GyroScale::Gs500)
+        .await
+        .expect("Failed to set the gyro scale");
+
+    // Both filters get the same samples, so that we can compare them.
+    // The complementary filter is simpler, the Madgwick filter
+    // handles any orientation.
+    let mut complementary = ComplementaryFilter::default();
+    let mut madgwick = Madgwick::new(DEFAULT_BETA);
+
+    // The filters integrate the gyro, so they need frequent samples
+    let mut ticker = Ticker::every(Duration::from_millis(10));
+    let mut count = 0u32;
+    loop {
+        ticker.next().await;
+        let measurement = mpu6500.read_all().await.unwrap();
+        // The timestamp tells the filters how much time
+        // passed since the previous sample
+        let sample = Sample::new(measurement, Instant::now());
+        let complementary_angles = complementary.update(sample);
+        let madgwick_angles = madgwick.update(sample);
+
+        // Printing every sample would slow down the loop
+        count += 1;
+        if count.is_multiple_of(20) {
+            info!(
+                "Complementary: roll {} pitch {} yaw {}",
+                complementary_angles.roll, complementary_angles.pitch, complementary_angles.yaw
+            );
+            info!(
+                "Madgwick: roll {} pitch {} yaw {}",
+                madgwick_angles.roll, madgwick_angles.pitch, madgwick_angles.yaw
+            );
+        }
+    }
+}
@@ -0,0 +1,185 @@
+#![no_std]
+#![no_main]
+
+use core::{cell::RefCell, fmt::Write};
+
+use defmt::{error, info};
+use defmt_rtt as _;
+use embassy_embedded_hal::shared_bus::blocking::spi::SpiDeviceWithConfig;
+use embassy_executor::Spawner;
+use embassy_futures::join::join;
+use embassy_stm32::{
+    Config,
+    exti::ExtiInput,
+    gpio::{Level, Output, Pull, Speed},
+    rcc::{Pll, PllDiv, PllMul, PllPreDiv, PllSource, Sysclk, VoltageScale, mux},
+    spi::{self, Spi},
+    time::Hertz,
+};
+use embassy_sync::{
+    blocking_mutex::{Mutex, raw::NoopRawMutex, raw::ThreadModeRawMutex},
+    channel::Channel,
+    watch::Watch,
+};
+use embassy_time::{Delay, Instant};
+use embedded_graphics::{
+    Drawable,
+    draw_target::DrawTarget,
+    mono_font::{MonoTextStyle, ascii::FONT_6X10},
+    pixelcolor::Rgb565,
+    prelude::{Point, RgbColor},
+    text::{Text, renderer::CharacterStyle},
+};
+use mipidsi::{
+    interface::SpiInterface,
+    models::ST7735s,
+    options::{Orientation, Rotation},
+};
+use panic_probe as _;
+
+use lab05::{
+    mpu6500::{
+        AccelDlpf, AccelScale, Acceleration, Config as Mpu6500Config, GyroDlpf, InterruptPin,
+        device_blocking::Mpu6500,
+    },
+    pedometer::{self, StepCounter, Steps},
+};
+
+// The main task sends the acceleration samples to the pedometer task
+static SAMPLES: Channel<ThreadModeRawMutex, (Acceleration, Instant), 16> = Channel::new();
+
+// The pedometer task publishes the steps, the main task displays them
+static STEPS: Watch<ThreadModeRawMutex, Steps, 1> = Watch::new();
+
+#[embassy_executor::task]
+async fn pedometer_task() {
+    let mut counter = StepCounter::new(pedometer::Config::default());
+    pedometer::run(&mut counter, SAMPLES.receiver(), STEPS.sender()).await
+}
+
+#[embassy_executor::main]
+async fn main(spawner: Spawner) {
+    // Increase the frequency of the microcontroller to make the
+    // display transfer faster, see `ex5.rs`.
+    let mut config = Config::default();
+    config.rcc.hsi = true;
+    config.rcc.pll1 = Some(Pll {
+        source: PllSource::HSI, // 16 MHz
+        prediv: PllPreDiv::DIV1,
+        mul: PllMul::MUL10,
+        divp: None,
+        divq: None,
+        divr: Some(PllDiv::DIV1), // 160 MHz
+    });
+    config.rcc.sys = Sysclk::PLL1_R;
+    config.rcc.voltage_range = VoltageScale::RANGE1;
+    config.rcc.mux.iclksel = mux::Iclksel::HSI48; // USB uses ICLK
+
+    let peripherals = embassy_stm32::init(config);
+    info!("Device started");
+
+    // screen reset is D2 (PC8)
+    let screen_rst = Output::new(peripherals.PC8, Level::Low, Speed::Low);
+    // screen dc is D3 (PB3)
+    let screen_dc = Output::new(peripherals.PB3, Level::Low, Speed::Low);
+
+    // SPI1 is exposed by the Arduino header using pins:
+    // - MISO - D12 (PA6)
+    // - MOSI - D11 (PA7)
+    // - CLK - D13 (PA5)
+    let spi = Spi::new_blocking(
+        peripherals.SPI1,
+        peripherals.PA5,
+        peripherals.PA7,
+        peripherals.PA6,
+        spi::Config::default(),
+    );
+    let spi_bus_mutex: Mutex<NoopRawMutex, _> = Mutex::new(RefCell::new(spi));
+
+    // The display uses D4 (PB5) as CS
+    let mut screen_spi_config = spi::Config::default();
+    screen_spi_config.frequency = Hertz(3_000_000);
+    let screen_cs = Output::new(peripherals.PB5, Level::High, Speed::Low);
+    let display_spi = SpiDeviceWithConfig::new(&spi_bus_mutex, screen_cs, screen_spi_config);
+
+    let mut screen_buffer = [0; 4096];
+    let di = SpiInterface::new(display_spi, screen_dc, &mut screen_buffer);
+    let mut screen = mipidsi::Builder::new(ST7735s, di)
+        .reset_pin(screen_rst)
+        .orientation(Orientation::new().rotate(Rotation::Deg180))
+        .init(&mut Delay)
+        .unwrap();
+
+    // The MPU6500 sensor uses D7 (PA8) as CS
+    let mut mpu6500_spi_config = spi::Config::default();
+    mpu6500_spi_config.frequency = Hertz(1_000_000);
+    let mpu6500_cs_pin = Output::new(peripherals.PA8, Level::High, Speed::Low);
+    let mut mpu6500_spi_device =
+        SpiDeviceWithConfig::new(&spi_bus_mutex, mpu6500_cs_pin, mpu6500_spi_config);
+    let mut mpu6500 = Mpu6500::new(&mut mpu6500_spi_device);
+
+    // The INT pin of the MPU6500 sensor is connected to D6 (PB10).
+    let mut mpu6500_int = ExtiInput::new(peripherals.PB10, peripherals.EXTI10, Pull::None);
+
+    screen.clear(Rgb565::BLACK).unwrap();
+    let mut style = MonoTextStyle::new(&FONT_6X10, Rgb565::WHITE);
+    style.set_background_color(Some(Rgb565::BLACK));
+
+    if let Err(error) = mpu6500.init() {
+        error!("MPU6500 sensor is not available: {}", error);
+        return;
+    }
+
+    // Steps are slow movements, we filter the acceleration
+    // and sample it at 100 Hz (1 kHz / (1 + 9)). The gyro filter
+    // sets the 1 kHz rate, the sensor ignores the divider at 8 kHz.
+    let mpu6500_config = Mpu6500Config::default()
+        .accel_scale(AccelScale::G4)
+        .accel_dlpf(AccelDlpf::Hz21)
+        .gyro_dlpf(GyroDlpf::Hz41)
+        .sample_rate_divider(9);
+    mpu6500
+        .configure(mpu6500_config)
+        .expect("Failed to configure the sensor");
+    mpu6500
+        .enable_data_ready(InterruptPin::default())
+        .expect("Failed to enable the data ready interrupt");
+
+    // The pedometer runs in its own task
+    spawner.spawn(pedometer_task()).unwrap();
+    let mut steps = STEPS.receiver().unwrap();
+
+    join(
+        // Read every sample and send it to the pedometer task
+        async {
+            loop {
+                mpu6500.wait_for_data(&mut mpu6500_int).await.unwrap();
+                let acceleration = mpu6500.read_acceleration().unwrap();
+                SAMPLES.send((acceleration, Instant::now())).await;
+            }
+        },
+        // Display the steps every time they change
+        async {
+            loop {
+                let steps = steps.changed().await;
+
+                let mut steps_buf = heapless::String::<100>::new();
+                core::write!(
+                    &mut steps_buf,
+                    "Steps: {}     \nCadence: {} /min     \nDistance: {} m     ",
+                    steps.count,
+                    steps.cadence as u32,
+                    steps.distance as u32
+                )
+                .unwrap();
+
+                Text::new(&steps_buf, Point::new(0, 20), style)
+                    .draw(&mut screen)
+                    .unwrap();
+
+                info!("Steps: {}", steps);
+            }
+        },
+    )
+    .await;
+}
@@ -0,0 +1,163 @@
+#![no_std]
+#![no_main]
+
+use core::cell::RefCell;
+
+use defmt::{error, info};
+use defmt_rtt as _;
+use embassy_embedded_hal::shared_bus::blocking::spi::SpiDeviceWithConfig;
+use embassy_executor::Spawner;
+use embassy_stm32::{
+    Config,
+    exti::ExtiInput,
+    gpio::{Level, Output, Pull, Speed},
+    rcc::{Pll, PllDiv, PllMul, PllPreDiv, PllSource, Sysclk, VoltageScale, mux},
+    spi::{self, Spi},
+    time::Hertz,
+};
+use embassy_sync::blocking_mutex::{Mutex, raw::NoopRawMutex};
+use embassy_time::Delay;
+use embedded_graphics::{
+    draw_target::DrawTarget,
+    pixelcolor::Rgb565,
+    prelude::{Point, RgbColor},
+};
+use mipidsi::{
+    interface::SpiInterface,
+    models::ST7735s,
+    options::{Orientation, Rotation},
+};
+use panic_probe as _;
+
+use lab05::{
+    display::plot::{self, Plot, Series},
+    mpu6500::{
+        AccelDlpf, AccelScale, Config as Mpu6500Config, GyroDlpf, GyroScale, InterruptPin,
+        device_blocking::Mpu6500,
+    },
+};
+
+/// The number of samples displayed, the plots fill the
+/// width of the screen (128 pixels) with the labels
+const SAMPLES: usize = 106;
+
+#[embassy_executor::main]
+async fn main(_spawner: Spawner) {
+    // Increase the frequency of the microcontroller to make the
+    // display transfer faster, see `ex5.rs`.
+    let mut config = Config::default();
+    config.rcc.hsi = true;
+    config.rcc.pll1 = Some(Pll {
+        source: PllSource::HSI, // 16 MHz
+        prediv: PllPreDiv::DIV1,
+        mul: PllMul::MUL10,
+        divp: None,
+        divq: None,
+        divr: Some(PllDiv::DIV1), // 160 MHz
+    });
+    config.rcc.sys = Sysclk::PLL1_R;
+    config.rcc.voltage_range = VoltageScale::RANGE1;
+    config.rcc.mux.iclksel = mux::Iclksel::HSI48; // USB uses ICLK
+
+    let peripherals = embassy_stm32::init(config);
+    info!("Device started");
+
+    // screen reset is D2 (PC8)
+    let screen_rst = Output::new(peripherals.PC8, Level::Low, Speed::Low);
+    // screen dc is D3 (PB3)
+    let screen_dc = Output::new(peripherals.PB3, Level::Low, Speed::Low);
+
+    // SPI1 is exposed by the Arduino header using pins:
+    // - MISO - D12 (PA6)
+    // - MOSI - D11 (PA7)
+    // - CLK - D13 (PA5)
+    let spi = Spi::new_blocking(
+        peripherals.SPI1,
+        peripherals.PA5,
+        peripherals.PA7,
+        peripherals.PA6,
+        spi::Config::default(),
+    );
+    let spi_bus_mutex: Mutex<NoopRawMutex, _> = Mutex::new(RefCell::new(spi));
+
+    // The display uses D4 (PB5) as CS
+    let mut screen_spi_config = spi::Config::default();
+    screen_spi_config.frequency = Hertz(3_000_000);
+    let screen_cs = Output::new(peripherals.PB5, Level::High, Speed::Low);
+    let display_spi = SpiDeviceWithConfig::new(&spi_bus_mutex, screen_cs, screen_spi_config);
+
+    let mut screen_buffer = [0; 4096];
+    let di = SpiInterface::new(display_spi, screen_dc, &mut screen_buffer);
+    let mut screen = mipidsi::Builder::new(ST7735s, di)
+        .reset_pin(screen_rst)
+        .orientation(Orientation::new().rotate(Rotation::Deg180))
+        .init(&mut Delay)
+        .unwrap();
+
+    // The MPU6500 sensor uses D7 (PA8) as CS
+    let mut mpu6500_spi_config = spi::Config::default();
+    mpu6500_spi_config.frequency = Hertz(1_000_000);
+    let mpu6500_cs_pin = Output::new(peripherals.PA8, Level::High, Speed::Low);
+    let mut mpu6500_spi_device =
+        SpiDeviceWithConfig::new(&spi_bus_mutex, mpu6500_cs_pin, mpu6500_spi_config);
+    let mut mpu6500 = Mpu6500::new(&mut mpu6500_spi_device);
+
+    // The INT pin of the MPU6500 sensor is connected to D6 (PB10).
+    let mut mpu6500_int = ExtiInput::new(peripherals.PB10, peripherals.EXTI10, Pull::None);
+
+    screen.clear(Rgb565::BLACK).unwrap();
+
+    if let Err(error) = mpu6500.init() {
+        error!("MPU6500 sensor is not available: {}", error);
+        return;
+    }
+
+    // Filter the values and sample at 50 Hz (1 kHz / (1 + 19))
+    let mpu6500_config = Mpu6500Config::default()
+        .accel_scale(AccelScale::G2)
+        .gyro_scale(GyroScale::Gs1000)
+        .gyro_dlpf(GyroDlpf::Hz20)
+        .accel_dlpf(AccelDlpf::Hz21)
+        .sample_rate_divider(19);
+    mpu6500
+        .configure(mpu6500_config)
+        .expect("Failed to configure the sensor");
+    mpu6500
+        .enable_data_ready(InterruptPin::default())
+        .expect("Failed to enable the data ready interrupt");
+
+    // The acceleration in the upper half of the screen, the
+    // gyro in the lower half
+    let axes = |x, y, z| {
+        [
+            Series::new(x, Rgb565::RED),
+            Series::new(y, Rgb565::GREEN),
+            Series::new(z, Rgb565::CYAN),
+        ]
+    };
+    let mut accel_plot = Plot::<3, SAMPLES>::new(
+        Point::new(0, 0),
+        78,
+        axes("aX", "aY", "aZ"),
+        plot::Config::default(),
+    );
+    let mut gyro_plot = Plot::<3, SAMPLES>::new(
+        Point::new(0, 81),
+        78,
+        axes("gX", "gY", "gZ"),
+        plot::Config::default().min_span(10f32),
+    );
+
+    loop {
+        // Drawing takes longer than a sample period if many columns
+        // change, the samples taken meanwhile are skipped
+        mpu6500.wait_for_data(&mut mpu6500_int).await.unwrap();
+        let acceleration = mpu6500.read_acceleration().unwrap();
+        let gyro = mpu6500.read_gyro().unwrap();
+
+        accel_plot.push([acceleration.x, acceleration.y, acceleration.z]);
+        gyro_plot.push([gyro.x, gyro.y, gyro.z]);
+        accel_plot.draw(&mut screen).unwrap();
+        gyro_plot.draw(&mut screen).unwrap();
+    }
+}
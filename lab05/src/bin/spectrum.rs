@@ -0,0 +1,237 @@
+#![no_std]
+#![no_main]
+
+use core::{cell::RefCell, fmt::Write};
+
+use defmt::{error, info, warn};
+use defmt_rtt as _;
+use embassy_embedded_hal::shared_bus::blocking::spi::SpiDeviceWithConfig;
+use embassy_executor::Spawner;
+use embassy_stm32::{
+    Config,
+    gpio::{Level, Output, Speed},
+    rcc::{Pll, PllDiv, PllMul, PllPreDiv, PllSource, Sysclk, VoltageScale, mux},
+    spi::{self, Spi},
+    time::Hertz,
+};
+use embassy_sync::blocking_mutex::{Mutex, raw::NoopRawMutex};
+use embassy_time::{Delay, Duration, Ticker};
+use embedded_graphics::{
+    Drawable,
+    draw_target::DrawTarget,
+    mono_font::{MonoTextStyle, ascii::FONT_6X10},
+    pixelcolor::Rgb565,
+    prelude::{Point, RgbColor, Size},
+    primitives::Rectangle,
+    text::{Text, renderer::CharacterStyle},
+};
+use mipidsi::{
+    interface::SpiInterface,
+    models::ST7735s,
+    options::{Orientation, Rotation},
+};
+use panic_probe as _;
+
+use lab05::{
+    mpu6500::{
+        AccelDlpf, AccelScale, Config as Mpu6500Config, FIFO_SIZE, FifoFrame, FifoSensors,
+        GyroDlpf, device_blocking::Mpu6500,
+    },
+    spectrum::{Axis, SpectrumAnalyzer, Window},
+};
+
+/// The number of samples analyzed at once
+const SAMPLES: usize = 256;
+
+/// The number of bars of every axis
+const BARS: usize = 32;
+
+/// The width of a bar in pixels
+const BAR_WIDTH: u32 = 4;
+
+/// The height of a full bar in pixels
+const BAR_HEIGHT: u32 = 36;
+
+/// The amplitude of a full bar in g
+const FULL_SCALE: f32 = 0.5;
+
+/// The height of the screen area of an axis (text and bars)
+const ROW_HEIGHT: i32 = 52;
+
+#[embassy_executor::main]
+async fn main(_spawner: Spawner) {
+    // Increase the frequency of the microcontroller to make the
+    // display transfer faster, see `ex5.rs`.
+    let mut config = Config::default();
+    config.rcc.hsi = true;
+    config.rcc.pll1 = Some(Pll {
+        source: PllSource::HSI, // 16 MHz
+        prediv: PllPreDiv::DIV1,
+        mul: PllMul::MUL10,
+        divp: None,
+        divq: None,
+        divr: Some(PllDiv::DIV1), // 160 MHz
+    });
+    config.rcc.sys = Sysclk::PLL1_R;
+    config.rcc.voltage_range = VoltageScale::RANGE1;
+    config.rcc.mux.iclksel = mux::Iclksel::HSI48; // USB uses ICLK
+
+    let peripherals = embassy_stm32::init(config);
+    info!("Device started");
+
+    // screen reset is D2 (PC8)
+    let screen_rst = Output::new(peripherals.PC8, Level::Low, Speed::Low);
+    // screen dc is D3 (PB3)
+    let screen_dc = Output::new(peripherals.PB3, Level::Low, Speed::Low);
+
+    // SPI1 is exposed by the Arduino header using pins:
+    // - MISO - D12 (PA6)
+    // - MOSI - D11 (PA7)
+    // - CLK - D13 (PA5)
+    let spi = Spi::new_blocking(
+        peripherals.SPI1,
+        peripherals.PA5,
+        peripherals.PA7,
+        peripherals.PA6,
+        spi::Config::default(),
+    );
+    let spi_bus_mutex: Mutex<NoopRawMutex, _> = Mutex::new(RefCell::new(spi));
+
+    // The display uses D4 (PB5) as CS
+    let mut screen_spi_config = spi::Config::default();
+    screen_spi_config.frequency = Hertz(3_000_000);
+    let screen_cs = Output::new(peripherals.PB5, Level::High, Speed::Low);
+    let display_spi = SpiDeviceWithConfig::new(&spi_bus_mutex, screen_cs, screen_spi_config);
+
+    let mut screen_buffer = [0; 4096];
+    let di = SpiInterface::new(display_spi, screen_dc, &mut screen_buffer);
+    let mut screen = mipidsi::Builder::new(ST7735s, di)
+        .reset_pin(screen_rst)
+        .orientation(Orientation::new().rotate(Rotation::Deg180))
+        .init(&mut Delay)
+        .unwrap();
+
+    // The MPU6500 sensor uses D7 (PA8) as CS
+    let mut mpu6500_spi_config = spi::Config::default();
+    mpu6500_spi_config.frequency = Hertz(1_000_000);
+    let mpu6500_cs_pin = Output::new(peripherals.PA8, Level::High, Speed::Low);
+    let mut mpu6500_spi_device =
+        SpiDeviceWithConfig::new(&spi_bus_mutex, mpu6500_cs_pin, mpu6500_spi_config);
+    let mut mpu6500 = Mpu6500::new(&mut mpu6500_spi_device);
+
+    screen.clear(Rgb565::BLACK).unwrap();
+    let mut style = MonoTextStyle::new(&FONT_6X10, Rgb565::WHITE);
+    style.set_background_color(Some(Rgb565::BLACK));
+
+    if let Err(error) = mpu6500.init() {
+        error!("MPU6500 sensor is not available: {}", error);
+        return;
+    }
+
+    // Sample at 250 Hz (1 kHz / (1 + 3)), the analyzer sees
+    // frequencies up to 125 Hz. The low pass filter removes the
+    // higher frequencies, they would appear as false lower ones.
+    let mpu6500_config = Mpu6500Config::default()
+        .accel_scale(AccelScale::G4)
+        .accel_dlpf(AccelDlpf::Hz99)
+        .gyro_dlpf(GyroDlpf::Hz92)
+        .sample_rate_divider(3);
+    mpu6500
+        .configure(mpu6500_config)
+        .expect("Failed to configure the sensor");
+
+    // The samples are read from the FIFO, so that none is lost
+    // while the screen is drawn
+    mpu6500
+        .enable_fifo(FifoSensors {
+            accel: true,
+            ..Default::default()
+        })
+        .expect("Failed to enable the FIFO");
+
+    // The analyzer takes the sample rate from the sensor, the
+    // frequencies are wrong if the two do not match
+    let mut analyzer = SpectrumAnalyzer::<SAMPLES>::from_config(&mpu6500.config(), Window::Hann);
+    info!(
+        "Sample rate {} Hz, resolution {} Hz",
+        analyzer.sample_rate(),
+        analyzer.resolution()
+    );
+
+    // The FIFO is full after 85 frames (340 ms), read it more often
+    let mut frames = [FifoFrame::default(); FIFO_SIZE / 6];
+    let mut ticker = Ticker::every(Duration::from_millis(100));
+    let mut bars = [0f32; BARS];
+
+    loop {
+        ticker.next().await;
+
+        let read = mpu6500.read_fifo(&mut frames).unwrap();
+        if read.overflow {
+            // The spectrum of a buffer with gaps is wrong
+            warn!("The FIFO overflowed, restarting the analysis");
+            analyzer.clear();
+        }
+        for frame in &frames[..read.frames] {
+            if let Some(accel) = frame.accel
+                && analyzer.push(accel)
+            {
+                let analysis = analyzer.analyze().unwrap();
+                info!("{}", analysis);
+
+                for (row, (axis, name)) in [(Axis::X, "X"), (Axis::Y, "Y"), (Axis::Z, "Z")]
+                    .into_iter()
+                    .enumerate()
+                {
+                    let top = row as i32 * ROW_HEIGHT;
+                    let axis_analysis = analysis.axis(axis);
+
+                    let mut text = heapless::String::<32>::new();
+                    match axis_analysis.peaks[0] {
+                        Some(peak) => core::write!(
+                            &mut text,
+                            "{} {:5.1}Hz {:.2}g     ",
+                            name,
+                            peak.frequency,
+                            axis_analysis.rms
+                        ),
+                        None => {
+                            core::write!(&mut text, "{} --- {:.2}g     ", name, axis_analysis.rms)
+                        }
+                    }
+                    .unwrap();
+                    Text::new(&text, Point::new(0, top + 8), style)
+                        .draw(&mut screen)
+                        .unwrap();
+
+                    // Draw every bar from the bottom, and clear
+                    // the part above it
+                    analyzer.bars(axis, &mut bars);
+                    let bottom = top + 12 + BAR_HEIGHT as i32;
+                    for (index, bar) in bars.iter().enumerate() {
+                        let height = ((bar / FULL_SCALE).min(1f32) * BAR_HEIGHT as f32) as u32;
+                        let x = index as i32 * BAR_WIDTH as i32;
+                        screen
+                            .fill_solid(
+                                &Rectangle::new(
+                                    Point::new(x, bottom - BAR_HEIGHT as i32),
+                                    Size::new(BAR_WIDTH - 1, BAR_HEIGHT - height),
+                                ),
+                                Rgb565::BLACK,
+                            )
+                            .unwrap();
+                        screen
+                            .fill_solid(
+                                &Rectangle::new(
+                                    Point::new(x, bottom - height as i32),
+                                    Size::new(BAR_WIDTH - 1, height),
+                                ),
+                                Rgb565::GREEN,
+                            )
+                            .unwrap();
+                    }
+                }
+            }
+        }
+    }
+}
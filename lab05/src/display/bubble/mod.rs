@@ -0,0 +1,126 @@
+//! Spirit level bubble.
+//!
+//! The [`Bubble`] draws the round vial of a bubble level, seen from
+//! above: a circle with a cross and a ring in the middle, and a bubble
+//! that moves away from the center as the board tilts. When the board
+//! is level, the bubble is inside the ring and changes its color.
+//!
+//! ```text
+//!       .-----.
+//!     /    |    \
+//!    |   ( o )   |   <- the bubble in the ring, level
+//!    |-----+-----|
+//!    |     |     |
+//!     \    |    /
+//!       '-----'
+//! ```
+//!
+//! The bubble moves in the screen's axes, the application converts
+//! the tilt of the board (see `crate::inclinometer`) to angles along
+//! the screen's X and Y axes. A bubble at [`Config::range`] degrees is
+//! at the edge of the vial, larger angles keep it at the edge.
+//!
+//! [`Bubble::draw`] draws the vial once and then only the bubble: it
+//! redraws the vial in the bubble's previous area and draws the bubble
+//! in its new position.
+//!
+//! ```ignore
+//! let mut bubble = Bubble::new(Point::new(64, 64), 50, Config::default());
+//! loop {
+//!     let tilt = inclinometer.update(mpu6500.read_acceleration()?, Instant::now());
+//!     bubble.set_position(tilt.roll, -tilt.pitch, inclinometer.is_level());
+//!     bubble.draw(&mut screen)?;
+//! }
+//! ```
+
+mod widget;
+
+#[cfg(test)]
+mod tests;
+
+pub use widget::Bubble;
+
+use embedded_graphics::{pixelcolor::Rgb565, prelude::RgbColor};
+
+/// The configuration of the bubble level.
+#[derive(Copy, Clone)]
+pub struct Config {
+    /// The angle in deg that moves the bubble to the edge of the vial
+    pub range: f32,
+    /// The radius of the bubble in pixels
+    pub bubble_radius: u32,
+    /// The color of the inside of the vial
+    pub background: Rgb565,
+    /// The color of the vial's outline, cross and ring
+    pub lines: Rgb565,
+    /// The color of the bubble
+    pub bubble: Rgb565,
+    /// The color of the bubble when the board is level
+    pub level: Rgb565,
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config {
+            range: 10.0,
+            bubble_radius: 7,
+            background: Rgb565::BLACK,
+            lines: Rgb565::WHITE,
+            bubble: Rgb565::YELLOW,
+            level: Rgb565::GREEN,
+        }
+    }
+}
+
+/// Builder functions
+impl Config {
+    /// Sets the angle that moves the bubble
+    /// to the edge of the vial
+    pub fn range(self, value: f32) -> Config {
+        Config {
+            range: value,
+            ..self
+        }
+    }
+
+    /// Sets the radius of the bubble
+    pub fn bubble_radius(self, value: u32) -> Config {
+        Config {
+            bubble_radius: value,
+            ..self
+        }
+    }
+
+    /// Sets the color of the inside of the vial
+    pub fn background(self, value: Rgb565) -> Config {
+        Config {
+            background: value,
+            ..self
+        }
+    }
+
+    /// Sets the color of the outline, cross and ring
+    pub fn lines(self, value: Rgb565) -> Config {
+        Config {
+            lines: value,
+            ..self
+        }
+    }
+
+    /// Sets the color of the bubble
+    pub fn bubble(self, value: Rgb565) -> Config {
+        Config {
+            bubble: value,
+            ..self
+        }
+    }
+
+    /// Sets the color of the bubble when the
+    /// board is level
+    pub fn level(self, value: Rgb565) -> Config {
+        Config {
+            level: value,
+            ..self
+        }
+    }
+}
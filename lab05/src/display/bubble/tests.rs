@@ -0,0 +1,82 @@
+//! Host tests for the bubble level.
+//!
+//! The bubble level is drawn into the `embedded_graphics` mock
+//! display, a vial of 30 pixels around (31, 31), and its pixels
+//! are checked.
+
+use embedded_graphics::{
+    mock_display::MockDisplay,
+    pixelcolor::Rgb565,
+    prelude::{Point, RgbColor, Size},
+    primitives::Rectangle,
+};
+
+use crate::display::bubble::{Bubble, Config};
+
+const CENTER: Point = Point::new(31, 31);
+
+fn new_bubble() -> Bubble {
+    Bubble::new(CENTER, 30, Config::default())
+}
+
+/// Draws the bubble level into an empty display, the
+/// display contains only the pixels drawn
+fn render(bubble: &mut Bubble) -> MockDisplay<Rgb565> {
+    let mut display = MockDisplay::new();
+    display.set_allow_overdraw(true);
+    bubble.draw(&mut display).unwrap();
+    display
+}
+
+#[test]
+fn the_vial_is_drawn_with_the_bubble_in_the_middle() {
+    let mut bubble = new_bubble();
+    bubble.set_position(0.0, 0.0, true);
+    let display = render(&mut bubble);
+    assert_eq!(display.affected_area(), bubble.bounding_box());
+    assert_eq!(display.get_pixel(CENTER), Some(Rgb565::GREEN));
+    // The cross and the inside of the vial
+    assert_eq!(display.get_pixel(Point::new(31, 5)), Some(Rgb565::WHITE));
+    assert_eq!(display.get_pixel(Point::new(15, 15)), Some(Rgb565::BLACK));
+}
+
+#[test]
+fn the_bubble_stays_inside_the_vial() {
+    let mut bubble = new_bubble();
+    // 30 - 7 - 1 pixels at most from the center
+    bubble.set_position(100.0, 0.0, false);
+    assert_eq!(bubble.position(), CENTER + Point::new(22, 0));
+    bubble.set_position(10.0, 10.0, false);
+    assert_eq!(bubble.position(), CENTER + Point::new(16, 16));
+    bubble.set_position(-5.0, 2.5, false);
+    assert_eq!(bubble.position(), CENTER + Point::new(-11, 6));
+
+    let display = render(&mut bubble);
+    assert_eq!(
+        display.get_pixel(CENTER + Point::new(-11, 6)),
+        Some(Rgb565::YELLOW)
+    );
+}
+
+#[test]
+fn only_the_previous_and_the_new_bubble_are_drawn() {
+    let mut bubble = new_bubble();
+    render(&mut bubble);
+    assert_eq!(render(&mut bubble).affected_area().size, Size::zero());
+
+    bubble.set_position(2.0, 0.0, false);
+    let display = render(&mut bubble);
+    assert_eq!(
+        display.affected_area(),
+        Rectangle::new(Point::new(24, 24), Size::new(19, 15))
+    );
+    // The cross and the inside of the vial under the previous bubble
+    assert_eq!(display.get_pixel(Point::new(24, 31)), Some(Rgb565::WHITE));
+    assert_eq!(display.get_pixel(Point::new(26, 37)), Some(Rgb565::BLACK));
+    assert_eq!(display.get_pixel(Point::new(35, 31)), Some(Rgb565::YELLOW));
+
+    // The level changes only the color
+    bubble.set_position(2.0, 0.0, true);
+    let display = render(&mut bubble);
+    assert_eq!(display.get_pixel(Point::new(35, 31)), Some(Rgb565::GREEN));
+}
@@ -0,0 +1,156 @@
+//! The bubble level widget.
+
+use embedded_graphics::{
+    Drawable,
+    draw_target::{DrawTarget, DrawTargetExt},
+    pixelcolor::Rgb565,
+    prelude::{Dimensions, Point, Primitive},
+    primitives::{Circle, Line, PrimitiveStyle, PrimitiveStyleBuilder, Rectangle},
+};
+
+use crate::display::bubble::Config;
+
+/// A round bubble level.
+pub struct Bubble {
+    center: Point,
+    radius: u32,
+    config: Config,
+    /// The position of the bubble, relative to the center
+    offset: Point,
+    level: bool,
+    /// Whether the vial was drawn
+    vial_drawn: bool,
+    /// The position and the level of the bubble drawn,
+    /// `None` if the bubble has to be drawn
+    drawn: Option<(Point, bool)>,
+}
+
+impl Bubble {
+    /// Creates a bubble level with a vial of `radius`
+    /// pixels around `center`, the bubble is in the center
+    pub fn new(center: Point, radius: u32, config: Config) -> Bubble {
+        assert!(
+            radius > config.bubble_radius + 1,
+            "the bubble has to be smaller than the vial"
+        );
+        Bubble {
+            center,
+            radius,
+            config,
+            offset: Point::zero(),
+            level: false,
+            vial_drawn: false,
+            drawn: None,
+        }
+    }
+
+    /// Returns the configuration
+    pub fn config(&self) -> Config {
+        self.config
+    }
+
+    /// Returns the screen area of the bubble level
+    pub fn bounding_box(&self) -> Rectangle {
+        self.vial().bounding_box()
+    }
+
+    /// Moves the bubble `x` deg to the right and `y` deg down,
+    /// `level` changes its color
+    pub fn set_position(&mut self, x: f32, y: f32, level: bool) {
+        let max = (self.radius - self.config.bubble_radius - 1) as f32;
+        let (mut x, mut y) = (x / self.config.range * max, y / self.config.range * max);
+        // The bubble stays inside the vial
+        let distance = libm::sqrtf(x * x + y * y);
+        if distance > max {
+            x *= max / distance;
+            y *= max / distance;
+        }
+        self.offset = Point::new(libm::roundf(x) as i32, libm::roundf(y) as i32);
+        self.level = level;
+    }
+
+    /// Returns the position of the bubble's center on the screen
+    pub fn position(&self) -> Point {
+        self.center + self.offset
+    }
+
+    /// Makes the next [`Bubble::draw`] draw the whole bubble level.
+    ///
+    /// Use this after something else was drawn over the
+    /// bubble level, like after clearing the screen.
+    pub fn invalidate(&mut self) {
+        self.vial_drawn = false;
+        self.drawn = None;
+    }
+
+    /// Draws the bubble if it moved since the last draw
+    pub fn draw<D: DrawTarget<Color = Rgb565>>(&mut self, target: &mut D) -> Result<(), D::Error> {
+        if !self.vial_drawn {
+            self.draw_vial(target)?;
+            self.vial_drawn = true;
+            self.drawn = None;
+        }
+        if self.drawn == Some((self.offset, self.level)) {
+            return Ok(());
+        }
+
+        // Draw the vial over the previous bubble
+        if let Some((offset, _)) = self.drawn {
+            let area = self.bubble(offset).bounding_box();
+            self.draw_vial(&mut target.clipped(&area))?;
+        }
+        let color = if self.level {
+            self.config.level
+        } else {
+            self.config.bubble
+        };
+        self.bubble(self.offset)
+            .into_styled(PrimitiveStyle::with_fill(color))
+            .draw(target)?;
+        self.drawn = Some((self.offset, self.level));
+        Ok(())
+    }
+}
+
+/// Private API
+impl Bubble {
+    fn vial(&self) -> Circle {
+        Circle::with_center(self.center, 2 * self.radius + 1)
+    }
+
+    fn bubble(&self, offset: Point) -> Circle {
+        Circle::with_center(self.center + offset, 2 * self.config.bubble_radius + 1)
+    }
+
+    /// Draws the vial without the bubble
+    fn draw_vial<D: DrawTarget<Color = Rgb565>>(&self, target: &mut D) -> Result<(), D::Error> {
+        let lines = PrimitiveStyle::with_stroke(self.config.lines, 1);
+        self.vial()
+            .into_styled(
+                PrimitiveStyleBuilder::new()
+                    .fill_color(self.config.background)
+                    .stroke_color(self.config.lines)
+                    .stroke_width(1)
+                    .build(),
+            )
+            .draw(target)?;
+
+        // The cross and the ring that the bubble is in when level
+        let radius = self.radius as i32;
+        Line::new(
+            self.center - Point::new(radius, 0),
+            self.center + Point::new(radius, 0),
+        )
+        .into_styled(lines)
+        .draw(target)?;
+        Line::new(
+            self.center - Point::new(0, radius),
+            self.center + Point::new(0, radius),
+        )
+        .into_styled(lines)
+        .draw(target)?;
+        Circle::with_center(self.center, 2 * (self.config.bubble_radius + 2) + 1)
+            .into_styled(lines)
+            .draw(target)
+    }
+}
@@ -0,0 +1,130 @@
+//! Scrolling log console.
+//!
+//! When the board runs without a debugger, the `defmt` messages are
+//! lost. The [`Console`] displays log messages on the screen, the last
+//! messages at the bottom, every line in the color of its [`Level`].
+//!
+//! ```text
+//! Device started          <- info, white
+//! Sample rate 100 Hz
+//! FIFO overflow, 12       <- warning, yellow
+//! frames lost
+//! Sensor not found        <- error, red
+//! ```
+//!
+//! The console keeps the last `L` lines in a ring buffer, more than
+//! the screen displays, so that older lines can be scrolled back
+//! with [`Console::scroll`]. Lines longer than the width of the
+//! console wrap, the text is cut only at the width, not at words.
+//!
+//! Besides [`Console::log`], the console is a [`core::fmt::Write`]
+//! sink, [`Console::writer`] formats messages with `write!`, just as
+//! the examples format text into a `heapless::String`. The text
+//! written continues the last line until a new line (`\n`).
+//!
+//! [`Console::draw`] redraws only the rows that changed. A new line
+//! scrolls the console and redraws all the rows, text written to the
+//! last line redraws only that row.
+//!
+//! ```ignore
+//! let mut console = Console::<32, 21>::new(
+//!     Point::zero(),
+//!     Size::new(128, 160),
+//!     Config::default(),
+//! );
+//! console.log(Level::Info, "Device started");
+//! core::write!(console.writer(Level::Warn), "Rate {} Hz\n", rate)?;
+//! console.draw(&mut screen)?;
+//! ```
+
+mod widget;
+
+#[cfg(test)]
+mod tests;
+
+pub use widget::{Console, Writer};
+
+use embedded_graphics::{
+    mono_font::{MonoFont, ascii::FONT_6X10},
+    pixelcolor::Rgb565,
+    prelude::RgbColor,
+};
+
+/// The largest number of rows displayed
+pub const MAX_ROWS: usize = 32;
+
+/// The level of a log line, the same as the `defmt` levels.
+///
+/// [`Copy`] and [`Clone`] are derived so that the value
+/// can be easily copied.
+///
+/// [`PartialEq`], [`Debug`] and [`defmt::Format`] are derived
+/// so that the values can be compared and printed.
+#[derive(Copy, Clone, PartialEq, Debug, defmt::Format)]
+pub enum Level {
+    Trace,
+    Debug,
+    Info,
+    Warn,
+    Error,
+}
+
+/// The configuration of the console.
+#[derive(Copy, Clone)]
+pub struct Config {
+    /// The color of the background
+    pub background: Rgb565,
+    /// The colors of the levels, in the order of [`Level`]
+    pub colors: [Rgb565; 5],
+    /// The font of the text
+    pub font: &'static MonoFont<'static>,
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config {
+            background: Rgb565::BLACK,
+            colors: [
+                Rgb565::new(12, 24, 12), // gray
+                Rgb565::CYAN,
+                Rgb565::WHITE,
+                Rgb565::YELLOW,
+                Rgb565::RED,
+            ],
+            font: &FONT_6X10,
+        }
+    }
+}
+
+/// Builder functions
+impl Config {
+    /// Sets the color of the background
+    pub fn background(self, value: Rgb565) -> Config {
+        Config {
+            background: value,
+            ..self
+        }
+    }
+
+    /// Sets the color of a level
+    pub fn color(self, level: Level, value: Rgb565) -> Config {
+        let mut colors = self.colors;
+        colors[level as usize] = value;
+        Config { colors, ..self }
+    }
+
+    /// Sets the font of the text
+    pub fn font(self, value: &'static MonoFont<'static>) -> Config {
+        Config {
+            font: value,
+            ..self
+        }
+    }
+}
+
+impl Config {
+    /// Returns the color of the lines of `level`
+    pub fn level_color(&self, level: Level) -> Rgb565 {
+        self.colors[level as usize]
+    }
+}
@@ -0,0 +1,176 @@
+//! Host tests for the console.
+//!
+//! The lines are checked without a display, the rendering tests
+//! draw the console into the `embedded_graphics` mock display.
+
+use core::fmt::Write;
+
+use embedded_graphics::{
+    Drawable,
+    mock_display::MockDisplay,
+    mono_font::{MonoTextStyle, ascii::FONT_4X6},
+    pixelcolor::Rgb565,
+    prelude::{Point, RgbColor, Size},
+    primitives::{PointsIter, Rectangle},
+    text::{Baseline, Text, renderer::CharacterStyle},
+};
+
+use crate::display::console::{Config, Console, Level};
+
+/// A console of 3 rows of 5 characters that keeps 4 lines
+fn new_console() -> Console<4, 8> {
+    Console::new(
+        Point::zero(),
+        Size::new(20, 18),
+        Config::default().font(&FONT_4X6),
+    )
+}
+
+fn lines<const L: usize, const C: usize>(console: &Console<L, C>) -> [Option<(&str, Level)>; L] {
+    core::array::from_fn(|index| console.line(index))
+}
+
+/// Draws the console into an empty display, the display
+/// contains only the pixels drawn
+fn render<const L: usize, const C: usize>(console: &mut Console<L, C>) -> MockDisplay<Rgb565> {
+    let mut display = MockDisplay::new();
+    console.draw(&mut display).unwrap();
+    display
+}
+
+#[test]
+fn long_lines_wrap() {
+    let mut console = new_console();
+    assert_eq!((console.columns(), console.rows()), (5, 3));
+    console.log(Level::Info, "abcdefgh");
+    console.log(Level::Warn, "ab\ncd");
+    assert_eq!(
+        lines(&console),
+        [
+            Some(("abcde", Level::Info)),
+            Some(("fgh", Level::Info)),
+            Some(("ab", Level::Warn)),
+            Some(("cd", Level::Warn)),
+        ]
+    );
+}
+
+#[test]
+fn the_writer_continues_the_line_until_a_new_line() {
+    let mut console = new_console();
+    write!(console.writer(Level::Info), "x={}", 1).unwrap();
+    write!(console.writer(Level::Info), "2\n\ny").unwrap();
+    // Another level starts a new line
+    write!(console.writer(Level::Error), "!").unwrap();
+    assert_eq!(
+        lines(&console),
+        [
+            Some(("x=12", Level::Info)),
+            Some(("", Level::Info)),
+            Some(("y", Level::Info)),
+            Some(("!", Level::Error)),
+        ]
+    );
+
+    // A message always starts on a new line
+    console.clear();
+    write!(console.writer(Level::Debug), "a").unwrap();
+    console.log(Level::Debug, "b");
+    assert_eq!(console.line(1), Some(("b", Level::Debug)));
+    assert_eq!(console.len(), 2);
+}
+
+#[test]
+fn the_oldest_lines_are_removed() {
+    let mut console = new_console();
+    for line in ["1", "2", "3", "4", "5", "6"] {
+        console.log(Level::Info, line);
+    }
+    assert_eq!(
+        lines(&console).map(|line| line.map(|(text, _)| text)),
+        [Some("3"), Some("4"), Some("5"), Some("6")]
+    );
+}
+
+/// Draws the reference image of `rows`, every row is
+/// 4 x 6 text and black up to the width of the console
+fn reference(rows: &[(&str, Rgb565)]) -> MockDisplay<Rgb565> {
+    let mut reference = MockDisplay::new();
+    for (index, (text, color)) in rows.iter().enumerate() {
+        let y = index as i32 * 6;
+        let mut style = MonoTextStyle::new(&FONT_4X6, *color);
+        style.set_background_color(Some(Rgb565::BLACK));
+        Text::with_baseline(text, Point::new(0, y), style, Baseline::Top)
+            .draw(&mut reference)
+            .unwrap();
+        for point in Rectangle::new(
+            Point::new(text.len() as i32 * 4, y),
+            Size::new(20 - text.len() as u32 * 4, 6),
+        )
+        .points()
+        {
+            reference.set_pixel(point, Some(Rgb565::BLACK));
+        }
+    }
+    reference
+}
+
+#[test]
+fn lines_are_drawn_from_the_bottom_in_the_colors_of_their_level() {
+    let mut console = new_console();
+    console.log(Level::Warn, "warn");
+    console.log(Level::Error, "error");
+    render(&mut console).assert_eq(&reference(&[
+        ("", Rgb565::BLACK),
+        ("warn", Rgb565::YELLOW),
+        ("error", Rgb565::RED),
+    ]));
+}
+
+#[test]
+fn only_the_changed_rows_are_drawn() {
+    let mut console = new_console();
+    write!(console.writer(Level::Info), "a").unwrap();
+    render(&mut console);
+    assert_eq!(render(&mut console).affected_area().size, Size::zero());
+
+    // Text written to the last line redraws only the last row
+    write!(console.writer(Level::Info), "b").unwrap();
+    assert_eq!(
+        render(&mut console).affected_area(),
+        Rectangle::new(Point::new(0, 12), Size::new(20, 6))
+    );
+
+    // A new line scrolls the rows, the first row stays empty
+    console.log(Level::Info, "c");
+    assert_eq!(
+        render(&mut console).affected_area(),
+        Rectangle::new(Point::new(0, 6), Size::new(20, 12))
+    );
+
+    console.invalidate();
+    assert_eq!(render(&mut console).affected_area(), console.bounding_box());
+}
+
+#[test]
+fn scrolling_back_displays_older_lines() {
+    let mut console = Console::<8, 8>::new(
+        Point::zero(),
+        Size::new(20, 18),
+        Config::default().font(&FONT_4X6),
+    );
+    for line in ["1", "2", "3", "4"] {
+        console.log(Level::Info, line);
+    }
+    // 4 lines and 3 rows, it scrolls back at most a line
+    console.scroll(5);
+    let rows = |texts: [&'static str; 3]| texts.map(|text| (text, Rgb565::WHITE));
+    render(&mut console).assert_eq(&reference(&rows(["1", "2", "3"])));
+
+    // New lines do not move the scrolled back lines
+    console.log(Level::Info, "5");
+    assert_eq!(render(&mut console).affected_area().size, Size::zero());
+
+    console.scroll_to_end();
+    render(&mut console).assert_eq(&reference(&rows(["3", "4", "5"])));
+}
@@ -0,0 +1,258 @@
+//! The console widget.
+
+use embedded_graphics::{
+    Drawable,
+    draw_target::DrawTarget,
+    mono_font::MonoTextStyle,
+    pixelcolor::Rgb565,
+    prelude::{Point, Size},
+    primitives::Rectangle,
+    text::{Baseline, Text, renderer::CharacterStyle},
+};
+
+use crate::display::console::{Config, Level, MAX_ROWS};
+
+/// A line of the console, at most `C` characters
+struct Line<const C: usize> {
+    /// Identifies the line, the rows displaying another
+    /// line have to be drawn
+    id: u32,
+    level: Level,
+    text: heapless::String<C>,
+}
+
+/// What a row displays: the id of the line and the
+/// length of its text, `None` if the row is empty
+type Row = Option<(u32, usize)>;
+
+/// A console that keeps the last `L` lines of at most `C` characters.
+pub struct Console<const L: usize, const C: usize> {
+    top_left: Point,
+    size: Size,
+    config: Config,
+    lines: heapless::Deque<Line<C>, L>,
+    /// The id of the next line
+    next_id: u32,
+    /// Whether the text written continues the last line
+    open: bool,
+    /// The number of lines scrolled back from the last line
+    scroll: usize,
+    /// What every row displays, `None` if the row has to be drawn
+    drawn: [Option<Row>; MAX_ROWS],
+}
+
+/// A [`core::fmt::Write`] sink that writes lines of a level
+/// to the console, returned by [`Console::writer`].
+pub struct Writer<'a, const L: usize, const C: usize> {
+    console: &'a mut Console<L, C>,
+    level: Level,
+}
+
+impl<const L: usize, const C: usize> Console<L, C> {
+    /// Creates a new console that fills the `top_left`, `size` area.
+    ///
+    /// The lines are as wide as the area, at most `C` characters.
+    pub fn new(top_left: Point, size: Size, config: Config) -> Console<L, C> {
+        let console = Console {
+            top_left,
+            size,
+            config,
+            lines: heapless::Deque::new(),
+            next_id: 0,
+            open: false,
+            scroll: 0,
+            drawn: [None; MAX_ROWS],
+        };
+        assert!(
+            console.columns() > 0 && console.rows() > 0,
+            "the console has to display at least a character"
+        );
+        console
+    }
+
+    /// Returns the configuration
+    pub fn config(&self) -> Config {
+        self.config
+    }
+
+    /// Returns the screen area of the console
+    pub fn bounding_box(&self) -> Rectangle {
+        Rectangle::new(self.top_left, self.size)
+    }
+
+    /// Returns the number of characters of a line
+    pub fn columns(&self) -> usize {
+        ((self.size.width / self.config.font.character_size.width) as usize).min(C)
+    }
+
+    /// Returns the number of lines displayed
+    pub fn rows(&self) -> usize {
+        ((self.size.height / self.config.font.character_size.height) as usize).min(MAX_ROWS)
+    }
+
+    /// Returns the number of lines kept
+    pub fn len(&self) -> usize {
+        self.lines.len()
+    }
+
+    /// Returns `true` if the console has no lines
+    pub fn is_empty(&self) -> bool {
+        self.lines.is_empty()
+    }
+
+    /// Returns the text and the level of a line,
+    /// the oldest line kept is `0`
+    pub fn line(&self, index: usize) -> Option<(&str, Level)> {
+        self.lines
+            .get(index)
+            .map(|line| (line.text.as_str(), line.level))
+    }
+
+    /// Adds a message on a new line.
+    ///
+    /// The `\n` characters in the message start new lines.
+    pub fn log(&mut self, level: Level, message: &str) {
+        self.open = false;
+        self.write(level, message);
+        self.open = false;
+    }
+
+    /// Returns a [`core::fmt::Write`] sink that writes
+    /// text of `level`
+    pub fn writer(&mut self, level: Level) -> Writer<'_, L, C> {
+        Writer {
+            console: self,
+            level,
+        }
+    }
+
+    /// Removes all the lines
+    pub fn clear(&mut self) {
+        self.lines.clear();
+        self.open = false;
+        self.scroll = 0;
+    }
+
+    /// Scrolls back `lines` lines (negative values scroll forward),
+    /// the last line cannot be scrolled above the bottom.
+    ///
+    /// New lines are displayed only when the console is scrolled
+    /// to the end, see [`Console::scroll_to_end`].
+    pub fn scroll(&mut self, lines: isize) {
+        let max = self.lines.len().saturating_sub(self.rows());
+        self.scroll = self.scroll.saturating_add_signed(lines).min(max);
+    }
+
+    /// Displays the last lines
+    pub fn scroll_to_end(&mut self) {
+        self.scroll = 0;
+    }
+
+    /// Makes the next [`Console::draw`] draw the whole console.
+    ///
+    /// Use this after something else was drawn over the
+    /// console, like after clearing the screen.
+    pub fn invalidate(&mut self) {
+        self.drawn = [None; MAX_ROWS];
+    }
+
+    /// Draws the rows that changed since the last draw
+    pub fn draw<D: DrawTarget<Color = Rgb565>>(&mut self, target: &mut D) -> Result<(), D::Error> {
+        let rows = self.rows();
+        // The index of the line displayed on the first row, the
+        // last line is on the last row, even with fewer lines
+        let first = self.lines.len() as isize - (self.scroll + rows) as isize;
+        for index in 0..rows {
+            let line = usize::try_from(first + index as isize)
+                .ok()
+                .and_then(|line| self.lines.get(line));
+            let row = line.map(|line| (line.id, line.text.len()));
+            if self.drawn[index] != Some(row) {
+                self.draw_row(target, index, line)?;
+                self.drawn[index] = Some(row);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Private API
+impl<const L: usize, const C: usize> Console<L, C> {
+    /// Writes text at the end of the last line
+    fn write(&mut self, level: Level, text: &str) {
+        for character in text.chars() {
+            match character {
+                '\r' => continue,
+                '\n' => {
+                    if !self.open {
+                        self.new_line(level);
+                    }
+                    self.open = false;
+                }
+                character => {
+                    let full = self.lines.back().is_none_or(|line| {
+                        line.level != level || line.text.len() >= self.columns()
+                    });
+                    if !self.open || full {
+                        self.new_line(level);
+                        self.open = true;
+                    }
+                    // The font has only ASCII characters
+                    let character = if character.is_ascii() { character } else { '?' };
+                    let _ = self.lines.back_mut().unwrap().text.push(character);
+                }
+            }
+        }
+    }
+
+    /// Adds an empty line, removing the oldest one if the buffer is full
+    fn new_line(&mut self, level: Level) {
+        if self.lines.is_full() {
+            self.lines.pop_front();
+        }
+        let line = Line {
+            id: self.next_id,
+            level,
+            text: heapless::String::new(),
+        };
+        self.next_id = self.next_id.wrapping_add(1);
+        let _ = self.lines.push_back(line);
+        // Keep the scrolled back lines on the screen
+        if self.scroll > 0 {
+            self.scroll(1);
+        }
+    }
+
+    fn draw_row<D: DrawTarget<Color = Rgb565>>(
+        &self,
+        target: &mut D,
+        index: usize,
+        line: Option<&Line<C>>,
+    ) -> Result<(), D::Error> {
+        let char_size = self.config.font.character_size;
+        let top_left = self.top_left + Point::new(0, (index as u32 * char_size.height) as i32);
+        let mut width = 0;
+        if let Some(line) = line {
+            let mut style =
+                MonoTextStyle::new(self.config.font, self.config.level_color(line.level));
+            style.set_background_color(Some(self.config.background));
+            Text::with_baseline(&line.text, top_left, style, Baseline::Top).draw(target)?;
+            width = line.text.len() as u32 * char_size.width;
+        }
+        // Clear the rest of the row
+        target.fill_solid(
+            &Rectangle::new(
+                top_left + Point::new(width as i32, 0),
+                Size::new(self.size.width - width, char_size.height),
+            ),
+            self.config.background,
+        )
+    }
+}
+
+impl<const L: usize, const C: usize> core::fmt::Write for Writer<'_, L, C> {
+    fn write_str(&mut self, text: &str) -> core::fmt::Result {
+        self.console.write(self.level, text);
+        Ok(())
+    }
+}
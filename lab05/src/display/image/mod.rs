@@ -0,0 +1,68 @@
+//! Images and sprites stored in flash.
+//!
+//! The images are embedded in the program with `include_bytes!` in the
+//! [QOI] format (*Quite OK Image*). QOI compresses about as well as PNG
+//! but decodes with a few lines of code, every pixel is either a
+//! repetition of the previous pixel, a small difference from it, a
+//! pixel seen recently or the full color. The 48 x 48 logo of the
+//! examples takes 4.5 KB as RGB565 and 2.2 KB as QOI.
+//!
+//! The screen is 128 x 160 pixels, a copy of it in RAM takes 40 KB, so
+//! the images are not decoded into a buffer. [`Qoi`] decodes the image
+//! while drawing it, pixel by pixel, and sends the pixels to the display
+//! as they are decoded.
+//!
+//! ```text
+//! flash --include_bytes!--> Qoi --decode--> pixels --fill_contiguous--> display
+//! ```
+//!
+//! QOI images with an alpha channel (RGBA) have transparent pixels,
+//! which are not drawn. The display cannot blend colors, so a pixel is
+//! either transparent (alpha below 128) or opaque.
+//!
+//! [`Qoi`] is an `embedded_graphics` [`ImageDrawable`], it is drawn with
+//! an [`Image`], like the other `embedded_graphics` images, and parts of
+//! it with [`ImageDrawableExt::sub_image`]. A [`SpriteSheet`] is an
+//! image that stores the frames of an animation next to each other and
+//! an [`Animation`] displays its frames one after the other.
+//!
+//! ```ignore
+//! static LOGO: &[u8] = include_bytes!("../../assets/logo.qoi");
+//!
+//! let logo = Qoi::new(LOGO)?;
+//! Image::new(&logo, Point::new(32, 16)).draw(&mut screen)?;
+//! ```
+//!
+//! PNG files are converted to QOI with the `qoiconv` tool from the
+//! [QOI] reference implementation or with an image editor like GIMP.
+//!
+//! [QOI]: https://qoiformat.org
+//! [`ImageDrawable`]: embedded_graphics::image::ImageDrawable
+//! [`Image`]: embedded_graphics::image::Image
+//! [`ImageDrawableExt::sub_image`]: embedded_graphics::image::ImageDrawableExt::sub_image
+
+mod qoi;
+mod sprite;
+
+#[cfg(test)]
+mod tests;
+
+pub use qoi::{Pixels, Qoi};
+pub use sprite::{Animation, SpriteSheet};
+
+/// The image errors.
+///
+/// [`Debug`] and [`defmt::Format`] are derived so that
+/// the errors can be printed, [`PartialEq`] so that
+/// they can be compared.
+#[derive(Debug, PartialEq, defmt::Format)]
+pub enum Error {
+    /// The data does not start with a QOI header, or
+    /// the header describes an image without pixels
+    InvalidHeader,
+    /// The data ends before the last pixel of the image
+    Truncated,
+    /// The size of the sprite sheet is not a multiple
+    /// of the size of the frames
+    FrameSize,
+}
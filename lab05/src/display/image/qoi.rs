@@ -0,0 +1,305 @@
+//! The QOI decoder, see the [specification](https://qoiformat.org/qoi-specification.pdf).
+
+use embedded_graphics::{
+    draw_target::DrawTarget,
+    image::ImageDrawable,
+    pixelcolor::{Rgb565, Rgb888},
+    prelude::{Dimensions, OriginDimensions, Point, RgbColor, Size},
+    primitives::Rectangle,
+};
+
+use crate::display::image::Error;
+
+/// The first bytes of a QOI file
+const MAGIC: &[u8] = b"qoif";
+
+/// The length of the header, the magic, the width, the
+/// height, the channels and the color space
+const HEADER_LEN: usize = 14;
+
+// The tags of the chunks, the 8 bit tags are checked first
+const OP_RGB: u8 = 0xfe;
+const OP_RGBA: u8 = 0xff;
+const OP_INDEX: u8 = 0x00;
+const OP_DIFF: u8 = 0x40;
+const OP_LUMA: u8 = 0x80;
+
+/// The mask of the 2 bit tags
+const TAG_MASK: u8 = 0xc0;
+
+/// The pixels with an alpha below this value are transparent
+const ALPHA_THRESHOLD: u8 = 128;
+
+/// The largest number of pixels of an image with
+/// transparent pixels sent to the display at once
+const RUN_LEN: usize = 32;
+
+/// A QOI image.
+///
+/// [`Copy`] and [`Clone`] are derived so that the image
+/// can be easily copied, it only refers to the data.
+#[derive(Copy, Clone)]
+pub struct Qoi<'a> {
+    /// The chunks, the data after the header
+    chunks: &'a [u8],
+    size: Size,
+    /// `true` if the image has no transparent pixels
+    opaque: bool,
+}
+
+/// The pixels of a [`Qoi`] image, row by row, returned
+/// by [`Qoi::pixels`].
+///
+/// The transparent pixels are `None`.
+pub struct Pixels<'a> {
+    chunks: &'a [u8],
+    /// The position of the next chunk
+    position: usize,
+    /// The number of pixels not yet returned
+    remaining: u32,
+    /// The last pixel decoded, RGBA
+    previous: [u8; 4],
+    /// The pixels seen recently, by their hash
+    index: [[u8; 4]; 64],
+    /// The number of times the last pixel is repeated
+    run: u8,
+}
+
+impl<'a> Qoi<'a> {
+    /// Reads the header of a QOI image and checks that `data`
+    /// holds all its pixels.
+    ///
+    /// The image is decoded once, it is not stored.
+    pub fn new(data: &'a [u8]) -> Result<Qoi<'a>, Error> {
+        let header = data.get(..HEADER_LEN).ok_or(Error::InvalidHeader)?;
+        let width = u32::from_be_bytes([header[4], header[5], header[6], header[7]]);
+        let height = u32::from_be_bytes([header[8], header[9], header[10], header[11]]);
+        let (channels, color_space) = (header[12], header[13]);
+        if &header[..4] != MAGIC
+            || !(3..=4).contains(&channels)
+            || color_space > 1
+            || width == 0
+            || height == 0
+            || width > i32::MAX as u32
+            || height > i32::MAX as u32
+            || width.checked_mul(height).is_none()
+        {
+            return Err(Error::InvalidHeader);
+        }
+
+        let mut image = Qoi {
+            chunks: &data[HEADER_LEN..],
+            size: Size::new(width, height),
+            opaque: true,
+        };
+        let mut pixels = image.pixels();
+        for _ in 0..width * height {
+            let pixel = pixels.next().ok_or(Error::Truncated)?;
+            image.opaque &= pixel.is_some();
+        }
+        Ok(image)
+    }
+
+    /// Returns `true` if the image has no transparent pixels
+    pub fn is_opaque(&self) -> bool {
+        self.opaque
+    }
+
+    /// Returns the pixels, decoded one by one
+    pub fn pixels(&self) -> Pixels<'a> {
+        Pixels {
+            chunks: self.chunks,
+            position: 0,
+            remaining: self.size.width * self.size.height,
+            previous: [0, 0, 0, 255],
+            index: [[0; 4]; 64],
+            run: 0,
+        }
+    }
+}
+
+impl OriginDimensions for Qoi<'_> {
+    fn size(&self) -> Size {
+        self.size
+    }
+}
+
+impl ImageDrawable for Qoi<'_> {
+    type Color = Rgb565;
+
+    fn draw<D>(&self, target: &mut D) -> Result<(), D::Error>
+    where
+        D: DrawTarget<Color = Rgb565>,
+    {
+        self.draw_area(target, &self.bounding_box(), None)
+    }
+
+    fn draw_sub_image<D>(&self, target: &mut D, area: &Rectangle) -> Result<(), D::Error>
+    where
+        D: DrawTarget<Color = Rgb565>,
+    {
+        self.draw_area(target, area, None)
+    }
+}
+
+/// Private API
+impl Qoi<'_> {
+    /// Draws the `area` of the image with its top left corner at the
+    /// origin of the target.
+    ///
+    /// The transparent pixels are drawn with the `background`, if any.
+    pub(crate) fn draw_area<D>(
+        &self,
+        target: &mut D,
+        area: &Rectangle,
+        background: Option<Rgb565>,
+    ) -> Result<(), D::Error>
+    where
+        D: DrawTarget<Color = Rgb565>,
+    {
+        // Like the `embedded_graphics` images, nothing is drawn
+        // if the area is not inside the image
+        if area.is_zero_sized() || self.bounding_box().intersection(area) != *area {
+            return Ok(());
+        }
+
+        let width = self.size.width as usize;
+        let left = area.top_left.x as usize;
+        let columns = left..left + area.size.width as usize;
+        // The pixels of the rows of the area, with their
+        // coordinates relative to the area
+        let pixels = self
+            .pixels()
+            .skip(area.top_left.y as usize * width)
+            .take(area.size.height as usize * width)
+            .enumerate()
+            .filter(|(index, _)| columns.contains(&(index % width)))
+            .map(|(index, pixel)| {
+                let point = Point::new((index % width - left) as i32, (index / width) as i32);
+                (point, pixel)
+            });
+
+        // Without transparent pixels, the area is sent at once
+        if self.opaque || background.is_some() {
+            let background = background.unwrap_or(Rgb565::BLACK);
+            return target.fill_contiguous(
+                &Rectangle::new(Point::zero(), area.size),
+                pixels.map(|(_, pixel)| pixel.unwrap_or(background)),
+            );
+        }
+
+        // Otherwise, the runs of opaque pixels of every row are sent,
+        // the display receives a whole run in one transfer
+        let last_column = area.size.width as i32 - 1;
+        let mut run = heapless::Vec::<Rgb565, RUN_LEN>::new();
+        let mut start = Point::zero();
+        for (point, pixel) in pixels {
+            match pixel {
+                Some(color) => {
+                    if run.is_empty() {
+                        start = point;
+                    }
+                    let _ = run.push(color);
+                    if run.is_full() || point.x == last_column {
+                        draw_run(target, start, &mut run)?;
+                    }
+                }
+                None => draw_run(target, start, &mut run)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Draws a horizontal run of pixels that starts at `start`
+/// and empties it
+fn draw_run<D>(
+    target: &mut D,
+    start: Point,
+    run: &mut heapless::Vec<Rgb565, RUN_LEN>,
+) -> Result<(), D::Error>
+where
+    D: DrawTarget<Color = Rgb565>,
+{
+    if run.is_empty() {
+        return Ok(());
+    }
+    target.fill_contiguous(
+        &Rectangle::new(start, Size::new(run.len() as u32, 1)),
+        run.iter().copied(),
+    )?;
+    run.clear();
+    Ok(())
+}
+
+impl Iterator for Pixels<'_> {
+    type Item = Option<Rgb565>;
+
+    /// Returns the next pixel, or `None` after the last pixel
+    /// or if the data ends before it
+    fn next(&mut self) -> Option<Option<Rgb565>> {
+        if self.remaining == 0 {
+            return None;
+        }
+        if self.run > 0 {
+            self.run -= 1;
+        } else {
+            let pixel = self.decode()?;
+            let [r, g, b, a] = pixel.map(usize::from);
+            self.index[(r * 3 + g * 5 + b * 7 + a * 11) % 64] = pixel;
+            self.previous = pixel;
+        }
+        self.remaining -= 1;
+
+        let [r, g, b, a] = self.previous;
+        Some((a >= ALPHA_THRESHOLD).then(|| Rgb888::new(r, g, b).into()))
+    }
+}
+
+/// Private API
+impl Pixels<'_> {
+    fn byte(&mut self) -> Option<u8> {
+        let byte = *self.chunks.get(self.position)?;
+        self.position += 1;
+        Some(byte)
+    }
+
+    /// Decodes the next chunk and returns its pixel
+    fn decode(&mut self) -> Option<[u8; 4]> {
+        let tag = self.byte()?;
+        let [r, g, b, a] = self.previous;
+        let pixel = match tag {
+            OP_RGB => [self.byte()?, self.byte()?, self.byte()?, a],
+            OP_RGBA => [self.byte()?, self.byte()?, self.byte()?, self.byte()?],
+            _ => match tag & TAG_MASK {
+                OP_INDEX => self.index[tag as usize],
+                // The differences are stored with a bias of 2
+                OP_DIFF => [
+                    r.wrapping_add((tag >> 4) & 0x03).wrapping_sub(2),
+                    g.wrapping_add((tag >> 2) & 0x03).wrapping_sub(2),
+                    b.wrapping_add(tag & 0x03).wrapping_sub(2),
+                    a,
+                ],
+                // The green difference has a bias of 32, the red
+                // and blue differences from it a bias of 8
+                OP_LUMA => {
+                    let next = self.byte()?;
+                    let dg = (tag & !TAG_MASK).wrapping_sub(32);
+                    [
+                        r.wrapping_add(dg).wrapping_add(next >> 4).wrapping_sub(8),
+                        g.wrapping_add(dg),
+                        b.wrapping_add(dg).wrapping_add(next & 0x0f).wrapping_sub(8),
+                        a,
+                    ]
+                }
+                // OP_RUN, the previous pixel repeated 1 to 62
+                // times, with a bias of 1
+                _ => {
+                    self.run = tag & !TAG_MASK;
+                    self.previous
+                }
+            },
+        };
+        Some(pixel)
+    }
+}
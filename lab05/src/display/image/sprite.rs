@@ -0,0 +1,151 @@
+//! Sprite sheets and animations.
+
+use embedded_graphics::{
+    draw_target::{DrawTarget, DrawTargetExt},
+    image::{ImageDrawableExt, SubImage},
+    pixelcolor::Rgb565,
+    prelude::{OriginDimensions, Point, Size},
+    primitives::Rectangle,
+};
+
+use crate::display::image::{Error, Qoi};
+
+/// An image that stores the frames of an animation, all
+/// of the same size, from left to right and from top to bottom.
+///
+/// [`Copy`] and [`Clone`] are derived so that the sprite
+/// sheet can be easily copied.
+#[derive(Copy, Clone)]
+pub struct SpriteSheet<'a> {
+    image: Qoi<'a>,
+    frame_size: Size,
+}
+
+/// Displays the frames of a [`SpriteSheet`] one after the other.
+pub struct Animation<'a> {
+    sheet: SpriteSheet<'a>,
+    position: Point,
+    background: Option<Rgb565>,
+    /// The frame displayed
+    frame: usize,
+    /// The frame drawn, `None` if it has to be drawn
+    drawn: Option<usize>,
+}
+
+impl<'a> SpriteSheet<'a> {
+    /// Creates a sprite sheet from an `image`
+    /// with frames of `frame_size`
+    pub fn new(image: Qoi<'a>, frame_size: Size) -> Result<SpriteSheet<'a>, Error> {
+        let size = image.size();
+        if frame_size.width == 0
+            || frame_size.height == 0
+            || !size.width.is_multiple_of(frame_size.width)
+            || !size.height.is_multiple_of(frame_size.height)
+        {
+            return Err(Error::FrameSize);
+        }
+        Ok(SpriteSheet { image, frame_size })
+    }
+
+    /// Returns the image of the sprite sheet
+    pub fn image(&self) -> &Qoi<'a> {
+        &self.image
+    }
+
+    /// Returns the size of a frame
+    pub fn frame_size(&self) -> Size {
+        self.frame_size
+    }
+
+    /// Returns the number of frames
+    pub fn frames(&self) -> usize {
+        let size = self.image.size();
+        ((size.width / self.frame_size.width) * (size.height / self.frame_size.height)) as usize
+    }
+
+    /// Returns the area of a frame in the image
+    pub fn frame_area(&self, index: usize) -> Rectangle {
+        assert!(index < self.frames(), "the sprite sheet has no such frame");
+        let columns = (self.image.size().width / self.frame_size.width) as usize;
+        let top_left = Point::new(
+            ((index % columns) as u32 * self.frame_size.width) as i32,
+            ((index / columns) as u32 * self.frame_size.height) as i32,
+        );
+        Rectangle::new(top_left, self.frame_size)
+    }
+
+    /// Returns a frame, drawn with an `embedded_graphics` `Image`
+    pub fn frame(&self, index: usize) -> SubImage<'_, Qoi<'a>> {
+        self.image.sub_image(&self.frame_area(index))
+    }
+}
+
+impl<'a> Animation<'a> {
+    /// Creates a new animation that displays the first frame of
+    /// the `sheet` with its top left corner at `position`.
+    ///
+    /// The transparent pixels are drawn with the `background`, which
+    /// erases the previous frame. Without a background, the transparent
+    /// pixels are not drawn and the previous frame is not erased.
+    pub fn new(
+        sheet: SpriteSheet<'a>,
+        position: Point,
+        background: Option<Rgb565>,
+    ) -> Animation<'a> {
+        Animation {
+            sheet,
+            position,
+            background,
+            frame: 0,
+            drawn: None,
+        }
+    }
+
+    /// Returns the sprite sheet
+    pub fn sheet(&self) -> &SpriteSheet<'a> {
+        &self.sheet
+    }
+
+    /// Returns the screen area of the animation
+    pub fn bounding_box(&self) -> Rectangle {
+        Rectangle::new(self.position, self.sheet.frame_size)
+    }
+
+    /// Returns the frame displayed
+    pub fn frame(&self) -> usize {
+        self.frame
+    }
+
+    /// Displays a frame, the index wraps around the number of frames
+    pub fn set_frame(&mut self, index: usize) {
+        self.frame = index % self.sheet.frames();
+    }
+
+    /// Displays the next frame, the first frame
+    /// follows the last one
+    pub fn next_frame(&mut self) {
+        self.set_frame(self.frame + 1);
+    }
+
+    /// Makes the next [`Animation::draw`] draw the frame.
+    ///
+    /// Use this after something else was drawn over the
+    /// animation, like after clearing the screen.
+    pub fn invalidate(&mut self) {
+        self.drawn = None;
+    }
+
+    /// Draws the frame if it changed since the last draw
+    pub fn draw<D: DrawTarget<Color = Rgb565>>(&mut self, target: &mut D) -> Result<(), D::Error> {
+        if self.drawn == Some(self.frame) {
+            return Ok(());
+        }
+        self.sheet.image.draw_area(
+            &mut target.translated(self.position),
+            &self.sheet.frame_area(self.frame),
+            self.background,
+        )?;
+        self.drawn = Some(self.frame);
+        Ok(())
+    }
+}
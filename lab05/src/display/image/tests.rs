@@ -0,0 +1,244 @@
+//! Host tests for the images.
+//!
+//! The test images are built chunk by chunk, the decoded pixels
+//! are compared to the expected colors and the images are drawn
+//! into the `embedded_graphics` mock display.
+
+extern crate std;
+
+use std::vec::Vec;
+
+use embedded_graphics::{
+    Drawable,
+    image::{Image, ImageDrawableExt},
+    mock_display::MockDisplay,
+    pixelcolor::{Rgb565, Rgb888},
+    prelude::{OriginDimensions, Point, RgbColor, Size},
+    primitives::Rectangle,
+};
+
+use crate::display::image::{Animation, Error, Qoi, SpriteSheet};
+
+/// Builds a QOI file from its chunks
+fn qoi(width: u32, height: u32, channels: u8, chunks: &[u8]) -> Vec<u8> {
+    let mut data = Vec::from(*b"qoif");
+    data.extend_from_slice(&width.to_be_bytes());
+    data.extend_from_slice(&height.to_be_bytes());
+    data.extend_from_slice(&[channels, 0]);
+    data.extend_from_slice(chunks);
+    data.extend_from_slice(&[0, 0, 0, 0, 0, 0, 0, 1]);
+    data
+}
+
+fn rgb(r: u8, g: u8, b: u8) -> Option<Rgb565> {
+    Some(Rgb888::new(r, g, b).into())
+}
+
+#[test]
+fn invalid_images_are_rejected() {
+    assert_eq!(Qoi::new(b"qoif").err(), Some(Error::InvalidHeader));
+    let mut data = qoi(1, 1, 3, &[0xfe, 1, 2, 3]);
+    data[0] = b'Q';
+    assert_eq!(Qoi::new(&data).err(), Some(Error::InvalidHeader));
+    assert_eq!(
+        Qoi::new(&qoi(0, 1, 3, &[])).err(),
+        Some(Error::InvalidHeader)
+    );
+    assert_eq!(
+        Qoi::new(&qoi(1, 1, 5, &[0xfe, 1, 2, 3])).err(),
+        Some(Error::InvalidHeader)
+    );
+
+    // The data ends in the middle of the second pixel
+    let data = qoi(2, 1, 3, &[0xfe, 1, 2, 3, 0xfe, 1]);
+    assert_eq!(
+        Qoi::new(&data[..data.len() - 8]).err(),
+        Some(Error::Truncated)
+    );
+}
+
+#[test]
+fn all_the_chunks_are_decoded() {
+    let data = qoi(
+        8,
+        1,
+        4,
+        &[
+            0xfe, 255, 0, 0,    // RGB, red
+            0x5e, // DIFF, -1 +1 0
+            0xaa, 0x6b, // LUMA, green +10, red -2 and blue +3 from it, red wraps
+            0xc1, // RUN, twice
+            0xff, 0, 0, 255, 0,    // RGBA, transparent blue
+            0x32, // INDEX, red
+            0x39, // INDEX, transparent blue
+        ],
+    );
+    let image = Qoi::new(&data).unwrap();
+    assert_eq!(image.size(), Size::new(8, 1));
+    assert!(!image.is_opaque());
+    assert_eq!(
+        image.pixels().collect::<Vec<_>>(),
+        [
+            rgb(255, 0, 0),
+            rgb(254, 1, 0),
+            rgb(6, 11, 13),
+            rgb(6, 11, 13),
+            rgb(6, 11, 13),
+            None,
+            rgb(255, 0, 0),
+            None,
+        ]
+    );
+}
+
+/// A 3 x 2 opaque image
+fn opaque_image() -> Vec<u8> {
+    qoi(
+        3,
+        2,
+        3,
+        &[
+            0xfe, 255, 0, 0, // red
+            0xfe, 0, 255, 0, // green
+            0xfe, 0, 0, 255, // blue
+            0xfe, 255, 255, 255, // white
+            0xfe, 0, 0, 0, // black
+            0xfe, 255, 255, 0, // yellow
+        ],
+    )
+}
+
+#[test]
+fn images_and_sub_images_are_drawn() {
+    let data = opaque_image();
+    let image = Qoi::new(&data).unwrap();
+    assert!(image.is_opaque());
+
+    let mut display = MockDisplay::<Rgb565>::new();
+    Image::new(&image, Point::new(1, 0))
+        .draw(&mut display)
+        .unwrap();
+    display.assert_pattern(&[" RGB", " WKY"]);
+
+    let mut display = MockDisplay::<Rgb565>::new();
+    let sub_image = image.sub_image(&Rectangle::new(Point::new(1, 0), Size::new(2, 2)));
+    Image::new(&sub_image, Point::new(0, 1))
+        .draw(&mut display)
+        .unwrap();
+    display.assert_pattern(&["  ", "GB", "KY"]);
+
+    // Sub images are clipped to the image
+    let mut display = MockDisplay::<Rgb565>::new();
+    let sub_image = image.sub_image(&Rectangle::new(Point::new(2, 0), Size::new(2, 2)));
+    Image::new(&sub_image, Point::zero())
+        .draw(&mut display)
+        .unwrap();
+    display.assert_pattern(&["B", "Y"]);
+}
+
+#[test]
+fn transparent_pixels_are_not_drawn() {
+    let data = qoi(
+        3,
+        2,
+        4,
+        &[
+            0xff, 0, 0, 0, 0, // transparent
+            0xff, 255, 0, 0, 255,  // red
+            0xc0, // red
+            0xff, 0, 0, 255, 127, // almost transparent
+            0xff, 0, 0, 255, 128, // blue
+            0xff, 0, 255, 0, 0, // transparent
+        ],
+    );
+    let image = Qoi::new(&data).unwrap();
+    let mut display = MockDisplay::<Rgb565>::new();
+    Image::new(&image, Point::zero())
+        .draw(&mut display)
+        .unwrap();
+    display.assert_pattern(&[" RR", " B "]);
+}
+
+#[test]
+fn long_runs_of_opaque_pixels_are_drawn() {
+    // A transparent pixel followed by 40 red pixels,
+    // more than a run sent to the display
+    let data = qoi(
+        41,
+        1,
+        4,
+        &[0xff, 0, 0, 0, 0, 0xff, 255, 0, 0, 255, 0xc0 | 38],
+    );
+    let image = Qoi::new(&data).unwrap();
+    let mut display = MockDisplay::<Rgb565>::new();
+    Image::new(&image, Point::zero())
+        .draw(&mut display)
+        .unwrap();
+    assert_eq!(
+        display.affected_area(),
+        Rectangle::new(Point::new(1, 0), Size::new(40, 1))
+    );
+}
+
+#[test]
+fn animations_display_the_frames_of_the_sprite_sheet() {
+    // Two 2 x 2 frames, a red and a green corner
+    let data = qoi(
+        4,
+        2,
+        4,
+        &[
+            0xff, 255, 0, 0, 255, // red
+            0xff, 0, 0, 0, 0,    // transparent
+            0xc0, // transparent
+            0xff, 0, 255, 0, 255, // green
+            0xff, 0, 0, 0, 0,    // transparent
+            0xc2, // transparent, 3 times
+        ],
+    );
+    let image = Qoi::new(&data).unwrap();
+    assert_eq!(
+        SpriteSheet::new(image, Size::new(3, 2)).err(),
+        Some(Error::FrameSize)
+    );
+    let sheet = SpriteSheet::new(image, Size::new(2, 2)).unwrap();
+    assert_eq!(sheet.frames(), 2);
+    assert_eq!(
+        sheet.frame_area(1),
+        Rectangle::new(Point::new(2, 0), Size::new(2, 2))
+    );
+
+    let mut animation = Animation::new(sheet, Point::new(1, 1), Some(Rgb565::BLACK));
+    let mut display = MockDisplay::<Rgb565>::new();
+    animation.draw(&mut display).unwrap();
+    display.assert_pattern(&["   ", " RK", " KK"]);
+
+    // The frame is drawn only when it changes
+    let mut display = MockDisplay::<Rgb565>::new();
+    animation.draw(&mut display).unwrap();
+    assert_eq!(display.affected_area().size, Size::zero());
+
+    animation.next_frame();
+    animation.draw(&mut display).unwrap();
+    display.assert_pattern(&["   ", " KG", " KK"]);
+
+    // The first frame follows the last one
+    animation.next_frame();
+    assert_eq!(animation.frame(), 0);
+}
+
+#[test]
+fn the_example_images_are_valid() {
+    let logo = Qoi::new(include_bytes!("../../../assets/logo.qoi")).unwrap();
+    assert_eq!(logo.size(), Size::new(48, 48));
+    assert!(logo.is_opaque());
+    let pixels = logo.pixels().collect::<Vec<_>>();
+    // The background and the white ring
+    assert_eq!(pixels[0], rgb(0, 0, 40));
+    assert_eq!(pixels[2 * 48 + 23], rgb(255, 255, 255));
+
+    let spinner = Qoi::new(include_bytes!("../../../assets/spinner.qoi")).unwrap();
+    assert!(!spinner.is_opaque());
+    let sheet = SpriteSheet::new(spinner, Size::new(16, 16)).unwrap();
+    assert_eq!(sheet.frames(), 8);
+}
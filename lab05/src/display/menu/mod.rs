@@ -0,0 +1,290 @@
+//! Button navigated menu.
+//!
+//! The [`Menu`] displays a list of [`Item`]s that the user navigates
+//! with four buttons. An item is a nested menu, a setting or the
+//! back action.
+//!
+//! ```text
+//! Settings            <- the title, the name of the menu
+//! ------------------
+//! Sensor           >  <- a nested menu (the selected item)
+//! Rate        100Hz   <- a numeric spinner
+//! Rotation      180   <- a choice between several options
+//! Mirror        off   <- a toggle
+//! Back                <- returns to the parent menu
+//! ```
+//!
+//! The buttons:
+//! - [`Button::Up`] and [`Button::Down`] select the previous or the
+//!   next item, the selection wraps around;
+//! - [`Button::Select`] opens a nested menu, flips a toggle or starts
+//!   editing a spinner or a choice;
+//! - [`Button::Back`] returns to the parent menu.
+//!
+//! While a value is edited, [`Button::Up`] and [`Button::Down`] change
+//! it, [`Button::Select`] saves it and [`Button::Back`] restores it.
+//!
+//! The menu stores the values of the settings, every setting has
+//! a number that is its index in the values. [`Menu::press`] returns
+//! a [`Change`] when the user saves a value and the application
+//! applies it, for instance by configuring the sensor.
+//!
+//! [`Menu::draw`] redraws only the rows that changed, moving the
+//! selection draws two rows.
+//!
+//! ```ignore
+//! const RATE: usize = 0;
+//! const MIRROR: usize = 1;
+//!
+//! const ITEMS: &[Item] = &[
+//!     Item::spinner("Rate", RATE, 10, 200, 10, "Hz"),
+//!     Item::toggle("Mirror", MIRROR),
+//! ];
+//!
+//! let mut menu = Menu::new(
+//!     Point::zero(),
+//!     Size::new(128, 84),
+//!     "Settings",
+//!     ITEMS,
+//!     [100, 0],
+//!     Config::default(),
+//! );
+//! loop {
+//!     menu.draw(&mut screen)?;
+//!     let button = wait_for_button().await;
+//!     if let Some(change) = menu.press(button) {
+//!         apply(change);
+//!     }
+//! }
+//! ```
+
+mod widget;
+
+#[cfg(test)]
+mod tests;
+
+pub use widget::Menu;
+
+use embedded_graphics::{
+    mono_font::{MonoFont, ascii::FONT_6X10},
+    pixelcolor::Rgb565,
+    prelude::RgbColor,
+};
+
+/// The depth of the nested menus
+pub const MAX_DEPTH: usize = 4;
+
+/// The largest number of rows displayed
+pub const MAX_ROWS: usize = 16;
+
+/// The buttons that navigate the menu.
+///
+/// [`PartialEq`], [`Debug`] and [`defmt::Format`] are derived
+/// so that the values can be compared and printed.
+#[derive(Copy, Clone, PartialEq, Debug, defmt::Format)]
+pub enum Button {
+    /// Selects the previous item or increases the edited value
+    Up,
+    /// Selects the next item or decreases the edited value
+    Down,
+    /// Opens, flips or edits the selected item, or saves
+    /// the edited value
+    Select,
+    /// Returns to the parent menu or restores the edited value
+    Back,
+}
+
+/// A setting changed by the user.
+#[derive(Copy, Clone, PartialEq, Debug, defmt::Format)]
+pub struct Change {
+    /// The number of the setting
+    pub setting: usize,
+    /// The new value
+    pub value: i32,
+}
+
+/// An item of the menu.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Item {
+    /// The text displayed
+    pub label: &'static str,
+    /// What the item does
+    pub kind: Kind,
+}
+
+/// What an item does.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Kind {
+    /// Opens a nested menu, titled with the item's label
+    Submenu(&'static [Item]),
+    /// A number between `min` and `max`, changed by `step`
+    Spinner {
+        setting: usize,
+        min: i32,
+        max: i32,
+        step: i32,
+        /// The text displayed after the value
+        unit: &'static str,
+    },
+    /// A setting that is on (1) or off (0)
+    Toggle { setting: usize },
+    /// The index of one of the `options`
+    Choice {
+        setting: usize,
+        options: &'static [&'static str],
+    },
+    /// Returns to the parent menu
+    Back,
+}
+
+impl Item {
+    /// Creates a nested menu
+    pub const fn submenu(label: &'static str, items: &'static [Item]) -> Item {
+        Item {
+            label,
+            kind: Kind::Submenu(items),
+        }
+    }
+
+    /// Creates a numeric spinner
+    pub const fn spinner(
+        label: &'static str,
+        setting: usize,
+        min: i32,
+        max: i32,
+        step: i32,
+        unit: &'static str,
+    ) -> Item {
+        Item {
+            label,
+            kind: Kind::Spinner {
+                setting,
+                min,
+                max,
+                step,
+                unit,
+            },
+        }
+    }
+
+    /// Creates a toggle
+    pub const fn toggle(label: &'static str, setting: usize) -> Item {
+        Item {
+            label,
+            kind: Kind::Toggle { setting },
+        }
+    }
+
+    /// Creates a choice between several options
+    pub const fn choice(
+        label: &'static str,
+        setting: usize,
+        options: &'static [&'static str],
+    ) -> Item {
+        Item {
+            label,
+            kind: Kind::Choice { setting, options },
+        }
+    }
+
+    /// Creates the back action
+    pub const fn back(label: &'static str) -> Item {
+        Item {
+            label,
+            kind: Kind::Back,
+        }
+    }
+}
+
+impl Kind {
+    /// Returns the number of the setting changed by the item
+    pub fn setting(&self) -> Option<usize> {
+        match *self {
+            Kind::Spinner { setting, .. }
+            | Kind::Toggle { setting }
+            | Kind::Choice { setting, .. } => Some(setting),
+            Kind::Submenu(_) | Kind::Back => None,
+        }
+    }
+}
+
+/// The configuration of the menu.
+#[derive(Copy, Clone)]
+pub struct Config {
+    /// The color of the background
+    pub background: Rgb565,
+    /// The color of the labels and of the values
+    pub text: Rgb565,
+    /// The background of the selected item
+    pub highlight: Rgb565,
+    /// The color of the value being edited
+    pub edit: Rgb565,
+    /// The color of the title and of the line below it
+    pub title: Rgb565,
+    /// The font of the items and of the title
+    pub font: &'static MonoFont<'static>,
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config {
+            background: Rgb565::BLACK,
+            text: Rgb565::WHITE,
+            highlight: Rgb565::BLUE,
+            edit: Rgb565::YELLOW,
+            title: Rgb565::CYAN,
+            font: &FONT_6X10,
+        }
+    }
+}
+
+/// Builder functions
+impl Config {
+    /// Sets the color of the background
+    pub fn background(self, value: Rgb565) -> Config {
+        Config {
+            background: value,
+            ..self
+        }
+    }
+
+    /// Sets the color of the labels and of the values
+    pub fn text(self, value: Rgb565) -> Config {
+        Config {
+            text: value,
+            ..self
+        }
+    }
+
+    /// Sets the background of the selected item
+    pub fn highlight(self, value: Rgb565) -> Config {
+        Config {
+            highlight: value,
+            ..self
+        }
+    }
+
+    /// Sets the color of the value being edited
+    pub fn edit(self, value: Rgb565) -> Config {
+        Config {
+            edit: value,
+            ..self
+        }
+    }
+
+    /// Sets the color of the title
+    pub fn title(self, value: Rgb565) -> Config {
+        Config {
+            title: value,
+            ..self
+        }
+    }
+
+    /// Sets the font of the items and of the title
+    pub fn font(self, value: &'static MonoFont<'static>) -> Config {
+        Config {
+            font: value,
+            ..self
+        }
+    }
+}
@@ -0,0 +1,302 @@
+//! Host tests for the menu.
+//!
+//! The buttons are pressed without a display, the rendering tests
+//! draw the menu into the `embedded_graphics` mock display.
+
+use embedded_graphics::{
+    Drawable,
+    draw_target::DrawTarget,
+    mock_display::MockDisplay,
+    mono_font::{MonoTextStyle, ascii::FONT_4X6},
+    pixelcolor::Rgb565,
+    prelude::{Point, RgbColor, Size},
+    primitives::{PointsIter, Rectangle},
+    text::{Baseline, Text},
+};
+
+use crate::display::menu::{Button, Change, Config, Item, Menu};
+
+const RATE: usize = 0;
+const MIRROR: usize = 1;
+const ROTATION: usize = 2;
+
+const DISPLAY: &[Item] = &[
+    Item::choice("Rot", ROTATION, &["0", "90", "180", "270"]),
+    Item::toggle("Mir", MIRROR),
+    Item::back("Back"),
+];
+
+const ITEMS: &[Item] = &[
+    Item::spinner("Rate", RATE, 10, 30, 10, "Hz"),
+    Item::submenu("Disp", DISPLAY),
+    Item::toggle("X", 3),
+    Item::toggle("Y", 4),
+    Item::toggle("Z", 5),
+];
+
+/// A 40 x 41 menu that displays 4 of the 5 items
+fn new_menu() -> Menu<6> {
+    Menu::new(
+        Point::zero(),
+        Size::new(40, 41),
+        "Menu",
+        ITEMS,
+        [20, 0, 0, 1, 0, 0],
+        Config::default().font(&FONT_4X6),
+    )
+}
+
+fn press<const N: usize>(menu: &mut Menu<N>, buttons: &[Button]) -> Option<Change> {
+    buttons.iter().fold(None, |_, button| menu.press(*button))
+}
+
+/// Draws the menu into an empty display, the display
+/// contains only the pixels drawn
+fn render<const N: usize>(menu: &mut Menu<N>) -> MockDisplay<Rgb565> {
+    let mut display = MockDisplay::new();
+    // The rows are filled before their text is drawn
+    display.set_allow_overdraw(true);
+    menu.draw(&mut display).unwrap();
+    display
+}
+
+/// Draws the menu into an empty display, copies the pixels drawn
+/// to `screen` and returns the area drawn
+fn update<const N: usize>(menu: &mut Menu<N>, screen: &mut MockDisplay<Rgb565>) -> Rectangle {
+    let update = render(menu);
+    for point in update.affected_area().points() {
+        if let Some(color) = update.get_pixel(point) {
+            screen.set_pixel(point, Some(color));
+        }
+    }
+    update.affected_area()
+}
+
+#[test]
+fn up_and_down_move_the_selection_and_wrap() {
+    let mut menu = new_menu();
+    assert_eq!(menu.selected().label, "Rate");
+    press(&mut menu, &[Button::Down]);
+    assert_eq!(menu.selected().label, "Disp");
+    press(&mut menu, &[Button::Up, Button::Up]);
+    assert_eq!(menu.selected().label, "Z");
+    press(&mut menu, &[Button::Down]);
+    assert_eq!(menu.selected().label, "Rate");
+}
+
+#[test]
+fn select_opens_nested_menus_and_back_returns() {
+    let mut menu = new_menu();
+    press(&mut menu, &[Button::Down, Button::Select]);
+    assert_eq!((menu.title(), menu.depth()), ("Disp", 1));
+    assert_eq!(menu.selected().label, "Rot");
+
+    // Back while editing restores the value and stays in the menu
+    press(&mut menu, &[Button::Select]);
+    assert!(menu.is_editing());
+    assert_eq!(press(&mut menu, &[Button::Up, Button::Back]), None);
+    assert!(!menu.is_editing());
+    assert_eq!((menu.title(), menu.value(ROTATION)), ("Disp", 0));
+
+    // The back action returns to the parent menu and
+    // selects the nested menu's item
+    press(&mut menu, &[Button::Up, Button::Select]);
+    assert_eq!((menu.title(), menu.depth()), ("Menu", 0));
+    assert_eq!(menu.selected().label, "Disp");
+
+    press(&mut menu, &[Button::Select, Button::Back]);
+    assert_eq!(menu.title(), "Menu");
+
+    // Back in the top menu does nothing
+    press(&mut menu, &[Button::Back]);
+    assert_eq!((menu.title(), menu.selected().label), ("Menu", "Disp"));
+}
+
+#[test]
+fn toggles_flip_and_report_the_change() {
+    let mut menu = new_menu();
+    press(&mut menu, &[Button::Down, Button::Select, Button::Down]);
+    assert_eq!(
+        press(&mut menu, &[Button::Select]),
+        Some(Change {
+            setting: MIRROR,
+            value: 1
+        })
+    );
+    assert_eq!(
+        press(&mut menu, &[Button::Select]),
+        Some(Change {
+            setting: MIRROR,
+            value: 0
+        })
+    );
+    assert_eq!(menu.values(), [20, 0, 0, 1, 0, 0]);
+}
+
+#[test]
+fn spinners_are_clamped_and_saved_by_select() {
+    let mut menu = new_menu();
+    assert_eq!(press(&mut menu, &[Button::Select, Button::Up]), None);
+    // The value is saved only by select
+    assert_eq!(menu.value(RATE), 20);
+    assert_eq!(
+        press(&mut menu, &[Button::Up, Button::Select]),
+        Some(Change {
+            setting: RATE,
+            value: 30
+        })
+    );
+    assert_eq!(menu.value(RATE), 30);
+
+    assert_eq!(
+        press(
+            &mut menu,
+            &[
+                Button::Select,
+                Button::Down,
+                Button::Down,
+                Button::Down,
+                Button::Select
+            ]
+        ),
+        Some(Change {
+            setting: RATE,
+            value: 10
+        })
+    );
+
+    // Saving the same value is not a change
+    assert_eq!(press(&mut menu, &[Button::Select, Button::Select]), None);
+}
+
+#[test]
+fn choices_wrap_around() {
+    let mut menu = new_menu();
+    press(&mut menu, &[Button::Down, Button::Select]);
+    assert_eq!(
+        press(&mut menu, &[Button::Select, Button::Down, Button::Select]),
+        Some(Change {
+            setting: ROTATION,
+            value: 3
+        })
+    );
+    assert_eq!(
+        press(&mut menu, &[Button::Select, Button::Up, Button::Select]),
+        Some(Change {
+            setting: ROTATION,
+            value: 0
+        })
+    );
+}
+
+#[test]
+fn the_menu_is_drawn() {
+    let mut menu = new_menu();
+    let display = render(&mut menu);
+
+    // The reference image: the title, the line below it and the
+    // first four items, the first one is selected
+    let mut reference = MockDisplay::new();
+    reference.set_allow_overdraw(true);
+    let text = |reference: &mut MockDisplay<Rgb565>, text, x, y, color| {
+        Text::with_baseline(
+            text,
+            Point::new(x, y),
+            MonoTextStyle::new(&FONT_4X6, color),
+            Baseline::Top,
+        )
+        .draw(reference)
+        .unwrap();
+    };
+    reference
+        .fill_solid(
+            &Rectangle::new(Point::zero(), Size::new(40, 41)),
+            Rgb565::BLACK,
+        )
+        .unwrap();
+    text(&mut reference, "Menu", 1, 1, Rgb565::CYAN);
+    reference
+        .fill_solid(
+            &Rectangle::new(Point::new(0, 8), Size::new(40, 1)),
+            Rgb565::CYAN,
+        )
+        .unwrap();
+    reference
+        .fill_solid(
+            &Rectangle::new(Point::new(0, 9), Size::new(40, 8)),
+            Rgb565::BLUE,
+        )
+        .unwrap();
+    for (index, (label, value)) in [("Rate", "20Hz"), ("Disp", ">"), ("X", "on"), ("Y", "off")]
+        .into_iter()
+        .enumerate()
+    {
+        let y = 10 + index as i32 * 8;
+        text(&mut reference, label, 1, y, Rgb565::WHITE);
+        text(
+            &mut reference,
+            value,
+            39 - value.len() as i32 * 4,
+            y,
+            Rgb565::WHITE,
+        );
+    }
+    display.assert_eq(&reference);
+
+    // The edited value is yellow
+    press(&mut menu, &[Button::Select, Button::Up]);
+    let display = render(&mut menu);
+    let mut edited = MockDisplay::new();
+    text(&mut edited, "30Hz", 23, 10, Rgb565::YELLOW);
+    for point in edited.affected_area().points() {
+        if edited.get_pixel(point).is_some() {
+            assert_eq!(display.get_pixel(point), Some(Rgb565::YELLOW));
+        }
+    }
+}
+
+#[test]
+fn only_the_changed_rows_are_drawn() {
+    let mut menu = new_menu();
+    let mut screen = render(&mut menu);
+    assert_eq!(update(&mut menu, &mut screen).size, Size::zero());
+
+    // The selection moves from the first to the second row
+    press(&mut menu, &[Button::Down]);
+    assert_eq!(
+        update(&mut menu, &mut screen),
+        Rectangle::new(Point::new(0, 9), Size::new(40, 16))
+    );
+
+    // Selecting the fifth item scrolls the menu, all the rows change
+    press(&mut menu, &[Button::Down, Button::Down, Button::Down]);
+    assert_eq!(
+        update(&mut menu, &mut screen),
+        Rectangle::new(Point::new(0, 9), Size::new(40, 32))
+    );
+    press(&mut menu, &[Button::Up]);
+    assert_eq!(
+        update(&mut menu, &mut screen),
+        Rectangle::new(Point::new(0, 25), Size::new(40, 16))
+    );
+
+    // A nested menu changes the title and all the rows
+    press(
+        &mut menu,
+        &[Button::Down, Button::Down, Button::Down, Button::Select],
+    );
+    assert_eq!(menu.title(), "Disp");
+    assert_eq!(
+        update(&mut menu, &mut screen),
+        Rectangle::new(Point::zero(), Size::new(40, 41))
+    );
+
+    // The updated screen is the same as the menu drawn at once
+    let mut reference = new_menu();
+    press(&mut reference, &[Button::Down, Button::Select]);
+    screen.assert_eq(&render(&mut reference));
+
+    // Invalidate draws the whole menu
+    menu.invalidate();
+    assert_eq!(render(&mut menu).affected_area(), menu.bounding_box());
+}
@@ -0,0 +1,409 @@
+//! The menu widget.
+
+use core::fmt::Write;
+
+use embedded_graphics::{
+    Drawable,
+    draw_target::DrawTarget,
+    mono_font::MonoTextStyle,
+    pixelcolor::Rgb565,
+    prelude::{Point, Size},
+    primitives::Rectangle,
+    text::{Baseline, Text},
+};
+
+use crate::display::menu::{Button, Change, Config, Item, Kind, MAX_DEPTH, MAX_ROWS};
+
+/// A menu (or nested menu) and its selected item
+#[derive(Copy, Clone)]
+struct Level {
+    title: &'static str,
+    items: &'static [Item],
+    selected: usize,
+}
+
+/// What a row displays
+#[derive(Copy, Clone, PartialEq)]
+enum Row {
+    Empty,
+    Item {
+        item: Item,
+        /// The value of the setting, `None` for
+        /// menus and for the back action
+        value: Option<i32>,
+        selected: bool,
+        editing: bool,
+    },
+}
+
+/// A menu that stores `N` settings.
+pub struct Menu<const N: usize> {
+    top_left: Point,
+    size: Size,
+    config: Config,
+    values: [i32; N],
+    /// The parent menus of the displayed menu
+    parents: heapless::Vec<Level, MAX_DEPTH>,
+    /// The displayed menu
+    level: Level,
+    /// The first item displayed
+    scroll: usize,
+    /// The value being edited, not yet saved
+    editing: Option<i32>,
+    /// The title displayed, `None` if it has to be drawn
+    drawn_title: Option<&'static str>,
+    /// What every row displays, `None` if the row has to be drawn
+    drawn: [Option<Row>; MAX_ROWS],
+}
+
+impl<const N: usize> Menu<N> {
+    /// Creates a new menu that fills the `top_left`, `size` area.
+    ///
+    /// The settings of the `items` are numbered from `0` to `N - 1`,
+    /// `values` are their initial values.
+    pub fn new(
+        top_left: Point,
+        size: Size,
+        title: &'static str,
+        items: &'static [Item],
+        values: [i32; N],
+        config: Config,
+    ) -> Menu<N> {
+        assert!(!items.is_empty(), "the menu has to have items");
+        assert!(
+            settings_fit(items, N),
+            "the settings have to be numbered from 0 to N - 1"
+        );
+        let menu = Menu {
+            top_left,
+            size,
+            config,
+            values,
+            parents: heapless::Vec::new(),
+            level: Level {
+                title,
+                items,
+                selected: 0,
+            },
+            scroll: 0,
+            editing: None,
+            drawn_title: None,
+            drawn: [None; MAX_ROWS],
+        };
+        assert!(menu.rows() > 0, "the menu has to display at least a row");
+        menu
+    }
+
+    /// Returns the configuration
+    pub fn config(&self) -> Config {
+        self.config
+    }
+
+    /// Returns the screen area of the menu
+    pub fn bounding_box(&self) -> Rectangle {
+        Rectangle::new(self.top_left, self.size)
+    }
+
+    /// Returns the value of a setting
+    pub fn value(&self, setting: usize) -> i32 {
+        self.values[setting]
+    }
+
+    /// Returns the values of all the settings
+    pub fn values(&self) -> [i32; N] {
+        self.values
+    }
+
+    /// Sets the value of a setting, for instance if the
+    /// application changes it
+    pub fn set_value(&mut self, setting: usize, value: i32) {
+        self.values[setting] = value;
+    }
+
+    /// Returns the title of the displayed menu
+    pub fn title(&self) -> &'static str {
+        self.level.title
+    }
+
+    /// Returns the selected item
+    pub fn selected(&self) -> &'static Item {
+        &self.level.items[self.level.selected]
+    }
+
+    /// Returns the number of parent menus, `0` for the top menu
+    pub fn depth(&self) -> usize {
+        self.parents.len()
+    }
+
+    /// Returns `true` while a value is edited
+    pub fn is_editing(&self) -> bool {
+        self.editing.is_some()
+    }
+
+    /// Handles a button press, returns the setting that
+    /// changed, if any
+    pub fn press(&mut self, button: Button) -> Option<Change> {
+        let item = *self.selected();
+        if let Some(value) = self.editing {
+            return self.edit(item, value, button);
+        }
+        match button {
+            Button::Up => {
+                let len = self.level.items.len();
+                self.select((self.level.selected + len - 1) % len);
+            }
+            Button::Down => self.select((self.level.selected + 1) % self.level.items.len()),
+            Button::Back => self.close(),
+            Button::Select => match item.kind {
+                Kind::Submenu(items) => self.open(item.label, items),
+                Kind::Back => self.close(),
+                Kind::Toggle { setting } => {
+                    let value = if self.values[setting] == 0 { 1 } else { 0 };
+                    return self.save(setting, value);
+                }
+                Kind::Spinner { setting, .. } | Kind::Choice { setting, .. } => {
+                    self.editing = Some(self.values[setting]);
+                }
+            },
+        }
+        None
+    }
+
+    /// Makes the next [`Menu::draw`] draw the whole menu.
+    ///
+    /// Use this after something else was drawn over the
+    /// menu, like after clearing the screen.
+    pub fn invalidate(&mut self) {
+        self.drawn_title = None;
+        self.drawn = [None; MAX_ROWS];
+    }
+
+    /// Draws the title and the rows that changed since the last draw
+    pub fn draw<D: DrawTarget<Color = Rgb565>>(&mut self, target: &mut D) -> Result<(), D::Error> {
+        if self.drawn_title != Some(self.level.title) {
+            self.draw_title(target)?;
+            self.drawn_title = Some(self.level.title);
+        }
+        for index in 0..self.rows() {
+            let row = self.row(self.scroll + index);
+            if self.drawn[index] != Some(row) {
+                self.draw_row(target, index, row)?;
+                self.drawn[index] = Some(row);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Returns `true` if all the settings of `items`
+/// and of their nested menus are below `count`
+fn settings_fit(items: &[Item], count: usize) -> bool {
+    items.iter().all(|item| match item.kind {
+        Kind::Submenu(items) => settings_fit(items, count),
+        kind => kind.setting().is_none_or(|setting| setting < count),
+    })
+}
+
+/// Private API
+impl<const N: usize> Menu<N> {
+    fn row_height(&self) -> u32 {
+        self.config.font.character_size.height + 2
+    }
+
+    /// The height of the title and of the line below it
+    fn title_height(&self) -> u32 {
+        self.row_height() + 1
+    }
+
+    /// The number of rows displayed
+    fn rows(&self) -> usize {
+        let height = self.size.height.saturating_sub(self.title_height());
+        ((height / self.row_height()) as usize).min(MAX_ROWS)
+    }
+
+    fn row_area(&self, index: usize) -> Rectangle {
+        Rectangle::new(
+            self.top_left
+                + Point::new(
+                    0,
+                    (self.title_height() + index as u32 * self.row_height()) as i32,
+                ),
+            Size::new(self.size.width, self.row_height()),
+        )
+    }
+
+    /// Selects an item and scrolls the menu so that it is displayed
+    fn select(&mut self, index: usize) {
+        self.level.selected = index;
+        let rows = self.rows();
+        if index < self.scroll {
+            self.scroll = index;
+        } else if index >= self.scroll + rows {
+            self.scroll = index + 1 - rows;
+        }
+    }
+
+    fn open(&mut self, title: &'static str, items: &'static [Item]) {
+        // Menus nested deeper than `MAX_DEPTH` are not opened
+        if items.is_empty() || self.parents.push(self.level).is_err() {
+            return;
+        }
+        self.level = Level {
+            title,
+            items,
+            selected: 0,
+        };
+        self.scroll = 0;
+    }
+
+    fn close(&mut self) {
+        if let Some(parent) = self.parents.pop() {
+            self.level = parent;
+            self.scroll = 0;
+            self.select(parent.selected);
+        }
+    }
+
+    /// Handles a button press while `item` is edited
+    fn edit(&mut self, item: Item, value: i32, button: Button) -> Option<Change> {
+        let setting = item.kind.setting()?;
+        match button {
+            Button::Select => {
+                self.editing = None;
+                if value != self.values[setting] {
+                    return self.save(setting, value);
+                }
+            }
+            Button::Back => self.editing = None,
+            Button::Up | Button::Down => {
+                let up = button == Button::Up;
+                self.editing = Some(match item.kind {
+                    Kind::Spinner { min, max, step, .. } => {
+                        let value = if up { value + step } else { value - step };
+                        value.clamp(min, max)
+                    }
+                    Kind::Choice { options, .. } => {
+                        let len = options.len() as i32;
+                        (value + if up { 1 } else { len - 1 }).rem_euclid(len)
+                    }
+                    _ => value,
+                });
+            }
+        }
+        None
+    }
+
+    fn save(&mut self, setting: usize, value: i32) -> Option<Change> {
+        self.values[setting] = value;
+        Some(Change { setting, value })
+    }
+
+    /// Returns what the row of the item `index` displays
+    fn row(&self, index: usize) -> Row {
+        let Some(item) = self.level.items.get(index) else {
+            return Row::Empty;
+        };
+        let selected = index == self.level.selected;
+        let editing = selected && self.editing.is_some();
+        let value = item.kind.setting().map(|setting| {
+            if editing {
+                self.editing.unwrap()
+            } else {
+                self.values[setting]
+            }
+        });
+        Row::Item {
+            item: *item,
+            value,
+            selected,
+            editing,
+        }
+    }
+
+    fn draw_title<D: DrawTarget<Color = Rgb565>>(&self, target: &mut D) -> Result<(), D::Error> {
+        let area = Rectangle::new(self.top_left, Size::new(self.size.width, self.row_height()));
+        target.fill_solid(&area, self.config.background)?;
+        let style = MonoTextStyle::new(self.config.font, self.config.title);
+        Text::with_baseline(
+            self.level.title,
+            self.top_left + Point::new(1, 1),
+            style,
+            Baseline::Top,
+        )
+        .draw(target)?;
+        target.fill_solid(
+            &Rectangle::new(
+                self.top_left + Point::new(0, self.row_height() as i32),
+                Size::new(self.size.width, 1),
+            ),
+            self.config.title,
+        )
+    }
+
+    fn draw_row<D: DrawTarget<Color = Rgb565>>(
+        &self,
+        target: &mut D,
+        index: usize,
+        row: Row,
+    ) -> Result<(), D::Error> {
+        let area = self.row_area(index);
+        let Row::Item {
+            item,
+            value,
+            selected,
+            editing,
+        } = row
+        else {
+            return target.fill_solid(&area, self.config.background);
+        };
+
+        let background = if selected {
+            self.config.highlight
+        } else {
+            self.config.background
+        };
+        target.fill_solid(&area, background)?;
+
+        let style = MonoTextStyle::new(self.config.font, self.config.text);
+        Text::with_baseline(
+            item.label,
+            area.top_left + Point::new(1, 1),
+            style,
+            Baseline::Top,
+        )
+        .draw(target)?;
+
+        // The value is aligned to the right
+        let mut text = heapless::String::<24>::new();
+        let _ = match (item.kind, value) {
+            (Kind::Submenu(_), _) => text.write_str(">"),
+            (Kind::Toggle { .. }, Some(value)) => {
+                text.write_str(if value != 0 { "on" } else { "off" })
+            }
+            (Kind::Spinner { unit, .. }, Some(value)) => {
+                core::write!(&mut text, "{}{}", value, unit)
+            }
+            (Kind::Choice { options, .. }, Some(value)) => text.write_str(
+                usize::try_from(value)
+                    .ok()
+                    .and_then(|index| options.get(index))
+                    .unwrap_or(&"?"),
+            ),
+            _ => Ok(()),
+        };
+        let width = text.len() as u32 * self.config.font.character_size.width;
+        let color = if editing {
+            self.config.edit
+        } else {
+            self.config.text
+        };
+        Text::with_baseline(
+            &text,
+            area.top_left + Point::new(area.size.width as i32 - 1 - width as i32, 1),
+            MonoTextStyle::new(self.config.font, color),
+            Baseline::Top,
+        )
+        .draw(target)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,20 @@
+//! Widgets for the ST7735s display.
+//!
+//! The widgets draw on any `embedded_graphics` [`DrawTarget`] with
+//! [`Rgb565`] colors, like the `mipidsi` display driver. Every pixel
+//! sent to the display is a transfer over the SPI bus, shared with the
+//! sensor, so the widgets remember what they have drawn and only send
+//! the pixels that changed.
+//!
+//! The widgets do not use the display, which allows their tests to
+//! render them on the host into the `embedded_graphics` mock display.
+//!
+//! [`DrawTarget`]: embedded_graphics::draw_target::DrawTarget
+//! [`Rgb565`]: embedded_graphics::pixelcolor::Rgb565
+
+pub mod bubble;
+pub mod console;
+pub mod image;
+pub mod menu;
+pub mod plot;
+pub mod st7735s;
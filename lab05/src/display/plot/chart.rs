@@ -0,0 +1,339 @@
+//! The plot widget.
+
+use core::fmt::Write;
+
+use embedded_graphics::{
+    Drawable,
+    draw_target::DrawTarget,
+    mono_font::MonoTextStyle,
+    pixelcolor::Rgb565,
+    prelude::{Point, Size},
+    primitives::Rectangle,
+    text::{Baseline, Text, renderer::CharacterStyle},
+};
+
+use crate::display::plot::{Config, Series, nice_range, nice_step};
+
+/// The rows drawn by every series in a column, `None` if
+/// the series has no value in the column
+type Column<const S: usize> = [Option<(u8, u8)>; S];
+
+/// A scrolling plot of `S` series that displays the last `W` samples.
+pub struct Plot<const S: usize, const W: usize> {
+    top_left: Point,
+    height: u32,
+    series: [Series; S],
+    config: Config,
+    /// The samples, a ring buffer that starts at `start`
+    samples: [[f32; S]; W],
+    start: usize,
+    len: usize,
+    /// The scale displayed, `None` before the first draw
+    range: Option<(f32, f32)>,
+    /// What every column displays, `None` if the column
+    /// has to be drawn
+    drawn: [Option<Column<S>>; W],
+    /// Whether the legend, the axis and the labels are displayed
+    decorated: bool,
+}
+
+impl<const S: usize, const W: usize> Plot<S, W> {
+    /// Creates a new plot, `height` pixels high, including the legend.
+    ///
+    /// The plot is `W` pixels wide, plus the width of the labels
+    /// and of the axis, see [`Plot::bounding_box`]. With labels, the
+    /// chart has to be at least as high as the font.
+    pub fn new(top_left: Point, height: u32, series: [Series; S], config: Config) -> Plot<S, W> {
+        let plot = Plot {
+            top_left,
+            height,
+            series,
+            config,
+            samples: [[0f32; S]; W],
+            start: 0,
+            len: 0,
+            range: None,
+            drawn: [None; W],
+            decorated: false,
+        };
+        assert!(
+            (2..=256).contains(&plot.chart_height()),
+            "the chart has to be between 2 and 256 pixels high"
+        );
+        assert!(
+            !plot.config.labels || plot.chart_height() >= plot.char_size().height,
+            "the chart has to be at least as high as the labels"
+        );
+        plot
+    }
+
+    /// Returns the configuration
+    pub fn config(&self) -> Config {
+        self.config
+    }
+
+    /// Returns the screen area of the plot
+    pub fn bounding_box(&self) -> Rectangle {
+        Rectangle::new(
+            self.top_left,
+            Size::new(self.label_width() + 1 + W as u32, self.height),
+        )
+    }
+
+    /// Returns the scale `(min, max)` displayed by the last
+    /// draw, `None` if the plot was not drawn
+    pub fn range(&self) -> Option<(f32, f32)> {
+        self.range
+    }
+
+    /// Returns the number of samples in the plot
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns `true` if the plot has no samples
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Adds a sample to every series, the oldest sample
+    /// is removed if the plot is full.
+    ///
+    /// A `NaN` value is not displayed.
+    pub fn push(&mut self, values: [f32; S]) {
+        if self.len < W {
+            self.samples[(self.start + self.len) % W] = values;
+            self.len += 1;
+        } else {
+            self.samples[self.start] = values;
+            self.start = (self.start + 1) % W;
+        }
+    }
+
+    /// Removes all the samples
+    pub fn clear(&mut self) {
+        self.start = 0;
+        self.len = 0;
+    }
+
+    /// Makes the next [`Plot::draw`] draw the whole plot.
+    ///
+    /// Use this after something else was drawn over the
+    /// plot, like after clearing the screen.
+    pub fn invalidate(&mut self) {
+        self.drawn = [None; W];
+        self.decorated = false;
+    }
+
+    /// Draws the columns that changed since the last draw.
+    ///
+    /// The first draw (or the first after [`Plot::invalidate`])
+    /// draws the whole plot.
+    pub fn draw<D: DrawTarget<Color = Rgb565>>(&mut self, target: &mut D) -> Result<(), D::Error> {
+        let range_changed = self.update_range();
+        let range = self.range.unwrap_or((0f32, 1f32));
+        if !self.decorated {
+            self.draw_legend(target)?;
+            self.draw_axis(target)?;
+        }
+        if !self.decorated || range_changed {
+            self.draw_labels(target, range)?;
+        }
+        self.decorated = true;
+
+        let zero = (range.0 < 0f32 && range.1 > 0f32).then(|| self.row(0f32, range));
+        for index in 0..W {
+            let column = self.column(index, range);
+            // The zero line moves when the scale changes, even
+            // the empty columns have to be drawn again
+            if self.drawn[index] == Some(column) && !range_changed {
+                continue;
+            }
+            let colors = (0..self.chart_height()).map(|row| row as u8).map(|row| {
+                column
+                    .iter()
+                    .zip(self.series.iter())
+                    .rev()
+                    .find(|(span, _)| {
+                        span.is_some_and(|(top, bottom)| (top..=bottom).contains(&row))
+                    })
+                    .map(|(_, series)| series.color)
+                    .unwrap_or(if zero == Some(row) {
+                        self.config.axis
+                    } else {
+                        self.config.background
+                    })
+            });
+            let area = Rectangle::new(
+                Point::new(self.chart_left() + index as i32, self.chart_top()),
+                Size::new(1, self.chart_height()),
+            );
+            target.fill_contiguous(&area, colors)?;
+            self.drawn[index] = Some(column);
+        }
+        Ok(())
+    }
+}
+
+/// Private API
+impl<const S: usize, const W: usize> Plot<S, W> {
+    /// The width of the labels, including the space
+    /// between them and the axis
+    fn label_width(&self) -> u32 {
+        if self.config.labels {
+            self.config.label_chars as u32 * self.char_size().width + 1
+        } else {
+            0
+        }
+    }
+
+    fn char_size(&self) -> Size {
+        self.config.font.character_size
+    }
+
+    fn legend_height(&self) -> u32 {
+        if self.config.legend {
+            self.char_size().height + 1
+        } else {
+            0
+        }
+    }
+
+    fn chart_top(&self) -> i32 {
+        self.top_left.y + self.legend_height() as i32
+    }
+
+    fn chart_height(&self) -> u32 {
+        self.height.saturating_sub(self.legend_height())
+    }
+
+    /// The x of the axis
+    fn axis_left(&self) -> i32 {
+        self.top_left.x + self.label_width() as i32
+    }
+
+    /// The x of the first column
+    fn chart_left(&self) -> i32 {
+        self.axis_left() + 1
+    }
+
+    /// Computes the scale of the samples, returns `true`
+    /// if it is not the displayed scale
+    fn update_range(&mut self) -> bool {
+        let range = if let Some(range) = self.config.range {
+            range
+        } else {
+            let (min, max) = (0..self.len)
+                .flat_map(|index| self.samples[(self.start + index) % W])
+                .filter(|value| !value.is_nan())
+                .fold((f32::INFINITY, f32::NEG_INFINITY), |(min, max), value| {
+                    (min.min(value), max.max(value))
+                });
+            if min > max {
+                // No samples
+                nice_range(0f32, 0f32, self.config.min_span)
+            } else {
+                let nice = nice_range(min, max, self.config.min_span);
+                match self.range {
+                    // Keep the scale while the samples fit and
+                    // use more than half of it
+                    Some((low, high))
+                        if low <= min && max <= high && (nice.1 - nice.0) * 2f32 > high - low =>
+                    {
+                        (low, high)
+                    }
+                    _ => nice,
+                }
+            }
+        };
+        let changed = self.range != Some(range);
+        self.range = Some(range);
+        changed
+    }
+
+    /// Returns the row of `value`, relative to the chart's top
+    fn row(&self, value: f32, (min, max): (f32, f32)) -> u8 {
+        let last = (self.chart_height() - 1) as f32;
+        let row = libm::roundf((max - value) / (max - min) * last);
+        row.clamp(0f32, last) as u8
+    }
+
+    /// Returns what the column `index` displays, the line from the
+    /// previous sample to the sample of the column
+    fn column(&self, index: usize, range: (f32, f32)) -> Column<S> {
+        let mut column = [None; S];
+        if index >= self.len {
+            return column;
+        }
+        let sample = self.samples[(self.start + index) % W];
+        let previous = if index > 0 {
+            self.samples[(self.start + index - 1) % W]
+        } else {
+            sample
+        };
+        for (span, (value, previous)) in column.iter_mut().zip(sample.into_iter().zip(previous)) {
+            if value.is_nan() {
+                continue;
+            }
+            let row = self.row(value, range);
+            let previous = if previous.is_nan() {
+                row
+            } else {
+                self.row(previous, range)
+            };
+            *span = Some((row.min(previous), row.max(previous)));
+        }
+        column
+    }
+
+    fn draw_legend<D: DrawTarget<Color = Rgb565>>(&self, target: &mut D) -> Result<(), D::Error> {
+        if !self.config.legend {
+            return Ok(());
+        }
+        let mut position = self.top_left;
+        for series in &self.series {
+            let mut style = MonoTextStyle::new(self.config.font, series.color);
+            style.set_background_color(Some(self.config.background));
+            position = Text::with_baseline(series.name, position, style, Baseline::Top)
+                .draw(target)?
+                + Point::new(self.char_size().width as i32, 0);
+        }
+        Ok(())
+    }
+
+    fn draw_axis<D: DrawTarget<Color = Rgb565>>(&self, target: &mut D) -> Result<(), D::Error> {
+        target.fill_solid(
+            &Rectangle::new(
+                Point::new(self.axis_left(), self.chart_top()),
+                Size::new(1, self.chart_height()),
+            ),
+            self.config.axis,
+        )
+    }
+
+    fn draw_labels<D: DrawTarget<Color = Rgb565>>(
+        &self,
+        target: &mut D,
+        (min, max): (f32, f32),
+    ) -> Result<(), D::Error> {
+        if !self.config.labels {
+            return Ok(());
+        }
+        let mut style = MonoTextStyle::new(self.config.font, self.config.text);
+        style.set_background_color(Some(self.config.background));
+        let step = nice_step((max - min) / 4f32);
+        let decimals = libm::ceilf(-libm::log10f(step)).clamp(0f32, 3f32) as usize;
+        let width = self.config.label_chars as usize;
+
+        let bottom = self.chart_top() + (self.chart_height() - self.char_size().height) as i32;
+        for (value, y) in [(max, self.chart_top()), (min, bottom)] {
+            let mut label = heapless::String::<16>::new();
+            // A label that does not fit is cut
+            let _ = core::write!(&mut label, "{:>width$.decimals$}", value);
+            label.truncate(width.min(label.len()));
+            Text::with_baseline(&label, Point::new(self.top_left.x, y), style, Baseline::Top)
+                .draw(target)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,216 @@
+//! Scrolling real time plot.
+//!
+//! The [`Plot`] draws several time series, for instance the X, Y and Z
+//! acceleration, as a chart that scrolls to the left when new samples
+//! are added. Every sample is a column of the chart, the last `W`
+//! samples are visible.
+//!
+//! ```text
+//! X Y Z               <- legend, in the colors of the series
+//!  1.0|    /\
+//!     |   /  \__/\    <- the series
+//!     |--/----------  <- zero
+//! -1.0|_/
+//! ```
+//!
+//! The vertical axis is scaled to the visible samples, rounded to
+//! *nice* values (1, 2 or 5 times a power of ten). The scale grows as
+//! soon as a sample does not fit, and shrinks only when the samples
+//! use less than half of it, so that it does not change at every
+//! sample. [`Config::range`] sets a fixed scale.
+//!
+//! [`Plot::draw`] redraws only the columns whose pixels changed. A
+//! slowly changing signal redraws only a few columns, which keeps
+//! the SPI transfers short.
+//!
+//! ```ignore
+//! let mut plot = Plot::<3, 100>::new(
+//!     Point::new(0, 20),
+//!     60,
+//!     [
+//!         Series::new("X", Rgb565::RED),
+//!         Series::new("Y", Rgb565::GREEN),
+//!         Series::new("Z", Rgb565::BLUE),
+//!     ],
+//!     Config::default(),
+//! );
+//! loop {
+//!     let accel = mpu6500.read_acceleration()?;
+//!     plot.push([accel.x, accel.y, accel.z]);
+//!     plot.draw(&mut screen)?;
+//! }
+//! ```
+
+mod chart;
+
+#[cfg(test)]
+mod tests;
+
+pub use chart::Plot;
+
+use embedded_graphics::{
+    mono_font::{MonoFont, ascii::FONT_4X6},
+    pixelcolor::Rgb565,
+    prelude::RgbColor,
+};
+
+/// A time series of the plot.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Series {
+    /// The name displayed in the legend
+    pub name: &'static str,
+    /// The color of the series and of its name
+    pub color: Rgb565,
+}
+
+impl Series {
+    /// Creates a new series
+    pub const fn new(name: &'static str, color: Rgb565) -> Series {
+        Series { name, color }
+    }
+}
+
+/// The configuration of the plot.
+#[derive(Copy, Clone)]
+pub struct Config {
+    /// The color of the background
+    pub background: Rgb565,
+    /// The color of the vertical axis and of the zero line
+    pub axis: Rgb565,
+    /// The color of the scale's labels
+    pub text: Rgb565,
+    /// The font of the legend and of the labels
+    pub font: &'static MonoFont<'static>,
+    /// Whether to display the legend above the chart
+    pub legend: bool,
+    /// Whether to display the scale's labels left of the chart
+    pub labels: bool,
+    /// The number of characters of a label
+    pub label_chars: u8,
+    /// A fixed scale `(min, max)`, `None` scales the chart
+    /// to the samples
+    pub range: Option<(f32, f32)>,
+    /// The smallest span of the automatic scale, a constant
+    /// signal is displayed in the middle of this span
+    pub min_span: f32,
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config {
+            background: Rgb565::BLACK,
+            axis: Rgb565::WHITE,
+            text: Rgb565::WHITE,
+            font: &FONT_4X6,
+            legend: true,
+            labels: true,
+            label_chars: 5,
+            range: None,
+            min_span: 0.1,
+        }
+    }
+}
+
+/// Builder functions
+impl Config {
+    /// Sets the color of the background
+    pub fn background(self, value: Rgb565) -> Config {
+        Config {
+            background: value,
+            ..self
+        }
+    }
+
+    /// Sets the color of the axis and of the zero line
+    pub fn axis(self, value: Rgb565) -> Config {
+        Config {
+            axis: value,
+            ..self
+        }
+    }
+
+    /// Sets the color of the labels
+    pub fn text(self, value: Rgb565) -> Config {
+        Config {
+            text: value,
+            ..self
+        }
+    }
+
+    /// Sets the font of the legend and of the labels
+    pub fn font(self, value: &'static MonoFont<'static>) -> Config {
+        Config {
+            font: value,
+            ..self
+        }
+    }
+
+    /// Sets whether to display the legend
+    pub fn legend(self, value: bool) -> Config {
+        Config {
+            legend: value,
+            ..self
+        }
+    }
+
+    /// Sets whether to display the labels
+    pub fn labels(self, value: bool) -> Config {
+        Config {
+            labels: value,
+            ..self
+        }
+    }
+
+    /// Sets the number of characters of a label
+    pub fn label_chars(self, value: u8) -> Config {
+        Config {
+            label_chars: value,
+            ..self
+        }
+    }
+
+    /// Sets a fixed scale, `None` scales the
+    /// chart to the samples
+    pub fn range(self, value: Option<(f32, f32)>) -> Config {
+        Config {
+            range: value,
+            ..self
+        }
+    }
+
+    /// Sets the smallest span of the automatic scale
+    pub fn min_span(self, value: f32) -> Config {
+        Config {
+            min_span: value,
+            ..self
+        }
+    }
+}
+
+/// Returns the *nice* scale (multiples of 1, 2 or 5 times a power of
+/// ten) that includes `min` and `max`, at least `min_span` wide
+pub fn nice_range(min: f32, max: f32, min_span: f32) -> (f32, f32) {
+    let (mut min, mut max) = (min, max);
+    if max - min < min_span {
+        let middle = (min + max) / 2f32;
+        min = middle - min_span / 2f32;
+        max = middle + min_span / 2f32;
+    }
+    // Values a rounding error away from a step are on the step
+    let step = nice_step((max - min) / 4f32);
+    (
+        libm::floorf(min / step + 1e-3) * step,
+        libm::ceilf(max / step - 1e-3) * step,
+    )
+}
+
+/// Returns the smallest of 1, 2 or 5 times a power of ten
+/// that is at least `value`
+fn nice_step(value: f32) -> f32 {
+    let power = libm::powf(10f32, libm::floorf(libm::log10f(value)));
+    [1f32, 2f32, 5f32, 10f32]
+        .into_iter()
+        .map(|factor| factor * power)
+        .find(|step| *step >= value * 0.999)
+        .unwrap_or(10f32 * power)
+}
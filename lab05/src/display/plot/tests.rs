@@ -0,0 +1,249 @@
+//! Host tests for the plot.
+//!
+//! The plots are rendered into the `embedded_graphics` mock display
+//! and compared to reference images, either patterns of colors or
+//! images drawn with the `embedded_graphics` primitives.
+
+use embedded_graphics::{
+    Drawable,
+    mock_display::MockDisplay,
+    mono_font::{MonoTextStyle, ascii::FONT_4X6},
+    pixelcolor::Rgb565,
+    prelude::{Point, RgbColor, Size},
+    primitives::{PointsIter, Rectangle},
+    text::{Baseline, Text, renderer::CharacterStyle},
+};
+
+use crate::display::plot::{Config, Plot, Series, nice_range};
+
+const RED: Series = Series::new("X", Rgb565::RED);
+
+/// A plot without legend and labels, 5 pixels high
+fn small_plot<const W: usize>(range: Option<(f32, f32)>) -> Plot<1, W> {
+    Plot::new(
+        Point::zero(),
+        5,
+        [RED],
+        Config::default().legend(false).labels(false).range(range),
+    )
+}
+
+/// Draws the plot into an empty display, the display contains
+/// only the pixels drawn
+fn render<const S: usize, const W: usize>(plot: &mut Plot<S, W>) -> MockDisplay<Rgb565> {
+    let mut display = MockDisplay::new();
+    plot.draw(&mut display).unwrap();
+    display
+}
+
+#[test]
+fn empty_plot_displays_the_axis_and_zero() {
+    let mut plot = small_plot::<8>(None);
+    render(&mut plot).assert_pattern(&[
+        "WKKKKKKKK",
+        "WKKKKKKKK",
+        "WWWWWWWWW",
+        "WKKKKKKKK",
+        "WKKKKKKKK",
+    ]);
+    assert_eq!(
+        plot.bounding_box(),
+        Rectangle::new(Point::zero(), Size::new(9, 5))
+    );
+}
+
+#[test]
+fn samples_are_connected_by_lines() {
+    let mut plot = small_plot::<8>(Some((0f32, 4f32)));
+    for value in [0f32, 1f32, 2f32, 3f32, 4f32, 4f32, 2f32, 0f32] {
+        plot.push([value]);
+    }
+    render(&mut plot).assert_pattern(&[
+        "WKKKKRRRK",
+        "WKKKRRKRK",
+        "WKKRRKKRR",
+        "WKRRKKKKR",
+        "WRRKKKKKR",
+    ]);
+}
+
+#[test]
+fn later_series_are_drawn_on_top() {
+    let mut plot = Plot::<2, 4>::new(
+        Point::zero(),
+        3,
+        [RED, Series::new("Y", Rgb565::GREEN)],
+        Config::default()
+            .legend(false)
+            .labels(false)
+            .range(Some((0f32, 2f32))),
+    );
+    plot.push([0f32, 0f32]);
+    plot.push([1f32, 2f32]);
+    plot.push([f32::NAN, 2f32]);
+    plot.push([1f32, f32::NAN]);
+    render(&mut plot).assert_pattern(&[
+        "WKGGK", //
+        "WKGKR", "WGGKK",
+    ]);
+}
+
+/// Draws the plot into an empty display, copies the pixels drawn
+/// to `screen` and returns the area drawn
+fn update<const S: usize, const W: usize>(
+    plot: &mut Plot<S, W>,
+    screen: &mut MockDisplay<Rgb565>,
+) -> Rectangle {
+    let update = render(plot);
+    for point in update.affected_area().points() {
+        if let Some(color) = update.get_pixel(point) {
+            screen.set_pixel(point, Some(color));
+        }
+    }
+    update.affected_area()
+}
+
+#[test]
+fn only_the_changed_columns_are_drawn() {
+    let mut plot = small_plot::<8>(Some((0f32, 4f32)));
+    for _ in 0..8 {
+        plot.push([2f32]);
+    }
+    let mut screen = render(&mut plot);
+
+    // The same sample, the columns do not change
+    plot.push([2f32]);
+    assert_eq!(update(&mut plot, &mut screen).size, Size::zero());
+
+    // A spike changes the last column
+    plot.push([4f32]);
+    assert_eq!(
+        update(&mut plot, &mut screen),
+        Rectangle::new(Point::new(8, 0), Size::new(1, 5))
+    );
+
+    // The spike scrolls, the line back down is the same
+    // as the line up
+    plot.push([2f32]);
+    assert_eq!(
+        update(&mut plot, &mut screen),
+        Rectangle::new(Point::new(7, 0), Size::new(1, 5))
+    );
+
+    // The updated screen is the same as a plot drawn at once
+    let mut reference = small_plot::<8>(Some((0f32, 4f32)));
+    for value in [2f32, 2f32, 2f32, 2f32, 2f32, 2f32, 4f32, 2f32] {
+        reference.push([value]);
+    }
+    screen.assert_eq(&render(&mut reference));
+}
+
+#[test]
+fn invalidate_draws_the_whole_plot() {
+    let mut plot = small_plot::<8>(None);
+    plot.push([1f32]);
+    render(&mut plot);
+    assert_eq!(render(&mut plot).affected_area().size, Size::zero());
+
+    plot.invalidate();
+    assert_eq!(render(&mut plot).affected_area(), plot.bounding_box());
+}
+
+fn assert_range((min, max): (f32, f32), (expected_min, expected_max): (f32, f32)) {
+    assert!(
+        (min - expected_min).abs() < 1e-4 && (max - expected_max).abs() < 1e-4,
+        "({min}, {max}), expected ({expected_min}, {expected_max})"
+    );
+}
+
+#[test]
+fn nice_ranges() {
+    assert_range(nice_range(-0.93, 1.02, 0.1), (-1f32, 1.5));
+    assert_range(nice_range(0f32, 0.3, 0.1), (0f32, 0.3));
+    assert_range(nice_range(-180f32, 170f32, 0.1), (-200f32, 200f32));
+    assert_range(nice_range(1f32, 1f32, 0.1), (0.95, 1.05));
+}
+
+#[test]
+fn scale_follows_the_samples() {
+    let mut plot = small_plot::<4>(None);
+    render(&mut plot);
+    assert_range(plot.range().unwrap(), (-0.05, 0.05));
+
+    // The scale grows for the larger samples
+    plot.push([0.2]);
+    plot.push([1.4]);
+    render(&mut plot);
+    assert_range(plot.range().unwrap(), (0f32, 1.5));
+
+    // And stays while the samples use more than half of it
+    for value in [0.1, 0.9, 0.6, 0.8] {
+        plot.push([value]);
+    }
+    render(&mut plot);
+    assert_range(plot.range().unwrap(), (0f32, 1.5));
+
+    // Smaller samples shrink it
+    for _ in 0..4 {
+        plot.push([0.1]);
+    }
+    render(&mut plot);
+    assert_range(plot.range().unwrap(), (0.05, 0.15));
+
+    // A fixed scale does not change
+    let mut plot = small_plot::<4>(Some((-2f32, 2f32)));
+    plot.push([10f32]);
+    render(&mut plot);
+    assert_range(plot.range().unwrap(), (-2f32, 2f32));
+}
+
+#[test]
+fn legend_and_labels_are_drawn() {
+    let series = [
+        Series::new("X", Rgb565::RED),
+        Series::new("Y", Rgb565::GREEN),
+        Series::new("Z", Rgb565::BLUE),
+    ];
+    let mut plot = Plot::<3, 20>::new(
+        Point::new(2, 3),
+        30,
+        series,
+        Config::default().range(Some((-1f32, 1f32))),
+    );
+    let display = render(&mut plot);
+    assert_eq!(
+        plot.bounding_box(),
+        Rectangle::new(Point::new(2, 3), Size::new(5 * 4 + 1 + 1 + 20, 30))
+    );
+
+    // The reference image: the legend and the labels in FONT_4X6,
+    // the axis and the zero line in the middle of the chart
+    let mut reference = MockDisplay::new();
+    for (index, series) in series.iter().enumerate() {
+        let mut style = MonoTextStyle::new(&FONT_4X6, series.color);
+        style.set_background_color(Some(Rgb565::BLACK));
+        let position = Point::new(2 + index as i32 * 8, 3);
+        Text::with_baseline(series.name, position, style, Baseline::Top)
+            .draw(&mut reference)
+            .unwrap();
+    }
+    let mut style = MonoTextStyle::new(&FONT_4X6, Rgb565::WHITE);
+    style.set_background_color(Some(Rgb565::BLACK));
+    for (label, y) in [("  1.0", 10), (" -1.0", 27)] {
+        Text::with_baseline(label, Point::new(2, y), style, Baseline::Top)
+            .draw(&mut reference)
+            .unwrap();
+    }
+    for y in 10..33 {
+        reference.set_pixel(Point::new(23, y), Some(Rgb565::WHITE));
+        for x in 24..44 {
+            let color = if y == 21 {
+                Rgb565::WHITE
+            } else {
+                Rgb565::BLACK
+            };
+            reference.set_pixel(Point::new(x, y), Some(color));
+        }
+    }
+    display.assert_eq(&reference);
+}
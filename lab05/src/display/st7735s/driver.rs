@@ -0,0 +1,241 @@
+//! The ST7735s driver.
+
+use embedded_graphics::{prelude::Size, primitives::Rectangle};
+use embedded_hal::digital::OutputPin;
+use embedded_hal_async::delay::DelayNs;
+use mipidsi::{
+    dcs::{
+        BitsPerPixel, DcsCommand, ExitSleepMode, PixelFormat, SetAddressMode, SetColumnAddress,
+        SetDisplayOn, SetInvertMode, SetPageAddress, SetPixelFormat, WriteMemoryStart,
+    },
+    models::ST7735s as Model,
+    options::{ModelOptions, Orientation},
+};
+
+use crate::display::st7735s::{CONTROLLER_SIZE, Config, Error, FrameBuffer, Interface};
+
+/// The MADCTL bit that reverses the rows
+const MADCTL_MY: u8 = 1 << 7;
+/// The MADCTL bit that reverses the columns
+const MADCTL_MX: u8 = 1 << 6;
+/// The MADCTL bit that swaps the rows and the columns
+const MADCTL_MV: u8 = 1 << 5;
+
+/// Async ST7735s driver that sends the frame buffer
+/// through the interface `DI`.
+pub struct St7735s<DI, RST> {
+    di: DI,
+    rst: RST,
+    config: Config,
+}
+
+impl<DI: Interface, RST: OutputPin> St7735s<DI, RST> {
+    /// Creates a new driver, the display has to be
+    /// initialised with [`St7735s::init`].
+    pub fn new(di: DI, rst: RST, config: Config) -> St7735s<DI, RST> {
+        assert!(
+            config.size.0 + config.offset.0 <= CONTROLLER_SIZE.0
+                && config.size.1 + config.offset.1 <= CONTROLLER_SIZE.1,
+            "the visible area has to fit in the ST7735s memory"
+        );
+        St7735s { di, rst, config }
+    }
+
+    /// Returns the configuration
+    pub fn config(&self) -> Config {
+        self.config
+    }
+
+    /// Returns the size of the screen in the current orientation,
+    /// the size of the frame buffer that can be flushed
+    pub fn size(&self) -> Size {
+        let (width, height) = self.config.size;
+        if self.config.orientation.rotation.is_horizontal() {
+            Size::new(width as u32, height as u32)
+        } else {
+            Size::new(height as u32, width as u32)
+        }
+    }
+
+    /// Resets and initialises the display.
+    ///
+    /// The commands are the ones sent by the `mipidsi`
+    /// ST7735s model.
+    pub async fn init(
+        &mut self,
+        delay: &mut impl DelayNs,
+    ) -> Result<(), Error<DI::Error, RST::Error>> {
+        self.rst.set_low().map_err(Error::Reset)?;
+        delay.delay_us(10).await;
+        self.rst.set_high().map_err(Error::Reset)?;
+        delay.delay_ms(200).await;
+
+        self.write_command(ExitSleepMode).await?;
+        delay.delay_ms(120).await;
+
+        self.write_command(SetInvertMode::new(self.config.invert_colors))
+            .await?;
+        for (command, args) in INIT_SEQUENCE {
+            self.send_command(*command, args).await?;
+        }
+        self.write_command(SetPixelFormat::new(PixelFormat::with_all(
+            BitsPerPixel::Sixteen,
+        )))
+        .await?;
+        self.write_command(self.address_mode()).await?;
+        self.write_command(SetDisplayOn).await
+    }
+
+    /// Changes the orientation of the display.
+    ///
+    /// The frame buffer has to be redrawn and the size of
+    /// the screen changes if the display is rotated by 90 degrees.
+    pub async fn set_orientation(
+        &mut self,
+        orientation: Orientation,
+    ) -> Result<(), Error<DI::Error, RST::Error>> {
+        self.config.orientation = orientation;
+        self.write_command(self.address_mode()).await
+    }
+
+    /// Sends the area of the frame buffer that changed since
+    /// the last flush to the display.
+    ///
+    /// The frame buffer is marked as clean only if all
+    /// the transfers succeed.
+    pub async fn flush<const W: usize, const H: usize>(
+        &mut self,
+        frame: &mut FrameBuffer<W, H>,
+    ) -> Result<(), Error<DI::Error, RST::Error>> {
+        if self.size() != Size::new(W as u32, H as u32) {
+            return Err(Error::FrameBufferSize);
+        }
+        let Some(area) = frame.dirty() else {
+            return Ok(());
+        };
+        self.set_address_window(area).await?;
+        self.write_command(WriteMemoryStart).await?;
+
+        let left = area.top_left.x as usize;
+        let top = area.top_left.y as usize;
+        let width = area.size.width as usize;
+        let height = area.size.height as usize;
+        if width == W {
+            // Full rows are contiguous in the frame buffer
+            self.send_pixels(frame.bytes(0, top, W, height)).await?;
+        } else {
+            for row in top..top + height {
+                self.send_pixels(frame.bytes(left, row, width, 1)).await?;
+            }
+        }
+        frame.mark_clean();
+        Ok(())
+    }
+}
+
+/// The ST7735s specific commands sent by [`St7735s::init`]
+/// (frame rate, power and gamma), copied from `mipidsi`
+const INIT_SEQUENCE: &[(u8, &[u8])] = &[
+    (0xB1, &[0x05, 0x3A, 0x3A]),
+    (0xB2, &[0x05, 0x3A, 0x3A]),
+    (0xB3, &[0x05, 0x3A, 0x3A, 0x05, 0x3A, 0x3A]),
+    (0xB4, &[0b0000_0011]),
+    (0xC0, &[0x62, 0x02, 0x04]),
+    (0xC1, &[0xC0]),
+    (0xC2, &[0x0D, 0x00]),
+    (0xC3, &[0x8D, 0x6A]),
+    (0xC4, &[0x8D, 0xEE]),
+    (0xC5, &[0x0E]),
+    (
+        0xE0,
+        &[
+            0x10, 0x0E, 0x02, 0x03, 0x0E, 0x07, 0x02, 0x07, 0x0A, 0x12, 0x27, 0x37, 0x00, 0x0D,
+            0x0E, 0x10,
+        ],
+    ),
+    (
+        0xE1,
+        &[
+            0x10, 0x0E, 0x03, 0x03, 0x0F, 0x06, 0x02, 0x08, 0x0A, 0x13, 0x26, 0x36, 0x00, 0x0D,
+            0x0E, 0x10,
+        ],
+    ),
+];
+
+/// Private API
+impl<DI: Interface, RST: OutputPin> St7735s<DI, RST> {
+    async fn send_command(
+        &mut self,
+        command: u8,
+        args: &[u8],
+    ) -> Result<(), Error<DI::Error, RST::Error>> {
+        self.di
+            .send_command(command, args)
+            .await
+            .map_err(Error::Interface)
+    }
+
+    async fn write_command(
+        &mut self,
+        command: impl DcsCommand,
+    ) -> Result<(), Error<DI::Error, RST::Error>> {
+        self.di
+            .write_command(command)
+            .await
+            .map_err(Error::Interface)
+    }
+
+    /// Sends the pixels in transfers of at most
+    /// `max_transfer` bytes (whole pixels)
+    async fn send_pixels(&mut self, pixels: &[u8]) -> Result<(), Error<DI::Error, RST::Error>> {
+        let chunk = (self.config.max_transfer & !1).max(2);
+        for pixels in pixels.chunks(chunk) {
+            self.di
+                .send_pixels(pixels)
+                .await
+                .map_err(Error::Interface)?;
+        }
+        Ok(())
+    }
+
+    /// The MADCTL command for the configuration
+    fn address_mode(&self) -> SetAddressMode {
+        let mut options = ModelOptions::full_size::<Model>();
+        options.color_order = self.config.color_order;
+        options.orientation = self.config.orientation;
+        SetAddressMode::from(&options)
+    }
+
+    /// Sets the area of the ST7735s memory written by the next
+    /// pixels, the same way `mipidsi` does
+    async fn set_address_window(
+        &mut self,
+        area: Rectangle,
+    ) -> Result<(), Error<DI::Error, RST::Error>> {
+        let mut madctl = [0u8; 1];
+        self.address_mode().fill_params_buf(&mut madctl);
+
+        // The offset is measured from the other side of
+        // the memory if the display is rotated
+        let (size, mut offset) = (self.config.size, self.config.offset);
+        if madctl[0] & MADCTL_MX != 0 {
+            offset.0 = CONTROLLER_SIZE.0 - (size.0 + offset.0);
+        }
+        if madctl[0] & MADCTL_MY != 0 {
+            offset.1 = CONTROLLER_SIZE.1 - (size.1 + offset.1);
+        }
+        if madctl[0] & MADCTL_MV != 0 {
+            offset = (offset.1, offset.0);
+        }
+
+        let start = (area.top_left.x as u16, area.top_left.y as u16);
+        let end = (
+            start.0 + area.size.width as u16 - 1,
+            start.1 + area.size.height as u16 - 1,
+        );
+        self.write_command(SetColumnAddress::new(start.0 + offset.0, end.0 + offset.0))
+            .await?;
+        self.write_command(SetPageAddress::new(start.1 + offset.1, end.1 + offset.1))
+            .await
+    }
+}
@@ -0,0 +1,127 @@
+//! The frame buffer, a copy of the screen in RAM.
+
+use embedded_graphics::{
+    Pixel,
+    draw_target::DrawTarget,
+    pixelcolor::{
+        Rgb565,
+        raw::{RawU16, ToBytes},
+    },
+    prelude::{Dimensions, OriginDimensions, Point, Size},
+    primitives::Rectangle,
+};
+
+/// A `W` x `H` frame buffer that remembers the area
+/// of the pixels that changed.
+///
+/// The pixels are stored as big endian RGB565, the format
+/// sent to the display, so that rows can be transferred
+/// without being converted.
+pub struct FrameBuffer<const W: usize, const H: usize> {
+    pixels: [[[u8; 2]; W]; H],
+    /// The area that has to be sent to the display
+    dirty: Option<Rectangle>,
+}
+
+impl<const W: usize, const H: usize> FrameBuffer<W, H> {
+    /// Creates a frame buffer filled with `color`.
+    ///
+    /// The whole frame buffer is dirty, the first flush
+    /// draws the whole screen.
+    pub fn new(color: Rgb565) -> FrameBuffer<W, H> {
+        let mut frame = FrameBuffer {
+            pixels: [[color.to_be_bytes(); W]; H],
+            dirty: None,
+        };
+        frame.invalidate();
+        frame
+    }
+
+    /// Returns the color of a pixel, `None` if the
+    /// pixel is outside the frame buffer
+    pub fn pixel(&self, point: Point) -> Option<Rgb565> {
+        let (x, y) = self.index(point)?;
+        Some(RawU16::new(u16::from_be_bytes(self.pixels[y][x])).into())
+    }
+
+    /// Returns the area that changed since the last flush
+    pub fn dirty(&self) -> Option<Rectangle> {
+        self.dirty
+    }
+
+    /// Marks the whole frame buffer as dirty, use this if
+    /// something else was drawn on the screen
+    pub fn invalidate(&mut self) {
+        self.dirty = Some(self.bounding_box());
+    }
+
+    /// Marks the frame buffer as clean, after it was sent to the display
+    pub(crate) fn mark_clean(&mut self) {
+        self.dirty = None;
+    }
+
+    /// Returns the bytes of the columns `left..left + width` of the
+    /// rows `top..top + height`, which have to be the width of
+    /// the frame buffer if there are several rows
+    pub(crate) fn bytes(&self, left: usize, top: usize, width: usize, height: usize) -> &[u8] {
+        if height == 1 {
+            self.pixels[top][left..left + width].as_flattened()
+        } else {
+            debug_assert!(left == 0 && width == W);
+            self.pixels[top..top + height].as_flattened().as_flattened()
+        }
+    }
+}
+
+/// Private API
+impl<const W: usize, const H: usize> FrameBuffer<W, H> {
+    fn index(&self, point: Point) -> Option<(usize, usize)> {
+        let x = usize::try_from(point.x).ok().filter(|x| *x < W)?;
+        let y = usize::try_from(point.y).ok().filter(|y| *y < H)?;
+        Some((x, y))
+    }
+
+    /// Extends the dirty area to include `area`
+    fn mark_dirty(&mut self, area: Rectangle) {
+        self.dirty = Some(match self.dirty {
+            Some(dirty) => {
+                let top_left = dirty.top_left.component_min(area.top_left);
+                let bottom_right = dirty
+                    .bottom_right()
+                    .unwrap()
+                    .component_max(area.bottom_right().unwrap());
+                Rectangle::with_corners(top_left, bottom_right)
+            }
+            None => area,
+        });
+    }
+}
+
+impl<const W: usize, const H: usize> OriginDimensions for FrameBuffer<W, H> {
+    fn size(&self) -> Size {
+        Size::new(W as u32, H as u32)
+    }
+}
+
+impl<const W: usize, const H: usize> DrawTarget for FrameBuffer<W, H> {
+    type Color = Rgb565;
+    type Error = core::convert::Infallible;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        for Pixel(point, color) in pixels {
+            let Some((x, y)) = self.index(point) else {
+                continue;
+            };
+            // Only the pixels that change have to be sent
+            let bytes = color.to_be_bytes();
+            if self.pixels[y][x] != bytes {
+                self.pixels[y][x] = bytes;
+                self.mark_dirty(Rectangle::new(point, Size::new(1, 1)));
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,76 @@
+//! The interface between the driver and the display.
+
+use embedded_hal::digital::OutputPin;
+use embedded_hal_async::spi::SpiDevice;
+use mipidsi::dcs::DcsCommand;
+
+/// Async access to the display.
+///
+/// This is the async version of the `mipidsi` `Interface` trait.
+/// The pixels are sent as bytes (big endian RGB565), so that
+/// they can be transferred by DMA directly from the frame buffer.
+// We allow `async fn` in the public trait, as the futures returned do not
+// have to be `Send`. The executor we use runs on a single core.
+#[allow(async_fn_in_trait)]
+pub trait Interface {
+    /// The error returned by the interface
+    type Error;
+
+    /// Sends a command and its parameters
+    async fn send_command(&mut self, command: u8, args: &[u8]) -> Result<(), Self::Error>;
+
+    /// Sends pixels, the `RAMWR` (0x2c) command has to be sent before
+    async fn send_pixels(&mut self, pixels: &[u8]) -> Result<(), Self::Error>;
+
+    /// Sends a `mipidsi` DCS command
+    async fn write_command(&mut self, command: impl DcsCommand) -> Result<(), Self::Error> {
+        let mut args = [0u8; 16];
+        let len = command.fill_params_buf(&mut args);
+        self.send_command(command.instruction(), &args[..len]).await
+    }
+}
+
+/// The errors of the [`SpiInterface`].
+#[derive(Debug, defmt::Format)]
+pub enum SpiError<S, D> {
+    /// The SPI transfer failed
+    Spi(S),
+    /// Setting the DC pin failed
+    Dc(D),
+}
+
+/// The display interface that uses an async SPI device and
+/// the DC (data / command) pin.
+pub struct SpiInterface<S, DC> {
+    spi: S,
+    dc: DC,
+}
+
+impl<S: SpiDevice, DC: OutputPin> SpiInterface<S, DC> {
+    /// Creates a new interface
+    pub fn new(spi: S, dc: DC) -> SpiInterface<S, DC> {
+        SpiInterface { spi, dc }
+    }
+}
+
+impl<S: SpiDevice, DC: OutputPin> Interface for SpiInterface<S, DC> {
+    type Error = SpiError<S::Error, DC::Error>;
+
+    async fn send_command(&mut self, command: u8, args: &[u8]) -> Result<(), Self::Error> {
+        // The display reads the DC pin only while its CS pin is low,
+        // another device may use the bus while DC is low
+        self.dc.set_low().map_err(SpiError::Dc)?;
+        self.spi.write(&[command]).await.map_err(SpiError::Spi)?;
+        self.dc.set_high().map_err(SpiError::Dc)?;
+        if !args.is_empty() {
+            self.spi.write(args).await.map_err(SpiError::Spi)?;
+        }
+        Ok(())
+    }
+
+    async fn send_pixels(&mut self, pixels: &[u8]) -> Result<(), Self::Error> {
+        // The display keeps writing pixels after CS goes high,
+        // until it receives another command
+        self.spi.write(pixels).await.map_err(SpiError::Spi)
+    }
+}
@@ -0,0 +1,166 @@
+//! Async ST7735s display driver.
+//!
+//! The `mipidsi` driver is blocking, every pixel sent to the display
+//! stalls the executor. A full screen takes about 100 ms at 3 MHz,
+//! during which the sensor is not read and its samples are lost.
+//!
+//! This driver draws in two steps:
+//! 1. The widgets draw into a [`FrameBuffer`], a copy of the screen
+//!    in RAM. Drawing is fast, as nothing is transferred, and the
+//!    frame buffer remembers the area of the pixels that changed.
+//! 2. [`St7735s::flush`] sends only the changed area to the display,
+//!    using an async SPI device. With an SPI that uses DMA, the CPU
+//!    is free during the transfer and the executor runs the other tasks.
+//!
+//! ```text
+//!  widgets --draw--> FrameBuffer --flush (dirty area)--> SPI + DMA --> ST7735s
+//! ```
+//!
+//! The display and the sensor share SPI1 through the async
+//! `embassy_embedded_hal::shared_bus::asynch::spi::SpiDeviceWithConfig`.
+//! A flush is split into transfers of at most [`Config::max_transfer`]
+//! bytes and every transfer is a separate SPI transaction, so the
+//! sensor's task gets the bus between two transfers and does not wait
+//! for the whole flush.
+//!
+//! The driver uses the `mipidsi` types for the display options
+//! ([`Orientation`], [`ColorOrder`], [`ColorInversion`]) and
+//! sends the same commands as the `mipidsi` ST7735s model, the
+//! screen looks the same as with `mipidsi`.
+//!
+//! ```ignore
+//! let mut display = St7735s::new(
+//!     SpiInterface::new(display_spi, screen_dc),
+//!     screen_rst,
+//!     Config::default().orientation(Orientation::new().rotate(Rotation::Deg180)),
+//! );
+//! display.init(&mut Delay).await?;
+//!
+//! let mut frame = FrameBuffer::<132, 162>::new(Rgb565::BLACK);
+//! display.flush(&mut frame).await?;
+//! loop {
+//!     plot.draw(&mut frame)?;
+//!     display.flush(&mut frame).await?;
+//! }
+//! ```
+
+mod driver;
+mod frame_buffer;
+mod interface;
+
+#[cfg(test)]
+mod tests;
+
+pub use driver::St7735s;
+pub use frame_buffer::FrameBuffer;
+pub use interface::{Interface, SpiError, SpiInterface};
+
+use mipidsi::options::{ColorInversion, ColorOrder, Orientation};
+
+/// The size (width, height) of the ST7735s memory, in
+/// the display's native (portrait) orientation
+pub const CONTROLLER_SIZE: (u16, u16) = (132, 162);
+
+/// The ST7735s driver errors.
+///
+/// [`Debug`] and [`defmt::Format`] are derived so that
+/// the errors can be printed.
+#[derive(Debug, defmt::Format)]
+pub enum Error<I, P> {
+    /// The transfer to the display failed
+    Interface(I),
+    /// Setting the reset pin failed
+    Reset(P),
+    /// The frame buffer does not have the size of the
+    /// display in the current orientation
+    FrameBufferSize,
+}
+
+/// The configuration of the display.
+///
+/// [`Copy`] and [`Clone`] are derived so that the value
+/// can be easily copied.
+#[derive(Copy, Clone)]
+pub struct Config {
+    /// The orientation of the display
+    pub orientation: Orientation,
+    /// The order of the red, green and blue subpixels
+    pub color_order: ColorOrder,
+    /// Whether the display inverts the colors
+    pub invert_colors: ColorInversion,
+    /// The size (width, height) of the visible area, in the
+    /// display's native orientation
+    pub size: (u16, u16),
+    /// The position (x, y) of the visible area in
+    /// the ST7735s memory
+    pub offset: (u16, u16),
+    /// The largest number of bytes sent in one SPI transaction
+    pub max_transfer: usize,
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config {
+            orientation: Orientation::new(),
+            color_order: ColorOrder::Rgb,
+            invert_colors: ColorInversion::Normal,
+            size: CONTROLLER_SIZE,
+            offset: (0, 0),
+            max_transfer: 1024,
+        }
+    }
+}
+
+/// Builder functions
+impl Config {
+    /// Sets the orientation of the display
+    pub fn orientation(self, value: Orientation) -> Config {
+        Config {
+            orientation: value,
+            ..self
+        }
+    }
+
+    /// Sets the order of the red, green and blue subpixels
+    pub fn color_order(self, value: ColorOrder) -> Config {
+        Config {
+            color_order: value,
+            ..self
+        }
+    }
+
+    /// Sets whether the display inverts the colors
+    pub fn invert_colors(self, value: ColorInversion) -> Config {
+        Config {
+            invert_colors: value,
+            ..self
+        }
+    }
+
+    /// Sets the size of the visible area, in the
+    /// display's native orientation
+    pub fn size(self, width: u16, height: u16) -> Config {
+        Config {
+            size: (width, height),
+            ..self
+        }
+    }
+
+    /// Sets the position of the visible area
+    /// in the ST7735s memory
+    pub fn offset(self, x: u16, y: u16) -> Config {
+        Config {
+            offset: (x, y),
+            ..self
+        }
+    }
+
+    /// Sets the largest number of bytes sent
+    /// in one SPI transaction
+    pub fn max_transfer(self, value: usize) -> Config {
+        Config {
+            max_transfer: value,
+            ..self
+        }
+    }
+}
@@ -0,0 +1,277 @@
+//! Host tests for the ST7735s driver.
+//!
+//! The driver sends its commands and pixels to a fake interface
+//! that records them, the tests compare them to the transfers
+//! expected by the display.
+
+extern crate std;
+
+use std::{cell::RefCell, convert::Infallible, rc::Rc, vec::Vec};
+
+use embassy_futures::block_on;
+use embedded_graphics::{
+    Drawable,
+    pixelcolor::{Rgb565, raw::ToBytes},
+    prelude::{Point, Primitive, RgbColor, Size},
+    primitives::{PrimitiveStyle, Rectangle},
+};
+use embedded_hal::digital::{ErrorType, OutputPin};
+use mipidsi::options::{Orientation, Rotation};
+
+use crate::display::st7735s::{Config, Error, FrameBuffer, Interface, St7735s};
+
+/// What the driver sent
+#[derive(Clone, PartialEq, Debug)]
+enum Transfer {
+    Reset(bool),
+    Command(u8, Vec<u8>),
+    Pixels(Vec<u8>),
+}
+
+type Log = Rc<RefCell<Vec<Transfer>>>;
+
+struct FakeInterface(Log);
+
+impl Interface for FakeInterface {
+    type Error = Infallible;
+
+    async fn send_command(&mut self, command: u8, args: &[u8]) -> Result<(), Infallible> {
+        self.0
+            .borrow_mut()
+            .push(Transfer::Command(command, args.to_vec()));
+        Ok(())
+    }
+
+    async fn send_pixels(&mut self, pixels: &[u8]) -> Result<(), Infallible> {
+        self.0.borrow_mut().push(Transfer::Pixels(pixels.to_vec()));
+        Ok(())
+    }
+}
+
+struct FakePin(Log);
+
+impl ErrorType for FakePin {
+    type Error = Infallible;
+}
+
+impl OutputPin for FakePin {
+    fn set_low(&mut self) -> Result<(), Infallible> {
+        self.0.borrow_mut().push(Transfer::Reset(false));
+        Ok(())
+    }
+
+    fn set_high(&mut self) -> Result<(), Infallible> {
+        self.0.borrow_mut().push(Transfer::Reset(true));
+        Ok(())
+    }
+}
+
+struct NoDelay;
+
+impl embedded_hal_async::delay::DelayNs for NoDelay {
+    async fn delay_ns(&mut self, _ns: u32) {}
+}
+
+fn new_display(config: Config) -> (St7735s<FakeInterface, FakePin>, Log) {
+    let log = Log::default();
+    let display = St7735s::new(FakeInterface(log.clone()), FakePin(log.clone()), config);
+    (display, log)
+}
+
+/// Flushes the frame buffer and returns the transfers
+fn flush<const W: usize, const H: usize>(
+    display: &mut St7735s<FakeInterface, FakePin>,
+    log: &Log,
+    frame: &mut FrameBuffer<W, H>,
+) -> Vec<Transfer> {
+    block_on(display.flush(frame)).unwrap();
+    log.take()
+}
+
+/// The commands that select the area `(x0, y0)` - `(x1, y1)`
+/// and start writing the pixels
+fn window(x0: u16, y0: u16, x1: u16, y1: u16) -> [Transfer; 3] {
+    let bytes = |a: u16, b: u16| [a.to_be_bytes(), b.to_be_bytes()].concat();
+    [
+        Transfer::Command(0x2A, bytes(x0, x1)),
+        Transfer::Command(0x2B, bytes(y0, y1)),
+        Transfer::Command(0x2C, Vec::new()),
+    ]
+}
+
+fn pixels(color: Rgb565, count: usize) -> Transfer {
+    Transfer::Pixels(color.to_be_bytes().repeat(count))
+}
+
+fn fill<const W: usize, const H: usize>(
+    frame: &mut FrameBuffer<W, H>,
+    area: Rectangle,
+    color: Rgb565,
+) {
+    area.into_styled(PrimitiveStyle::with_fill(color))
+        .draw(frame)
+        .unwrap();
+}
+
+#[test]
+fn init_resets_and_configures_the_display() {
+    let (mut display, log) = new_display(Config::default());
+    block_on(display.init(&mut NoDelay)).unwrap();
+    let transfers = log.take();
+
+    assert_eq!(
+        transfers[..4],
+        [
+            Transfer::Reset(false),
+            Transfer::Reset(true),
+            Transfer::Command(0x11, Vec::new()),
+            Transfer::Command(0x20, Vec::new()),
+        ]
+    );
+    // 16 bits per pixel, MADCTL and display on
+    assert_eq!(
+        transfers[transfers.len() - 3..],
+        [
+            Transfer::Command(0x3A, std::vec![0x55]),
+            Transfer::Command(0x36, std::vec![0x00]),
+            Transfer::Command(0x29, Vec::new()),
+        ]
+    );
+}
+
+#[test]
+fn frame_buffer_tracks_the_changed_pixels() {
+    let mut frame = FrameBuffer::<8, 8>::new(Rgb565::BLACK);
+    assert_eq!(
+        frame.dirty(),
+        Some(Rectangle::new(Point::zero(), Size::new(8, 8)))
+    );
+    frame.mark_clean();
+
+    // Pixels that keep their color are not dirty
+    fill(
+        &mut frame,
+        Rectangle::new(Point::zero(), Size::new(8, 8)),
+        Rgb565::BLACK,
+    );
+    assert_eq!(frame.dirty(), None);
+
+    fill(
+        &mut frame,
+        Rectangle::new(Point::new(1, 2), Size::new(2, 1)),
+        Rgb565::RED,
+    );
+    fill(
+        &mut frame,
+        Rectangle::new(Point::new(4, 5), Size::new(1, 1)),
+        Rgb565::RED,
+    );
+    assert_eq!(
+        frame.dirty(),
+        Some(Rectangle::with_corners(Point::new(1, 2), Point::new(4, 5)))
+    );
+    assert_eq!(frame.pixel(Point::new(4, 5)), Some(Rgb565::RED));
+    assert_eq!(frame.pixel(Point::new(0, 0)), Some(Rgb565::BLACK));
+    assert_eq!(frame.pixel(Point::new(8, 0)), None);
+}
+
+#[test]
+fn flush_sends_only_the_dirty_area() {
+    let (mut display, log) = new_display(Config::default().size(8, 8));
+    let mut frame = FrameBuffer::<8, 8>::new(Rgb565::BLACK);
+    flush(&mut display, &log, &mut frame);
+
+    fill(
+        &mut frame,
+        Rectangle::new(Point::new(2, 3), Size::new(3, 2)),
+        Rgb565::RED,
+    );
+    let transfers = flush(&mut display, &log, &mut frame);
+    assert_eq!(
+        transfers,
+        [
+            window(2, 3, 4, 4).to_vec(),
+            std::vec![pixels(Rgb565::RED, 3), pixels(Rgb565::RED, 3)],
+        ]
+        .concat()
+    );
+
+    // Nothing changed, nothing is sent
+    assert_eq!(flush(&mut display, &log, &mut frame), []);
+}
+
+#[test]
+fn full_rows_are_sent_in_transfers_of_max_transfer_bytes() {
+    let (mut display, log) = new_display(Config::default().size(4, 4).max_transfer(10));
+    let mut frame = FrameBuffer::<4, 4>::new(Rgb565::BLUE);
+
+    // 16 pixels (32 bytes) in transfers of 5 pixels
+    let transfers = flush(&mut display, &log, &mut frame);
+    assert_eq!(
+        transfers,
+        [
+            window(0, 0, 3, 3).to_vec(),
+            std::vec![
+                pixels(Rgb565::BLUE, 5),
+                pixels(Rgb565::BLUE, 5),
+                pixels(Rgb565::BLUE, 5),
+                pixels(Rgb565::BLUE, 1),
+            ],
+        ]
+        .concat()
+    );
+}
+
+#[test]
+fn rotation_moves_the_offset_and_swaps_the_size() {
+    // The visible area is 128 x 160 pixels, 1 pixel right
+    // of the corner of the memory
+    let config = Config::default().size(128, 160).offset(1, 0);
+
+    let (mut display, log) = new_display(config);
+    let mut frame = FrameBuffer::<128, 160>::new(Rgb565::BLACK);
+    frame.mark_clean();
+    fill(
+        &mut frame,
+        Rectangle::new(Point::zero(), Size::new(1, 1)),
+        Rgb565::RED,
+    );
+    assert_eq!(
+        flush(&mut display, &log, &mut frame)[..3],
+        window(1, 0, 1, 0)
+    );
+
+    // Rotated by 180 degrees, the offset is measured from
+    // the other corner of the 132 x 162 memory
+    let (mut display, log) =
+        new_display(config.orientation(Orientation::new().rotate(Rotation::Deg180)));
+    fill(
+        &mut frame,
+        Rectangle::new(Point::zero(), Size::new(1, 1)),
+        Rgb565::GREEN,
+    );
+    assert_eq!(
+        flush(&mut display, &log, &mut frame)[..3],
+        window(3, 2, 3, 2)
+    );
+
+    // Rotated by 90 degrees, the screen is 160 x 128 pixels and
+    // the rows and columns of the memory are swapped
+    let (mut display, log) =
+        new_display(config.orientation(Orientation::new().rotate(Rotation::Deg90)));
+    assert!(matches!(
+        block_on(display.flush(&mut frame)),
+        Err(Error::FrameBufferSize)
+    ));
+    let mut frame = FrameBuffer::<160, 128>::new(Rgb565::BLACK);
+    frame.mark_clean();
+    fill(
+        &mut frame,
+        Rectangle::new(Point::zero(), Size::new(1, 1)),
+        Rgb565::RED,
+    );
+    assert_eq!(
+        flush(&mut display, &log, &mut frame)[..3],
+        window(0, 3, 0, 3)
+    );
+}
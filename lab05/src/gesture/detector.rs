@@ -0,0 +1,333 @@
+//! The gesture detector.
+//!
+//! The detector separates the gravity from the measured acceleration
+//! using a low pass filter. What is left is the *dynamic* acceleration,
+//! produced by the movements of the board:
+//! - a tap is a spike of the dynamic acceleration shorter than
+//!   `tap_max_duration`
+//! - a shake is a series of rising edges of the dynamic acceleration
+//!   above `shake_threshold`
+//! - a free fall is a measured acceleration (not only the dynamic one)
+//!   close to 0 g
+//! - the position is given by the measured acceleration when the board
+//!   does not move (it is close to 1 g)
+
+use core::convert::Infallible;
+
+use embassy_sync::{blocking_mutex::raw::RawMutex, channel::Sender};
+use embassy_time::{Duration, Instant, Ticker};
+use heapless::Vec;
+
+use crate::{
+    gesture::{Config, Event, Position},
+    low_pass::LowPass,
+    mpu6500::{Acceleration, Error, Interface, Mpu6500},
+};
+
+/// The maximum number of events generated by a sample
+const MAX_EVENTS: usize = 4;
+
+/// The events generated by a sample
+pub type Events = Vec<Event, MAX_EVENTS>;
+
+/// The time constant of the gravity low pass filter
+const GRAVITY_TIME_CONSTANT: Duration = Duration::from_millis(200);
+
+/// The largest difference from 1 g of an acceleration
+/// that is used to find the position
+const STILL_TOLERANCE: f32 = 0.2;
+
+/// Recognizes gestures in acceleration samples.
+pub struct Detector {
+    config: Config,
+    /// Estimates the gravity
+    gravity: LowPass<3>,
+    /// The time when the dynamic acceleration rose above
+    /// the tap threshold
+    spike: Option<Instant>,
+    /// The end of the previous spike
+    spike_end: Option<Instant>,
+    /// The end of a tap that waits for a second tap
+    pending_tap: Option<Instant>,
+    /// Whether the dynamic acceleration is above the shake threshold
+    swing: bool,
+    /// The time of the first swing and the number of swings
+    swings: Option<(Instant, u8)>,
+    /// The time of the last swing of a reported shake, the next shake
+    /// is reported after a quiet `shake_window`
+    shake: Option<Instant>,
+    /// The time when the acceleration fell under the free fall
+    /// threshold and whether the fall was reported
+    fall: Option<(Instant, bool)>,
+    /// The reported position
+    position: Option<Position>,
+    /// The position that the board is in and the time since when
+    candidate: Option<(Position, Instant)>,
+}
+
+impl Detector {
+    /// Creates a new detector
+    pub fn new(config: Config) -> Detector {
+        Detector {
+            config,
+            gravity: LowPass::new(GRAVITY_TIME_CONSTANT),
+            spike: None,
+            spike_end: None,
+            pending_tap: None,
+            swing: false,
+            swings: None,
+            shake: None,
+            fall: None,
+            position: None,
+            candidate: None,
+        }
+    }
+
+    /// Returns the configuration
+    pub fn config(&self) -> Config {
+        self.config
+    }
+
+    /// Sets the configuration
+    ///
+    /// The detector keeps its state, gestures in progress
+    /// use the new thresholds.
+    pub fn set_config(&mut self, config: Config) {
+        self.config = config;
+    }
+
+    /// Returns the last reported position, `None` if the board
+    /// did not stay in any position yet
+    pub fn position(&self) -> Option<Position> {
+        self.position
+    }
+
+    /// Forgets all the gestures in progress and the position
+    pub fn reset(&mut self) {
+        *self = Detector::new(self.config);
+    }
+
+    /// Adds a sample and returns the recognized gestures.
+    ///
+    /// The samples have to be added in the order in which they
+    /// were taken.
+    pub fn update(&mut self, accel: Acceleration, timestamp: Instant) -> Events {
+        let mut events = Events::new();
+        let measured = [accel.x, accel.y, accel.z];
+        let magnitude = norm(measured);
+
+        let gravity = self.gravity.update(measured, timestamp);
+        let dynamic = norm([
+            measured[0] - gravity[0],
+            measured[1] - gravity[1],
+            measured[2] - gravity[2],
+        ]);
+
+        if self.free_fall(magnitude, timestamp) {
+            push(&mut events, Event::FreeFall);
+        }
+        if let Some(event) = self.tap(dynamic, timestamp) {
+            push(&mut events, event);
+        }
+        if self.shake(dynamic, timestamp) {
+            push(&mut events, Event::Shake);
+        }
+        if let Some(position) = self.position_change(measured, magnitude, timestamp) {
+            push(&mut events, Event::Position(position));
+        }
+        events
+    }
+
+    /// Adds a sample and sends the recognized gestures
+    /// to `sender`.
+    ///
+    /// The function waits if the channel is full.
+    pub async fn publish<M: RawMutex, const N: usize>(
+        &mut self,
+        accel: Acceleration,
+        timestamp: Instant,
+        sender: &Sender<'_, M, Event, N>,
+    ) {
+        for event in self.update(accel, timestamp) {
+            sender.send(event).await;
+        }
+    }
+}
+
+/// Private API
+impl Detector {
+    /// Returns `true` when a fall lasts `free_fall_duration`
+    fn free_fall(&mut self, magnitude: f32, timestamp: Instant) -> bool {
+        if magnitude >= self.config.free_fall_threshold {
+            self.fall = None;
+            return false;
+        }
+        match self.fall {
+            None => {
+                self.fall = Some((timestamp, false));
+                false
+            }
+            Some((start, false))
+                if timestamp.saturating_duration_since(start) >= self.config.free_fall_duration =>
+            {
+                self.fall = Some((start, true));
+                true
+            }
+            Some(_) => false,
+        }
+    }
+
+    /// Returns the tap or the double tap that ended
+    fn tap(&mut self, dynamic: f32, timestamp: Instant) -> Option<Event> {
+        if dynamic > self.config.tap_threshold {
+            if self.spike.is_none() {
+                self.spike = Some(timestamp);
+            }
+        } else if let Some(start) = self.spike.take() {
+            let duration = timestamp.saturating_duration_since(start);
+            // A spike that starts shortly after the previous one is
+            // the previous movement ringing
+            let ringing = self.spike_end.is_some_and(|end| {
+                start.saturating_duration_since(end) < self.config.double_tap_latency
+            });
+            self.spike_end = Some(timestamp);
+            if duration <= self.config.tap_max_duration && !ringing {
+                if self.pending_tap.take().is_some() {
+                    return Some(Event::DoubleTap);
+                }
+                self.pending_tap = Some(timestamp);
+            }
+        }
+
+        // A single tap is reported when the double tap window
+        // ends, unless a spike that might be the second tap
+        // is still in progress
+        match (self.pending_tap, self.spike) {
+            (Some(first), None)
+                if timestamp.saturating_duration_since(first) > self.config.double_tap_window =>
+            {
+                self.pending_tap = None;
+                Some(Event::Tap)
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns `true` when a shake is recognized
+    fn shake(&mut self, dynamic: f32, timestamp: Instant) -> bool {
+        let swing = dynamic > self.config.shake_threshold;
+        let rising = swing && !self.swing;
+        self.swing = swing;
+
+        let window = self.config.shake_window;
+        if let Some(last) = self.shake {
+            // The shake that was reported is still going on
+            if rising {
+                self.shake = Some(timestamp);
+            } else if timestamp.saturating_duration_since(last) > window {
+                self.shake = None;
+            }
+            return false;
+        }
+        if !rising {
+            return false;
+        }
+
+        let (first, count) = match self.swings {
+            Some((first, count)) if timestamp.saturating_duration_since(first) <= window => {
+                (first, count + 1)
+            }
+            _ => (timestamp, 1),
+        };
+        if count >= self.config.shake_count {
+            self.swings = None;
+            self.shake = Some(timestamp);
+            // The swings of the shake are not taps
+            self.pending_tap = None;
+            true
+        } else {
+            self.swings = Some((first, count));
+            false
+        }
+    }
+
+    /// Returns the new position after the board stays in it
+    /// for `position_hold`
+    fn position_change(
+        &mut self,
+        measured: [f32; 3],
+        magnitude: f32,
+        timestamp: Instant,
+    ) -> Option<Position> {
+        // A moving board does not have a position
+        if (magnitude - 1f32).abs() > STILL_TOLERANCE {
+            return None;
+        }
+        let Some(position) = classify(measured, magnitude, self.config.position_threshold) else {
+            self.candidate = None;
+            return None;
+        };
+        match self.candidate {
+            Some((candidate, since)) if candidate == position => {
+                if self.position != Some(position)
+                    && timestamp.saturating_duration_since(since) >= self.config.position_hold
+                {
+                    self.position = Some(position);
+                    return Some(position);
+                }
+            }
+            _ => self.candidate = Some((position, timestamp)),
+        }
+        None
+    }
+}
+
+/// Reads the acceleration every `period` and sends the
+/// recognized gestures to `sender`.
+///
+/// The function returns only if reading the sensor fails.
+pub async fn run<I: Interface, M: RawMutex, const N: usize>(
+    mpu6500: &mut Mpu6500<I>,
+    detector: &mut Detector,
+    period: Duration,
+    sender: Sender<'_, M, Event, N>,
+) -> Result<Infallible, Error<I::Error>> {
+    let mut ticker = Ticker::every(period);
+    loop {
+        ticker.next().await;
+        let accel = mpu6500.read_acceleration().await?;
+        detector.publish(accel, Instant::now(), &sender).await;
+    }
+}
+
+/// Returns the position given by the axis on which the
+/// gravity is, if any.
+fn classify(measured: [f32; 3], magnitude: f32, threshold: f32) -> Option<Position> {
+    let limit = threshold * magnitude;
+    let [x, y, z] = measured;
+    if z > limit {
+        Some(Position::FaceUp)
+    } else if z < -limit {
+        Some(Position::FaceDown)
+    } else if y > limit {
+        Some(Position::Portrait)
+    } else if y < -limit {
+        Some(Position::PortraitUpsideDown)
+    } else if x > limit {
+        Some(Position::LandscapeLeft)
+    } else if x < -limit {
+        Some(Position::LandscapeRight)
+    } else {
+        None
+    }
+}
+
+fn norm(v: [f32; 3]) -> f32 {
+    libm::sqrtf(v[0] * v[0] + v[1] * v[1] + v[2] * v[2])
+}
+
+/// Adds an event, a sample generates at most one event
+/// of every kind, so there is always space.
+fn push(events: &mut Events, event: Event) {
+    let _ = events.push(event);
+}
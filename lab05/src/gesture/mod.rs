@@ -0,0 +1,227 @@
+//! Gesture detection from MPU 6500 acceleration samples.
+//!
+//! The [`Detector`] receives acceleration samples and recognizes:
+//! - [`Event::Tap`] and [`Event::DoubleTap`] - short and strong spikes
+//!   of the acceleration, like the ones produced by a finger tapping
+//!   the board
+//! - [`Event::Shake`] - several strong swings in a short time
+//! - [`Event::FreeFall`] - the acceleration is close to 0 g for a while,
+//!   the board falls
+//! - [`Event::Position`] - the board stays in a new [`Position`]
+//!   (face up, face down, portrait or landscape)
+//!
+//! The thresholds and the time windows are set using a [`Config`].
+//! The detector uses the samples' timestamps, so it works at any
+//! sample rate, but taps are only seen if the sample rate is high
+//! enough (at least 100 Hz).
+//!
+//! The events can be sent through an embassy channel using
+//! [`Detector::publish`] or [`run`], which reads the sensor
+//! periodically.
+//!
+//! ```ignore
+//! static EVENTS: Channel<ThreadModeRawMutex, Event, 8> = Channel::new();
+//!
+//! let mut detector = Detector::new(Config::default());
+//! join(
+//!     run(&mut mpu6500, &mut detector, Duration::from_millis(5), EVENTS.sender()),
+//!     async {
+//!         loop {
+//!             if EVENTS.receive().await == Event::Shake {
+//!                 info!("Reset");
+//!             }
+//!         }
+//!     },
+//! )
+//! .await;
+//! ```
+
+mod detector;
+
+#[cfg(test)]
+mod tests;
+
+use embassy_time::Duration;
+
+pub use detector::{Detector, Events, run};
+
+/// A gesture recognized by the [`Detector`].
+#[derive(Copy, Clone, PartialEq, Debug, defmt::Format)]
+pub enum Event {
+    /// A single tap
+    Tap,
+    /// Two taps, one shortly after the other
+    DoubleTap,
+    /// The board is shaken
+    Shake,
+    /// The board is falling
+    FreeFall,
+    /// The board stays in a new position
+    Position(Position),
+}
+
+/// The position of the board, given by the axis that
+/// points up.
+#[derive(Copy, Clone, PartialEq, Debug, defmt::Format)]
+pub enum Position {
+    /// The Z axis points up, the chip is up
+    FaceUp,
+    /// The Z axis points down, the chip is down
+    FaceDown,
+    /// The Y axis points up
+    Portrait,
+    /// The Y axis points down
+    PortraitUpsideDown,
+    /// The X axis points up
+    LandscapeLeft,
+    /// The X axis points down
+    LandscapeRight,
+}
+
+/// The gesture detector configuration.
+///
+/// The accelerations are in g. The *dynamic* acceleration is the
+/// measured acceleration without the gravity.
+#[derive(Copy, Clone, PartialEq, Debug, defmt::Format)]
+pub struct Config {
+    /// The dynamic acceleration that starts a tap
+    pub tap_threshold: f32,
+    /// The longest time that the dynamic acceleration can stay above
+    /// the tap threshold, longer spikes are not taps
+    pub tap_max_duration: Duration,
+    /// The shortest time between the two taps of a double tap,
+    /// spikes closer than this to the previous spike are the
+    /// previous movement ringing
+    pub double_tap_latency: Duration,
+    /// The longest time between the two taps of a double tap
+    ///
+    /// A single tap is reported after this time, when it is
+    /// clear that no second tap follows.
+    pub double_tap_window: Duration,
+    /// The dynamic acceleration of a shake swing
+    pub shake_threshold: f32,
+    /// The number of swings of a shake
+    pub shake_count: u8,
+    /// The time in which the swings of a shake have to happen
+    pub shake_window: Duration,
+    /// The acceleration under which the board is falling
+    pub free_fall_threshold: f32,
+    /// The time that the acceleration has to stay under the
+    /// free fall threshold
+    pub free_fall_duration: Duration,
+    /// The smallest part of the gravity that has to be on an axis
+    /// for it to point up or down, from 0 to 1
+    pub position_threshold: f32,
+    /// The time that the board has to stay in a new position
+    pub position_hold: Duration,
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config {
+            tap_threshold: 0.5,
+            tap_max_duration: Duration::from_millis(60),
+            double_tap_latency: Duration::from_millis(80),
+            double_tap_window: Duration::from_millis(400),
+            shake_threshold: 1.2,
+            shake_count: 4,
+            shake_window: Duration::from_millis(1000),
+            free_fall_threshold: 0.3,
+            free_fall_duration: Duration::from_millis(100),
+            position_threshold: 0.8,
+            position_hold: Duration::from_millis(500),
+        }
+    }
+}
+
+/// Builder functions
+impl Config {
+    /// Sets the dynamic acceleration that starts a tap
+    pub fn tap_threshold(self, value: f32) -> Config {
+        Config {
+            tap_threshold: value,
+            ..self
+        }
+    }
+
+    /// Sets the longest duration of a tap
+    pub fn tap_max_duration(self, value: Duration) -> Config {
+        Config {
+            tap_max_duration: value,
+            ..self
+        }
+    }
+
+    /// Sets the shortest time between the two taps of a double tap
+    pub fn double_tap_latency(self, value: Duration) -> Config {
+        Config {
+            double_tap_latency: value,
+            ..self
+        }
+    }
+
+    /// Sets the longest time between the two taps of a double tap
+    pub fn double_tap_window(self, value: Duration) -> Config {
+        Config {
+            double_tap_window: value,
+            ..self
+        }
+    }
+
+    /// Sets the dynamic acceleration of a shake swing
+    pub fn shake_threshold(self, value: f32) -> Config {
+        Config {
+            shake_threshold: value,
+            ..self
+        }
+    }
+
+    /// Sets the number of swings of a shake
+    pub fn shake_count(self, value: u8) -> Config {
+        Config {
+            shake_count: value,
+            ..self
+        }
+    }
+
+    /// Sets the time in which the swings of a shake have to happen
+    pub fn shake_window(self, value: Duration) -> Config {
+        Config {
+            shake_window: value,
+            ..self
+        }
+    }
+
+    /// Sets the acceleration under which the board is falling
+    pub fn free_fall_threshold(self, value: f32) -> Config {
+        Config {
+            free_fall_threshold: value,
+            ..self
+        }
+    }
+
+    /// Sets the time that the board has to fall
+    pub fn free_fall_duration(self, value: Duration) -> Config {
+        Config {
+            free_fall_duration: value,
+            ..self
+        }
+    }
+
+    /// Sets the smallest part of the gravity on an axis
+    /// that points up or down
+    pub fn position_threshold(self, value: f32) -> Config {
+        Config {
+            position_threshold: value,
+            ..self
+        }
+    }
+
+    /// Sets the time that the board has to stay in a new position
+    pub fn position_hold(self, value: Duration) -> Config {
+        Config {
+            position_hold: value,
+            ..self
+        }
+    }
+}
@@ -0,0 +1,280 @@
+//! Host tests for the gesture detector.
+//!
+//! The tests feed the detector synthetic traces sampled at 100 Hz,
+//! with a small deterministic noise added to every sample.
+
+extern crate std;
+
+use std::vec::Vec;
+
+use embassy_time::{Duration, Instant};
+
+use crate::{
+    gesture::{Config, Detector, Event, Position},
+    mpu6500::Acceleration,
+};
+
+/// The time between two samples in ms
+const PERIOD_MS: u64 = 10;
+
+/// The acceleration of a board lying face up
+const FACE_UP: [f32; 3] = [0f32, 0f32, 1f32];
+
+/// Feeds samples to a detector and collects the events.
+struct Trace {
+    detector: Detector,
+    /// The time of the next sample in ms
+    time: u64,
+    /// The state of the noise generator
+    noise: u32,
+    events: Vec<Event>,
+}
+
+impl Trace {
+    fn new(config: Config) -> Trace {
+        Trace {
+            detector: Detector::new(config),
+            time: 0,
+            noise: 0x2545_f491,
+            events: Vec::new(),
+        }
+    }
+
+    /// Returns a value between -0.02 and 0.02 g
+    fn noise(&mut self) -> f32 {
+        self.noise = self
+            .noise
+            .wrapping_mul(1_664_525)
+            .wrapping_add(1_013_904_223);
+        ((self.noise >> 8) as f32 / (1u32 << 23) as f32 - 1f32) * 0.02
+    }
+
+    /// Adds one sample
+    fn sample(&mut self, accel: [f32; 3]) {
+        let accel = Acceleration {
+            x: accel[0] + self.noise(),
+            y: accel[1] + self.noise(),
+            z: accel[2] + self.noise(),
+        };
+        let events = self.detector.update(accel, Instant::from_millis(self.time));
+        self.events.extend(events);
+        self.time += PERIOD_MS;
+    }
+
+    /// Keeps the same acceleration for `ms`
+    fn hold(&mut self, accel: [f32; 3], ms: u64) {
+        for _ in 0..ms / PERIOD_MS {
+            self.sample(accel);
+        }
+    }
+
+    /// A tap on the board lying face up, a one sample spike
+    fn tap(&mut self) {
+        self.sample([0.3, -0.2, 2.5]);
+    }
+
+    /// Returns the events, without the position events
+    fn gestures(&self) -> Vec<Event> {
+        self.events
+            .iter()
+            .copied()
+            .filter(|event| !matches!(event, Event::Position(_)))
+            .collect()
+    }
+
+    /// Returns the position events
+    fn positions(&self) -> Vec<Position> {
+        self.events
+            .iter()
+            .filter_map(|event| match event {
+                Event::Position(position) => Some(*position),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+/// Returns a trace in which the board lies face up for 1 s
+fn face_up(config: Config) -> Trace {
+    let mut trace = Trace::new(config);
+    trace.hold(FACE_UP, 1000);
+    trace
+}
+
+#[test]
+fn still_board_reports_its_position_once() {
+    let mut trace = face_up(Config::default());
+    trace.hold(FACE_UP, 2000);
+    assert_eq!(trace.events, [Event::Position(Position::FaceUp)]);
+    assert_eq!(trace.detector.position(), Some(Position::FaceUp));
+}
+
+#[test]
+fn single_tap_is_reported_after_the_double_tap_window() {
+    let mut trace = face_up(Config::default());
+    trace.tap();
+    trace.hold(FACE_UP, 300);
+    assert_eq!(trace.gestures(), []);
+    trace.hold(FACE_UP, 200);
+    assert_eq!(trace.gestures(), [Event::Tap]);
+}
+
+#[test]
+fn two_close_taps_are_a_double_tap() {
+    let mut trace = face_up(Config::default());
+    trace.tap();
+    trace.hold(FACE_UP, 200);
+    trace.tap();
+    trace.hold(FACE_UP, 1000);
+    assert_eq!(trace.gestures(), [Event::DoubleTap]);
+}
+
+#[test]
+fn distant_taps_are_single_taps() {
+    let mut trace = face_up(Config::default());
+    trace.tap();
+    trace.hold(FACE_UP, 700);
+    trace.tap();
+    trace.hold(FACE_UP, 1000);
+    assert_eq!(trace.gestures(), [Event::Tap, Event::Tap]);
+}
+
+#[test]
+fn tap_ringing_is_one_tap() {
+    let mut trace = face_up(Config::default());
+    trace.tap();
+    trace.hold(FACE_UP, 30);
+    trace.tap();
+    trace.hold(FACE_UP, 1000);
+    assert_eq!(trace.gestures(), [Event::Tap]);
+}
+
+#[test]
+fn long_push_is_not_a_tap() {
+    let mut trace = face_up(Config::default());
+    trace.hold([0f32, 0f32, 2f32], 200);
+    trace.hold(FACE_UP, 1000);
+    assert_eq!(trace.gestures(), []);
+}
+
+#[test]
+fn shake_is_reported_once() {
+    let mut trace = face_up(Config::default());
+    // Shake the board along the X axis, 5 Hz and 2 g for 1.5 s
+    for index in 0..150 {
+        let phase = 2f32 * core::f32::consts::PI * 5f32 * index as f32 * 0.01;
+        trace.sample([2f32 * libm::sinf(phase), 0f32, 1f32]);
+    }
+    trace.hold(FACE_UP, 2000);
+    assert_eq!(trace.gestures(), [Event::Shake]);
+
+    // A new shake is reported again
+    for index in 0..100 {
+        let phase = 2f32 * core::f32::consts::PI * 5f32 * index as f32 * 0.01;
+        trace.sample([0f32, 2f32 * libm::sinf(phase), 1f32]);
+    }
+    assert_eq!(trace.gestures(), [Event::Shake, Event::Shake]);
+}
+
+#[test]
+fn slow_swings_are_not_a_shake() {
+    let mut trace = face_up(Config::default());
+    // Swings that are too far from each other
+    for _ in 0..4 {
+        trace.hold([2f32, 0f32, 1f32], 100);
+        trace.hold(FACE_UP, 1000);
+    }
+    assert_eq!(trace.gestures(), []);
+}
+
+#[test]
+fn free_fall_is_reported_once() {
+    let mut trace = face_up(Config::default());
+    trace.hold([0f32, 0f32, 0.05], 400);
+    assert_eq!(trace.gestures(), [Event::FreeFall]);
+
+    // The board lands and falls again
+    trace.hold(FACE_UP, 1000);
+    trace.hold([0f32, 0f32, 0.05], 200);
+    assert_eq!(trace.gestures(), [Event::FreeFall, Event::FreeFall]);
+}
+
+#[test]
+fn short_drop_is_not_a_free_fall() {
+    let mut trace = face_up(Config::default());
+    trace.hold([0f32, 0f32, 0.1], 50);
+    trace.hold(FACE_UP, 1000);
+    assert!(!trace.gestures().contains(&Event::FreeFall));
+}
+
+#[test]
+fn position_changes_are_reported() {
+    let mut trace = face_up(Config::default());
+    trace.hold([0f32, 0f32, -1f32], 1000);
+    trace.hold([0f32, 1f32, 0f32], 1000);
+    trace.hold([0f32, -1f32, 0f32], 1000);
+    trace.hold([1f32, 0f32, 0f32], 1000);
+    trace.hold([-1f32, 0f32, 0f32], 1000);
+    // Tilted between two positions, the position does not change
+    trace.hold([-0.7, 0f32, 0.7], 1000);
+    assert_eq!(
+        trace.positions(),
+        [
+            Position::FaceUp,
+            Position::FaceDown,
+            Position::Portrait,
+            Position::PortraitUpsideDown,
+            Position::LandscapeLeft,
+            Position::LandscapeRight
+        ]
+    );
+    assert_eq!(trace.gestures(), []);
+}
+
+#[test]
+fn short_flip_does_not_change_the_position() {
+    let mut trace = face_up(Config::default());
+    trace.hold([0f32, 0f32, -1f32], 300);
+    trace.hold(FACE_UP, 1000);
+    assert_eq!(trace.positions(), [Position::FaceUp]);
+}
+
+#[test]
+fn thresholds_and_windows_are_configurable() {
+    // A light tap is ignored with a higher threshold
+    let mut trace = face_up(Config::default().tap_threshold(2f32));
+    trace.tap();
+    trace.hold(FACE_UP, 1000);
+    assert_eq!(trace.gestures(), []);
+
+    // With a shorter window, two taps are not a double tap
+    let config = Config::default().double_tap_window(Duration::from_millis(100));
+    let mut trace = face_up(config);
+    trace.tap();
+    trace.hold(FACE_UP, 200);
+    trace.tap();
+    trace.hold(FACE_UP, 1000);
+    assert_eq!(trace.gestures(), [Event::Tap, Event::Tap]);
+
+    // Two swings are a shake if the shake count is 2
+    let mut trace = face_up(Config::default().shake_count(2));
+    trace.hold([2f32, 0f32, 1f32], 100);
+    trace.hold(FACE_UP, 100);
+    trace.hold([-2f32, 0f32, 1f32], 100);
+    trace.hold(FACE_UP, 1000);
+    assert_eq!(trace.gestures(), [Event::Shake]);
+
+    // A shorter hold time reports the flip
+    let mut trace = face_up(Config::default().position_hold(Duration::from_millis(100)));
+    trace.hold([0f32, 0f32, -1f32], 300);
+    assert_eq!(trace.positions(), [Position::FaceUp, Position::FaceDown]);
+}
+
+#[test]
+fn reset_forgets_the_position() {
+    let mut trace = face_up(Config::default());
+    trace.detector.reset();
+    assert_eq!(trace.detector.position(), None);
+    trace.hold(FACE_UP, 1000);
+    assert_eq!(trace.positions(), [Position::FaceUp, Position::FaceUp]);
+}
@@ -0,0 +1,208 @@
+//! Two axis inclinometer (digital spirit level).
+//!
+//! When the board does not move, the accelerometer measures only the
+//! gravity, and its direction gives the pitch and the roll of the board,
+//! see [`accel_angles`](crate::orientation::accel_angles).
+//!
+//! The [`Inclinometer`] smooths the acceleration with a [`LowPass`] filter
+//! before computing the angles, so that the vibrations of the table or
+//! of the hand do not make the angles jitter. The acceleration is
+//! filtered and not the angles, as the roll jumps from 180 to -180 deg
+//! when the board is upside down.
+//!
+//! A table is never exactly level and the sensor is never soldered
+//! exactly flat on the board. [`Inclinometer::calibrate`] takes the
+//! current angles as the zero, the following angles are relative to
+//! them.
+//!
+//! The board is *level* when both angles are below
+//! [`Config::level_threshold`]. It stops being level only when an angle
+//! is above the threshold plus the [`Config::hysteresis`], so that the
+//! level indication does not flicker when an angle is close to the
+//! threshold.
+//!
+//! ```ignore
+//! let mut inclinometer = Inclinometer::new(Config::default());
+//! loop {
+//!     let tilt = inclinometer.update(mpu6500.read_acceleration()?, Instant::now());
+//!     info!("Pitch {} Roll {} Level {}", tilt.pitch, tilt.roll, inclinometer.is_level());
+//! }
+//! ```
+
+#[cfg(test)]
+mod tests;
+
+use embassy_time::{Duration, Instant};
+
+use crate::{
+    low_pass::LowPass,
+    mpu6500::Acceleration,
+    orientation::{accel_angles, wrap_degrees},
+};
+
+/// The tilt of the board, in deg.
+#[derive(Copy, Clone, Default, PartialEq, Debug, defmt::Format)]
+pub struct Tilt {
+    /// The rotation around the Y axis, -90 ... 90 deg
+    pub pitch: f32,
+    /// The rotation around the X axis, -180 ... 180 deg
+    pub roll: f32,
+}
+
+/// Computes the smoothed tilt of the board.
+pub struct Inclinometer {
+    config: Config,
+    /// Smooths the acceleration
+    filter: LowPass<3>,
+    /// The tilt taken as zero
+    offset: Tilt,
+    /// The last tilt, relative to the offset
+    tilt: Tilt,
+    level: bool,
+}
+
+/// The inclinometer configuration.
+#[derive(Copy, Clone, PartialEq, Debug, defmt::Format)]
+pub struct Config {
+    /// The time constant of the low pass filter, longer
+    /// values are smoother but slower
+    pub time_constant: Duration,
+    /// The largest angle in deg of a level board
+    pub level_threshold: f32,
+    /// The angle in deg above the threshold at which
+    /// a level board stops being level
+    pub hysteresis: f32,
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config {
+            time_constant: Duration::from_millis(300),
+            level_threshold: 0.5,
+            hysteresis: 0.2,
+        }
+    }
+}
+
+/// Builder functions
+impl Config {
+    /// Sets the time constant of the low pass filter
+    pub fn time_constant(self, value: Duration) -> Config {
+        Config {
+            time_constant: value,
+            ..self
+        }
+    }
+
+    /// Sets the largest angle of a level board
+    pub fn level_threshold(self, value: f32) -> Config {
+        Config {
+            level_threshold: value,
+            ..self
+        }
+    }
+
+    /// Sets the angle above the threshold at which
+    /// the board stops being level
+    pub fn hysteresis(self, value: f32) -> Config {
+        Config {
+            hysteresis: value,
+            ..self
+        }
+    }
+}
+
+impl Inclinometer {
+    /// Creates a new inclinometer, without an offset
+    pub fn new(config: Config) -> Inclinometer {
+        Inclinometer {
+            config,
+            filter: LowPass::new(config.time_constant),
+            offset: Tilt::default(),
+            tilt: Tilt::default(),
+            level: false,
+        }
+    }
+
+    /// Returns the configuration
+    pub fn config(&self) -> Config {
+        self.config
+    }
+
+    /// Adds a sample and returns the tilt, relative to the offset.
+    ///
+    /// The first sample is not smoothed.
+    pub fn update(&mut self, accel: Acceleration, timestamp: Instant) -> Tilt {
+        let filtered = self.filter.update([accel.x, accel.y, accel.z], timestamp);
+
+        // The roll of a board calibrated upside down jumps
+        // between 180 and -180 deg, the difference is wrapped
+        let absolute = absolute_tilt(filtered);
+        self.tilt = Tilt {
+            pitch: absolute.pitch - self.offset.pitch,
+            roll: wrap_degrees(absolute.roll - self.offset.roll),
+        };
+
+        let largest = self.tilt.pitch.abs().max(self.tilt.roll.abs());
+        self.level = if self.level {
+            largest <= self.config.level_threshold + self.config.hysteresis
+        } else {
+            largest <= self.config.level_threshold
+        };
+        self.tilt
+    }
+
+    /// Returns the last tilt, relative to the offset
+    pub fn tilt(&self) -> Tilt {
+        self.tilt
+    }
+
+    /// Returns `true` if the board is level
+    pub fn is_level(&self) -> bool {
+        self.level
+    }
+
+    /// Takes the current tilt as the zero.
+    ///
+    /// The board has to be still, the following samples are
+    /// relative to it. Before the first sample, it does nothing.
+    pub fn calibrate(&mut self) {
+        if let Some(filtered) = self.filter.output() {
+            self.offset = absolute_tilt(filtered);
+            self.tilt = Tilt::default();
+            self.level = true;
+        }
+    }
+
+    /// Returns the tilt taken as zero
+    pub fn offset(&self) -> Tilt {
+        self.offset
+    }
+
+    /// Sets the tilt taken as zero, like an offset
+    /// saved by a previous calibration
+    pub fn set_offset(&mut self, offset: Tilt) {
+        self.offset = offset;
+    }
+
+    /// Forgets the samples, the offset is kept
+    pub fn reset(&mut self) {
+        self.filter.reset();
+        self.tilt = Tilt::default();
+        self.level = false;
+    }
+}
+
+/// Returns the tilt given by the acceleration,
+/// without the offset
+fn absolute_tilt(accel: [f32; 3]) -> Tilt {
+    let angles = accel_angles(Acceleration {
+        x: accel[0],
+        y: accel[1],
+        z: accel[2],
+    });
+    Tilt {
+        pitch: angles.pitch,
+        roll: angles.roll,
+    }
+}
@@ -0,0 +1,129 @@
+//! Host tests for the inclinometer.
+//!
+//! The samples are the gravity of a still board, sampled
+//! at 100 Hz, computed from the pitch and the roll.
+
+use embassy_time::{Duration, Instant};
+
+use crate::{
+    inclinometer::{Config, Inclinometer, Tilt},
+    mpu6500::Acceleration,
+};
+
+/// The time between two samples in ms
+const PERIOD_MS: u64 = 10;
+
+/// Returns the gravity measured by a board tilted by `pitch` and `roll`
+fn gravity(pitch: f32, roll: f32) -> Acceleration {
+    let (pitch, roll) = (pitch.to_radians(), roll.to_radians());
+    Acceleration {
+        x: -libm::sinf(pitch),
+        y: libm::cosf(pitch) * libm::sinf(roll),
+        z: libm::cosf(pitch) * libm::cosf(roll),
+    }
+}
+
+/// Feeds `count` samples, starting at `start` ms, and
+/// returns the last tilt
+fn feed(inclinometer: &mut Inclinometer, accel: Acceleration, start: u64, count: u64) -> Tilt {
+    (0..count)
+        .map(|index| inclinometer.update(accel, Instant::from_millis(start + index * PERIOD_MS)))
+        .last()
+        .unwrap()
+}
+
+fn assert_close(tilt: Tilt, pitch: f32, roll: f32) {
+    assert!(
+        (tilt.pitch - pitch).abs() < 0.01 && (tilt.roll - roll).abs() < 0.01,
+        "{tilt:?} is not {pitch} {roll}"
+    );
+}
+
+#[test]
+fn the_tilt_is_the_direction_of_the_gravity() {
+    let mut inclinometer = Inclinometer::new(Config::default());
+    assert_close(feed(&mut inclinometer, gravity(0.0, 0.0), 0, 1), 0.0, 0.0);
+    assert!(inclinometer.is_level());
+
+    let mut inclinometer = Inclinometer::new(Config::default());
+    assert_close(
+        feed(&mut inclinometer, gravity(10.0, -20.0), 0, 1),
+        10.0,
+        -20.0,
+    );
+    assert!(!inclinometer.is_level());
+}
+
+#[test]
+fn the_tilt_is_smoothed() {
+    let mut inclinometer =
+        Inclinometer::new(Config::default().time_constant(Duration::from_millis(300)));
+    feed(&mut inclinometer, gravity(0.0, 0.0), 0, 1);
+
+    // A vibration of a sample moves the tilt only a little
+    let tilt = feed(&mut inclinometer, gravity(0.0, 10.0), 10, 1);
+    assert!(tilt.roll > 0.0 && tilt.roll < 0.5, "{tilt:?}");
+
+    // After a time constant, the tilt changed by about 63%
+    let tilt = feed(&mut inclinometer, gravity(0.0, 10.0), 20, 29);
+    assert!(tilt.roll > 5.5 && tilt.roll < 7.0, "{tilt:?}");
+
+    // After a few, it settles
+    let tilt = feed(&mut inclinometer, gravity(0.0, 10.0), 310, 300);
+    assert_close(tilt, 0.0, 10.0);
+}
+
+#[test]
+fn calibrating_takes_the_tilt_as_zero() {
+    let mut inclinometer = Inclinometer::new(Config::default());
+    // Calibrating without samples does nothing
+    inclinometer.calibrate();
+    assert_eq!(inclinometer.offset(), Tilt::default());
+
+    feed(&mut inclinometer, gravity(2.0, 3.0), 0, 1);
+    inclinometer.calibrate();
+    assert_close(inclinometer.offset(), 2.0, 3.0);
+    assert!(inclinometer.is_level());
+
+    let tilt = feed(&mut inclinometer, gravity(5.0, 3.0), 10, 500);
+    assert_close(tilt, 3.0, 0.0);
+}
+
+#[test]
+fn the_level_has_a_hysteresis() {
+    let mut inclinometer = Inclinometer::new(
+        Config::default()
+            .time_constant(Duration::from_millis(0))
+            .level_threshold(0.5)
+            .hysteresis(0.2),
+    );
+    let mut time = 0;
+    let mut level = |pitch| {
+        feed(&mut inclinometer, gravity(pitch, 0.0), time, 1);
+        time += PERIOD_MS;
+        inclinometer.is_level()
+    };
+    assert!(!level(0.6));
+    assert!(level(0.4));
+    // Level until above 0.7 deg
+    assert!(level(0.6));
+    assert!(!level(0.8));
+    assert!(!level(0.6));
+}
+
+#[test]
+fn the_roll_is_wrapped_around_upside_down() {
+    let mut inclinometer =
+        Inclinometer::new(Config::default().time_constant(Duration::from_millis(0)));
+    feed(&mut inclinometer, gravity(0.0, 179.8), 0, 1);
+    inclinometer.calibrate();
+
+    // The roll jumps to -180 deg, the board moved only a little
+    let tilt = feed(&mut inclinometer, gravity(0.0, -179.9), 10, 1);
+    assert_close(tilt, 0.0, 0.3);
+    assert!(inclinometer.is_level());
+
+    let tilt = feed(&mut inclinometer, gravity(0.0, 178.8), 20, 1);
+    assert_close(tilt, 0.0, -1.0);
+    assert!(!inclinometer.is_level());
+}
@@ -1,3 +1,11 @@
 #![no_std]
 
+pub mod display;
+pub mod gesture;
+pub mod inclinometer;
+pub mod low_pass;
+pub mod maze;
 pub mod mpu6500;
+pub mod orientation;
+pub mod pedometer;
+pub mod spectrum;
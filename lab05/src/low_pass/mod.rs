@@ -0,0 +1,72 @@
+//! First order low pass filter.
+//!
+//! The [`LowPass`] filter smooths samples that are not taken at a fixed
+//! rate. Every sample moves the output towards it by
+//! `dt / (time_constant + dt)` of the difference, where `dt` is the time
+//! since the previous sample. The weight depends on the time between the
+//! samples, so the smoothing does not depend on the sample rate: after a
+//! time constant, the output covered about 63% of a step.
+//!
+//! ```ignore
+//! let mut gravity = LowPass::<3>::new(Duration::from_millis(200));
+//! loop {
+//!     let accel = mpu6500.read_acceleration()?;
+//!     let [x, y, z] = gravity.update([accel.x, accel.y, accel.z], Instant::now());
+//! }
+//! ```
+
+#[cfg(test)]
+mod tests;
+
+use embassy_time::{Duration, Instant};
+
+/// Smooths `N` values that are sampled together.
+pub struct LowPass<const N: usize> {
+    /// The time constant in s
+    time_constant: f32,
+    /// The output and the time of the previous
+    /// sample, `None` before the first sample
+    state: Option<([f32; N], Instant)>,
+}
+
+impl<const N: usize> LowPass<N> {
+    /// Creates a new filter, without samples
+    pub fn new(time_constant: Duration) -> LowPass<N> {
+        LowPass {
+            time_constant: time_constant.as_micros() as f32 / 1_000_000f32,
+            state: None,
+        }
+    }
+
+    /// Adds a sample and returns the output.
+    ///
+    /// The first sample is not smoothed. A sample that is not newer
+    /// than the previous one does not change the output, even
+    /// with a time constant of 0.
+    pub fn update(&mut self, sample: [f32; N], timestamp: Instant) -> [f32; N] {
+        let output = match self.state {
+            None => sample,
+            Some((mut output, last)) => {
+                let dt =
+                    timestamp.saturating_duration_since(last).as_micros() as f32 / 1_000_000f32;
+                let weight = dt / (self.time_constant + dt).max(f32::EPSILON);
+                for (output, sample) in output.iter_mut().zip(sample) {
+                    *output += weight * (sample - *output);
+                }
+                output
+            }
+        };
+        self.state = Some((output, timestamp));
+        output
+    }
+
+    /// Returns the output, `None` before the first sample
+    pub fn output(&self) -> Option<[f32; N]> {
+        self.state.map(|(output, _)| output)
+    }
+
+    /// Forgets the samples
+    pub fn reset(&mut self) {
+        self.state = None;
+    }
+}
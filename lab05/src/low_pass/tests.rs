@@ -0,0 +1,56 @@
+//! Host tests for the low pass filter.
+
+use embassy_time::{Duration, Instant};
+
+use crate::low_pass::LowPass;
+
+/// Feeds a step from 0 to 1 sampled every `period_ms` for
+/// `duration_ms` and returns the output
+fn step_response(period_ms: u64, duration_ms: u64) -> f32 {
+    let mut filter = LowPass::<1>::new(Duration::from_millis(100));
+    filter.update([0.0], Instant::from_millis(0));
+    let mut output = [0.0];
+    for time in (period_ms..=duration_ms).step_by(period_ms as usize) {
+        output = filter.update([1.0], Instant::from_millis(time));
+    }
+    output[0]
+}
+
+#[test]
+fn the_first_sample_is_not_smoothed() {
+    let mut filter = LowPass::<2>::new(Duration::from_millis(100));
+    assert_eq!(filter.output(), None);
+    assert_eq!(
+        filter.update([1.0, -2.0], Instant::from_millis(0)),
+        [1.0, -2.0]
+    );
+    assert_eq!(filter.output(), Some([1.0, -2.0]));
+
+    filter.reset();
+    assert_eq!(filter.output(), None);
+    assert_eq!(
+        filter.update([3.0, 4.0], Instant::from_millis(10)),
+        [3.0, 4.0]
+    );
+}
+
+#[test]
+fn the_smoothing_does_not_depend_on_the_sample_rate() {
+    // After a time constant, about 63% of the step
+    for period_ms in [1, 5, 10] {
+        let output = step_response(period_ms, 100);
+        assert!(output > 0.6 && output < 0.7, "{period_ms} ms: {output}");
+    }
+    // After a few, it settles
+    assert!(step_response(10, 1000) > 0.99);
+}
+
+#[test]
+fn samples_that_are_not_newer_do_not_change_the_output() {
+    let mut filter = LowPass::<1>::new(Duration::from_millis(0));
+    filter.update([1.0], Instant::from_millis(10));
+    assert_eq!(filter.update([5.0], Instant::from_millis(10)), [1.0]);
+    assert_eq!(filter.update([5.0], Instant::from_millis(5)), [1.0]);
+    // Without a time constant, the output is the sample
+    assert_eq!(filter.update([5.0], Instant::from_millis(20)), [5.0]);
+}
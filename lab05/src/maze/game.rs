@@ -0,0 +1,306 @@
+//! The game logic.
+//!
+//! The ball is a square of `2 * ball_radius` pixels for the walls, it
+//! moves along X and then along Y, and every move that takes the ball
+//! into a wall cell puts it back against the wall and reverses its
+//! speed. A step moves the ball at most `ball_radius` pixels at once,
+//! a fast ball moves in several smaller moves, so that it does not
+//! jump over a wall.
+
+use embassy_time::{Duration, Instant};
+use embedded_graphics::prelude::{Point, Size};
+use heapless::Vec;
+
+use crate::maze::{Config, Event, MAX_COINS};
+
+/// The maximum number of events generated by a step
+const MAX_EVENTS: usize = 4;
+
+/// The events generated by a step
+pub type Events = Vec<Event, MAX_EVENTS>;
+
+/// The largest number of steps returned by [`FixedStep::steps`]
+const MAX_STEPS: u32 = 5;
+
+/// Keeps the ball off the next cell when it touches a wall
+const EPSILON: f32 = 0.001;
+
+/// The state of a game.
+pub struct Game {
+    maze: &'static [&'static str],
+    config: Config,
+    /// The position of the ball's center at the start
+    start: [f32; 2],
+    /// The position of the ball's center
+    position: [f32; 2],
+    /// The speed of the ball in pixels/s
+    velocity: [f32; 2],
+    /// The cells (column, row) of the coins
+    coins: Vec<(u32, u32), MAX_COINS>,
+    /// A bit for every coin, set if the coin was collected
+    collected: u32,
+    score: u32,
+}
+
+/// Counts the game steps to run for the time that
+/// elapsed, see the module documentation.
+pub struct FixedStep {
+    step: Duration,
+    /// The time of the previous call of [`FixedStep::steps`]
+    last: Option<Instant>,
+    /// The time that elapsed and was not simulated yet
+    lag: Duration,
+}
+
+impl Game {
+    /// Creates a new game in `maze`, the ball is at the start position.
+    ///
+    /// The rows of the maze have to have the same length,
+    /// the maze has to have a start and at most [`MAX_COINS`] coins.
+    pub fn new(maze: &'static [&'static str], config: Config) -> Game {
+        let width = maze.first().map_or(0, |row| row.len());
+        assert!(
+            width > 0 && maze.iter().all(|row| row.len() == width),
+            "the rows of the maze have to have the same length"
+        );
+        assert!(
+            config.ball_radius > 0.0 && 2.0 * config.ball_radius < config.cell_size as f32,
+            "the ball has to be smaller than a cell"
+        );
+
+        let mut start = None;
+        let mut coins = Vec::new();
+        for (row, line) in maze.iter().enumerate() {
+            for (column, cell) in line.bytes().enumerate() {
+                match cell {
+                    b'o' => start = Some((column as u32, row as u32)),
+                    b'*' => coins
+                        .push((column as u32, row as u32))
+                        .expect("the maze has too many coins"),
+                    _ => {}
+                }
+            }
+        }
+        let (column, row) = start.expect("the maze has no start");
+        let start = cell_center(config.cell_size, column, row);
+        Game {
+            maze,
+            config,
+            start,
+            position: start,
+            velocity: [0.0, 0.0],
+            coins,
+            collected: 0,
+            score: 0,
+        }
+    }
+
+    /// Returns the configuration
+    pub fn config(&self) -> Config {
+        self.config
+    }
+
+    /// Returns the size of the maze in pixels
+    pub fn size(&self) -> Size {
+        Size::new(
+            self.maze[0].len() as u32 * self.config.cell_size,
+            self.maze.len() as u32 * self.config.cell_size,
+        )
+    }
+
+    /// Returns `true` if the cell is a wall, the cells
+    /// outside the maze are walls
+    pub fn is_wall(&self, column: i32, row: i32) -> bool {
+        usize::try_from(row)
+            .ok()
+            .and_then(|row| self.maze.get(row))
+            .zip(usize::try_from(column).ok())
+            .and_then(|(line, column)| line.as_bytes().get(column))
+            .is_none_or(|cell| *cell == b'#')
+    }
+
+    /// Returns the position of the ball's center in pixels
+    pub fn position(&self) -> [f32; 2] {
+        self.position
+    }
+
+    /// Returns the speed of the ball in pixels/s
+    pub fn velocity(&self) -> [f32; 2] {
+        self.velocity
+    }
+
+    /// Returns the number of coins collected
+    pub fn score(&self) -> u32 {
+        self.score
+    }
+
+    /// Returns the cells (column, row) of the coins
+    /// and whether they were collected
+    pub fn coins(&self) -> impl Iterator<Item = (Point, bool)> + '_ {
+        self.coins.iter().enumerate().map(|(index, (column, row))| {
+            (
+                Point::new(*column as i32, *row as i32),
+                self.collected & (1 << index) != 0,
+            )
+        })
+    }
+
+    /// Puts the ball at the start and the coins
+    /// back, the score is 0
+    pub fn reset(&mut self) {
+        self.position = self.start;
+        self.velocity = [0.0, 0.0];
+        self.collected = 0;
+        self.score = 0;
+    }
+
+    /// Moves the ball for a [`Config::time_step`].
+    ///
+    /// `tilt` is the acceleration in g that pulls the ball to the right
+    /// (X) and down (Y) on the screen, the part of the gravity along the
+    /// board that the accelerometer measures.
+    pub fn step(&mut self, tilt: [f32; 2]) -> Events {
+        let mut events = Events::new();
+        let dt = self.config.time_step.as_micros() as f32 / 1_000_000f32;
+
+        let damping = (1.0 - self.config.friction * dt).max(0.0);
+        for (velocity, tilt) in self.velocity.iter_mut().zip(tilt) {
+            *velocity = (*velocity + tilt * self.config.gravity * dt) * damping;
+        }
+
+        // Move in parts of at most a ball radius
+        let distance = self.velocity[0].abs().max(self.velocity[1].abs()) * dt;
+        let moves = libm::ceilf(distance / self.config.ball_radius).max(1.0) as u32;
+        let mut hit = false;
+        for _ in 0..moves {
+            for axis in 0..2 {
+                let delta = self.velocity[axis] * dt / moves as f32;
+                hit |= self.move_along(axis, delta);
+            }
+        }
+        if hit {
+            push(&mut events, Event::Wall);
+        }
+
+        self.collect_coins(&mut events);
+        events
+    }
+}
+
+/// Private API
+impl Game {
+    /// Returns the bits of the collected coins
+    pub(crate) fn collected(&self) -> u32 {
+        self.collected
+    }
+
+    /// Moves the ball along an axis (0 for X, 1 for Y), returns
+    /// `true` if it hit a wall faster than the hit speed
+    fn move_along(&mut self, axis: usize, delta: f32) -> bool {
+        if delta == 0.0 {
+            return false;
+        }
+        let radius = self.config.ball_radius;
+        let cell_size = self.config.cell_size as f32;
+        self.position[axis] += delta;
+
+        // The cell that the front of the ball entered
+        let front = if delta > 0.0 {
+            self.position[axis] + radius - EPSILON
+        } else {
+            self.position[axis] - radius
+        };
+        let line = libm::floorf(front / cell_size) as i32;
+
+        // The cells that the ball covers on the other axis
+        let other = self.position[1 - axis];
+        let first = libm::floorf((other - radius) / cell_size) as i32;
+        let last = libm::floorf((other + radius - EPSILON) / cell_size) as i32;
+        let blocked = (first..=last).any(|cell| match axis {
+            0 => self.is_wall(line, cell),
+            _ => self.is_wall(cell, line),
+        });
+        if !blocked {
+            return false;
+        }
+
+        // Put the ball against the wall and bounce
+        self.position[axis] = if delta > 0.0 {
+            line as f32 * cell_size - radius
+        } else {
+            (line + 1) as f32 * cell_size + radius
+        };
+        let speed = self.velocity[axis].abs();
+        self.velocity[axis] = -self.velocity[axis] * self.config.bounce;
+        speed > self.config.hit_speed
+    }
+
+    /// Collects the coins that the ball touches
+    fn collect_coins(&mut self, events: &mut Events) {
+        let reach = self.config.ball_radius + self.config.cell_size as f32 / 4.0;
+        for (index, (column, row)) in self.coins.iter().enumerate() {
+            let [x, y] = cell_center(self.config.cell_size, *column, *row);
+            let (dx, dy) = (x - self.position[0], y - self.position[1]);
+            if self.collected & (1 << index) == 0 && dx * dx + dy * dy < reach * reach {
+                self.collected |= 1 << index;
+                self.score += 1;
+                push(events, Event::Coin);
+            }
+        }
+        if !self.coins.is_empty() && self.collected.count_ones() as usize == self.coins.len() {
+            self.collected = 0;
+            push(events, Event::Cleared);
+        }
+    }
+}
+
+/// Returns the center of a cell in pixels
+fn cell_center(cell_size: u32, column: u32, row: u32) -> [f32; 2] {
+    let cell_size = cell_size as f32;
+    [
+        (column as f32 + 0.5) * cell_size,
+        (row as f32 + 0.5) * cell_size,
+    ]
+}
+
+fn push(events: &mut Events, event: Event) {
+    // The score counts all the coins, even if
+    // their events do not fit
+    let _ = events.push(event);
+}
+
+impl FixedStep {
+    /// Creates a clock with steps of `step`
+    pub fn new(step: Duration) -> FixedStep {
+        assert!(
+            step > Duration::from_ticks(0),
+            "the step has to be longer than 0"
+        );
+        FixedStep {
+            step,
+            last: None,
+            lag: Duration::from_ticks(0),
+        }
+    }
+
+    /// Returns the number of steps that fit in the time elapsed since
+    /// the previous call, `0` for the first call.
+    ///
+    /// The time that does not fit in a step is kept for the next call.
+    /// At most 5 steps are returned, after a long pause (like
+    /// a debugger stop) the game continues from where it was.
+    pub fn steps(&mut self, now: Instant) -> u32 {
+        if let Some(last) = self.last {
+            self.lag += now.saturating_duration_since(last);
+        }
+        self.last = Some(now);
+
+        let steps = (self.lag.as_ticks() / self.step.as_ticks()) as u32;
+        if steps > MAX_STEPS {
+            self.lag = Duration::from_ticks(0);
+            return MAX_STEPS;
+        }
+        self.lag -= self.step * steps;
+        steps
+    }
+}
@@ -0,0 +1,221 @@
+//! Tilt controlled ball-in-maze game.
+//!
+//! A ball rolls through a maze as if the board were a tray: tilting the
+//! board accelerates the ball downhill, the walls stop it and it slows
+//! down by itself. The ball collects the coins of the maze, every coin
+//! adds a point to the score. When all the coins are collected, they
+//! appear again.
+//!
+//! The maze is described by text, a character for every cell:
+//! - `#` - a wall
+//! - `o` - the start position of the ball
+//! - `*` - a coin
+//! - any other character - an empty cell
+//!
+//! ```text
+//! #######
+//! #o  #*#
+//! # # # #
+//! #   * #
+//! #######
+//! ```
+//!
+//! The game is split in two parts, so that the game logic runs
+//! on the host in tests:
+//! - [`Game`] - the game logic, moves the ball in steps of
+//!   [`Config::time_step`]
+//! - [`View`] - draws the game on any `embedded_graphics` target; it
+//!   draws the maze once and then only the pixels that changed: the
+//!   ball's previous and new position and the collected coins
+//!
+//! # Fixed time step
+//!
+//! The ball moves the same distance in every step, whatever the frame
+//! rate. Drawing takes a different time in every frame, so the game
+//! does not step once per frame. [`FixedStep`] counts the steps that
+//! fit in the time elapsed since the previous frame, the game runs that
+//! many steps and then the frame is drawn. A slow frame is followed by
+//! several steps, the game does not slow down.
+//!
+//! ```ignore
+//! let mut game = Game::new(MAZE, Config::default());
+//! let mut view = View::new(Point::new(0, 12), Config::default());
+//! let mut clock = FixedStep::new(Config::default().time_step);
+//! loop {
+//!     let accel = mpu6500.read_acceleration()?;
+//!     for _ in 0..clock.steps(Instant::now()) {
+//!         for event in game.step([-accel.x, accel.y]) {
+//!             info!("{}", event);
+//!         }
+//!     }
+//!     view.draw(&game, &mut screen)?;
+//! }
+//! ```
+
+mod game;
+mod view;
+
+#[cfg(test)]
+mod tests;
+
+use embassy_time::Duration;
+use embedded_graphics::{pixelcolor::Rgb565, prelude::RgbColor};
+
+pub use game::{Events, FixedStep, Game};
+pub use view::View;
+
+/// The largest number of coins of a maze
+pub const MAX_COINS: usize = 32;
+
+/// Something that happened during a game step.
+#[derive(Copy, Clone, PartialEq, Debug, defmt::Format)]
+pub enum Event {
+    /// The ball hit a wall
+    Wall,
+    /// The ball collected a coin
+    Coin,
+    /// The ball collected the last coin, the coins appear again
+    Cleared,
+}
+
+/// The game configuration.
+///
+/// The distances are in pixels, the cells of the maze
+/// are squares of `cell_size` pixels.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Config {
+    /// The size of a cell of the maze
+    pub cell_size: u32,
+    /// The radius of the ball, smaller than half a cell
+    pub ball_radius: f32,
+    /// The acceleration of the ball when the board is tilted
+    /// by 90 degrees (1 g on the axis), in pixels/s^2
+    pub gravity: f32,
+    /// The part of its speed that the ball loses every second
+    pub friction: f32,
+    /// The part of its speed that the ball keeps when
+    /// it bounces off a wall, from 0 to 1
+    pub bounce: f32,
+    /// The speed in pixels/s above which hitting a wall
+    /// is reported as [`Event::Wall`]
+    pub hit_speed: f32,
+    /// The time simulated by a game step
+    pub time_step: Duration,
+    /// The color of the empty cells
+    pub background: Rgb565,
+    /// The color of the walls
+    pub wall: Rgb565,
+    /// The color of the ball
+    pub ball: Rgb565,
+    /// The color of the coins
+    pub coin: Rgb565,
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config {
+            cell_size: 8,
+            ball_radius: 2.5,
+            gravity: 400.0,
+            friction: 0.8,
+            bounce: 0.5,
+            hit_speed: 20.0,
+            time_step: Duration::from_millis(20),
+            background: Rgb565::BLACK,
+            wall: Rgb565::BLUE,
+            ball: Rgb565::WHITE,
+            coin: Rgb565::YELLOW,
+        }
+    }
+}
+
+/// Builder functions
+impl Config {
+    /// Sets the size of a cell of the maze
+    pub fn cell_size(self, value: u32) -> Config {
+        Config {
+            cell_size: value,
+            ..self
+        }
+    }
+
+    /// Sets the radius of the ball
+    pub fn ball_radius(self, value: f32) -> Config {
+        Config {
+            ball_radius: value,
+            ..self
+        }
+    }
+
+    /// Sets the acceleration of the ball at 1 g
+    pub fn gravity(self, value: f32) -> Config {
+        Config {
+            gravity: value,
+            ..self
+        }
+    }
+
+    /// Sets the part of its speed that the ball loses every second
+    pub fn friction(self, value: f32) -> Config {
+        Config {
+            friction: value,
+            ..self
+        }
+    }
+
+    /// Sets the part of its speed that the ball keeps after a bounce
+    pub fn bounce(self, value: f32) -> Config {
+        Config {
+            bounce: value,
+            ..self
+        }
+    }
+
+    /// Sets the speed above which a hit is reported
+    pub fn hit_speed(self, value: f32) -> Config {
+        Config {
+            hit_speed: value,
+            ..self
+        }
+    }
+
+    /// Sets the time simulated by a game step
+    pub fn time_step(self, value: Duration) -> Config {
+        Config {
+            time_step: value,
+            ..self
+        }
+    }
+
+    /// Sets the color of the empty cells
+    pub fn background(self, value: Rgb565) -> Config {
+        Config {
+            background: value,
+            ..self
+        }
+    }
+
+    /// Sets the color of the walls
+    pub fn wall(self, value: Rgb565) -> Config {
+        Config {
+            wall: value,
+            ..self
+        }
+    }
+
+    /// Sets the color of the ball
+    pub fn ball(self, value: Rgb565) -> Config {
+        Config {
+            ball: value,
+            ..self
+        }
+    }
+
+    /// Sets the color of the coins
+    pub fn coin(self, value: Rgb565) -> Config {
+        Config {
+            coin: value,
+            ..self
+        }
+    }
+}
@@ -0,0 +1,175 @@
+//! Host tests for the maze game.
+//!
+//! The game runs with the default configuration, 8 pixel cells
+//! and steps of 20 ms, the view draws into the `embedded_graphics`
+//! mock display.
+
+extern crate std;
+
+use std::vec::Vec;
+
+use embassy_time::{Duration, Instant};
+use embedded_graphics::{
+    mock_display::MockDisplay,
+    pixelcolor::Rgb565,
+    prelude::{Point, RgbColor, Size},
+    primitives::Rectangle,
+};
+
+use crate::maze::{Config, Event, FixedStep, Game, View};
+
+/// An empty room, the ball starts at (12, 12)
+const ROOM: &[&str] = &[
+    "#######", //
+    "#o    #", "#     #", "#######",
+];
+
+/// A corridor with two coins
+const COINS: &[&str] = &[
+    "######", //
+    "#o*.*#", "######",
+];
+
+/// Runs `steps` steps and collects the events
+fn run(game: &mut Game, tilt: [f32; 2], steps: usize) -> Vec<Event> {
+    (0..steps).flat_map(|_| game.step(tilt)).collect()
+}
+
+#[test]
+fn the_ball_rolls_downhill_and_slows_down() {
+    let mut game = Game::new(ROOM, Config::default());
+    assert_eq!(game.position(), [12.0, 12.0]);
+
+    run(&mut game, [0.5, 0.0], 10);
+    let [x, y] = game.position();
+    assert!(x > 14.0 && x < 20.0, "x = {x}");
+    assert_eq!(y, 12.0);
+
+    // On a level board, only the friction acts on the ball
+    let speed = game.velocity()[0];
+    run(&mut game, [0.0, 0.0], 10);
+    assert!(game.velocity()[0] > 0.0 && game.velocity()[0] < speed);
+}
+
+#[test]
+fn walls_stop_and_bounce_the_ball() {
+    let mut game = Game::new(ROOM, Config::default());
+    let mut hits = 0;
+    let mut bounced = false;
+    for _ in 0..100 {
+        let events = game.step([1.0, 1.0]);
+        // The ball touches the right and the bottom walls at most
+        let [x, y] = game.position();
+        assert!(x <= 6.0 * 8.0 - 2.5 && y <= 3.0 * 8.0 - 2.5, "{x} {y}");
+        if events.contains(&Event::Wall) {
+            hits += 1;
+            bounced |= game.velocity()[0] < 0.0;
+        }
+    }
+    assert!(hits > 0 && bounced);
+
+    // The ball rests in the corner, without hits
+    assert!(run(&mut game, [1.0, 1.0], 100).is_empty());
+    assert_eq!(game.position(), [6.0 * 8.0 - 2.5, 3.0 * 8.0 - 2.5]);
+}
+
+#[test]
+fn fast_balls_do_not_pass_through_walls() {
+    const THIN_WALL: &[&str] = &[
+        "#####", //
+        "#o# #", "#####",
+    ];
+    let mut game = Game::new(THIN_WALL, Config::default().friction(0.0).bounce(1.0));
+    for _ in 0..50 {
+        game.step([50.0, 0.0]);
+        assert!(game.position()[0] <= 2.0 * 8.0 - 2.5);
+    }
+}
+
+#[test]
+fn coins_are_collected_once_and_appear_again_when_all_are_collected() {
+    let mut game = Game::new(COINS, Config::default());
+    let mut events = Vec::new();
+    while !events.contains(&Event::Cleared) {
+        assert!(events.len() < 1000, "the coins are not collected");
+        events.extend(game.step([1.0, 0.0]));
+    }
+    assert_eq!(
+        events
+            .iter()
+            .filter(|event| **event != Event::Wall)
+            .collect::<Vec<_>>(),
+        [&Event::Coin, &Event::Coin, &Event::Cleared]
+    );
+    assert_eq!(game.score(), 2);
+    assert!(game.coins().all(|(_, collected)| !collected));
+
+    game.reset();
+    assert_eq!((game.position(), game.score()), ([12.0, 12.0], 0));
+}
+
+#[test]
+fn fixed_step_runs_the_steps_of_the_elapsed_time() {
+    let mut clock = FixedStep::new(Duration::from_millis(20));
+    assert_eq!(clock.steps(Instant::from_millis(1000)), 0);
+    // 50 ms, 10 ms are left for the next call
+    assert_eq!(clock.steps(Instant::from_millis(1050)), 2);
+    assert_eq!(clock.steps(Instant::from_millis(1065)), 1);
+    assert_eq!(clock.steps(Instant::from_millis(1070)), 0);
+    // A long pause does not make the game catch up
+    assert_eq!(clock.steps(Instant::from_millis(5000)), 5);
+    assert_eq!(clock.steps(Instant::from_millis(5030)), 1);
+}
+
+/// Draws the game into an empty display, the display
+/// contains only the pixels drawn
+fn render(view: &mut View, game: &Game) -> MockDisplay<Rgb565> {
+    let mut display = MockDisplay::new();
+    display.set_allow_overdraw(true);
+    view.draw(game, &mut display).unwrap();
+    display
+}
+
+#[test]
+fn the_view_draws_the_maze_and_then_only_the_ball() {
+    let mut game = Game::new(ROOM, Config::default());
+    let mut view = View::new(Point::new(1, 2), Config::default());
+    let display = render(&mut view, &game);
+    assert_eq!(
+        display.affected_area(),
+        Rectangle::new(Point::new(1, 2), Size::new(56, 32))
+    );
+    assert_eq!(display.get_pixel(Point::new(1, 2)), Some(Rgb565::BLUE));
+    assert_eq!(display.get_pixel(Point::new(13, 14)), Some(Rgb565::WHITE));
+    assert_eq!(display.get_pixel(Point::new(20, 14)), Some(Rgb565::BLACK));
+
+    assert_eq!(render(&mut view, &game).affected_area().size, Size::zero());
+
+    // The ball's previous and new areas are drawn
+    let previous = view.ball_area(&game);
+    run(&mut game, [0.5, 0.0], 10);
+    let ball = view.ball_area(&game);
+    assert_ne!(ball, previous);
+    let display = render(&mut view, &game);
+    assert_eq!(
+        display.affected_area(),
+        Rectangle::with_corners(previous.top_left, ball.bottom_right().unwrap())
+    );
+    assert_eq!(display.get_pixel(previous.top_left), Some(Rgb565::BLACK));
+}
+
+#[test]
+fn the_view_erases_the_collected_coins() {
+    let mut game = Game::new(COINS, Config::default());
+    let mut view = View::new(Point::zero(), Config::default());
+    let display = render(&mut view, &game);
+    // The coin in the middle of the cell (2, 1)
+    assert_eq!(display.get_pixel(Point::new(20, 12)), Some(Rgb565::YELLOW));
+
+    while game.score() == 0 {
+        game.step([1.0, 0.0]);
+    }
+    run(&mut game, [-1.0, 0.0], 10);
+    let display = render(&mut view, &game);
+    assert_eq!(display.get_pixel(Point::new(20, 12)), Some(Rgb565::BLACK));
+}
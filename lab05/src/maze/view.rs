@@ -0,0 +1,158 @@
+//! Draws the game.
+
+use embedded_graphics::{
+    Drawable,
+    draw_target::DrawTarget,
+    pixelcolor::Rgb565,
+    prelude::{Point, Primitive, Size},
+    primitives::{Circle, PrimitiveStyle, Rectangle},
+};
+
+use crate::maze::{Config, Game};
+
+/// Draws a [`Game`], only the pixels that changed since the
+/// last draw are sent to the display.
+pub struct View {
+    top_left: Point,
+    config: Config,
+    /// Whether the walls were drawn
+    maze_drawn: bool,
+    /// The area of the ball drawn
+    drawn_ball: Option<Rectangle>,
+    /// The coins drawn, a bit for every coin, set if the coin
+    /// was collected, `None` if the coins have to be drawn
+    drawn_coins: Option<u32>,
+}
+
+impl View {
+    /// Creates a view that draws the maze with its
+    /// top left corner at `top_left`.
+    ///
+    /// The `config` has to be the configuration of the game.
+    pub fn new(top_left: Point, config: Config) -> View {
+        View {
+            top_left,
+            config,
+            maze_drawn: false,
+            drawn_ball: None,
+            drawn_coins: None,
+        }
+    }
+
+    /// Returns the screen area of the ball
+    pub fn ball_area(&self, game: &Game) -> Rectangle {
+        let [x, y] = game.position();
+        let radius = self.config.ball_radius;
+        let diameter = libm::roundf(2.0 * radius) as u32;
+        let top_left = Point::new(
+            libm::roundf(x - radius) as i32,
+            libm::roundf(y - radius) as i32,
+        );
+        Rectangle::new(self.top_left + top_left, Size::new(diameter, diameter))
+    }
+
+    /// Makes the next [`View::draw`] draw the whole maze.
+    ///
+    /// Use this after something else was drawn over the
+    /// maze, like after clearing the screen.
+    pub fn invalidate(&mut self) {
+        self.maze_drawn = false;
+        self.drawn_ball = None;
+        self.drawn_coins = None;
+    }
+
+    /// Draws the parts of the game that changed since the last draw
+    pub fn draw<D: DrawTarget<Color = Rgb565>>(
+        &mut self,
+        game: &Game,
+        target: &mut D,
+    ) -> Result<(), D::Error> {
+        if !self.maze_drawn {
+            self.draw_maze(game, target)?;
+            self.maze_drawn = true;
+            self.drawn_ball = None;
+            self.drawn_coins = None;
+        }
+
+        let ball = self.ball_area(game);
+        let mut ball_dirty = self.drawn_ball != Some(ball);
+
+        // Erase the ball where it was, the coins under it are drawn again
+        let mut redraw = self
+            .drawn_coins
+            .map_or(u32::MAX, |drawn| drawn ^ game.collected());
+        if let Some(drawn) = self.drawn_ball
+            && ball_dirty
+        {
+            target.fill_solid(&drawn, self.config.background)?;
+            for (index, (cell, _)) in game.coins().enumerate() {
+                if self.coin_area(cell).intersection(&drawn).size != Size::zero() {
+                    redraw |= 1 << index;
+                }
+            }
+        }
+
+        for (index, (cell, collected)) in game.coins().enumerate() {
+            if redraw & (1 << index) == 0 {
+                continue;
+            }
+            let area = self.coin_area(cell);
+            target.fill_solid(&area, self.config.background)?;
+            if !collected {
+                Circle::new(area.top_left, area.size.width)
+                    .into_styled(PrimitiveStyle::with_fill(self.config.coin))
+                    .draw(target)?;
+            }
+            ball_dirty |= area.intersection(&ball).size != Size::zero();
+        }
+        self.drawn_coins = Some(game.collected());
+
+        if ball_dirty {
+            Circle::new(ball.top_left, ball.size.width)
+                .into_styled(PrimitiveStyle::with_fill(self.config.ball))
+                .draw(target)?;
+            self.drawn_ball = Some(ball);
+        }
+        Ok(())
+    }
+}
+
+/// Private API
+impl View {
+    fn cell_area(&self, cell: Point) -> Rectangle {
+        let cell_size = self.config.cell_size;
+        Rectangle::new(
+            self.top_left + cell * cell_size as i32,
+            Size::new(cell_size, cell_size),
+        )
+    }
+
+    /// The area of a coin, a square of half a cell
+    /// in the middle of the cell
+    fn coin_area(&self, cell: Point) -> Rectangle {
+        let cell_area = self.cell_area(cell);
+        let size = (self.config.cell_size / 2).max(1);
+        Rectangle::with_center(cell_area.center(), Size::new(size, size))
+    }
+
+    fn draw_maze<D: DrawTarget<Color = Rgb565>>(
+        &self,
+        game: &Game,
+        target: &mut D,
+    ) -> Result<(), D::Error> {
+        target.fill_solid(
+            &Rectangle::new(self.top_left, game.size()),
+            self.config.background,
+        )?;
+        let cells = game.size() / self.config.cell_size;
+        for row in 0..cells.height as i32 {
+            for column in 0..cells.width as i32 {
+                if game.is_wall(column, row) {
+                    target
+                        .fill_solid(&self.cell_area(Point::new(column, row)), self.config.wall)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,476 @@
+//! AK8963 magnetometer.
+//!
+//! The MPU 9250 has an AK8963 magnetometer connected to its auxiliary
+//! I2C bus. An AK8963 breakout can also be connected to the auxiliary
+//! bus of an MPU 6500. The driver uses the I2C master (see
+//! [`crate::mpu6500::I2cMasterClock`]) to:
+//! 1. read the magnetometer's sensitivity adjustment values (`ASAX`,
+//!    `ASAY` and `ASAZ`) from its fuse ROM
+//! 2. start the continuous measurement mode, with 16 bits values
+//! 3. read the magnetic field (`HXL` ... `HZH`) and the `ST2` register
+//!    after every sample into the `EXT_SENS_DATA` registers
+//!
+//! The `EXT_SENS_DATA` registers follow the gyro registers, so the
+//! acceleration, temperature, gyro and magnetic field are read in
+//! a single 21 bytes transfer (the *9 axis* measurement).
+//!
+//! The magnetometer's axes are not the same as the accelerometer's
+//! axes. The driver returns the magnetic field using the
+//! accelerometer's axes:
+//! - X is the magnetometer's Y axis
+//! - Y is the magnetometer's X axis
+//! - Z is the magnetometer's -Z axis
+//!
+//! The magnetic field measured is distorted by the magnetised (*hard
+//! iron*) and magnetic (*soft iron*) materials near the sensor, like the
+//! board's components. The [`MagCalibrator`] computes the
+//! [`MagCalibration`] that removes the distortion while the sensor is
+//! rotated in all the directions.
+//!
+//! ```ignore
+//! mpu6500.init_magnetometer(MagMode::Continuous100Hz, &mut Delay).await?;
+//! let measurement = mpu6500.read_9_axis().await?;
+//! info!("Heading: {} deg", measurement.heading);
+//! ```
+
+use embedded_hal_async::delay::DelayNs;
+
+use crate::mpu6500::{
+    Acceleration, AuxSlave, BlockingInterface, BlockingMpu6500, Error, Gyro, I2cMasterClock,
+    Interface, Mpu6500, ValueRegister, driver::to_i16_triple, interface::BlockingDelay,
+    temperature_to_celsius,
+};
+
+/// The I2C address of the AK8963
+const AK8963_ADDRESS: u8 = 0x0c;
+
+/// WIA Register Address
+const WIA: u8 = 0x00;
+
+/// WIA Register Value for the AK8963
+const AK8963_WHO_AM_I: u8 = 0x48;
+
+/// HXL Register Address, followed by HXH ... HZH, ST2
+const HXL: u8 = 0x03;
+
+/// CNTL1 Register Address
+const CNTL1: u8 = 0x0a;
+
+/// CNTL2 Register Address
+const CNTL2: u8 = 0x0b;
+
+/// ASAX Register Address, followed by ASAY and ASAZ
+const ASAX: u8 = 0x10;
+
+/// The `SRST` bit of the `CNTL2` register, resets the magnetometer
+const SRST: u8 = 1;
+
+/// The `BIT` bit of the `CNTL1` register, the values have 16 bits
+const BIT_16: u8 = 1 << 4;
+
+/// The `CNTL1` value for the power down mode
+const POWER_DOWN: u8 = 0b0000;
+
+/// The `CNTL1` value for the fuse ROM access mode
+const FUSE_ROM: u8 = 0b1111;
+
+/// The `HOFL` bit of the `ST2` register, the magnetic
+/// sensor overflowed
+const HOFL: u8 = 1 << 3;
+
+/// The number of registers read after every sample,
+/// `HXL` ... `HZH` and `ST2`. `ST2` has to be read to
+/// end the measurement.
+const MAG_DATA_LEN: u8 = 7;
+
+/// The time required to change the mode, in us (the datasheet
+/// requires at least 100 us)
+const MODE_CHANGE_US: u32 = 1000;
+
+/// The sensitivity of the 16 bits values, in uT / LSB
+const UT_PER_LSB: f32 = 0.15;
+
+/// The continuous measurement modes of the `CNTL1` register.
+#[repr(u8)]
+#[derive(Copy, Clone, PartialEq, Debug, defmt::Format)]
+pub enum MagMode {
+    /// A measurement every 125 ms
+    Continuous8Hz = 0b0010,
+    /// A measurement every 10 ms
+    Continuous100Hz = 0b0110,
+}
+
+/// Stores the magnetic field on all the three axes in uT.
+#[derive(Copy, Clone, Default, PartialEq, Debug, defmt::Format)]
+pub struct MagneticField {
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+}
+
+/// The hard iron and soft iron calibration of the magnetometer.
+///
+/// The calibrated field is `soft_iron * (field - hard_iron)`.
+///
+/// The default value does not change the field.
+#[derive(Copy, Clone, PartialEq, Debug, defmt::Format)]
+pub struct MagCalibration {
+    /// The field added by the magnetised materials, in uT
+    pub hard_iron: [f32; 3],
+    /// The matrix that turns the ellipsoid of the
+    /// measured values into a sphere
+    pub soft_iron: [[f32; 3]; 3],
+}
+
+impl Default for MagCalibration {
+    fn default() -> MagCalibration {
+        MagCalibration {
+            hard_iron: [0f32; 3],
+            soft_iron: [[1f32, 0f32, 0f32], [0f32, 1f32, 0f32], [0f32, 0f32, 1f32]],
+        }
+    }
+}
+
+impl MagCalibration {
+    /// Computes the calibration from the minimum and maximum values
+    /// measured on every axis while the sensor was rotated.
+    ///
+    /// The hard iron offset is the center of the values. The soft
+    /// iron matrix is diagonal, it scales every axis to the average
+    /// radius of the values.
+    pub fn from_extremes(min: [f32; 3], max: [f32; 3]) -> MagCalibration {
+        let mut calibration = MagCalibration::default();
+        let mut radius = [0f32; 3];
+        for axis in 0..3 {
+            calibration.hard_iron[axis] = (max[axis] + min[axis]) / 2f32;
+            radius[axis] = (max[axis] - min[axis]) / 2f32;
+        }
+        let average = (radius[0] + radius[1] + radius[2]) / 3f32;
+        for (axis, radius) in radius.into_iter().enumerate() {
+            if radius > 0f32 {
+                calibration.soft_iron[axis][axis] = average / radius;
+            }
+        }
+        calibration
+    }
+
+    /// Returns the calibrated magnetic field.
+    pub fn apply(&self, field: MagneticField) -> MagneticField {
+        let centered = [
+            field.x - self.hard_iron[0],
+            field.y - self.hard_iron[1],
+            field.z - self.hard_iron[2],
+        ];
+        let [x, y, z] = self
+            .soft_iron
+            .map(|row| row[0] * centered[0] + row[1] * centered[1] + row[2] * centered[2]);
+        MagneticField { x, y, z }
+    }
+}
+
+/// Computes the [`MagCalibration`] from the values measured while
+/// the sensor is rotated in all the directions.
+///
+/// The values have to be measured without a calibration,
+/// using the default [`MagCalibration`].
+#[derive(Copy, Clone, Debug, defmt::Format)]
+pub struct MagCalibrator {
+    min: [f32; 3],
+    max: [f32; 3],
+    samples: u32,
+}
+
+impl Default for MagCalibrator {
+    fn default() -> MagCalibrator {
+        MagCalibrator::new()
+    }
+}
+
+impl MagCalibrator {
+    /// Creates a calibrator without any values
+    pub fn new() -> MagCalibrator {
+        MagCalibrator {
+            min: [f32::MAX; 3],
+            max: [f32::MIN; 3],
+            samples: 0,
+        }
+    }
+
+    /// Adds a measured value
+    pub fn update(&mut self, field: MagneticField) {
+        for (axis, value) in [field.x, field.y, field.z].into_iter().enumerate() {
+            self.min[axis] = self.min[axis].min(value);
+            self.max[axis] = self.max[axis].max(value);
+        }
+        self.samples += 1;
+    }
+
+    /// Returns the number of values added
+    pub fn samples(&self) -> u32 {
+        self.samples
+    }
+
+    /// Returns the calibration, `None` if no value was added
+    pub fn calibration(&self) -> Option<MagCalibration> {
+        if self.samples == 0 {
+            None
+        } else {
+            Some(MagCalibration::from_extremes(self.min, self.max))
+        }
+    }
+}
+
+/// The magnetometer state stored by the driver.
+#[derive(Copy, Clone, PartialEq, Debug, defmt::Format)]
+pub struct Ak8963 {
+    /// The sensitivity adjustment of the X, Y and Z axes
+    /// (the magnetometer's axes), computed from `ASAX`,
+    /// `ASAY` and `ASAZ`
+    pub adjustment: [f32; 3],
+    /// The calibration applied to the values
+    pub calibration: MagCalibration,
+}
+
+impl Ak8963 {
+    /// Returns the sensitivity adjustment of an `ASA` register value.
+    ///
+    /// The datasheet defines the adjusted value as:
+    /// Hadj = H * ((ASA - 128) * 0.5 / 128 + 1)
+    fn adjustment(asa: u8) -> f32 {
+        (asa as f32 - 128f32) * 0.5 / 128f32 + 1f32
+    }
+
+    /// Converts the `HXL` ... `HZH` and `ST2` values to the calibrated
+    /// magnetic field, using the accelerometer's axes.
+    ///
+    /// The function returns `None` if the magnetic sensor overflowed.
+    fn convert(&self, data: &[u8]) -> Option<MagneticField> {
+        if data[6] & HOFL != 0 {
+            return None;
+        }
+        // The magnetometer stores the values as little endian
+        let raw = [
+            i16::from_le_bytes([data[0], data[1]]),
+            i16::from_le_bytes([data[2], data[3]]),
+            i16::from_le_bytes([data[4], data[5]]),
+        ];
+        let [x, y, z] = [0, 1, 2].map(|axis| raw[axis] as f32 * self.adjustment[axis] * UT_PER_LSB);
+        Some(self.calibration.apply(MagneticField { x: y, y: x, z: -z }))
+    }
+}
+
+/// Stores the acceleration, temperature, gyro and magnetic field values
+/// that belong to the same sample.
+#[derive(Copy, Clone, Debug, defmt::Format)]
+pub struct NineAxisMeasurement {
+    /// The acceleration in g
+    pub accel: Acceleration,
+    /// The die temperature in deg C
+    pub temperature: f32,
+    /// The gyro in deg/s
+    pub gyro: Gyro,
+    /// The calibrated magnetic field in uT
+    pub mag: MagneticField,
+    /// The tilt compensated heading in deg, see [`heading`]
+    pub heading: f32,
+}
+
+/// Computes the magnetic heading in deg, from 0 to 360.
+///
+/// The heading is the angle between the magnetic north and the
+/// sensor's X axis, clockwise. The acceleration is used to find the
+/// horizontal plane, so the heading is correct even if the sensor is
+/// tilted, as long as it does not accelerate.
+///
+/// The horizontal component of the magnetic field points north. The
+/// west direction is perpendicular on both the vertical (`up`) and
+/// the north directions. The heading is the angle of the X axis
+/// measured in these two directions.
+pub fn heading(accel: Acceleration, mag: MagneticField) -> f32 {
+    let norm = libm::sqrtf(accel.x * accel.x + accel.y * accel.y + accel.z * accel.z);
+    if norm == 0f32 {
+        // Free fall, there is no vertical direction
+        return f32::NAN;
+    }
+    let up = [accel.x / norm, accel.y / norm, accel.z / norm];
+    let vertical = mag.x * up[0] + mag.y * up[1] + mag.z * up[2];
+    // The X component of the north direction
+    let north_x = mag.x - vertical * up[0];
+    // The X component of the west direction, (up x mag).x
+    let west_x = up[1] * mag.z - up[2] * mag.y;
+    let heading = libm::atan2f(-west_x, north_x).to_degrees();
+    if heading < 0f32 {
+        heading + 360f32
+    } else {
+        heading
+    }
+}
+
+/// Magnetometer API
+impl<I: Interface> Mpu6500<I> {
+    /// Initialises the AK8963 magnetometer
+    ///
+    /// The I2C master is enabled at 400 kHz. The magnetometer is reset,
+    /// its sensitivity adjustment values are read and it is started in
+    /// the continuous measurement `mode`. `I2C_SLV0` reads the magnetic
+    /// field after every sample.
+    ///
+    /// The function returns `Error::UnexpectedWhoAmI(value)` if the
+    /// magnetometer's `WIA` register has an unexpected value.
+    pub async fn init_magnetometer(
+        &mut self,
+        mode: MagMode,
+        delay: &mut impl DelayNs,
+    ) -> Result<Ak8963, Error<I::Error>> {
+        self.check_initialised()?;
+        self.magnetometer = None;
+        self.enable_i2c_master(I2cMasterClock::Khz400).await?;
+
+        let wia = self.aux_read(AK8963_ADDRESS, WIA, delay).await?;
+        if wia != AK8963_WHO_AM_I {
+            return Err(Error::UnexpectedWhoAmI(wia));
+        }
+        self.aux_write(AK8963_ADDRESS, CNTL2, SRST, delay).await?;
+        self.set_magnetometer_mode(POWER_DOWN, delay).await?;
+        self.set_magnetometer_mode(FUSE_ROM, delay).await?;
+        let mut adjustment = [0f32; 3];
+        for (axis, value) in adjustment.iter_mut().enumerate() {
+            let asa = self
+                .aux_read(AK8963_ADDRESS, ASAX + axis as u8, delay)
+                .await?;
+            *value = Ak8963::adjustment(asa);
+        }
+        // The mode can only be changed from the power down mode
+        self.set_magnetometer_mode(POWER_DOWN, delay).await?;
+        self.set_magnetometer_mode(BIT_16 | mode as u8, delay)
+            .await?;
+
+        self.configure_slave_read(AuxSlave::Slv0, AK8963_ADDRESS, HXL, MAG_DATA_LEN)
+            .await?;
+        let magnetometer = Ak8963 {
+            adjustment,
+            calibration: MagCalibration::default(),
+        };
+        self.magnetometer = Some(magnetometer);
+        Ok(magnetometer)
+    }
+
+    /// Sets the calibration applied to the magnetic field
+    ///
+    /// The function returns `Error::NotInitialised` if the
+    /// magnetometer was not initialised.
+    pub fn set_mag_calibration(
+        &mut self,
+        calibration: MagCalibration,
+    ) -> Result<(), Error<I::Error>> {
+        let magnetometer = self.magnetometer.as_mut().ok_or(Error::NotInitialised)?;
+        magnetometer.calibration = calibration;
+        Ok(())
+    }
+
+    /// Returns the magnetometer initialised by [`Mpu6500::init_magnetometer`]
+    pub fn magnetometer(&self) -> Option<Ak8963> {
+        self.magnetometer
+    }
+
+    /// Read the magnetic field
+    ///
+    /// The function returns `Error::NotInitialised` if the magnetometer
+    /// was not initialised and `Error::MagneticOverflow` if the
+    /// magnetic sensor overflowed.
+    pub async fn read_magnetic_field(&mut self) -> Result<MagneticField, Error<I::Error>> {
+        let magnetometer = self.magnetometer.ok_or(Error::NotInitialised)?;
+        let rx: [u8; MAG_DATA_LEN as usize] = self.read_value(ValueRegister::ExtSensData00).await?;
+        magnetometer.convert(&rx).ok_or(Error::MagneticOverflow)
+    }
+
+    /// Read the acceleration, temperature, gyro and magnetic field
+    ///
+    /// The values are read in a single 21 bytes transfer, so they
+    /// belong to the same sample. The heading is computed from the
+    /// acceleration and the magnetic field.
+    pub async fn read_9_axis(&mut self) -> Result<NineAxisMeasurement, Error<I::Error>> {
+        let magnetometer = self.magnetometer.ok_or(Error::NotInitialised)?;
+        // The registers are:
+        // - ACCEL_XOUT_H ... GYRO_ZOUT_L (14 bytes)
+        // - EXT_SENS_DATA_00 ... EXT_SENS_DATA_06 (7 bytes)
+        let rx: [u8; 21] = self.read_value(ValueRegister::AccelXOutH).await?;
+        let mag = magnetometer
+            .convert(&rx[14..21])
+            .ok_or(Error::MagneticOverflow)?;
+        let [ax, ay, az] = to_i16_triple(&rx[0..6]);
+        let [gx, gy, gz] = to_i16_triple(&rx[8..14]);
+        let accel = Acceleration {
+            x: self.config.accel_scale.to_g(ax),
+            y: self.config.accel_scale.to_g(ay),
+            z: self.config.accel_scale.to_g(az),
+        };
+        Ok(NineAxisMeasurement {
+            accel,
+            temperature: temperature_to_celsius(i16::from_be_bytes([rx[6], rx[7]])),
+            gyro: Gyro {
+                x: self.config.gyro_scale.to_deg_s(gx),
+                y: self.config.gyro_scale.to_deg_s(gy),
+                z: self.config.gyro_scale.to_deg_s(gz),
+            },
+            mag,
+            heading: heading(accel, mag),
+        })
+    }
+}
+
+/// Private API
+impl<I: Interface> Mpu6500<I> {
+    /// Internal function that writes the magnetometer's `CNTL1`
+    /// register and waits for the mode to change.
+    async fn set_magnetometer_mode(
+        &mut self,
+        cntl1: u8,
+        delay: &mut impl DelayNs,
+    ) -> Result<(), Error<I::Error>> {
+        self.aux_write(AK8963_ADDRESS, CNTL1, cntl1, delay).await?;
+        delay.delay_us(MODE_CHANGE_US).await;
+        Ok(())
+    }
+}
+
+/// Magnetometer API
+///
+/// The functions have the same meaning as the functions
+/// of the [`Mpu6500`] driver.
+impl<I: BlockingInterface> BlockingMpu6500<I> {
+    /// Initialises the AK8963 magnetometer
+    pub fn init_magnetometer(
+        &mut self,
+        mode: MagMode,
+        delay: &mut impl embedded_hal::delay::DelayNs,
+    ) -> Result<Ak8963, Error<I::Error>> {
+        embassy_futures::block_on(
+            self.driver
+                .init_magnetometer(mode, &mut BlockingDelay(delay)),
+        )
+    }
+
+    /// Sets the calibration applied to the magnetic field
+    pub fn set_mag_calibration(
+        &mut self,
+        calibration: MagCalibration,
+    ) -> Result<(), Error<I::Error>> {
+        self.driver.set_mag_calibration(calibration)
+    }
+
+    /// Returns the magnetometer initialised by [`BlockingMpu6500::init_magnetometer`]
+    pub fn magnetometer(&self) -> Option<Ak8963> {
+        self.driver.magnetometer()
+    }
+
+    /// Read the magnetic field
+    pub fn read_magnetic_field(&mut self) -> Result<MagneticField, Error<I::Error>> {
+        embassy_futures::block_on(self.driver.read_magnetic_field())
+    }
+
+    /// Read the acceleration, temperature, gyro and magnetic field
+    pub fn read_9_axis(&mut self) -> Result<NineAxisMeasurement, Error<I::Error>> {
+        embassy_futures::block_on(self.driver.read_9_axis())
+    }
+}
@@ -0,0 +1,205 @@
+//! MPU 6500 bias calibration.
+//!
+//! Even when it does not move, the sensor reports a small rotation
+//! (the gyro *bias*) and an acceleration that is slightly different
+//! from gravity (the accelerometer bias). The sensor can remove the
+//! bias itself, before the values are written into the data registers,
+//! using the offset registers:
+//! - `XG_OFFSET_H` ... `ZG_OFFSET_L` - the gyro offsets, in steps of
+//!   1 / 32.8 deg/s (the sensitivity of the 1000 deg/s scale)
+//! - `XA_OFFSET_H` ... `ZA_OFFSET_L` - the accelerometer offsets, in
+//!   steps of 0.98 mg
+//!
+//! The accelerometer offsets are stored as 15 bits values, bits 15:1
+//! of the register pair. Bit 0 of `XA_OFFSET_L`, `YA_OFFSET_L` and
+//! `ZA_OFFSET_L` is reserved, it is used by the sensor's temperature
+//! compensation and has to be kept unchanged. The accelerometer offsets
+//! are not 0 at power on, they store the factory trim values.
+//!
+//! The calibration computes the offsets while the sensor is still
+//! and returns them, so that the application can store them and
+//! write them again at boot. The MPU 6000 does not have the same offset
+//! registers, the functions return `Error::Unsupported` for it.
+//!
+//! ```ignore
+//! let gravity = Acceleration { x: 0.0, y: 0.0, z: 1.0 };
+//! let offsets = mpu6500.calibrate(100, gravity, &mut Delay).await?;
+//! // at the next boot
+//! mpu6500.write_offsets(offsets).await?;
+//! ```
+
+use embedded_hal_async::delay::DelayNs;
+
+use crate::mpu6500::{
+    Acceleration, BlockingInterface, BlockingMpu6500, Error, Interface, Mpu6500,
+    driver::to_i16_triple, interface::BlockingDelay,
+};
+
+/// XG_OFFSET_H Register Address, followed by the other gyro offsets
+const XG_OFFSET_H: u8 = 0x13;
+
+/// The accelerometer offset registers are not consecutive, every
+/// axis uses the `XA_OFFSET_H` and `XA_OFFSET_L` register pair.
+const ACCEL_OFFSET_H: [u8; 3] = [0x77, 0x7a, 0x7d];
+
+/// The reserved bit of `XA_OFFSET_L`, `YA_OFFSET_L` and `ZA_OFFSET_L`
+const ACCEL_OFFSET_RESERVED: u16 = 1;
+
+/// The gyro offset registers use the sensitivity of the 1000 deg/s scale
+const GYRO_OFFSET_LSB_PER_DEG_S: f32 = 32.8;
+
+/// The accelerometer offset registers have a resolution of 0.98 mg
+const ACCEL_OFFSET_G_PER_LSB: f32 = 0.00098;
+
+/// The accelerometer offsets have 15 bits
+const ACCEL_OFFSET_MIN: i32 = -(1 << 14);
+const ACCEL_OFFSET_MAX: i32 = (1 << 14) - 1;
+
+/// The values of the offset registers.
+#[derive(Copy, Clone, Default, PartialEq, Debug, defmt::Format)]
+pub struct Offsets {
+    /// The gyro offsets, in steps of 1 / 32.8 deg/s
+    pub gyro: [i16; 3],
+    /// The accelerometer offsets, 15 bits values in steps of 0.98 mg
+    pub accel: [i16; 3],
+}
+
+/// Calibration API
+impl<I: Interface> Mpu6500<I> {
+    /// Reads the offset registers
+    pub async fn read_offsets(&mut self) -> Result<Offsets, Error<I::Error>> {
+        self.check_initialised()?;
+        self.check_mpu6500_compatible()?;
+        let mut rx = [0u8; 6];
+        self.interface.read_registers(XG_OFFSET_H, &mut rx).await?;
+        let gyro = to_i16_triple(&rx);
+
+        let mut accel = [0i16; 3];
+        for (offset, register) in accel.iter_mut().zip(ACCEL_OFFSET_H) {
+            // Shifting a signed value keeps the sign, bit 0 is dropped
+            *offset = self.read_accel_offset(register).await? as i16 >> 1;
+        }
+        Ok(Offsets { gyro, accel })
+    }
+
+    /// Writes the offset registers
+    ///
+    /// The reserved bit of the accelerometer offset registers is kept.
+    pub async fn write_offsets(&mut self, offsets: Offsets) -> Result<(), Error<I::Error>> {
+        self.check_initialised()?;
+        self.check_mpu6500_compatible()?;
+        for (axis, offset) in offsets.gyro.iter().enumerate() {
+            let [high, low] = offset.to_be_bytes();
+            let register = XG_OFFSET_H + 2 * axis as u8;
+            self.interface.write_register(register, high).await?;
+            self.interface.write_register(register + 1, low).await?;
+        }
+        for (offset, register) in offsets.accel.iter().zip(ACCEL_OFFSET_H) {
+            let reserved = self.read_accel_offset(register).await? & ACCEL_OFFSET_RESERVED;
+            let [high, low] = ((*offset as u16) << 1 | reserved).to_be_bytes();
+            self.interface.write_register(register, high).await?;
+            self.interface.write_register(register + 1, low).await?;
+        }
+        Ok(())
+    }
+
+    /// Calibrates the gyro and the accelerometer
+    ///
+    /// The sensor has to be still while `samples` samples are read.
+    /// `gravity` is the acceleration, in g, that the sensor should
+    /// measure in its position, for instance `(0, 0, 1)` if the sensor
+    /// lies flat. The function waits for a new sample between the reads,
+    /// using the configured sample rate.
+    ///
+    /// The offsets that remove the bias are written into the offset
+    /// registers and returned.
+    ///
+    /// The function returns `Error::InvalidConfiguration` if `samples` is 0.
+    pub async fn calibrate(
+        &mut self,
+        samples: u16,
+        gravity: Acceleration,
+        delay: &mut impl DelayNs,
+    ) -> Result<Offsets, Error<I::Error>> {
+        self.check_initialised()?;
+        self.check_mpu6500_compatible()?;
+        if samples == 0 {
+            return Err(Error::InvalidConfiguration);
+        }
+        let sample_period_us = (1_000_000f32 / self.sample_rate()) as u32;
+
+        let mut accel_sum = [0i32; 3];
+        let mut gyro_sum = [0i32; 3];
+        for _ in 0..samples {
+            let raw = self.read_raw().await?;
+            for axis in 0..3 {
+                accel_sum[axis] += raw.accel[axis] as i32;
+                gyro_sum[axis] += raw.gyro[axis] as i32;
+            }
+            delay.delay_us(sample_period_us).await;
+        }
+
+        // The values read already have the current offsets applied,
+        // so the new offsets correct the current ones.
+        let mut offsets = self.read_offsets().await?;
+        let gravity = [gravity.x, gravity.y, gravity.z];
+        for axis in 0..3 {
+            let gyro_bias =
+                gyro_sum[axis] as f32 / samples as f32 / self.config.gyro_scale.sensitivity();
+            let gyro = offsets.gyro[axis] as i32
+                - libm::roundf(gyro_bias * GYRO_OFFSET_LSB_PER_DEG_S) as i32;
+            offsets.gyro[axis] = gyro.clamp(i16::MIN as i32, i16::MAX as i32) as i16;
+
+            let accel_bias =
+                accel_sum[axis] as f32 / samples as f32 / self.config.accel_scale.sensitivity()
+                    - gravity[axis];
+            let accel = offsets.accel[axis] as i32
+                - libm::roundf(accel_bias / ACCEL_OFFSET_G_PER_LSB) as i32;
+            offsets.accel[axis] = accel.clamp(ACCEL_OFFSET_MIN, ACCEL_OFFSET_MAX) as i16;
+        }
+
+        self.write_offsets(offsets).await?;
+        Ok(offsets)
+    }
+}
+
+/// Private API
+impl<I: Interface> Mpu6500<I> {
+    /// Internal function that reads the 16 bits value of an
+    /// accelerometer offset register pair, including the reserved bit.
+    async fn read_accel_offset(&mut self, register: u8) -> Result<u16, Error<I::Error>> {
+        let mut rx = [0u8; 2];
+        self.interface.read_registers(register, &mut rx).await?;
+        Ok(u16::from_be_bytes(rx))
+    }
+}
+
+/// Calibration API
+///
+/// The functions have the same meaning as the functions
+/// of the [`Mpu6500`] driver.
+impl<I: BlockingInterface> BlockingMpu6500<I> {
+    /// Reads the offset registers
+    pub fn read_offsets(&mut self) -> Result<Offsets, Error<I::Error>> {
+        embassy_futures::block_on(self.driver.read_offsets())
+    }
+
+    /// Writes the offset registers
+    pub fn write_offsets(&mut self, offsets: Offsets) -> Result<(), Error<I::Error>> {
+        embassy_futures::block_on(self.driver.write_offsets(offsets))
+    }
+
+    /// Calibrates the gyro and the accelerometer
+    pub fn calibrate(
+        &mut self,
+        samples: u16,
+        gravity: Acceleration,
+        delay: &mut impl embedded_hal::delay::DelayNs,
+    ) -> Result<Offsets, Error<I::Error>> {
+        embassy_futures::block_on(self.driver.calibrate(
+            samples,
+            gravity,
+            &mut BlockingDelay(delay),
+        ))
+    }
+}
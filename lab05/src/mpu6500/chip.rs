@@ -0,0 +1,137 @@
+//! The sensors supported by the driver.
+//!
+//! The MPU 6500 belongs to a family of sensors that share most of
+//! their registers. Student boards carry breakouts with any of them,
+//! so the driver reads the `WHO_AM_I` register and adapts to the
+//! sensor it finds. The differences are:
+//! - the MPU 6000, ICM 20602 and ICM 20608 start in sleep mode and
+//!   have to be woken up
+//! - the MPU 6000 does not have the `ACCEL_CONFIG2` register, the
+//!   accelerometer uses the gyro low pass filter
+//! - the MPU 6000 does not have the wake on motion registers, the
+//!   accelerometer offset registers and the same self-test procedure
+//! - the ICM 20602 has a wake on motion threshold for every axis and
+//!   disables the I2C interface using the `I2C_IF` register
+//! - the ICM 20602 and ICM 20608 do not have the auxiliary I2C bus
+//!
+//! The MPU 9250 is an MPU 6500 and an AK8963 magnetometer in the
+//! same package.
+
+use crate::mpu6500::{ConfigRegister, fifo::USER_CTRL};
+
+/// WHO_AM_I Register Value for the MPU6000 sensor
+const MPU6000_WHO_AM_I: u8 = 0x68;
+
+/// WHO_AM_I Register Value for the MPU6500 sensor
+const MPU6500_WHO_AM_I: u8 = 0x70;
+
+/// WHO_AM_I Register Value for the MPU9250 sensor
+const MPU9250_WHO_AM_I: u8 = 0x71;
+
+/// WHO_AM_I Register Value for the ICM20602 sensor
+const ICM20602_WHO_AM_I: u8 = 0x12;
+
+/// WHO_AM_I Register Value for the ICM20608 sensor
+const ICM20608_WHO_AM_I: u8 = 0xaf;
+
+/// The `I2C_IF_DIS` bit of the `USER_CTRL` register
+const I2C_IF_DIS: u8 = 1 << 4;
+
+/// I2C_IF Register Address of the ICM20602 sensor
+const ICM20602_I2C_IF: u8 = 0x70;
+
+/// The `I2C_IF_DIS` bit of the ICM20602 `I2C_IF` register
+const ICM20602_I2C_IF_DIS: u8 = 1 << 6;
+
+/// PWR_MGMT_1 Register Address
+const PWR_MGMT_1: u8 = ConfigRegister::PowerManagement1 as u8;
+
+/// The `SLEEP` bit and the `CLKSEL` field of the `PWR_MGMT_1` register
+const SLEEP_CLKSEL: u8 = 1 << 6 | 0b111;
+
+/// The `PWR_MGMT_1` value that wakes the sensor up and uses the gyro PLL
+const WAKE_UP: u8 = 1;
+
+/// A step of an init sequence, `(register, mask, bits)`: the `mask`
+/// bits of `register` are set to the values of `bits`.
+pub(crate) type InitStep = (u8, u8, u8);
+
+/// The sensors supported by the driver.
+#[derive(Copy, Clone, PartialEq, Debug, defmt::Format)]
+pub enum Chip {
+    Mpu6000,
+    Mpu6500,
+    Mpu9250,
+    Icm20602,
+    Icm20608,
+}
+
+impl Chip {
+    /// Returns the sensor that has the `WHO_AM_I` register `value`,
+    /// `None` if the sensor is not supported.
+    pub fn from_who_am_i(value: u8) -> Option<Chip> {
+        match value {
+            MPU6000_WHO_AM_I => Some(Chip::Mpu6000),
+            MPU6500_WHO_AM_I => Some(Chip::Mpu6500),
+            MPU9250_WHO_AM_I => Some(Chip::Mpu9250),
+            ICM20602_WHO_AM_I => Some(Chip::Icm20602),
+            ICM20608_WHO_AM_I => Some(Chip::Icm20608),
+            _ => None,
+        }
+    }
+
+    /// Returns the value of the sensor's `WHO_AM_I` register.
+    pub fn who_am_i(&self) -> u8 {
+        match self {
+            Chip::Mpu6000 => MPU6000_WHO_AM_I,
+            Chip::Mpu6500 => MPU6500_WHO_AM_I,
+            Chip::Mpu9250 => MPU9250_WHO_AM_I,
+            Chip::Icm20602 => ICM20602_WHO_AM_I,
+            Chip::Icm20608 => ICM20608_WHO_AM_I,
+        }
+    }
+
+    /// Returns the steps that `Mpu6500::init` runs after the sensor
+    /// was detected.
+    ///
+    /// The sensors that start in sleep mode are woken up. They also
+    /// start with both the I2C and the SPI interfaces enabled. If the
+    /// driver uses `spi`, the I2C interface is disabled. If the driver
+    /// uses I2C, the I2C interface is left enabled.
+    pub(crate) fn init_sequence(&self, spi: bool) -> &'static [InitStep] {
+        match (self, spi) {
+            // They start awake, no init is required
+            (Chip::Mpu6500 | Chip::Mpu9250, _) => &[],
+            (Chip::Mpu6000 | Chip::Icm20608, true) => &[
+                (USER_CTRL, I2C_IF_DIS, I2C_IF_DIS),
+                (PWR_MGMT_1, SLEEP_CLKSEL, WAKE_UP),
+            ],
+            (Chip::Icm20602, true) => &[
+                (ICM20602_I2C_IF, ICM20602_I2C_IF_DIS, ICM20602_I2C_IF_DIS),
+                (PWR_MGMT_1, SLEEP_CLKSEL, WAKE_UP),
+            ],
+            (Chip::Mpu6000 | Chip::Icm20608 | Chip::Icm20602, false) => {
+                &[(PWR_MGMT_1, SLEEP_CLKSEL, WAKE_UP)]
+            }
+        }
+    }
+
+    /// Returns `true` if the sensor has the `ACCEL_CONFIG2`
+    /// register, with a separate accelerometer low pass filter.
+    pub(crate) fn has_accel_config2(&self) -> bool {
+        !matches!(self, Chip::Mpu6000)
+    }
+
+    /// Returns `true` if the sensor has the wake on motion, the
+    /// accelerometer offset and the self-test registers of the
+    /// MPU 6500.
+    pub(crate) fn is_mpu6500_compatible(&self) -> bool {
+        !matches!(self, Chip::Mpu6000)
+    }
+
+    /// Returns `true` if the sensor has the auxiliary I2C bus,
+    /// with the I2C master and the pass-through mode.
+    pub(crate) fn has_i2c_master(&self) -> bool {
+        matches!(self, Chip::Mpu6000 | Chip::Mpu6500 | Chip::Mpu9250)
+    }
+}
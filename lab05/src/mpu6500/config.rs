@@ -0,0 +1,365 @@
+//! MPU 6500 configuration.
+//!
+//! The sensor's configuration is spread across several registers:
+//! - `SMPLRT_DIV` - the sample rate divider
+//! - `CONFIG` - the gyro and temperature digital low pass filter (`DLPF_CFG`)
+//! - `GYRO_CONFIG` - the gyro scale
+//! - `ACCEL_CONFIG` - the acceleration scale
+//! - `ACCEL_CONFIG2` - the acceleration digital low pass filter (`A_DLPF_CFG`)
+//! - `PWR_MGMT_1` - sleep, cycle and clock source
+//! - `PWR_MGMT_2` - the per axis standby
+//!
+//! The [`Config`] structure stores all of them. It is built using
+//! the builder functions and applied with `Mpu6500::configure`:
+//!
+//! ```ignore
+//! let config = Config::default()
+//!     .accel_scale(AccelScale::G2)
+//!     .gyro_scale(GyroScale::Gs1000)
+//!     .gyro_dlpf(GyroDlpf::Hz41)
+//!     .accel_dlpf(AccelDlpf::Hz45)
+//!     .sample_rate_divider(9);
+//! mpu6500.configure(config).await?;
+//! ```
+
+use crate::mpu6500::{AccelScale, Chip, ConfigRegister, GyroScale};
+
+/// The `FIFO_MODE` bit of the `CONFIG` register.
+///
+/// The driver always sets it, so that when the FIFO is full the
+/// sensor stops writing to it. This way the frames that are in the
+/// FIFO are not overwritten and stay aligned.
+pub(crate) const FIFO_MODE: u8 = 1 << 6;
+
+/// The possible values for the `DLPF_CFG` field of the
+/// `CONFIG` register.
+///
+/// The value sets the bandwidth of the gyro and temperature
+/// digital low pass filter. A lower bandwidth means less noise,
+/// but a larger delay.
+///
+/// The [`GyroDlpf::Hz250`] and [`GyroDlpf::Hz3600`] filters sample
+/// the gyro at 8 kHz, all the others at 1 kHz.
+#[repr(u8)]
+#[derive(Copy, Clone, PartialEq, Debug, defmt::Format)]
+pub enum GyroDlpf {
+    Hz250 = 0,
+    Hz184 = 1,
+    Hz92 = 2,
+    Hz41 = 3,
+    Hz20 = 4,
+    Hz10 = 5,
+    Hz5 = 6,
+    Hz3600 = 7,
+}
+
+impl GyroDlpf {
+    /// Returns the filter stored in the `CONFIG` register value.
+    fn from_config(config: u8) -> GyroDlpf {
+        match config & 0b111 {
+            0 => GyroDlpf::Hz250,
+            1 => GyroDlpf::Hz184,
+            2 => GyroDlpf::Hz92,
+            3 => GyroDlpf::Hz41,
+            4 => GyroDlpf::Hz20,
+            5 => GyroDlpf::Hz10,
+            6 => GyroDlpf::Hz5,
+            _ => GyroDlpf::Hz3600,
+        }
+    }
+
+    /// Returns the internal sample rate in Hz used by the filter.
+    pub fn internal_sample_rate(&self) -> f32 {
+        match self {
+            GyroDlpf::Hz250 | GyroDlpf::Hz3600 => 8000f32,
+            _ => 1000f32,
+        }
+    }
+}
+
+/// The possible values for the `ACCEL_FCHOICE_B` and `A_DLPF_CFG`
+/// fields of the `ACCEL_CONFIG2` register.
+///
+/// The value sets the bandwidth of the acceleration digital
+/// low pass filter. [`AccelDlpf::Hz1046`] bypasses the filter.
+#[repr(u8)]
+#[derive(Copy, Clone, PartialEq, Debug, defmt::Format)]
+pub enum AccelDlpf {
+    Hz218 = 0,
+    Hz99 = 2,
+    Hz45 = 3,
+    Hz21 = 4,
+    Hz10 = 5,
+    Hz5 = 6,
+    Hz420 = 7,
+    /// `ACCEL_FCHOICE_B` is set, the filter is bypassed
+    Hz1046 = 1 << 3,
+}
+
+impl AccelDlpf {
+    /// Returns the filter stored in the `ACCEL_CONFIG2` register value.
+    fn from_config(config: u8) -> AccelDlpf {
+        if config & (1 << 3) != 0 {
+            return AccelDlpf::Hz1046;
+        }
+        match config & 0b111 {
+            // 0 and 1 are the same filter
+            0 | 1 => AccelDlpf::Hz218,
+            2 => AccelDlpf::Hz99,
+            3 => AccelDlpf::Hz45,
+            4 => AccelDlpf::Hz21,
+            5 => AccelDlpf::Hz10,
+            6 => AccelDlpf::Hz5,
+            _ => AccelDlpf::Hz420,
+        }
+    }
+}
+
+/// The possible values for the `CLKSEL` field of the
+/// `PWR_MGMT_1` register.
+#[repr(u8)]
+#[derive(Copy, Clone, PartialEq, Debug, defmt::Format)]
+pub enum ClockSource {
+    /// The internal 20 MHz oscillator
+    Internal = 0,
+    /// The gyro PLL if it is ready, otherwise the
+    /// internal oscillator
+    Auto = 1,
+    /// Stops the clock and keeps the timing generator in reset
+    Stop = 7,
+}
+
+impl ClockSource {
+    /// Returns the clock source stored in the `PWR_MGMT_1` register value.
+    fn from_config(config: u8) -> ClockSource {
+        match config & 0b111 {
+            0 | 6 => ClockSource::Internal,
+            7 => ClockSource::Stop,
+            _ => ClockSource::Auto,
+        }
+    }
+}
+
+/// The axes that are put in standby, the `PWR_MGMT_2` register.
+///
+/// An axis in standby is not sampled, which saves power.
+#[derive(Copy, Clone, Default, PartialEq, Debug, defmt::Format)]
+pub struct Standby {
+    pub accel_x: bool,
+    pub accel_y: bool,
+    pub accel_z: bool,
+    pub gyro_x: bool,
+    pub gyro_y: bool,
+    pub gyro_z: bool,
+}
+
+impl Standby {
+    /// Returns the value of the `PWR_MGMT_2` register.
+    fn bits(&self) -> u8 {
+        (self.accel_x as u8) << 5
+            | (self.accel_y as u8) << 4
+            | (self.accel_z as u8) << 3
+            | (self.gyro_x as u8) << 2
+            | (self.gyro_y as u8) << 1
+            | self.gyro_z as u8
+    }
+
+    /// Returns the standby axes stored in the `PWR_MGMT_2` register value.
+    fn from_config(config: u8) -> Standby {
+        Standby {
+            accel_x: config & (1 << 5) != 0,
+            accel_y: config & (1 << 4) != 0,
+            accel_z: config & (1 << 3) != 0,
+            gyro_x: config & (1 << 2) != 0,
+            gyro_y: config & (1 << 1) != 0,
+            gyro_z: config & 1 != 0,
+        }
+    }
+}
+
+/// The MPU 6500 configuration.
+///
+/// The default value is the sensor's power on configuration,
+/// except for the clock source which uses the gyro PLL.
+#[derive(Copy, Clone, PartialEq, Debug, defmt::Format)]
+pub struct Config {
+    /// The gyro scale
+    pub gyro_scale: GyroScale,
+    /// The acceleration scale
+    pub accel_scale: AccelScale,
+    /// The gyro and temperature low pass filter
+    pub gyro_dlpf: GyroDlpf,
+    /// The acceleration low pass filter
+    pub accel_dlpf: AccelDlpf,
+    /// The sample rate divider, the sample rate is
+    /// `internal_sample_rate / (1 + sample_rate_divider)`.
+    /// The sensor ignores it for the 8 kHz gyro filters.
+    pub sample_rate_divider: u8,
+    /// The clock source
+    pub clock_source: ClockSource,
+    /// Puts the sensor into sleep mode
+    pub sleep: bool,
+    /// Puts the sensor into cycle mode, the sensor
+    /// wakes up, takes a sample and goes back to sleep
+    pub cycle: bool,
+    /// The axes in standby
+    pub standby: Standby,
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config {
+            gyro_scale: GyroScale::Gs250,
+            accel_scale: AccelScale::G2,
+            gyro_dlpf: GyroDlpf::Hz250,
+            accel_dlpf: AccelDlpf::Hz218,
+            sample_rate_divider: 0,
+            clock_source: ClockSource::Auto,
+            sleep: false,
+            cycle: false,
+            standby: Standby::default(),
+        }
+    }
+}
+
+/// Builder functions
+///
+/// Every function returns a new configuration with
+/// the field set to `value`, so that the calls can be chained.
+impl Config {
+    /// Sets the gyro scale
+    pub fn gyro_scale(self, value: GyroScale) -> Config {
+        Config {
+            gyro_scale: value,
+            ..self
+        }
+    }
+
+    /// Sets the acceleration scale
+    pub fn accel_scale(self, value: AccelScale) -> Config {
+        Config {
+            accel_scale: value,
+            ..self
+        }
+    }
+
+    /// Sets the gyro and temperature low pass filter
+    pub fn gyro_dlpf(self, value: GyroDlpf) -> Config {
+        Config {
+            gyro_dlpf: value,
+            ..self
+        }
+    }
+
+    /// Sets the acceleration low pass filter
+    pub fn accel_dlpf(self, value: AccelDlpf) -> Config {
+        Config {
+            accel_dlpf: value,
+            ..self
+        }
+    }
+
+    /// Sets the sample rate divider
+    pub fn sample_rate_divider(self, value: u8) -> Config {
+        Config {
+            sample_rate_divider: value,
+            ..self
+        }
+    }
+
+    /// Sets the clock source
+    pub fn clock_source(self, value: ClockSource) -> Config {
+        Config {
+            clock_source: value,
+            ..self
+        }
+    }
+
+    /// Puts the sensor into sleep mode
+    pub fn sleep(self, value: bool) -> Config {
+        Config {
+            sleep: value,
+            ..self
+        }
+    }
+
+    /// Puts the sensor into cycle mode
+    pub fn cycle(self, value: bool) -> Config {
+        Config {
+            cycle: value,
+            ..self
+        }
+    }
+
+    /// Sets the axes that are in standby
+    pub fn standby(self, value: Standby) -> Config {
+        Config {
+            standby: value,
+            ..self
+        }
+    }
+}
+
+impl Config {
+    /// Returns the rate in Hz at which an MPU 6500 compatible
+    /// sensor updates the values.
+    ///
+    /// The sensor ignores the divider for [`GyroDlpf::Hz250`]
+    /// and [`GyroDlpf::Hz3600`], it samples at 8 kHz. The MPU 6000
+    /// always uses the divider, use [`Config::chip_sample_rate`] or
+    /// `Mpu6500::sample_rate` for it.
+    pub fn sample_rate(&self) -> f32 {
+        self.chip_sample_rate(Chip::Mpu6500)
+    }
+
+    /// Returns the rate in Hz at which the `chip` sensor updates
+    /// the values.
+    pub fn chip_sample_rate(&self, chip: Chip) -> f32 {
+        let internal = self.gyro_dlpf.internal_sample_rate();
+        match (chip, self.gyro_dlpf) {
+            (Chip::Mpu6000, _) => internal / (1f32 + self.sample_rate_divider as f32),
+            (_, GyroDlpf::Hz250 | GyroDlpf::Hz3600) => internal,
+            _ => internal / (1f32 + self.sample_rate_divider as f32),
+        }
+    }
+
+    /// Returns the values of the configuration registers, in the order
+    /// in which they have to be written.
+    ///
+    /// `PWR_MGMT_1` is written last, so that the sensor goes to
+    /// sleep or cycle mode after it was configured.
+    ///
+    /// `CONFIG` always has the [`FIFO_MODE`] bit set.
+    pub(crate) fn registers(&self) -> [(ConfigRegister, u8); 7] {
+        [
+            (ConfigRegister::SampleRateDivider, self.sample_rate_divider),
+            (ConfigRegister::Config, FIFO_MODE | self.gyro_dlpf as u8),
+            (ConfigRegister::Gyro, (self.gyro_scale as u8) << 3),
+            (ConfigRegister::Accel, (self.accel_scale as u8) << 3),
+            (ConfigRegister::Accel2, self.accel_dlpf as u8),
+            (ConfigRegister::PowerManagement2, self.standby.bits()),
+            (
+                ConfigRegister::PowerManagement1,
+                (self.sleep as u8) << 6 | (self.cycle as u8) << 5 | self.clock_source as u8,
+            ),
+        ]
+    }
+
+    /// Returns the configuration stored in the registers.
+    ///
+    /// - `block` stores the values of `SMPLRT_DIV` ... `ACCEL_CONFIG2`
+    /// - `power` stores the values of `PWR_MGMT_1` and `PWR_MGMT_2`
+    pub(crate) fn from_registers(block: &[u8; 5], power: &[u8; 2]) -> Config {
+        Config {
+            sample_rate_divider: block[0],
+            gyro_dlpf: GyroDlpf::from_config(block[1]),
+            gyro_scale: GyroScale::from_config(block[2]),
+            accel_scale: AccelScale::from_config(block[3]),
+            accel_dlpf: AccelDlpf::from_config(block[4]),
+            sleep: power[0] & (1 << 6) != 0,
+            cycle: power[0] & (1 << 5) != 0,
+            clock_source: ClockSource::from_config(power[0]),
+            standby: Standby::from_config(power[1]),
+        }
+    }
+}
@@ -0,0 +1,553 @@
+//! MPU 6500 driver core.
+//!
+//! This is the only place where the driver logic lives: the register
+//! addresses, the values written to them and the conversion of the
+//! values read from the sensor.
+//!
+//! The driver does not know how bytes move to and from the sensor.
+//! It uses an [`Interface`] for that. The `bus`, `device` and
+//! `device_blocking` modules provide the interfaces and the
+//! constructors for each type of bus.
+
+use embedded_hal_async::delay::DelayNs;
+
+use crate::mpu6500::{
+    AccelScale, Acceleration, Ak8963, Chip, Config, ConfigRegister, DEVICE_RESET, Error,
+    FifoSensors, Gyro, GyroScale, InterruptPin, Measurement, RawMeasurement, SIGNAL_PATH_RESET,
+    ValueRegister, WHO_AM_I,
+    interface::{Blocking, BlockingDelay, BlockingInterface, Interface},
+    motion::SavedState,
+    temperature_to_celsius,
+};
+
+/// The full scale bits of the `GYRO_CONFIG` and `ACCEL_CONFIG` registers
+const FS_SEL: u8 = 0b11 << 3;
+
+/// MPU 6500 async driver
+///
+/// The type `I` used by the driver is defined as *any type that
+/// implements the `Interface` trait*.
+pub struct Mpu6500<I: Interface> {
+    /// The interface used to access the registers
+    pub(super) interface: I,
+
+    /// The sensor detected by `Mpu6500::probe`
+    pub(super) chip: Chip,
+
+    /// The configuration of the sensor
+    pub(super) config: Config,
+
+    /// Whether `Mpu6500::init` was successful
+    pub(super) initialised: bool,
+
+    /// The sensors written into the FIFO, if the FIFO is enabled
+    pub(super) fifo: Option<FifoSensors>,
+
+    /// Whether the FIFO overflowed since the last `Mpu6500::read_fifo`
+    pub(super) fifo_overflow: bool,
+
+    /// The `INT` pin configuration, if the data ready interrupt is enabled
+    pub(super) interrupt_pin: Option<InterruptPin>,
+
+    /// The state restored when leaving the wake on motion mode,
+    /// if the mode is enabled
+    pub(super) wake_on_motion: Option<SavedState>,
+
+    /// The AK8963 magnetometer, if it was initialised
+    pub(super) magnetometer: Option<Ak8963>,
+}
+
+/// Public API
+///
+/// The functions defined here are exported by the driver.
+impl<I: Interface> Mpu6500<I> {
+    /// Create a new MPU6500 driver instance that uses
+    /// the `interface` to access the sensor.
+    pub fn with_interface(interface: I) -> Mpu6500<I> {
+        Mpu6500 {
+            interface,
+            // The actual sensor is detected by `Mpu6500::probe`
+            chip: Chip::Mpu6500,
+            // The actual configuration is read by `Mpu6500::init`
+            config: Config::default(),
+            initialised: false,
+            fifo: None,
+            fifo_overflow: false,
+            interrupt_pin: None,
+            wake_on_motion: None,
+            magnetometer: None,
+        }
+    }
+
+    /// Verifies if a supported sensor is connected to the bus
+    ///
+    /// The function returns:
+    /// - `Ok(chip)` if the WHO_AM_I register has the value of a supported sensor
+    /// - `Err(Error::UnexpectedWhoAmI(value))` if another value was read
+    /// - `Err(Error::Bus(error))` if the transfer failed
+    pub async fn probe(&mut self) -> Result<Chip, Error<I::Error>> {
+        // This is the receive buffer for the value of the WHO_AM_I register.
+        let mut rx = [0u8; 1];
+        self.interface.read_registers(WHO_AM_I, &mut rx).await?;
+
+        // If the register's value is one that we know, we confirm
+        // that a supported sensor is connected to the bus.
+        let chip = Chip::from_who_am_i(rx[0]).ok_or(Error::UnexpectedWhoAmI(rx[0]))?;
+        self.chip = chip;
+        Ok(chip)
+    }
+
+    /// Initialises the driver
+    ///
+    /// The function detects the sensor, runs the sensor's init
+    /// sequence and reads the configuration of the sensor. The
+    /// sensor might have been configured before the microcontroller
+    /// was reset.
+    ///
+    /// This function has to be called before any other function
+    /// that accesses the sensor.
+    pub async fn init(&mut self) -> Result<(), Error<I::Error>> {
+        self.initialised = false;
+        let chip = self.probe().await?;
+        for &(register, mask, bits) in chip.init_sequence(I::SPI) {
+            self.modify_register(register, mask, bits).await?;
+        }
+        self.config = self.read_config().await?;
+        self.initialised = true;
+        Ok(())
+    }
+
+    /// Resets the sensor
+    ///
+    /// The sequence is the one required by the datasheet for SPI:
+    /// 1. reset the device and wait 100 ms
+    /// 2. reset the gyro, accelerometer and temperature signal paths and wait 100 ms
+    ///
+    /// All the registers return to their power on values. The reset
+    /// puts the MPU 6000 and the ICM sensors to sleep and enables their
+    /// I2C interface, so the sensor's init sequence runs again.
+    pub async fn reset(&mut self, delay: &mut impl DelayNs) -> Result<(), Error<I::Error>> {
+        self.interface
+            .write_register(ConfigRegister::PowerManagement1 as u8, DEVICE_RESET)
+            .await?;
+        delay.delay_ms(100).await;
+        self.interface
+            .write_register(ConfigRegister::SignalPathReset as u8, SIGNAL_PATH_RESET)
+            .await?;
+        delay.delay_ms(100).await;
+        for &(register, mask, bits) in self.chip.init_sequence(I::SPI) {
+            self.modify_register(register, mask, bits).await?;
+        }
+        self.config = self.read_config().await?;
+        self.fifo = None;
+        self.fifo_overflow = false;
+        self.interrupt_pin = None;
+        self.wake_on_motion = None;
+        self.magnetometer = None;
+        Ok(())
+    }
+
+    /// Reads the configuration stored in the sensor's registers
+    pub async fn read_config(&mut self) -> Result<Config, Error<I::Error>> {
+        // SMPLRT_DIV, CONFIG, GYRO_CONFIG, ACCEL_CONFIG and ACCEL_CONFIG2
+        // are consecutive registers.
+        let mut block = [0u8; 5];
+        self.interface
+            .read_registers(ConfigRegister::SampleRateDivider as u8, &mut block)
+            .await?;
+        // PWR_MGMT_1 and PWR_MGMT_2 are consecutive registers.
+        let mut power = [0u8; 2];
+        self.interface
+            .read_registers(ConfigRegister::PowerManagement1 as u8, &mut power)
+            .await?;
+        let config = Config::from_registers(&block, &power);
+        Ok(self.supported(config))
+    }
+
+    /// Applies the configuration
+    ///
+    /// All the configuration registers are written and read back
+    /// to verify that the sensor stored the values. If a value is
+    /// different, the function returns `Error::InvalidConfiguration`.
+    ///
+    /// The MPU 6000 does not have a separate accelerometer low pass
+    /// filter, the `accel_dlpf` field is ignored.
+    pub async fn configure(&mut self, config: Config) -> Result<(), Error<I::Error>> {
+        self.check_initialised()?;
+        for (register, value) in config.registers() {
+            if matches!(register, ConfigRegister::Accel2) && !self.chip.has_accel_config2() {
+                continue;
+            }
+            self.interface.write_register(register as u8, value).await?;
+        }
+
+        // Store the configuration that the sensor actually uses,
+        // so that the conversions use the right scales.
+        self.config = self.read_config().await?;
+        if self.config == self.supported(config) {
+            Ok(())
+        } else {
+            Err(Error::InvalidConfiguration)
+        }
+    }
+
+    /// Set the gyro scale
+    ///
+    /// Only the `FS_SEL` bits are changed, the filter and
+    /// the self-test bits are kept.
+    pub async fn set_gyro_scale(&mut self, scale: GyroScale) -> Result<(), Error<I::Error>> {
+        self.write_scale(ConfigRegister::Gyro, scale as u8).await?;
+        // The configuration was written, store the new gyro_scale value
+        self.config.gyro_scale = scale;
+        Ok(())
+    }
+
+    /// Set the acceleration scale
+    ///
+    /// Only the `ACCEL_FS_SEL` bits are changed, the
+    /// self-test bits are kept.
+    pub async fn set_accel_scale(&mut self, scale: AccelScale) -> Result<(), Error<I::Error>> {
+        self.write_scale(ConfigRegister::Accel, scale as u8).await?;
+        // The configuration was written, store the new accel_scale value
+        self.config.accel_scale = scale;
+        Ok(())
+    }
+
+    /// Read the acceleration
+    ///
+    /// The function returns either the acceleration value or an error
+    pub async fn read_acceleration(&mut self) -> Result<Acceleration, Error<I::Error>> {
+        let rx: [u8; 6] = self.read_value(ValueRegister::AccelXOutH).await?;
+        let [x, y, z] = to_i16_triple(&rx);
+        Ok(Acceleration {
+            x: self.config.accel_scale.to_g(x),
+            y: self.config.accel_scale.to_g(y),
+            z: self.config.accel_scale.to_g(z),
+        })
+    }
+
+    /// Read the gyro
+    ///
+    /// The function returns either the gyro value or an error
+    pub async fn read_gyro(&mut self) -> Result<Gyro, Error<I::Error>> {
+        let rx: [u8; 6] = self.read_value(ValueRegister::GyroXOutH).await?;
+        let [x, y, z] = to_i16_triple(&rx);
+        Ok(Gyro {
+            x: self.config.gyro_scale.to_deg_s(x),
+            y: self.config.gyro_scale.to_deg_s(y),
+            z: self.config.gyro_scale.to_deg_s(z),
+        })
+    }
+
+    /// Read the raw acceleration, temperature and gyro values
+    ///
+    /// The values are read in a single transfer, so they belong to
+    /// the same sample. They are not converted, use the
+    /// [`Mpu6500::accel_scale`] and [`Mpu6500::gyro_scale`] to
+    /// convert them.
+    pub async fn read_raw(&mut self) -> Result<RawMeasurement, Error<I::Error>> {
+        // The registers are:
+        // - ACCEL_XOUT_H ... ACCEL_ZOUT_L (6 bytes)
+        // - TEMP_OUT_H and TEMP_OUT_L (2 bytes)
+        // - GYRO_XOUT_H ... GYRO_ZOUT_L (6 bytes)
+        let rx: [u8; 14] = self.read_value(ValueRegister::AccelXOutH).await?;
+        Ok(RawMeasurement {
+            accel: to_i16_triple(&rx[0..6]),
+            temperature: i16::from_be_bytes([rx[6], rx[7]]),
+            gyro: to_i16_triple(&rx[8..14]),
+        })
+    }
+
+    /// Read the acceleration, temperature and gyro
+    ///
+    /// The values are read in a single 14 bytes transfer, so
+    /// they belong to the same sample.
+    pub async fn read_all(&mut self) -> Result<Measurement, Error<I::Error>> {
+        let raw = self.read_raw().await?;
+        let [ax, ay, az] = raw.accel;
+        let [gx, gy, gz] = raw.gyro;
+        Ok(Measurement {
+            accel: Acceleration {
+                x: self.config.accel_scale.to_g(ax),
+                y: self.config.accel_scale.to_g(ay),
+                z: self.config.accel_scale.to_g(az),
+            },
+            temperature: temperature_to_celsius(raw.temperature),
+            gyro: Gyro {
+                x: self.config.gyro_scale.to_deg_s(gx),
+                y: self.config.gyro_scale.to_deg_s(gy),
+                z: self.config.gyro_scale.to_deg_s(gz),
+            },
+        })
+    }
+
+    /// Returns the configured acceleration scale
+    pub fn accel_scale(&self) -> AccelScale {
+        self.config.accel_scale
+    }
+
+    /// Returns the configured gyro scale
+    pub fn gyro_scale(&self) -> GyroScale {
+        self.config.gyro_scale
+    }
+
+    /// Returns the sensor's configuration
+    pub fn config(&self) -> Config {
+        self.config
+    }
+
+    /// Returns the sensor detected by [`Mpu6500::probe`]
+    pub fn chip(&self) -> Chip {
+        self.chip
+    }
+
+    /// Returns the rate in Hz at which the sensor updates the
+    /// values, for the detected sensor
+    pub fn sample_rate(&self) -> f32 {
+        self.config.chip_sample_rate(self.chip)
+    }
+}
+
+/// Private API
+///
+/// The functions defined here are not exported by the driver and
+/// are only used by the driver itself and by the modules that
+/// add features to the driver (like `fifo`, `interrupt` and `motion`).
+impl<I: Interface> Mpu6500<I> {
+    /// Internal function that verifies if the driver was initialised.
+    pub(super) fn check_initialised(&self) -> Result<(), Error<I::Error>> {
+        if self.initialised {
+            Ok(())
+        } else {
+            Err(Error::NotInitialised)
+        }
+    }
+
+    /// Internal function that verifies if the detected sensor has the
+    /// MPU 6500 registers used by a feature.
+    pub(super) fn check_mpu6500_compatible(&self) -> Result<(), Error<I::Error>> {
+        if self.chip.is_mpu6500_compatible() {
+            Ok(())
+        } else {
+            Err(Error::Unsupported)
+        }
+    }
+
+    /// Internal function that returns the part of `config` that the
+    /// detected sensor supports.
+    ///
+    /// The MPU 6000 does not have a separate accelerometer low pass
+    /// filter, so its `accel_dlpf` is always the default one.
+    fn supported(&self, config: Config) -> Config {
+        if self.chip.has_accel_config2() {
+            config
+        } else {
+            config.accel_dlpf(Config::default().accel_dlpf)
+        }
+    }
+
+    /// Internal function that sets the value of a config register.
+    ///
+    /// The function reads back the register's value to make sure
+    /// that the sensor stored it.
+    ///
+    /// This function is used by `Mpu6500::set_accel_scale` and `Mpu6500::set_gyro_scale`.
+    pub(super) async fn write_config(
+        &mut self,
+        config_register: ConfigRegister,
+        value: u8,
+    ) -> Result<(), Error<I::Error>> {
+        self.check_initialised()?;
+        self.interface
+            .write_register(config_register as u8, value)
+            .await?;
+
+        let mut rx = [0u8; 1];
+        self.interface
+            .read_registers(config_register as u8, &mut rx)
+            .await?;
+        if rx[0] == value {
+            Ok(())
+        } else {
+            Err(Error::InvalidConfiguration)
+        }
+    }
+
+    /// Internal function that reads `N` values from the sensor starting from
+    /// the address of the `value_register` provided.
+    ///
+    /// This function is used by `Mpu6500::read_acceleration`, `Mpu6500::read_gyro`
+    /// and `Mpu6500::read_raw`.
+    pub(super) async fn read_value<const N: usize>(
+        &mut self,
+        value_register: ValueRegister,
+    ) -> Result<[u8; N], Error<I::Error>> {
+        self.check_initialised()?;
+        let mut rx = [0u8; N];
+        self.interface
+            .read_registers(value_register as u8, &mut rx)
+            .await?;
+        Ok(rx)
+    }
+
+    /// Internal function that writes the full scale bits of the
+    /// `GYRO_CONFIG` or `ACCEL_CONFIG` register and verifies them,
+    /// keeping the other bits unchanged.
+    async fn write_scale(
+        &mut self,
+        config_register: ConfigRegister,
+        scale: u8,
+    ) -> Result<(), Error<I::Error>> {
+        self.check_initialised()?;
+        let mut rx = [0u8; 1];
+        self.interface
+            .read_registers(config_register as u8, &mut rx)
+            .await?;
+        self.write_config(config_register, (rx[0] & !FS_SEL) | (scale << 3))
+            .await
+    }
+
+    /// Internal function that changes only the `mask` bits of a register
+    /// to the values of `bits`, keeping the other bits unchanged.
+    pub(super) async fn modify_register(
+        &mut self,
+        register: u8,
+        mask: u8,
+        bits: u8,
+    ) -> Result<(), Error<I::Error>> {
+        let mut rx = [0u8; 1];
+        self.interface.read_registers(register, &mut rx).await?;
+        self.interface
+            .write_register(register, (rx[0] & !mask) | (bits & mask))
+            .await?;
+        Ok(())
+    }
+}
+
+/// Converts six bytes read from the sensor to three `i16` values.
+///
+/// The sensor stores the values as big endian, the high
+/// byte is first.
+pub(super) fn to_i16_triple(rx: &[u8]) -> [i16; 3] {
+    [
+        i16::from_be_bytes([rx[0], rx[1]]),
+        i16::from_be_bytes([rx[2], rx[3]]),
+        i16::from_be_bytes([rx[4], rx[5]]),
+    ]
+}
+
+/// MPU 6500 blocking driver
+///
+/// This driver wraps the async [`Mpu6500`] driver. The interface
+/// is blocking, so the futures returned by the async driver finish
+/// the first time they are polled. We use [`embassy_futures::block_on`]
+/// to poll them.
+///
+/// The type `I` used by the driver is defined as *any type that
+/// implements the `BlockingInterface` trait*.
+pub struct BlockingMpu6500<I: BlockingInterface> {
+    /// The async driver
+    pub(super) driver: Mpu6500<Blocking<I>>,
+}
+
+/// Public API
+///
+/// The functions defined here are exported by the driver. They
+/// have the same meaning as the functions of the [`Mpu6500`] driver.
+impl<I: BlockingInterface> BlockingMpu6500<I> {
+    /// Create a new MPU6500 blocking driver instance that uses
+    /// the `interface` to access the sensor.
+    pub fn with_interface(interface: I) -> BlockingMpu6500<I> {
+        BlockingMpu6500 {
+            driver: Mpu6500::with_interface(Blocking(interface)),
+        }
+    }
+
+    /// Verifies if a supported sensor is connected to the bus
+    pub fn probe(&mut self) -> Result<Chip, Error<I::Error>> {
+        embassy_futures::block_on(self.driver.probe())
+    }
+
+    /// Initialises the driver
+    pub fn init(&mut self) -> Result<(), Error<I::Error>> {
+        embassy_futures::block_on(self.driver.init())
+    }
+
+    /// Resets the sensor
+    pub fn reset(
+        &mut self,
+        delay: &mut impl embedded_hal::delay::DelayNs,
+    ) -> Result<(), Error<I::Error>> {
+        embassy_futures::block_on(self.driver.reset(&mut BlockingDelay(delay)))
+    }
+
+    /// Reads the configuration stored in the sensor's registers
+    pub fn read_config(&mut self) -> Result<Config, Error<I::Error>> {
+        embassy_futures::block_on(self.driver.read_config())
+    }
+
+    /// Applies the configuration
+    pub fn configure(&mut self, config: Config) -> Result<(), Error<I::Error>> {
+        embassy_futures::block_on(self.driver.configure(config))
+    }
+
+    /// Set the gyro scale
+    pub fn set_gyro_scale(&mut self, scale: GyroScale) -> Result<(), Error<I::Error>> {
+        embassy_futures::block_on(self.driver.set_gyro_scale(scale))
+    }
+
+    /// Set the acceleration scale
+    pub fn set_accel_scale(&mut self, scale: AccelScale) -> Result<(), Error<I::Error>> {
+        embassy_futures::block_on(self.driver.set_accel_scale(scale))
+    }
+
+    /// Read the acceleration
+    ///
+    /// The function returns either the acceleration value or an error
+    pub fn read_acceleration(&mut self) -> Result<Acceleration, Error<I::Error>> {
+        embassy_futures::block_on(self.driver.read_acceleration())
+    }
+
+    /// Read the gyro
+    ///
+    /// The function returns either the gyro value or an error
+    pub fn read_gyro(&mut self) -> Result<Gyro, Error<I::Error>> {
+        embassy_futures::block_on(self.driver.read_gyro())
+    }
+
+    /// Read the raw acceleration, temperature and gyro values
+    pub fn read_raw(&mut self) -> Result<RawMeasurement, Error<I::Error>> {
+        embassy_futures::block_on(self.driver.read_raw())
+    }
+
+    /// Read the acceleration, temperature and gyro
+    pub fn read_all(&mut self) -> Result<Measurement, Error<I::Error>> {
+        embassy_futures::block_on(self.driver.read_all())
+    }
+
+    /// Returns the configured acceleration scale
+    pub fn accel_scale(&self) -> AccelScale {
+        self.driver.accel_scale()
+    }
+
+    /// Returns the configured gyro scale
+    pub fn gyro_scale(&self) -> GyroScale {
+        self.driver.gyro_scale()
+    }
+
+    /// Returns the sensor's configuration
+    pub fn config(&self) -> Config {
+        self.driver.config()
+    }
+
+    /// Returns the sensor detected by [`BlockingMpu6500::probe`]
+    pub fn chip(&self) -> Chip {
+        self.driver.chip()
+    }
+
+    /// Returns the rate in Hz at which the sensor updates the
+    /// values, for the detected sensor
+    pub fn sample_rate(&self) -> f32 {
+        self.driver.sample_rate()
+    }
+}
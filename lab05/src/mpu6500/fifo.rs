@@ -0,0 +1,264 @@
+//! MPU 6500 FIFO support.
+//!
+//! The sensor can store the samples in a 512 bytes FIFO. Instead of
+//! reading every sample when it is available, the application reads
+//! the FIFO from time to time and gets all the samples stored since
+//! the last read.
+//!
+//! Every time a sample is taken, the sensor writes a *frame* into the
+//! FIFO. The frame contains the values of the sensors enabled with
+//! [`FifoSensors`], in the order of their registers:
+//! | ACCEL X, Y, Z (6 bytes) | TEMP (2 bytes) | GYRO X, Y, Z (6 bytes) |
+//!
+//! The driver uses the FIFO in the *stop when full* mode. If the
+//! application does not read the FIFO fast enough, the FIFO overflows,
+//! the new samples are lost and the last frame might be incomplete.
+//! [`Mpu6500::read_fifo`] reads the complete frames, reports the
+//! overflow and resets the FIFO so that the next frames are aligned.
+
+use crate::mpu6500::{
+    Acceleration, BlockingInterface, BlockingMpu6500, Error, Gyro, Interface, Mpu6500,
+    driver::to_i16_triple, temperature_to_celsius,
+};
+
+/// FIFO_EN Register Address
+const FIFO_EN: u8 = 0x23;
+
+/// The `FIFO_OFLOW_INT` bit of the `INT_STATUS` register
+pub(crate) const FIFO_OFLOW_INT: u8 = 1 << 4;
+
+/// USER_CTRL Register Address
+pub(crate) const USER_CTRL: u8 = 0x6a;
+
+/// The `FIFO_EN` bit of the `USER_CTRL` register
+const USER_CTRL_FIFO_EN: u8 = 1 << 6;
+
+/// The `FIFO_RST` bit of the `USER_CTRL` register
+const USER_CTRL_FIFO_RST: u8 = 1 << 2;
+
+/// FIFO_COUNTH Register Address, followed by FIFO_COUNTL
+const FIFO_COUNT_H: u8 = 0x72;
+
+/// FIFO_R_W Register Address
+const FIFO_R_W: u8 = 0x74;
+
+/// The size of the FIFO in bytes
+pub const FIFO_SIZE: usize = 512;
+
+/// The sensors whose values are written into the FIFO.
+#[derive(Copy, Clone, Default, PartialEq, Debug, defmt::Format)]
+pub struct FifoSensors {
+    /// The acceleration on all the three axes
+    pub accel: bool,
+    /// The die temperature
+    pub temperature: bool,
+    /// The gyro on all the three axes
+    pub gyro: bool,
+}
+
+impl FifoSensors {
+    /// Returns the value of the `FIFO_EN` register.
+    fn bits(&self) -> u8 {
+        // TEMP_OUT is bit 7, GYRO_XOUT, GYRO_YOUT and GYRO_ZOUT are
+        // bits 6, 5 and 4, ACCEL is bit 3.
+        (self.temperature as u8) << 7 | (self.gyro as u8 * 0b111) << 4 | (self.accel as u8) << 3
+    }
+
+    /// Returns the number of bytes of a frame.
+    pub fn frame_len(&self) -> usize {
+        self.accel as usize * 6 + self.temperature as usize * 2 + self.gyro as usize * 6
+    }
+}
+
+/// A frame read from the FIFO.
+///
+/// The values of the sensors that are not written into
+/// the FIFO are `None`.
+#[derive(Copy, Clone, Default, Debug, defmt::Format)]
+pub struct FifoFrame {
+    /// The acceleration in g
+    pub accel: Option<Acceleration>,
+    /// The die temperature in deg C
+    pub temperature: Option<f32>,
+    /// The gyro in deg/s
+    pub gyro: Option<Gyro>,
+}
+
+/// The result of a FIFO read.
+#[derive(Copy, Clone, PartialEq, Debug, defmt::Format)]
+pub struct FifoRead {
+    /// The number of frames written into the buffer
+    pub frames: usize,
+    /// Whether the FIFO overflowed. Samples were lost and
+    /// the FIFO was reset.
+    pub overflow: bool,
+}
+
+/// FIFO API
+impl<I: Interface> Mpu6500<I> {
+    /// Enables the FIFO for the `sensors`
+    ///
+    /// The FIFO is reset, so it starts empty. The sensor writes
+    /// a frame at the configured sample rate.
+    pub async fn enable_fifo(&mut self, sensors: FifoSensors) -> Result<(), Error<I::Error>> {
+        self.check_initialised()?;
+        // Stop writing into the FIFO while it is configured
+        self.disable_fifo().await?;
+        self.interface
+            .write_register(FIFO_EN, sensors.bits())
+            .await?;
+        self.modify_register(
+            USER_CTRL,
+            USER_CTRL_FIFO_EN | USER_CTRL_FIFO_RST,
+            USER_CTRL_FIFO_EN | USER_CTRL_FIFO_RST,
+        )
+        .await?;
+        self.fifo = Some(sensors);
+        self.fifo_overflow = false;
+        Ok(())
+    }
+
+    /// Disables the FIFO
+    pub async fn disable_fifo(&mut self) -> Result<(), Error<I::Error>> {
+        self.check_initialised()?;
+        self.modify_register(USER_CTRL, USER_CTRL_FIFO_EN, 0)
+            .await?;
+        self.interface.write_register(FIFO_EN, 0).await?;
+        self.fifo = None;
+        Ok(())
+    }
+
+    /// Empties the FIFO
+    pub async fn reset_fifo(&mut self) -> Result<(), Error<I::Error>> {
+        self.check_initialised()?;
+        // The `FIFO_RST` bit clears itself after the FIFO is reset
+        self.modify_register(USER_CTRL, USER_CTRL_FIFO_RST, USER_CTRL_FIFO_RST)
+            .await
+    }
+
+    /// Returns the number of bytes stored in the FIFO
+    pub async fn fifo_count(&mut self) -> Result<usize, Error<I::Error>> {
+        self.check_initialised()?;
+        let mut rx = [0u8; 2];
+        self.interface.read_registers(FIFO_COUNT_H, &mut rx).await?;
+        // The count has 13 bits, FIFO_COUNTH stores bits 12:8
+        Ok(u16::from_be_bytes([rx[0] & 0x1f, rx[1]]) as usize)
+    }
+
+    /// Reads the frames stored in the FIFO into `frames`
+    ///
+    /// Only complete frames are read, at most `frames.len()`. An
+    /// incomplete frame stays in the FIFO and is read next time.
+    ///
+    /// If the FIFO overflowed, the complete frames are read and the
+    /// FIFO is reset, as the frames that follow might not be aligned.
+    ///
+    /// The function returns `Error::InvalidConfiguration` if the FIFO
+    /// is not enabled.
+    pub async fn read_fifo(
+        &mut self,
+        frames: &mut [FifoFrame],
+    ) -> Result<FifoRead, Error<I::Error>> {
+        self.check_initialised()?;
+        let Some(sensors) = self.fifo else {
+            return Err(Error::InvalidConfiguration);
+        };
+        let frame_len = sensors.frame_len();
+        if frame_len == 0 {
+            return Err(Error::InvalidConfiguration);
+        }
+
+        // Reading INT_STATUS clears the overflow flag, the driver
+        // remembers it until it is reported here.
+        self.read_interrupt_status().await?;
+        let overflow = core::mem::take(&mut self.fifo_overflow);
+
+        let count = self.fifo_count().await?;
+        let available = (count / frame_len).min(frames.len());
+
+        // All the frames are read in a single transfer. The FIFO_R_W
+        // register does not auto increment, every byte read from it is
+        // the next byte in the FIFO.
+        let mut buffer = [0u8; FIFO_SIZE];
+        let bytes = &mut buffer[..available * frame_len];
+        if !bytes.is_empty() {
+            self.interface.read_registers(FIFO_R_W, bytes).await?;
+        }
+
+        for (frame, data) in frames.iter_mut().zip(bytes.chunks_exact(frame_len)) {
+            *frame = self.decode_fifo_frame(sensors, data);
+        }
+
+        if overflow {
+            self.reset_fifo().await?;
+        }
+
+        Ok(FifoRead {
+            frames: available,
+            overflow,
+        })
+    }
+}
+
+/// Private API
+impl<I: Interface> Mpu6500<I> {
+    /// Converts the bytes of a frame using the configured scales
+    fn decode_fifo_frame(&self, sensors: FifoSensors, mut data: &[u8]) -> FifoFrame {
+        let mut frame = FifoFrame::default();
+        if sensors.accel {
+            let [x, y, z] = to_i16_triple(data);
+            frame.accel = Some(Acceleration {
+                x: self.config.accel_scale.to_g(x),
+                y: self.config.accel_scale.to_g(y),
+                z: self.config.accel_scale.to_g(z),
+            });
+            data = &data[6..];
+        }
+        if sensors.temperature {
+            frame.temperature = Some(temperature_to_celsius(i16::from_be_bytes([
+                data[0], data[1],
+            ])));
+            data = &data[2..];
+        }
+        if sensors.gyro {
+            let [x, y, z] = to_i16_triple(data);
+            frame.gyro = Some(Gyro {
+                x: self.config.gyro_scale.to_deg_s(x),
+                y: self.config.gyro_scale.to_deg_s(y),
+                z: self.config.gyro_scale.to_deg_s(z),
+            });
+        }
+        frame
+    }
+}
+
+/// FIFO API
+///
+/// The functions have the same meaning as the functions
+/// of the [`Mpu6500`] driver.
+impl<I: BlockingInterface> BlockingMpu6500<I> {
+    /// Enables the FIFO for the `sensors`
+    pub fn enable_fifo(&mut self, sensors: FifoSensors) -> Result<(), Error<I::Error>> {
+        embassy_futures::block_on(self.driver.enable_fifo(sensors))
+    }
+
+    /// Disables the FIFO
+    pub fn disable_fifo(&mut self) -> Result<(), Error<I::Error>> {
+        embassy_futures::block_on(self.driver.disable_fifo())
+    }
+
+    /// Empties the FIFO
+    pub fn reset_fifo(&mut self) -> Result<(), Error<I::Error>> {
+        embassy_futures::block_on(self.driver.reset_fifo())
+    }
+
+    /// Returns the number of bytes stored in the FIFO
+    pub fn fifo_count(&mut self) -> Result<usize, Error<I::Error>> {
+        embassy_futures::block_on(self.driver.fifo_count())
+    }
+
+    /// Reads the frames stored in the FIFO into `frames`
+    pub fn read_fifo(&mut self, frames: &mut [FifoFrame]) -> Result<FifoRead, Error<I::Error>> {
+        embassy_futures::block_on(self.driver.read_fifo(frames))
+    }
+}
@@ -0,0 +1,102 @@
+//! MPU 6500 I2C async driver.
+//!
+//! The sensor also has an I2C interface. It answers to the
+//! address `0x68` or `0x69`, depending on the level of the AD0 pin.
+//!
+//! Several devices can be connected to the same I2C bus, each
+//! one of them has a different address. The driver receives any
+//! type that implements the `I2c` trait:
+//! - a `&mut` reference to the I2C bus, if the bus is not used by
+//!   other drivers while this driver is available
+//! - an I2C device of a shared I2C bus (like `embassy_embedded_hal`s
+//!   `I2cDevice`), if the bus is used by other drivers, like the
+//!   BMP390 and AT24C256 drivers
+//!
+//! ## Advantages
+//! - it uses only two wires (SCL and SDA) for any number of devices
+//!
+//! ## Disadvantages
+//! - it is slower than SPI, at most 400 kHz
+//! - every transfer starts with the device's address
+
+// The `embedded_hal_async` crate exports standard async Hardware Abstraction
+// Layer (HAL) traits that libraries like `embassy` implement. Drivers
+// use these traits instead of the actual implementation of the HALs.
+
+// This allows drivers to function with any type of bus implementation
+// library that implements these traits. In our case, we use `embassy`s
+// implementation of the I2C bus, but the driver could be used with
+// any other library.
+use embedded_hal_async::i2c::I2c;
+
+use crate::mpu6500::{driver, interface::Interface};
+
+/// The I2C address of the sensor.
+///
+/// The least significant bit of the address is the level
+/// of the AD0 pin.
+///
+/// This is represented as a `u8` so that it can be cast
+/// to a `u8` using the `as` keyword
+///
+/// [`Copy`] and [`Clone`] are derived so that the value
+/// can be copied when sent as a parameter to a function.
+///
+/// [`PartialEq`], [`Debug`] and [`defmt::Format`] are derived
+/// so that the values can be compared and printed.
+#[repr(u8)]
+#[derive(Copy, Clone, Default, PartialEq, Debug, defmt::Format)]
+pub enum Address {
+    /// The AD0 pin is connected to GND
+    #[default]
+    Ad0Low = 0x68,
+    /// The AD0 pin is connected to VDDIO
+    Ad0High = 0x69,
+}
+
+/// MPU 6500 I2C driver
+///
+/// This is the MPU6500 driver that uses the [`I2cInterface`].
+pub type Mpu6500<I> = driver::Mpu6500<I2cInterface<I>>;
+
+/// The interface that accesses the MPU 6500 registers using
+/// the I2C bus.
+pub struct I2cInterface<I: I2c> {
+    /// The I2C bus or device
+    i2c: I,
+
+    /// The address of the sensor
+    address: Address,
+}
+
+impl<I: I2c> Mpu6500<I> {
+    /// Create a new MPU6500 I2C driver instance for the
+    /// sensor that uses the `address`
+    pub fn new(i2c: I, address: Address) -> Mpu6500<I> {
+        driver::Mpu6500::with_interface(I2cInterface { i2c, address })
+    }
+}
+
+/// The type `I` used by the interface is defined as *any type that
+/// implements the `I2c` trait*.
+impl<I: I2c> Interface for I2cInterface<I> {
+    type Error = I::Error;
+
+    const SPI: bool = false;
+
+    async fn read_registers(&mut self, register: u8, data: &mut [u8]) -> Result<(), I::Error> {
+        // We write the register's address and, without releasing the
+        // bus (repeated START), we read the registers' values. The
+        // sensor sends us the register's value followed by the values
+        // of the next registers.
+        self.i2c
+            .write_read(self.address as u8, &[register], data)
+            .await
+    }
+
+    async fn write_register(&mut self, register: u8, value: u8) -> Result<(), I::Error> {
+        // The first byte is the register's address, the second
+        // byte is the register's new value.
+        self.i2c.write(self.address as u8, &[register, value]).await
+    }
+}
@@ -0,0 +1,52 @@
+//! MPU 6500 I2C blocking driver.
+//!
+//! This is the blocking version of the [`crate::mpu6500::i2c`] driver.
+
+// The `embedded_hal` crate exports standard Hardware Abstraction
+// Layer (HAL) traits that libraries like `embassy` implement. Drivers
+// use these traits instead of the actual implementation of the HALs.
+use embedded_hal::i2c::I2c;
+
+use crate::mpu6500::{driver, i2c::Address, interface::BlockingInterface};
+
+/// MPU 6500 I2C blocking driver
+///
+/// This is the MPU6500 blocking driver that uses the [`I2cInterface`].
+pub type Mpu6500<I> = driver::BlockingMpu6500<I2cInterface<I>>;
+
+/// The interface that accesses the MPU 6500 registers using
+/// a blocking I2C bus.
+pub struct I2cInterface<I: I2c> {
+    /// The I2C bus or device
+    i2c: I,
+
+    /// The address of the sensor
+    address: Address,
+}
+
+impl<I: I2c> Mpu6500<I> {
+    /// Create a new MPU6500 blocking I2C driver instance for the
+    /// sensor that uses the `address`
+    pub fn new(i2c: I, address: Address) -> Mpu6500<I> {
+        driver::BlockingMpu6500::with_interface(I2cInterface { i2c, address })
+    }
+}
+
+/// The type `I` used by the interface is defined as *any type that
+/// implements the `I2c` trait*.
+impl<I: I2c> BlockingInterface for I2cInterface<I> {
+    type Error = I::Error;
+
+    const SPI: bool = false;
+
+    fn read_registers(&mut self, register: u8, data: &mut [u8]) -> Result<(), I::Error> {
+        // Write the register's address and read the registers'
+        // values using a repeated START.
+        self.i2c.write_read(self.address as u8, &[register], data)
+    }
+
+    fn write_register(&mut self, register: u8, value: u8) -> Result<(), I::Error> {
+        // The register's address followed by the register's new value.
+        self.i2c.write(self.address as u8, &[register, value])
+    }
+}
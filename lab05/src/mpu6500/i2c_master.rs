@@ -0,0 +1,441 @@
+//! MPU 6500 auxiliary I2C master.
+//!
+//! The sensor has a second I2C bus (`AUX_CL` and `AUX_DA`) for external
+//! sensors, usually a magnetometer. The MPU 9250 has an AK8963
+//! magnetometer connected to this bus inside the package.
+//!
+//! The sensor can use this bus in two ways:
+//! - *I2C master* - the sensor is the bus master. After every sample,
+//!   it reads up to four external sensors (`I2C_SLV0` ... `I2C_SLV3`)
+//!   and stores the values in the `EXT_SENS_DATA_00` ... `EXT_SENS_DATA_23`
+//!   registers, right after the gyro values. The application reads them
+//!   like any other sensor value. `I2C_SLV4` performs single transfers,
+//!   used to configure the external sensors.
+//! - *pass-through (bypass)* - the auxiliary bus is connected to the
+//!   sensor's I2C interface. The application accesses the external
+//!   sensors directly, using their own drivers. This only works if
+//!   the driver uses the I2C interface.
+//!
+//! ```ignore
+//! mpu6500.enable_i2c_master(I2cMasterClock::Khz400).await?;
+//! // Read 7 bytes starting with register 0x03 of the device 0x0c
+//! mpu6500.configure_slave_read(AuxSlave::Slv0, 0x0c, 0x03, 7).await?;
+//! let mut data = [0u8; 7];
+//! mpu6500.read_external_data(&mut data).await?;
+//! ```
+//!
+//! The ICM 20602 and ICM 20608 do not have an auxiliary I2C bus, the
+//! functions return `Error::Unsupported` for them.
+
+use embedded_hal_async::delay::DelayNs;
+
+use crate::mpu6500::{
+    BlockingInterface, BlockingMpu6500, Error, Interface, Mpu6500, ValueRegister, fifo::USER_CTRL,
+    interface::BlockingDelay, interrupt::INT_PIN_CFG,
+};
+
+/// I2C_MST_CTRL Register Address
+const I2C_MST_CTRL: u8 = 0x24;
+
+/// I2C_SLV0_ADDR Register Address. Every slave uses three
+/// consecutive registers: `I2C_SLVx_ADDR`, `I2C_SLVx_REG`
+/// and `I2C_SLVx_CTRL`.
+const I2C_SLV0_ADDR: u8 = 0x25;
+
+/// I2C_SLV4_ADDR Register Address, followed by `I2C_SLV4_REG`,
+/// `I2C_SLV4_DO`, `I2C_SLV4_CTRL` and `I2C_SLV4_DI`
+const I2C_SLV4_ADDR: u8 = 0x31;
+const I2C_SLV4_REG: u8 = 0x32;
+const I2C_SLV4_DO: u8 = 0x33;
+const I2C_SLV4_CTRL: u8 = 0x34;
+const I2C_SLV4_DI: u8 = 0x35;
+
+/// I2C_MST_STATUS Register Address
+const I2C_MST_STATUS: u8 = 0x36;
+
+/// I2C_SLV0_DO Register Address, followed by the
+/// values written to the other slaves
+const I2C_SLV0_DO: u8 = 0x63;
+
+/// The `I2C_SLVx_RNW` bit of the `I2C_SLVx_ADDR` registers,
+/// the transfer is a read
+const I2C_SLV_READ: u8 = 1 << 7;
+
+/// The `I2C_SLVx_EN` bit of the `I2C_SLVx_CTRL` registers
+const I2C_SLV_EN: u8 = 1 << 7;
+
+/// The `I2C_SLV4_DONE` bit of the `I2C_MST_STATUS` register
+const I2C_SLV4_DONE: u8 = 1 << 6;
+
+/// The `I2C_LOST_ARB` and `I2C_SLV4_NACK` bits of the
+/// `I2C_MST_STATUS` register
+const I2C_SLV4_FAILED: u8 = 1 << 5 | 1 << 4;
+
+/// The `I2C_MST_EN` bit of the `USER_CTRL` register
+const I2C_MST_EN: u8 = 1 << 5;
+
+/// The `BYPASS_EN` bit of the `INT_PIN_CFG` register
+const BYPASS_EN: u8 = 1 << 1;
+
+/// The number of bytes that a slave can read
+const SLAVE_MAX_LEN: u8 = 15;
+
+/// The number of `EXT_SENS_DATA` registers
+pub const EXT_SENS_DATA_SIZE: usize = 24;
+
+/// The number of sample periods that the driver waits for an
+/// `I2C_SLV4` transfer. The sensor performs the transfer after
+/// a sample was taken.
+const SLV4_ATTEMPTS: u8 = 10;
+
+/// The possible values for the `I2C_MST_CLK` field of the
+/// `I2C_MST_CTRL` register, the clock of the auxiliary bus.
+///
+/// The default value is 400 kHz, the fastest clock of the
+/// I2C fast mode.
+#[repr(u8)]
+#[derive(Copy, Clone, Default, PartialEq, Debug, defmt::Format)]
+pub enum I2cMasterClock {
+    Khz258 = 8,
+    Khz348 = 0,
+    #[default]
+    Khz400 = 13,
+    Khz500 = 9,
+}
+
+/// The slaves that the I2C master reads or writes after every sample.
+///
+/// The values read are stored in the `EXT_SENS_DATA` registers in
+/// the order of the slaves: first the values of `Slv0`, then the
+/// values of `Slv1` and so on.
+#[repr(u8)]
+#[derive(Copy, Clone, PartialEq, Debug, defmt::Format)]
+pub enum AuxSlave {
+    Slv0 = 0,
+    Slv1 = 1,
+    Slv2 = 2,
+    Slv3 = 3,
+}
+
+impl AuxSlave {
+    /// Returns the address of the slave's `I2C_SLVx_ADDR` register.
+    fn addr_register(&self) -> u8 {
+        I2C_SLV0_ADDR + 3 * *self as u8
+    }
+}
+
+/// Auxiliary I2C API
+impl<I: Interface> Mpu6500<I> {
+    /// Enables the I2C master
+    ///
+    /// The pass-through mode is disabled, the auxiliary bus is
+    /// driven by the sensor using the `clock`.
+    pub async fn enable_i2c_master(
+        &mut self,
+        clock: I2cMasterClock,
+    ) -> Result<(), Error<I::Error>> {
+        self.check_initialised()?;
+        self.check_i2c_master()?;
+        self.modify_register(INT_PIN_CFG, BYPASS_EN, 0).await?;
+        self.interface
+            .write_register(I2C_MST_CTRL, clock as u8)
+            .await?;
+        self.modify_register(USER_CTRL, I2C_MST_EN, I2C_MST_EN)
+            .await
+    }
+
+    /// Disables the I2C master
+    pub async fn disable_i2c_master(&mut self) -> Result<(), Error<I::Error>> {
+        self.check_initialised()?;
+        self.check_i2c_master()?;
+        self.modify_register(USER_CTRL, I2C_MST_EN, 0).await
+    }
+
+    /// Enables or disables the pass-through (bypass) mode
+    ///
+    /// When the mode is enabled, the I2C master is disabled and the
+    /// auxiliary bus is connected to the sensor's I2C interface. The
+    /// external sensors appear on the application's I2C bus.
+    ///
+    /// The mode only works if the driver uses the I2C interface,
+    /// for SPI the function returns `Error::Unsupported`.
+    pub async fn set_bypass(&mut self, enable: bool) -> Result<(), Error<I::Error>> {
+        self.check_initialised()?;
+        self.check_i2c_master()?;
+        if I::SPI {
+            return Err(Error::Unsupported);
+        }
+        if enable {
+            // The I2C master has to be disabled, otherwise it
+            // drives the auxiliary bus.
+            self.modify_register(USER_CTRL, I2C_MST_EN, 0).await?;
+            self.modify_register(INT_PIN_CFG, BYPASS_EN, BYPASS_EN)
+                .await
+        } else {
+            self.modify_register(INT_PIN_CFG, BYPASS_EN, 0).await
+        }
+    }
+
+    /// Reads `len` registers of an external sensor after every sample
+    ///
+    /// The external sensor has the 7 bits I2C `address`, `register`
+    /// is the first register read. The function returns
+    /// `Error::InvalidConfiguration` if `len` is 0 or larger than 15.
+    pub async fn configure_slave_read(
+        &mut self,
+        slave: AuxSlave,
+        address: u8,
+        register: u8,
+        len: u8,
+    ) -> Result<(), Error<I::Error>> {
+        self.check_initialised()?;
+        self.check_i2c_master()?;
+        if len == 0 || len > SLAVE_MAX_LEN {
+            return Err(Error::InvalidConfiguration);
+        }
+        let addr_register = slave.addr_register();
+        self.interface
+            .write_register(addr_register, I2C_SLV_READ | address)
+            .await?;
+        self.interface
+            .write_register(addr_register + 1, register)
+            .await?;
+        self.interface
+            .write_register(addr_register + 2, I2C_SLV_EN | len)
+            .await?;
+        Ok(())
+    }
+
+    /// Writes `value` to a register of an external sensor after every sample
+    pub async fn configure_slave_write(
+        &mut self,
+        slave: AuxSlave,
+        address: u8,
+        register: u8,
+        value: u8,
+    ) -> Result<(), Error<I::Error>> {
+        self.check_initialised()?;
+        self.check_i2c_master()?;
+        let addr_register = slave.addr_register();
+        self.interface
+            .write_register(I2C_SLV0_DO + slave as u8, value)
+            .await?;
+        self.interface
+            .write_register(addr_register, !I2C_SLV_READ & address)
+            .await?;
+        self.interface
+            .write_register(addr_register + 1, register)
+            .await?;
+        self.interface
+            .write_register(addr_register + 2, I2C_SLV_EN | 1)
+            .await?;
+        Ok(())
+    }
+
+    /// Stops the transfers of a slave
+    pub async fn disable_slave(&mut self, slave: AuxSlave) -> Result<(), Error<I::Error>> {
+        self.check_initialised()?;
+        self.check_i2c_master()?;
+        self.interface
+            .write_register(slave.addr_register() + 2, 0)
+            .await?;
+        Ok(())
+    }
+
+    /// Reads the values that the slaves read from the external sensors
+    ///
+    /// The values are read starting with `EXT_SENS_DATA_00`. The function
+    /// returns `Error::InvalidConfiguration` if `data` is larger than
+    /// [`EXT_SENS_DATA_SIZE`].
+    pub async fn read_external_data(&mut self, data: &mut [u8]) -> Result<(), Error<I::Error>> {
+        self.check_initialised()?;
+        self.check_i2c_master()?;
+        if data.len() > EXT_SENS_DATA_SIZE {
+            return Err(Error::InvalidConfiguration);
+        }
+        self.interface
+            .read_registers(ValueRegister::ExtSensData00 as u8, data)
+            .await?;
+        Ok(())
+    }
+
+    /// Reads a register of an external sensor
+    ///
+    /// The transfer is performed once by `I2C_SLV4`, after the next
+    /// sample. The I2C master has to be enabled. The function returns
+    /// `Error::AuxI2c` if the external sensor does not answer.
+    pub async fn aux_read(
+        &mut self,
+        address: u8,
+        register: u8,
+        delay: &mut impl DelayNs,
+    ) -> Result<u8, Error<I::Error>> {
+        self.check_initialised()?;
+        self.check_i2c_master()?;
+        self.interface
+            .write_register(I2C_SLV4_ADDR, I2C_SLV_READ | address)
+            .await?;
+        self.slv4_transfer(register, delay).await?;
+        let mut rx = [0u8; 1];
+        self.interface.read_registers(I2C_SLV4_DI, &mut rx).await?;
+        Ok(rx[0])
+    }
+
+    /// Writes a register of an external sensor
+    ///
+    /// The transfer is performed once by `I2C_SLV4`, after the next
+    /// sample. The I2C master has to be enabled. The function returns
+    /// `Error::AuxI2c` if the external sensor does not answer.
+    pub async fn aux_write(
+        &mut self,
+        address: u8,
+        register: u8,
+        value: u8,
+        delay: &mut impl DelayNs,
+    ) -> Result<(), Error<I::Error>> {
+        self.check_initialised()?;
+        self.check_i2c_master()?;
+        self.interface
+            .write_register(I2C_SLV4_ADDR, !I2C_SLV_READ & address)
+            .await?;
+        self.interface.write_register(I2C_SLV4_DO, value).await?;
+        self.slv4_transfer(register, delay).await
+    }
+}
+
+/// Private API
+impl<I: Interface> Mpu6500<I> {
+    /// Internal function that verifies if the detected sensor
+    /// has an auxiliary I2C bus.
+    fn check_i2c_master(&self) -> Result<(), Error<I::Error>> {
+        if self.chip.has_i2c_master() {
+            Ok(())
+        } else {
+            Err(Error::Unsupported)
+        }
+    }
+
+    /// Internal function that starts the `I2C_SLV4` transfer
+    /// of `register` and waits for it to finish.
+    ///
+    /// The `I2C_SLV4_ADDR` and `I2C_SLV4_DO` registers have
+    /// to be written before.
+    async fn slv4_transfer(
+        &mut self,
+        register: u8,
+        delay: &mut impl DelayNs,
+    ) -> Result<(), Error<I::Error>> {
+        self.interface
+            .write_register(I2C_SLV4_REG, register)
+            .await?;
+        self.interface
+            .write_register(I2C_SLV4_CTRL, I2C_SLV_EN)
+            .await?;
+
+        let sample_period_us = (1_000_000f32 / self.sample_rate()) as u32;
+        for _ in 0..SLV4_ATTEMPTS {
+            // I2C_MST_STATUS is cleared when read
+            let mut status = [0u8; 1];
+            self.interface
+                .read_registers(I2C_MST_STATUS, &mut status)
+                .await?;
+            if status[0] & I2C_SLV4_FAILED != 0 {
+                return Err(Error::AuxI2c);
+            }
+            if status[0] & I2C_SLV4_DONE != 0 {
+                return Ok(());
+            }
+            delay.delay_us(sample_period_us).await;
+        }
+        Err(Error::AuxI2c)
+    }
+}
+
+/// Auxiliary I2C API
+///
+/// The functions have the same meaning as the functions
+/// of the [`Mpu6500`] driver.
+impl<I: BlockingInterface> BlockingMpu6500<I> {
+    /// Enables the I2C master
+    pub fn enable_i2c_master(&mut self, clock: I2cMasterClock) -> Result<(), Error<I::Error>> {
+        embassy_futures::block_on(self.driver.enable_i2c_master(clock))
+    }
+
+    /// Disables the I2C master
+    pub fn disable_i2c_master(&mut self) -> Result<(), Error<I::Error>> {
+        embassy_futures::block_on(self.driver.disable_i2c_master())
+    }
+
+    /// Enables or disables the pass-through (bypass) mode
+    pub fn set_bypass(&mut self, enable: bool) -> Result<(), Error<I::Error>> {
+        embassy_futures::block_on(self.driver.set_bypass(enable))
+    }
+
+    /// Reads `len` registers of an external sensor after every sample
+    pub fn configure_slave_read(
+        &mut self,
+        slave: AuxSlave,
+        address: u8,
+        register: u8,
+        len: u8,
+    ) -> Result<(), Error<I::Error>> {
+        embassy_futures::block_on(
+            self.driver
+                .configure_slave_read(slave, address, register, len),
+        )
+    }
+
+    /// Writes `value` to a register of an external sensor after every sample
+    pub fn configure_slave_write(
+        &mut self,
+        slave: AuxSlave,
+        address: u8,
+        register: u8,
+        value: u8,
+    ) -> Result<(), Error<I::Error>> {
+        embassy_futures::block_on(
+            self.driver
+                .configure_slave_write(slave, address, register, value),
+        )
+    }
+
+    /// Stops the transfers of a slave
+    pub fn disable_slave(&mut self, slave: AuxSlave) -> Result<(), Error<I::Error>> {
+        embassy_futures::block_on(self.driver.disable_slave(slave))
+    }
+
+    /// Reads the values that the slaves read from the external sensors
+    pub fn read_external_data(&mut self, data: &mut [u8]) -> Result<(), Error<I::Error>> {
+        embassy_futures::block_on(self.driver.read_external_data(data))
+    }
+
+    /// Reads a register of an external sensor
+    pub fn aux_read(
+        &mut self,
+        address: u8,
+        register: u8,
+        delay: &mut impl embedded_hal::delay::DelayNs,
+    ) -> Result<u8, Error<I::Error>> {
+        embassy_futures::block_on(self.driver.aux_read(
+            address,
+            register,
+            &mut BlockingDelay(delay),
+        ))
+    }
+
+    /// Writes a register of an external sensor
+    pub fn aux_write(
+        &mut self,
+        address: u8,
+        register: u8,
+        value: u8,
+        delay: &mut impl embedded_hal::delay::DelayNs,
+    ) -> Result<(), Error<I::Error>> {
+        embassy_futures::block_on(self.driver.aux_write(
+            address,
+            register,
+            value,
+            &mut BlockingDelay(delay),
+        ))
+    }
+}
@@ -0,0 +1,97 @@
+//! The interface between the MPU6500 driver and the bus.
+//!
+//! The driver logic (register addresses, scales, conversions) is
+//! the same no matter how the bytes reach the sensor. The only thing
+//! that differs between the SPI Bus, the SPI Device, the I2C and the
+//! blocking drivers is how a register is read or written.
+//!
+//! This module defines two traits that describe exactly that:
+//! - [`Interface`] - an async interface, used by [`crate::mpu6500::Mpu6500`]
+//! - [`BlockingInterface`] - a blocking interface, used by [`crate::mpu6500::BlockingMpu6500`]
+//!
+//! Every front-end (`bus`, `device`, `device_blocking`, `i2c` and
+//! `i2c_blocking`) implements one of these traits and reuses the same driver.
+
+/// The bit that has to be set in the register address to
+/// read a register over SPI.
+///
+/// The first byte sent to the sensor has the format:
+/// | R/W REGISTER_ADDRESS |
+/// - R/W is the the most significant bit (first bit):
+///  - 1 - read the register's value from the sensor
+///  - 0 - write a value to the sensor's register
+pub(crate) const SPI_READ: u8 = 1 << 7;
+
+/// Async access to the MPU6500 registers.
+///
+/// The sensor's registers are read and written in bursts. When reading
+/// or writing, the register provided is the first register. Every other
+/// value that is read or written is to or from the following registers.
+// We allow `async fn` in the public trait, as the futures returned do not
+// have to be `Send`. The executor we use runs on a single core.
+#[allow(async_fn_in_trait)]
+pub trait Interface {
+    /// The error returned by the bus
+    type Error;
+
+    /// Whether the interface uses SPI.
+    ///
+    /// Some sensors start with both the SPI and the I2C interfaces
+    /// enabled. `Mpu6500::init` disables the I2C interface only if
+    /// the driver uses SPI.
+    const SPI: bool = true;
+
+    /// Reads `data.len()` consecutive registers starting with `register`.
+    async fn read_registers(&mut self, register: u8, data: &mut [u8]) -> Result<(), Self::Error>;
+
+    /// Writes `value` to `register`.
+    async fn write_register(&mut self, register: u8, value: u8) -> Result<(), Self::Error>;
+}
+
+/// Blocking access to the MPU6500 registers.
+///
+/// This is the blocking version of [`Interface`].
+pub trait BlockingInterface {
+    /// The error returned by the bus
+    type Error;
+
+    /// Whether the interface uses SPI.
+    const SPI: bool = true;
+
+    /// Reads `data.len()` consecutive registers starting with `register`.
+    fn read_registers(&mut self, register: u8, data: &mut [u8]) -> Result<(), Self::Error>;
+
+    /// Writes `value` to `register`.
+    fn write_register(&mut self, register: u8, value: u8) -> Result<(), Self::Error>;
+}
+
+/// Adapter that allows a [`BlockingInterface`] to be used
+/// where an [`Interface`] is required.
+///
+/// The futures returned by this adapter never wait, they
+/// finish the transfer the first time they are polled.
+pub(crate) struct Blocking<I: BlockingInterface>(pub(crate) I);
+
+impl<I: BlockingInterface> Interface for Blocking<I> {
+    type Error = I::Error;
+
+    const SPI: bool = I::SPI;
+
+    async fn read_registers(&mut self, register: u8, data: &mut [u8]) -> Result<(), Self::Error> {
+        self.0.read_registers(register, data)
+    }
+
+    async fn write_register(&mut self, register: u8, value: u8) -> Result<(), Self::Error> {
+        self.0.write_register(register, value)
+    }
+}
+
+/// Adapter that allows a blocking delay to be used where
+/// an async delay is required.
+pub(crate) struct BlockingDelay<'a, D: embedded_hal::delay::DelayNs>(pub(crate) &'a mut D);
+
+impl<D: embedded_hal::delay::DelayNs> embedded_hal_async::delay::DelayNs for BlockingDelay<'_, D> {
+    async fn delay_ns(&mut self, ns: u32) {
+        self.0.delay_ns(ns)
+    }
+}
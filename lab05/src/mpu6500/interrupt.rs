@@ -0,0 +1,210 @@
+//! MPU 6500 data ready interrupt.
+//!
+//! Instead of reading the sensor from time to time, the application
+//! can wait for the sensor to signal that a new sample is available.
+//! The sensor has an `INT` pin that it activates when a new sample is
+//! written into the data registers (the *data ready* interrupt).
+//!
+//! The `INT` pin is connected to a pin of the microcontroller. Using an
+//! `ExtiInput`, a task waits for the pin without using the CPU:
+//!
+//! ```ignore
+//! mpu6500.enable_data_ready(InterruptPin::default()).await?;
+//! loop {
+//!     mpu6500.wait_for_data(&mut int_pin).await?;
+//!     let measurement = mpu6500.read_all().await?;
+//! }
+//! ```
+//!
+//! The driver always *latches* the interrupt: the pin stays active until
+//! the `INT_STATUS` register is read. This way a sample that becomes
+//! available while the application is busy is not missed.
+
+use core::convert::Infallible;
+
+use embedded_hal_async::digital::Wait;
+
+use crate::mpu6500::{
+    BlockingInterface, BlockingMpu6500, Error, Interface, Mpu6500, fifo::FIFO_OFLOW_INT,
+};
+
+/// INT_PIN_CFG Register Address
+pub(super) const INT_PIN_CFG: u8 = 0x37;
+
+/// INT_ENABLE Register Address
+pub(super) const INT_ENABLE: u8 = 0x38;
+
+/// INT_STATUS Register Address
+const INT_STATUS: u8 = 0x3a;
+
+/// The `ACTL` bit of the `INT_PIN_CFG` register, the pin is active low
+const ACTL: u8 = 1 << 7;
+
+/// The `OPEN` bit of the `INT_PIN_CFG` register, the pin is open drain
+const OPEN: u8 = 1 << 6;
+
+/// The `LATCH_INT_EN` bit of the `INT_PIN_CFG` register
+const LATCH_INT_EN: u8 = 1 << 5;
+
+/// The `RAW_RDY_EN` bit of the `INT_ENABLE` register and the
+/// `RAW_DATA_RDY_INT` bit of the `INT_STATUS` register
+const RAW_RDY: u8 = 1;
+
+/// The electrical configuration of the `INT` pin.
+///
+/// The default value is an active high, push pull pin.
+#[derive(Copy, Clone, Default, PartialEq, Debug, defmt::Format)]
+pub struct InterruptPin {
+    /// The pin is LOW when the interrupt is active
+    pub active_low: bool,
+    /// The pin is open drain, it requires a pull up resistor
+    pub open_drain: bool,
+}
+
+impl InterruptPin {
+    /// Returns the value of the `INT_PIN_CFG` register.
+    ///
+    /// The interrupt is always latched.
+    fn bits(&self) -> u8 {
+        (self.active_low as u8 * ACTL) | (self.open_drain as u8 * OPEN) | LATCH_INT_EN
+    }
+}
+
+/// Interrupt API
+impl<I: Interface> Mpu6500<I> {
+    /// Enables the data ready interrupt on the `INT` pin
+    ///
+    /// The sensor activates the pin every time a new sample is
+    /// available, at the configured sample rate.
+    pub async fn enable_data_ready(&mut self, pin: InterruptPin) -> Result<(), Error<I::Error>> {
+        self.check_initialised()?;
+        self.configure_interrupt_pin(pin).await?;
+        self.modify_register(INT_ENABLE, RAW_RDY, RAW_RDY).await?;
+        // Clear a pending interrupt, the next one signals a new sample
+        self.read_interrupt_status().await?;
+        Ok(())
+    }
+
+    /// Disables the data ready interrupt
+    pub async fn disable_data_ready(&mut self) -> Result<(), Error<I::Error>> {
+        self.check_initialised()?;
+        self.modify_register(INT_ENABLE, RAW_RDY, 0).await?;
+        self.interrupt_pin = None;
+        Ok(())
+    }
+
+    /// Verifies if a new sample is available
+    ///
+    /// This function can be used to poll the sensor when the `INT`
+    /// pin is not connected. The data ready flag is cleared when read.
+    pub async fn data_ready(&mut self) -> Result<bool, Error<I::Error>> {
+        self.check_initialised()?;
+        Ok(self.read_interrupt_status().await? & RAW_RDY != 0)
+    }
+
+    /// Waits until a new sample is available
+    ///
+    /// The function waits for the `int` pin, that is connected to the
+    /// sensor's `INT` pin, to become active and then clears the interrupt.
+    /// If a sample became available since the last call, the function
+    /// returns immediately.
+    ///
+    /// The `int` pin can be an `ExtiInput` or any other pin that
+    /// implements the [`Wait`] trait and cannot fail.
+    ///
+    /// The function returns `Error::InvalidConfiguration` if the data
+    /// ready interrupt is not enabled.
+    pub async fn wait_for_data(
+        &mut self,
+        int: &mut impl Wait<Error = Infallible>,
+    ) -> Result<(), Error<I::Error>> {
+        self.check_initialised()?;
+        self.wait_for_interrupt(int).await?;
+        Ok(())
+    }
+}
+
+/// Private API
+impl<I: Interface> Mpu6500<I> {
+    /// Internal function that configures the `INT` pin.
+    ///
+    /// The other bits of `INT_PIN_CFG` (like `BYPASS_EN`) are kept.
+    pub(super) async fn configure_interrupt_pin(
+        &mut self,
+        pin: InterruptPin,
+    ) -> Result<(), Error<I::Error>> {
+        self.modify_register(INT_PIN_CFG, ACTL | OPEN | LATCH_INT_EN, pin.bits())
+            .await?;
+        self.interrupt_pin = Some(pin);
+        Ok(())
+    }
+
+    /// Internal function that waits for the `int` pin to become active
+    /// and returns the value of the `INT_STATUS` register.
+    ///
+    /// The function returns `Error::InvalidConfiguration` if the
+    /// `INT` pin was not configured.
+    pub(super) async fn wait_for_interrupt(
+        &mut self,
+        int: &mut impl Wait<Error = Infallible>,
+    ) -> Result<u8, Error<I::Error>> {
+        let Some(pin) = self.interrupt_pin else {
+            return Err(Error::InvalidConfiguration);
+        };
+        // The interrupt is latched, so we wait for the level, not the
+        // edge. The pin is already active if the interrupt is pending.
+        let Ok(()) = if pin.active_low {
+            int.wait_for_low().await
+        } else {
+            int.wait_for_high().await
+        };
+        // Reading INT_STATUS clears the interrupt and deactivates the pin
+        self.read_interrupt_status().await
+    }
+
+    /// Internal function that reads the `INT_STATUS` register.
+    ///
+    /// Reading the register clears all the interrupt flags. The
+    /// FIFO overflow flag is stored by the driver, so that
+    /// `Mpu6500::read_fifo` still reports it.
+    pub(super) async fn read_interrupt_status(&mut self) -> Result<u8, Error<I::Error>> {
+        let mut status = [0u8; 1];
+        self.interface
+            .read_registers(INT_STATUS, &mut status)
+            .await?;
+        self.fifo_overflow |= status[0] & FIFO_OFLOW_INT != 0;
+        Ok(status[0])
+    }
+}
+
+/// Interrupt API
+///
+/// The functions have the same meaning as the functions
+/// of the [`Mpu6500`] driver.
+impl<I: BlockingInterface> BlockingMpu6500<I> {
+    /// Enables the data ready interrupt on the `INT` pin
+    pub fn enable_data_ready(&mut self, pin: InterruptPin) -> Result<(), Error<I::Error>> {
+        embassy_futures::block_on(self.driver.enable_data_ready(pin))
+    }
+
+    /// Disables the data ready interrupt
+    pub fn disable_data_ready(&mut self) -> Result<(), Error<I::Error>> {
+        embassy_futures::block_on(self.driver.disable_data_ready())
+    }
+
+    /// Verifies if a new sample is available
+    pub fn data_ready(&mut self) -> Result<bool, Error<I::Error>> {
+        embassy_futures::block_on(self.driver.data_ready())
+    }
+
+    /// Waits until a new sample is available
+    ///
+    /// The blocking driver accesses the sensor without waiting,
+    /// but this function is `async`, as it waits for the `int` pin.
+    pub async fn wait_for_data(
+        &mut self,
+        int: &mut impl Wait<Error = Infallible>,
+    ) -> Result<(), Error<I::Error>> {
+        self.driver.wait_for_data(int).await
+    }
+}
@@ -1,27 +1,132 @@
-//! MPU 6500 SPI driver that uses the SPI Bus.
+//! MPU 6500 SPI and I2C driver.
 //!
-//! This module exports three drivers
+//! The driver also works with the other sensors of the family, see
+//! [`Chip`]. It detects the sensor when it is initialised.
+//!
+//! This module exports five drivers
 //! - MPU6500 async SPI Bus driver
 //! - MPU6500 async SPI Device driver
 //! - MPU6500 blocking SPI Device driver
+//! - MPU6500 async I2C driver
+//! - MPU6500 blocking I2C driver
+//!
+//! All the drivers share the same driver core, [`Mpu6500`]. They only
+//! differ in the [`Interface`] or [`BlockingInterface`] that moves
+//! the bytes to and from the sensor.
 //!
 //! It defines several data structures used by all the drivers.
 
+mod ak8963;
 pub mod bus;
+mod calibration;
+mod chip;
+mod config;
 pub mod device;
 pub mod device_blocking;
+mod driver;
+mod fifo;
+pub mod i2c;
+pub mod i2c_blocking;
+mod i2c_master;
+mod interface;
+mod interrupt;
+mod motion;
+mod self_test;
+
+#[cfg(test)]
+mod sim;
+#[cfg(test)]
+mod tests;
+
+pub use ak8963::{
+    Ak8963, MagCalibration, MagCalibrator, MagMode, MagneticField, NineAxisMeasurement, heading,
+};
+pub use calibration::Offsets;
+pub use chip::Chip;
+pub use config::{AccelDlpf, ClockSource, Config, GyroDlpf, Standby};
+pub use driver::{BlockingMpu6500, Mpu6500};
+pub use fifo::{FIFO_SIZE, FifoFrame, FifoRead, FifoSensors};
+pub use i2c::Address;
+pub use i2c_master::{AuxSlave, EXT_SENS_DATA_SIZE, I2cMasterClock};
+pub use interface::{BlockingInterface, Interface};
+pub use interrupt::InterruptPin;
+pub use motion::{LpAccelOdr, WakeOnMotion};
+pub use self_test::{SELF_TEST_SAMPLES, SelfTestAxis, SelfTestReport};
 
 /// WHO_AM_I Register Address
 const WHO_AM_I: u8 = 0x75;
 
-/// WHO_AM_I Register Value for the MPU6500 sensor
-const WHO_AM_I_VALUE: u8 = 0x70;
+/// The `DEVICE_RESET` bit of the `PWR_MGMT_1` register
+const DEVICE_RESET: u8 = 1 << 7;
+
+/// The `GYRO_RST`, `ACCEL_RST` and `TEMP_RST` bits of the
+/// `SIGNAL_PATH_RESET` register
+const SIGNAL_PATH_RESET: u8 = 0b111;
 
 /// The gravitational acceleration
 const G: f32 = 9.80665;
 
-/// The register address that the [`bus::Mpu6500::write_config`]
-/// function should read
+/// The temperature sensor's sensitivity in LSB / deg C
+const TEMP_SENSITIVITY: f32 = 333.87;
+
+/// The temperature in deg C for which the temperature
+/// sensor reports 0 (RoomTemp_Offset)
+const TEMP_ROOM: f32 = 21.0;
+
+/// Converts the raw value of the `TEMP_OUT` registers to deg C.
+///
+/// The datasheet defines the temperature as:
+/// TEMP_degC = ((TEMP_OUT - RoomTemp_Offset) / Temp_Sensitivity) + 21 deg C
+///
+/// The RoomTemp_Offset is 0 for the MPU 6500.
+pub fn temperature_to_celsius(raw: i16) -> f32 {
+    raw as f32 / TEMP_SENSITIVITY + TEMP_ROOM
+}
+
+/// The errors returned by the MPU 6500 drivers.
+///
+/// The type `E` is the error type of the bus (the
+/// [`Interface::Error`] of the driver).
+///
+/// [`Debug`] and [`defmt::Format`] are derived so that
+/// the error can be printed.
+#[derive(Debug, defmt::Format)]
+pub enum Error<E> {
+    /// The transfer on the bus failed
+    Bus(E),
+    /// The WHO_AM_I register (or the magnetometer's WIA register)
+    /// has an unexpected value.
+    ///
+    /// This usually means that a sensor that is not supported by
+    /// the driver is connected or that no sensor is connected (a missing sensor usually
+    /// reads as `0x00` or `0xff`).
+    UnexpectedWhoAmI(u8),
+    /// The driver is used before being initialised
+    /// with `Mpu6500::init`
+    NotInitialised,
+    /// The value read back from a configuration register
+    /// is not the value that was written
+    InvalidConfiguration,
+    /// The feature is not available on the detected sensor
+    Unsupported,
+    /// The external sensor did not answer on the auxiliary I2C bus
+    AuxI2c,
+    /// The magnetic field is too strong for the magnetometer
+    MagneticOverflow,
+}
+
+/// Converts a bus error to an MPU 6500 driver error.
+///
+/// This allows us to use the `?` operator on the results
+/// returned by the [`Interface`].
+impl<E> From<E> for Error<E> {
+    fn from(error: E) -> Error<E> {
+        Error::Bus(error)
+    }
+}
+
+/// The register address that the `Mpu6500::write_config`
+/// function should write
 ///
 /// Instead of using numbers we defined this as an enum to
 /// make sure that users cannot use any other values
@@ -35,11 +140,17 @@ const G: f32 = 9.80665;
 #[repr(u8)]
 #[derive(Copy, Clone)]
 pub enum ConfigRegister {
+    SampleRateDivider = 0x19,
+    Config = 0x1a,
     Gyro = 0x1b,
     Accel = 0x1c,
+    Accel2 = 0x1d,
+    SignalPathReset = 0x68,
+    PowerManagement1 = 0x6b,
+    PowerManagement2 = 0x6c,
 }
 
-/// The register address that the [`bus::Mpu6500::read_value`]
+/// The register address that the `Mpu6500::read_value`
 /// function should read
 ///
 /// Instead of using numbers we defined this as an enum to
@@ -56,6 +167,7 @@ pub enum ConfigRegister {
 pub enum ValueRegister {
     AccelXOutH = 0x3b,
     GyroXOutH = 0x43,
+    ExtSensData00 = 0x49,
 }
 
 /// The possible values for the `GYRO_FS_SEL` field of
@@ -70,8 +182,11 @@ pub enum ValueRegister {
 ///
 /// [`Copy`] and [`Clone`] are derived so that the value
 /// can be copied when sent as a parameter to a function.
+///
+/// [`PartialEq`], [`Debug`] and [`defmt::Format`] are derived
+/// so that the values can be compared and printed.
 #[repr(u8)]
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, PartialEq, Debug, defmt::Format)]
 pub enum GyroScale {
     Gs250 = 0b00,
     Gs500 = 0b01,
@@ -80,9 +195,20 @@ pub enum GyroScale {
 }
 
 impl GyroScale {
+    /// Returns the gyro scale stored in the `GYRO_CONFIG` register
+    /// value.
+    fn from_config(config: u8) -> GyroScale {
+        match (config >> 3) & 0b11 {
+            0b00 => GyroScale::Gs250,
+            0b01 => GyroScale::Gs500,
+            0b10 => GyroScale::Gs1000,
+            _ => GyroScale::Gs2000,
+        }
+    }
+
     /// Returns the absolute maximum value in deg/s for
     /// each scale value.
-    pub fn value(&self) -> f32 {
+    pub fn full_scale(&self) -> f32 {
         match self {
             GyroScale::Gs250 => 250f32,
             GyroScale::Gs500 => 500f32,
@@ -90,6 +216,28 @@ impl GyroScale {
             GyroScale::Gs2000 => 2000f32,
         }
     }
+
+    /// Returns the sensitivity in LSB / (deg/s) for each scale value,
+    /// as defined by the datasheet.
+    ///
+    /// This is the raw value that the sensor reports for 1 deg/s.
+    pub fn sensitivity(&self) -> f32 {
+        match self {
+            GyroScale::Gs250 => 131f32,
+            GyroScale::Gs500 => 65.5f32,
+            GyroScale::Gs1000 => 32.8f32,
+            GyroScale::Gs2000 => 16.4f32,
+        }
+    }
+
+    /// Converts a raw gyro value to deg/s.
+    pub fn to_deg_s(&self, raw: i16) -> f32 {
+        // self.sensitivity() ...... 1 deg/s
+        // raw ..................... gyro
+        //
+        // gyro = raw / self.sensitivity()
+        raw as f32 / self.sensitivity()
+    }
 }
 
 /// The possible values for the `ACCEL_FS_SEL` field of
@@ -104,8 +252,11 @@ impl GyroScale {
 ///
 /// [`Copy`] and [`Clone`] are derived so that the value
 /// can be copied when sent as a parameter to a function.
+///
+/// [`PartialEq`], [`Debug`] and [`defmt::Format`] are derived
+/// so that the values can be compared and printed.
 #[repr(u8)]
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, PartialEq, Debug, defmt::Format)]
 pub enum AccelScale {
     G2 = 0b00,
     G4 = 0b01,
@@ -114,28 +265,102 @@ pub enum AccelScale {
 }
 
 impl AccelScale {
-    /// Returns the absolute maximum value in m/s^2 for
+    /// Returns the acceleration scale stored in the `ACCEL_CONFIG`
+    /// register value.
+    fn from_config(config: u8) -> AccelScale {
+        match (config >> 3) & 0b11 {
+            0b00 => AccelScale::G2,
+            0b01 => AccelScale::G4,
+            0b10 => AccelScale::G8,
+            _ => AccelScale::G16,
+        }
+    }
+
+    /// Returns the absolute maximum value in g for
     /// each scale value.
-    pub fn value(&self) -> f32 {
+    pub fn full_scale(&self) -> f32 {
+        match self {
+            AccelScale::G2 => 2f32,
+            AccelScale::G4 => 4f32,
+            AccelScale::G8 => 8f32,
+            AccelScale::G16 => 16f32,
+        }
+    }
+
+    /// Returns the sensitivity in LSB / g for each scale value,
+    /// as defined by the datasheet.
+    ///
+    /// This is the raw value that the sensor reports for 1 g.
+    pub fn sensitivity(&self) -> f32 {
         match self {
-            AccelScale::G2 => 2f32 * G,
-            AccelScale::G4 => 4f32 * G,
-            AccelScale::G8 => 8f32 * G,
-            AccelScale::G16 => 16f32 * G,
+            AccelScale::G2 => 16384f32,
+            AccelScale::G4 => 8192f32,
+            AccelScale::G8 => 4096f32,
+            AccelScale::G16 => 2048f32,
         }
     }
+
+    /// Converts a raw acceleration value to g.
+    pub fn to_g(&self, raw: i16) -> f32 {
+        // self.sensitivity() ...... 1 g
+        // raw ..................... acceleration
+        //
+        // acceleration = raw / self.sensitivity()
+        raw as f32 / self.sensitivity()
+    }
 }
 
-/// Stores the acceleration on all the three axes.
+/// Stores the acceleration on all the three axes in g.
+///
+/// 1 g is the gravitational acceleration, 9.80665 m/s^2.
+#[derive(Copy, Clone, Debug, defmt::Format)]
 pub struct Acceleration {
     pub x: f32,
     pub y: f32,
     pub z: f32,
 }
 
-/// Stores the gyro values on all the three axes.
+impl Acceleration {
+    /// Returns the acceleration on all the three axes
+    /// in m/s^2.
+    pub fn to_m_s2(&self) -> [f32; 3] {
+        [self.x * G, self.y * G, self.z * G]
+    }
+}
+
+/// Stores the gyro values on all the three axes in deg/s.
+#[derive(Copy, Clone, Debug, defmt::Format)]
 pub struct Gyro {
     pub x: f32,
     pub y: f32,
     pub z: f32,
 }
+
+/// Stores the raw values read from the sensor, as they are
+/// stored in the sensor's registers.
+///
+/// Use [`AccelScale::to_g`] and [`GyroScale::to_deg_s`] with
+/// the configured scales and [`temperature_to_celsius`] to convert them.
+///
+/// This is useful for applications that cannot afford `f32` math.
+#[derive(Copy, Clone, Debug, PartialEq, defmt::Format)]
+pub struct RawMeasurement {
+    /// The raw acceleration on the X, Y and Z axes
+    pub accel: [i16; 3],
+    /// The raw die temperature
+    pub temperature: i16,
+    /// The raw gyro on the X, Y and Z axes
+    pub gyro: [i16; 3],
+}
+
+/// Stores the acceleration, temperature and gyro values
+/// that belong to the same sample.
+#[derive(Copy, Clone, Debug, defmt::Format)]
+pub struct Measurement {
+    /// The acceleration in g
+    pub accel: Acceleration,
+    /// The die temperature in deg C
+    pub temperature: f32,
+    /// The gyro in deg/s
+    pub gyro: Gyro,
+}
@@ -0,0 +1,309 @@
+//! MPU 6500 wake on motion.
+//!
+//! Battery powered devices can put the sensor into a low power mode
+//! until it is moved. In this mode:
+//! - the gyro is in standby
+//! - the accelerometer wakes up at the [`LpAccelOdr`] rate, takes a
+//!   sample and goes back to sleep (*cycle* mode)
+//! - if the acceleration changed by more than a threshold since the
+//!   previous sample, the sensor activates the `INT` pin
+//!
+//! The sequence that enters this mode is the one in the datasheet:
+//! 1. make sure the accelerometer is running and put the gyro in standby
+//! 2. set the accelerometer low pass filter to 184 Hz
+//! 3. enable the wake on motion interrupt (`INT_ENABLE`)
+//! 4. enable the accelerometer hardware intelligence (`ACCEL_INTEL_CTRL`)
+//! 5. set the motion threshold (`WOM_THR`)
+//! 6. set the wake up frequency (`LP_ACCEL_ODR`)
+//! 7. enable the cycle mode (`PWR_MGMT_1`)
+//!
+//! On the ICM 20602 and ICM 20608, the `0x1E` register is `LP_MODE_CFG`,
+//! which has a different layout. The accelerometer wakes up at the
+//! sample rate, `1 kHz / (1 + SMPLRT_DIV)`, so the driver sets the wake
+//! up frequency with the sample rate divider. They do not wake up
+//! slower than [`LpAccelOdr::Hz3_91`].
+//!
+//! ```ignore
+//! mpu6500.enable_wake_on_motion(wake_on_motion, InterruptPin::default()).await?;
+//! mpu6500.wait_for_motion(&mut int_pin).await?;
+//! // Back to the full measurement mode
+//! mpu6500.disable_wake_on_motion().await?;
+//! ```
+
+use core::convert::Infallible;
+
+use embedded_hal_async::digital::Wait;
+
+use crate::mpu6500::{
+    BlockingInterface, BlockingMpu6500, Chip, Config, ConfigRegister, Error, Interface,
+    InterruptPin, Mpu6500,
+    interrupt::{INT_ENABLE, INT_PIN_CFG},
+};
+
+/// LP_ACCEL_ODR Register Address
+const LP_ACCEL_ODR: u8 = 0x1e;
+
+/// WOM_THR Register Address
+const WOM_THR: u8 = 0x1f;
+
+/// ACCEL_WOM_X_THR Register Address of the ICM20602 sensor,
+/// followed by the Y and Z thresholds
+const ICM20602_ACCEL_WOM_X_THR: u8 = 0x20;
+
+/// ACCEL_INTEL_CTRL Register Address
+const ACCEL_INTEL_CTRL: u8 = 0x69;
+
+/// The `ACCEL_INTEL_EN` and `ACCEL_INTEL_MODE` bits of the
+/// `ACCEL_INTEL_CTRL` register. The mode compares every sample
+/// with the previous one.
+const ACCEL_INTEL_EN_MODE: u8 = 0b11 << 6;
+
+/// The `WOM_EN` bit of the `INT_ENABLE` register and the
+/// `WOM_INT` bit of the `INT_STATUS` register
+const WOM: u8 = 1 << 6;
+
+/// The `ACCEL_CONFIG2` value used in wake on motion mode,
+/// a 184 Hz low pass filter
+const WOM_ACCEL_CONFIG2: u8 = 1;
+
+/// The `PWR_MGMT_2` value used in wake on motion mode,
+/// the gyro is in standby
+const WOM_PWR_MGMT_2: u8 = 0b111;
+
+/// The `PWR_MGMT_1` value used in wake on motion mode,
+/// the `CYCLE` bit and the automatic clock source
+const WOM_PWR_MGMT_1: u8 = 1 << 5 | 1;
+
+/// The `WOM_THR` register has a resolution of 4 mg
+const WOM_THR_MG_PER_LSB: u16 = 4;
+
+/// `LP_ACCEL_ODR` value of the rate that needs a sample rate divider of
+/// 1 on the ICM sensors, every lower value halves the rate
+const ICM_ODR_DIVIDER_1: u8 = LpAccelOdr::Hz500 as u8;
+
+/// The possible values for the `LP_ACCEL_ODR` register, the rate
+/// at which the accelerometer wakes up in cycle mode.
+///
+/// A lower rate uses less power, but motion is detected later.
+/// The ICM 20602 and ICM 20608 support only the rates
+/// starting with [`LpAccelOdr::Hz3_91`].
+#[repr(u8)]
+#[derive(Copy, Clone, PartialEq, Debug, defmt::Format)]
+pub enum LpAccelOdr {
+    Hz0_24 = 0,
+    Hz0_49 = 1,
+    Hz0_98 = 2,
+    Hz1_95 = 3,
+    Hz3_91 = 4,
+    Hz7_81 = 5,
+    Hz15_63 = 6,
+    Hz31_25 = 7,
+    Hz62_5 = 8,
+    Hz125 = 9,
+    Hz250 = 10,
+    Hz500 = 11,
+}
+
+/// The wake on motion settings.
+#[derive(Copy, Clone, PartialEq, Debug, defmt::Format)]
+pub struct WakeOnMotion {
+    /// The acceleration change that wakes up the application, in mg.
+    ///
+    /// The sensor has a resolution of 4 mg and a maximum of 1020 mg.
+    pub threshold_mg: u16,
+    /// The rate at which the accelerometer is sampled
+    pub odr: LpAccelOdr,
+}
+
+impl WakeOnMotion {
+    /// Returns the value of the `WOM_THR` register.
+    fn threshold(&self) -> u8 {
+        (self.threshold_mg / WOM_THR_MG_PER_LSB).min(u8::MAX as u16) as u8
+    }
+
+    /// Returns the `SMPLRT_DIV` value that sets the wake up rate of the
+    /// ICM sensors, `None` if they do not support the rate.
+    fn icm_sample_rate_divider(&self) -> Option<u8> {
+        let halvings = ICM_ODR_DIVIDER_1.checked_sub(self.odr as u8)?;
+        u8::try_from((2u16 << halvings) - 1).ok()
+    }
+}
+
+/// The state of the sensor before entering the wake on motion mode,
+/// restored by `Mpu6500::disable_wake_on_motion`.
+#[derive(Copy, Clone)]
+pub(super) struct SavedState {
+    config: Config,
+    int_pin_cfg: u8,
+    int_enable: u8,
+    interrupt_pin: Option<InterruptPin>,
+}
+
+/// Wake on motion API
+impl<I: Interface> Mpu6500<I> {
+    /// Puts the sensor into the low power wake on motion mode
+    ///
+    /// The sensor activates the `INT` pin, configured with `pin`,
+    /// when it is moved. All the other interrupts are disabled.
+    ///
+    /// The current configuration is saved and restored by
+    /// [`Mpu6500::disable_wake_on_motion`].
+    ///
+    /// The MPU 6000 does not support this mode, the function
+    /// returns `Error::Unsupported`. For the ICM 20602 and ICM 20608,
+    /// a rate below [`LpAccelOdr::Hz3_91`] returns
+    /// `Error::InvalidConfiguration`.
+    pub async fn enable_wake_on_motion(
+        &mut self,
+        wake_on_motion: WakeOnMotion,
+        pin: InterruptPin,
+    ) -> Result<(), Error<I::Error>> {
+        self.check_initialised()?;
+        self.check_mpu6500_compatible()?;
+        let icm_divider = match self.chip {
+            Chip::Icm20602 | Chip::Icm20608 => Some(
+                wake_on_motion
+                    .icm_sample_rate_divider()
+                    .ok_or(Error::InvalidConfiguration)?,
+            ),
+            _ => None,
+        };
+        // Save the state only the first time, the function might
+        // be called again to change the settings.
+        if self.wake_on_motion.is_none() {
+            let mut int = [0u8; 2];
+            // INT_PIN_CFG and INT_ENABLE are consecutive registers
+            self.interface.read_registers(INT_PIN_CFG, &mut int).await?;
+            self.wake_on_motion = Some(SavedState {
+                config: self.config,
+                int_pin_cfg: int[0],
+                int_enable: int[1],
+                interrupt_pin: self.interrupt_pin,
+            });
+        }
+
+        // The accelerometer has to run, clear SLEEP and CYCLE
+        self.interface
+            .write_register(ConfigRegister::PowerManagement1 as u8, 1)
+            .await?;
+        self.interface
+            .write_register(ConfigRegister::PowerManagement2 as u8, WOM_PWR_MGMT_2)
+            .await?;
+        self.interface
+            .write_register(ConfigRegister::Accel2 as u8, WOM_ACCEL_CONFIG2)
+            .await?;
+        self.configure_interrupt_pin(pin).await?;
+        self.interface.write_register(INT_ENABLE, WOM).await?;
+        self.interface
+            .write_register(ACCEL_INTEL_CTRL, ACCEL_INTEL_EN_MODE)
+            .await?;
+        if self.chip == Chip::Icm20602 {
+            // The ICM20602 has a threshold for every axis
+            for axis in 0..3 {
+                self.interface
+                    .write_register(ICM20602_ACCEL_WOM_X_THR + axis, wake_on_motion.threshold())
+                    .await?;
+            }
+        } else {
+            self.interface
+                .write_register(WOM_THR, wake_on_motion.threshold())
+                .await?;
+        }
+        if let Some(divider) = icm_divider {
+            // LP_MODE_CFG is not LP_ACCEL_ODR, the accelerometer
+            // wakes up at the sample rate
+            self.interface
+                .write_register(ConfigRegister::SampleRateDivider as u8, divider)
+                .await?;
+        } else {
+            self.interface
+                .write_register(LP_ACCEL_ODR, wake_on_motion.odr as u8)
+                .await?;
+        }
+        self.interface
+            .write_register(ConfigRegister::PowerManagement1 as u8, WOM_PWR_MGMT_1)
+            .await?;
+
+        self.config = self.read_config().await?;
+        // Clear a pending interrupt, the next one signals motion
+        self.read_interrupt_status().await?;
+        Ok(())
+    }
+
+    /// Waits until the sensor is moved
+    ///
+    /// The `int` pin is the microcontroller pin connected to the
+    /// sensor's `INT` pin, usually an `ExtiInput`.
+    ///
+    /// The function returns `Error::InvalidConfiguration` if the
+    /// wake on motion mode is not enabled.
+    pub async fn wait_for_motion(
+        &mut self,
+        int: &mut impl Wait<Error = Infallible>,
+    ) -> Result<(), Error<I::Error>> {
+        self.check_initialised()?;
+        if self.wake_on_motion.is_none() {
+            return Err(Error::InvalidConfiguration);
+        }
+        while self.wait_for_interrupt(int).await? & WOM == 0 {}
+        Ok(())
+    }
+
+    /// Returns to the full measurement mode
+    ///
+    /// The configuration and the interrupts used before
+    /// [`Mpu6500::enable_wake_on_motion`] are restored. The function
+    /// does nothing if the wake on motion mode is not enabled.
+    pub async fn disable_wake_on_motion(&mut self) -> Result<(), Error<I::Error>> {
+        self.check_initialised()?;
+        let Some(saved) = self.wake_on_motion else {
+            return Ok(());
+        };
+        self.interface.write_register(ACCEL_INTEL_CTRL, 0).await?;
+        self.interface
+            .write_register(INT_ENABLE, saved.int_enable)
+            .await?;
+        self.interface
+            .write_register(INT_PIN_CFG, saved.int_pin_cfg)
+            .await?;
+        self.interrupt_pin = saved.interrupt_pin;
+        self.wake_on_motion = None;
+        // `configure` writes PWR_MGMT_1 last, so the sensor leaves
+        // the cycle mode after it was configured.
+        self.configure(saved.config).await?;
+        // Clear the interrupts triggered while switching modes
+        self.read_interrupt_status().await?;
+        Ok(())
+    }
+}
+
+/// Wake on motion API
+///
+/// The functions have the same meaning as the functions
+/// of the [`Mpu6500`] driver.
+impl<I: BlockingInterface> BlockingMpu6500<I> {
+    /// Puts the sensor into the low power wake on motion mode
+    pub fn enable_wake_on_motion(
+        &mut self,
+        wake_on_motion: WakeOnMotion,
+        pin: InterruptPin,
+    ) -> Result<(), Error<I::Error>> {
+        embassy_futures::block_on(self.driver.enable_wake_on_motion(wake_on_motion, pin))
+    }
+
+    /// Waits until the sensor is moved
+    ///
+    /// The blocking driver accesses the sensor without waiting,
+    /// but this function is `async`, as it waits for the `int` pin.
+    pub async fn wait_for_motion(
+        &mut self,
+        int: &mut impl Wait<Error = Infallible>,
+    ) -> Result<(), Error<I::Error>> {
+        self.driver.wait_for_motion(int).await
+    }
+
+    /// Returns to the full measurement mode
+    pub fn disable_wake_on_motion(&mut self) -> Result<(), Error<I::Error>> {
+        embassy_futures::block_on(self.driver.disable_wake_on_motion())
+    }
+}
@@ -0,0 +1,283 @@
+//! MPU 6500 factory self-test.
+//!
+//! The sensor can move its own gyro and accelerometer mechanical parts
+//! using an electrostatic force, as if it were rotated or accelerated.
+//! The difference between the values read with and without this force
+//! is the *self-test response*. During production, the manufacturer
+//! measured the response and stored it (the *factory trim*) in the
+//! `SELF_TEST_X_GYRO` ... `SELF_TEST_Z_ACCEL` registers.
+//!
+//! The procedure, from the datasheet, is:
+//! 1. configure a 1 kHz sample rate, the 92 Hz gyro filter, the 99 Hz
+//!    accelerometer filter and the 250 deg/s and 2 g scales
+//! 2. average [`SELF_TEST_SAMPLES`] samples
+//! 3. enable the self-test bits and wait 20 ms for the values to settle
+//! 4. average [`SELF_TEST_SAMPLES`] samples
+//! 5. disable the self-test bits and wait 20 ms
+//! 6. compare the response (4 - 2) with the factory trim
+//!
+//! An axis passes the test if:
+//! - gyro: the response is larger than 50% of the factory trim
+//! - accelerometer: the response is between 50% and 150% of the factory trim
+//!
+//! If the factory trim of an axis is 0, the response is compared
+//! with the absolute limits of the datasheet instead.
+
+use embedded_hal_async::delay::DelayNs;
+
+use crate::mpu6500::{
+    AccelDlpf, AccelScale, BlockingInterface, BlockingMpu6500, ConfigRegister, Error, GyroDlpf,
+    GyroScale, Interface, Mpu6500, interface::BlockingDelay,
+};
+
+/// SELF_TEST_X_GYRO Register Address, followed by Y and Z
+const SELF_TEST_X_GYRO: u8 = 0x00;
+
+/// SELF_TEST_X_ACCEL Register Address, followed by Y and Z
+const SELF_TEST_X_ACCEL: u8 = 0x0d;
+
+/// The `XG_ST`, `YG_ST` and `ZG_ST` bits of `GYRO_CONFIG` and
+/// the `XA_ST`, `YA_ST` and `ZA_ST` bits of `ACCEL_CONFIG`
+const SELF_TEST_ENABLE: u8 = 0b111 << 5;
+
+/// The number of samples averaged with and without the self-test
+pub const SELF_TEST_SAMPLES: u16 = 200;
+
+/// The time required by the values to settle, in ms
+const SETTLE_TIME_MS: u32 = 20;
+
+/// The factory trim of code 1, in LSB
+const FACTORY_TRIM_BASE: f32 = 2620f32;
+
+/// The minimum gyro response, relative to the factory trim
+const GYRO_MIN_RATIO: f32 = 0.5;
+
+/// The minimum gyro response in deg/s, if there is no factory trim
+const GYRO_MIN_RESPONSE: f32 = 60f32;
+
+/// The accelerometer response limits, relative to the factory trim
+const ACCEL_MIN_RATIO: f32 = 0.5;
+const ACCEL_MAX_RATIO: f32 = 1.5;
+
+/// The accelerometer response limits in g, if there is no factory trim
+const ACCEL_MIN_RESPONSE: f32 = 0.225;
+const ACCEL_MAX_RESPONSE: f32 = 0.675;
+
+/// The self-test result of an axis.
+#[derive(Copy, Clone, PartialEq, Debug, defmt::Format)]
+pub struct SelfTestAxis {
+    /// The measured self-test response, in deg/s for
+    /// the gyro and g for the accelerometer
+    pub response: f32,
+    /// The factory trim, in the same unit as the response,
+    /// `None` if the sensor does not store it
+    pub factory_trim: Option<f32>,
+    /// The difference between the response and the factory trim,
+    /// in percent of the factory trim
+    pub deviation: Option<f32>,
+    /// Whether the axis passed the test
+    pub passed: bool,
+}
+
+/// The self-test result of all the axes.
+#[derive(Copy, Clone, PartialEq, Debug, defmt::Format)]
+pub struct SelfTestReport {
+    /// The X, Y and Z axes of the accelerometer
+    pub accel: [SelfTestAxis; 3],
+    /// The X, Y and Z axes of the gyro
+    pub gyro: [SelfTestAxis; 3],
+}
+
+impl SelfTestReport {
+    /// Returns `true` if all the axes passed the test
+    pub fn passed(&self) -> bool {
+        self.accel
+            .iter()
+            .chain(self.gyro.iter())
+            .all(|axis| axis.passed)
+    }
+}
+
+/// Self-test API
+impl<I: Interface> Mpu6500<I> {
+    /// Runs the factory self-test
+    ///
+    /// The sensor has to be still during the test, which takes
+    /// about half a second. The configuration is restored after
+    /// the test.
+    ///
+    /// The MPU 6000 uses a different procedure, the function
+    /// returns `Error::Unsupported`.
+    pub async fn self_test(
+        &mut self,
+        delay: &mut impl DelayNs,
+    ) -> Result<SelfTestReport, Error<I::Error>> {
+        self.check_initialised()?;
+        self.check_mpu6500_compatible()?;
+        let saved = self.config;
+
+        let test_config = saved
+            .gyro_scale(GyroScale::Gs250)
+            .accel_scale(AccelScale::G2)
+            .gyro_dlpf(GyroDlpf::Hz92)
+            .accel_dlpf(AccelDlpf::Hz99)
+            .sample_rate_divider(0)
+            .sleep(false)
+            .cycle(false);
+        // The configuration is restored even if the test fails
+        let result: Result<SelfTestReport, Error<I::Error>> = async {
+            self.configure(test_config).await?;
+            delay.delay_ms(SETTLE_TIME_MS).await;
+            let (accel, gyro) = self.average_samples(delay).await?;
+
+            self.set_self_test(SELF_TEST_ENABLE).await?;
+            delay.delay_ms(SETTLE_TIME_MS).await;
+            let (accel_st, gyro_st) = self.average_samples(delay).await?;
+
+            self.set_self_test(0).await?;
+            delay.delay_ms(SETTLE_TIME_MS).await;
+
+            let mut codes = [0u8; 3];
+            self.interface
+                .read_registers(SELF_TEST_X_GYRO, &mut codes)
+                .await?;
+            let gyro_codes = codes;
+            self.interface
+                .read_registers(SELF_TEST_X_ACCEL, &mut codes)
+                .await?;
+            let accel_codes = codes;
+
+            let gyro_sensitivity = GyroScale::Gs250.sensitivity();
+            let accel_sensitivity = AccelScale::G2.sensitivity();
+            let mut report = SelfTestReport {
+                accel: [SelfTestAxis::EMPTY; 3],
+                gyro: [SelfTestAxis::EMPTY; 3],
+            };
+            for axis in 0..3 {
+                report.gyro[axis] = SelfTestAxis::new(
+                    (gyro_st[axis] - gyro[axis]) / gyro_sensitivity,
+                    factory_trim(gyro_codes[axis]).map(|trim| trim / gyro_sensitivity),
+                    |ratio| ratio > GYRO_MIN_RATIO,
+                    |response| response >= GYRO_MIN_RESPONSE,
+                );
+                report.accel[axis] = SelfTestAxis::new(
+                    (accel_st[axis] - accel[axis]) / accel_sensitivity,
+                    factory_trim(accel_codes[axis]).map(|trim| trim / accel_sensitivity),
+                    |ratio| (ACCEL_MIN_RATIO..=ACCEL_MAX_RATIO).contains(&ratio),
+                    |response| (ACCEL_MIN_RESPONSE..=ACCEL_MAX_RESPONSE).contains(&response),
+                );
+            }
+            Ok(report)
+        }
+        .await;
+
+        // `configure` also clears the self-test bits, the error
+        // of the test is returned first
+        let restored = self.configure(saved).await;
+        let report = result?;
+        restored?;
+        Ok(report)
+    }
+}
+
+/// Private API
+impl<I: Interface> Mpu6500<I> {
+    /// Internal function that sets the self-test bits of the gyro and
+    /// accelerometer, keeping the 250 deg/s and 2 g scales.
+    async fn set_self_test(&mut self, bits: u8) -> Result<(), Error<I::Error>> {
+        self.interface
+            .write_register(ConfigRegister::Gyro as u8, bits)
+            .await?;
+        self.interface
+            .write_register(ConfigRegister::Accel as u8, bits)
+            .await?;
+        Ok(())
+    }
+
+    /// Internal function that returns the average raw acceleration
+    /// and gyro of [`SELF_TEST_SAMPLES`] samples read at 1 kHz.
+    async fn average_samples(
+        &mut self,
+        delay: &mut impl DelayNs,
+    ) -> Result<([f32; 3], [f32; 3]), Error<I::Error>> {
+        let mut accel_sum = [0i32; 3];
+        let mut gyro_sum = [0i32; 3];
+        for _ in 0..SELF_TEST_SAMPLES {
+            let raw = self.read_raw().await?;
+            for axis in 0..3 {
+                accel_sum[axis] += raw.accel[axis] as i32;
+                gyro_sum[axis] += raw.gyro[axis] as i32;
+            }
+            delay.delay_ms(1).await;
+        }
+        Ok((
+            accel_sum.map(|sum| sum as f32 / SELF_TEST_SAMPLES as f32),
+            gyro_sum.map(|sum| sum as f32 / SELF_TEST_SAMPLES as f32),
+        ))
+    }
+}
+
+impl SelfTestAxis {
+    /// The value used before the axis is tested
+    const EMPTY: SelfTestAxis = SelfTestAxis {
+        response: 0f32,
+        factory_trim: None,
+        deviation: None,
+        passed: false,
+    };
+
+    /// Compares the `response` with the `factory_trim`.
+    ///
+    /// `ratio_passes` checks the response relative to the factory trim,
+    /// `response_passes` checks the absolute response when the sensor
+    /// does not store the factory trim.
+    fn new(
+        response: f32,
+        factory_trim: Option<f32>,
+        ratio_passes: impl Fn(f32) -> bool,
+        response_passes: impl Fn(f32) -> bool,
+    ) -> SelfTestAxis {
+        // The response might be negative, depending on the
+        // orientation of the axis
+        let magnitude = if response < 0f32 { -response } else { response };
+        match factory_trim {
+            Some(trim) => SelfTestAxis {
+                response,
+                factory_trim,
+                deviation: Some((magnitude / trim - 1f32) * 100f32),
+                passed: ratio_passes(magnitude / trim),
+            },
+            None => SelfTestAxis {
+                response,
+                factory_trim,
+                deviation: None,
+                passed: response_passes(magnitude),
+            },
+        }
+    }
+}
+
+/// Returns the factory trim, in LSB, stored as `code` in a
+/// `SELF_TEST` register: `2620 * 1.01 ^ (code - 1)`.
+///
+/// Code 0 means that the sensor does not store the factory trim.
+fn factory_trim(code: u8) -> Option<f32> {
+    if code == 0 {
+        return None;
+    }
+    Some(FACTORY_TRIM_BASE * libm::powf(1.01, code as f32 - 1.0))
+}
+
+/// Self-test API
+///
+/// The functions have the same meaning as the functions
+/// of the [`Mpu6500`] driver.
+impl<I: BlockingInterface> BlockingMpu6500<I> {
+    /// Runs the factory self-test
+    pub fn self_test(
+        &mut self,
+        delay: &mut impl embedded_hal::delay::DelayNs,
+    ) -> Result<SelfTestReport, Error<I::Error>> {
+        embassy_futures::block_on(self.driver.self_test(&mut BlockingDelay(delay)))
+    }
+}
@@ -0,0 +1,720 @@
+//! A simulated MPU 6500 sensor for the host tests.
+//!
+//! The [`Sensor`] stores the register file and answers SPI and I2C
+//! transfers the way the MPU 6500 does:
+//! - the first byte of an SPI transaction is the register address,
+//!   bit 7 ([`SPI_READ`](crate::mpu6500::interface::SPI_READ)) selects
+//!   a read or a write;
+//! - bursts auto increment the register address, except for `FIFO_R_W`;
+//! - the read only registers ignore writes, `INT_STATUS` and
+//!   `I2C_MST_STATUS` are cleared when read;
+//! - `DEVICE_RESET` restores the power on values and `FIFO_RST`
+//!   empties the FIFO;
+//! - [`Sensor::sample`] takes a sample, like the sensor does at every
+//!   sample period, it loads the next scripted [`Sample`] into the data
+//!   registers, writes the FIFO and sets `RAW_DATA_RDY_INT`;
+//! - an AK8963 magnetometer can be connected to the auxiliary I2C bus.
+//!
+//! The sensor is shared by the fake buses ([`Bus`] and [`Cs`], [`Device`],
+//! [`I2cBus`]) and the fake [`Int`] pin, the tests keep a [`Shared`]
+//! reference to inspect and change it while a driver uses it.
+//!
+//! A [`Fault`] makes transfers fail, like a broken wire or a missing sensor.
+
+extern crate std;
+
+use core::{cell::RefCell, convert::Infallible};
+use std::{collections::VecDeque, rc::Rc, vec::Vec};
+
+use embedded_hal::{
+    i2c,
+    spi::{ErrorKind, ErrorType, Operation},
+};
+
+use crate::mpu6500::Address;
+
+/// The `RAW_DATA_RDY_INT` bit of `INT_STATUS`
+pub(crate) const RAW_DATA_RDY_INT: u8 = 1;
+
+/// The `WOM_INT` bit of `INT_STATUS`
+pub(crate) const WOM_INT: u8 = 1 << 6;
+
+/// A register access seen by the fake sensor
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Access {
+    Read(u8, Vec<u8>),
+    Write(u8, Vec<u8>),
+}
+
+/// The raw values of a sample, in the order of the data registers
+#[derive(Copy, Clone, Default, Debug, PartialEq)]
+pub(crate) struct Sample {
+    pub(crate) accel: [i16; 3],
+    pub(crate) temperature: i16,
+    pub(crate) gyro: [i16; 3],
+}
+
+/// The faults that can be injected into the transfers
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub(crate) enum Fault {
+    /// Every transfer fails
+    Always,
+    /// The transactions after the first `n` fail
+    After(usize),
+    /// The transactions that start with the register fail
+    Register(u8),
+    /// The sensor does not answer, the transfers work, but the
+    /// sensor ignores writes and reads return `0xff`
+    Disconnected,
+}
+
+/// The simulated MPU 6500 sensor
+pub(crate) struct Sensor {
+    /// The register file
+    pub(crate) registers: [u8; 128],
+    /// The register accessed by the current transaction and the
+    /// data transferred so far
+    pub(crate) current: Option<Access>,
+    /// The accesses of all the finished transactions, including
+    /// the failed ones
+    pub(crate) log: Vec<Access>,
+    /// The fault injected into the transfers
+    pub(crate) fault: Option<Fault>,
+    /// The number of transactions started so far
+    pub(crate) transactions: usize,
+    /// Whether the current (or the last) transaction fails
+    pub(crate) failing: bool,
+    /// A register that ignores writes
+    pub(crate) read_only: Option<u8>,
+    /// The FIFO
+    pub(crate) fifo: VecDeque<u8>,
+    /// The samples loaded into the data registers by [`Sensor::sample`]
+    pub(crate) script: VecDeque<Sample>,
+    /// Takes a sample every time a burst read starts with a data
+    /// register, so that consecutive reads return the scripted samples
+    pub(crate) sample_on_read: bool,
+    /// The values added to ACCEL_XOUT ... ACCEL_ZOUT and GYRO_XOUT ...
+    /// GYRO_ZOUT when the self-test bits are set
+    pub(crate) self_test_response: [i16; 6],
+    /// The registers of the AK8963 connected to the auxiliary
+    /// I2C bus, if one is connected
+    pub(crate) ak8963: Option<[u8; 32]>,
+    /// The AK8963 register writes performed by `I2C_SLV4`
+    pub(crate) aux_log: Vec<(u8, u8)>,
+}
+
+impl Sensor {
+    pub(crate) fn new() -> Sensor {
+        Sensor {
+            registers: Sensor::power_on_registers(),
+            current: None,
+            log: Vec::new(),
+            fault: None,
+            transactions: 0,
+            failing: false,
+            read_only: None,
+            fifo: VecDeque::new(),
+            script: VecDeque::new(),
+            sample_on_read: false,
+            self_test_response: [0; 6],
+            ak8963: None,
+            aux_log: Vec::new(),
+        }
+    }
+
+    /// Returns a new sensor shared by the fake buses
+    pub(crate) fn shared() -> Shared {
+        Rc::new(RefCell::new(Sensor::new()))
+    }
+
+    /// The values of the registers after power on or reset
+    fn power_on_registers() -> [u8; 128] {
+        let mut registers = [0u8; 128];
+        // PWR_MGMT_1
+        registers[0x6b] = 0x01;
+        // WHO_AM_I
+        registers[0x75] = 0x70;
+        registers
+    }
+
+    /// Starts a transaction (CS LOW)
+    pub(crate) fn select(&mut self) {
+        self.current = None;
+        self.transactions += 1;
+        self.failing = match self.fault {
+            Some(Fault::Always) => true,
+            Some(Fault::After(count)) => self.transactions > count,
+            _ => false,
+        };
+    }
+
+    /// Ends a transaction (CS HIGH)
+    pub(crate) fn deselect(&mut self) {
+        if let Some(access) = self.current.take() {
+            self.log.push(access);
+        }
+    }
+
+    /// Whether the sensor answers the current transaction
+    fn answers(&self) -> bool {
+        !self.failing && self.fault != Some(Fault::Disconnected)
+    }
+
+    /// Exchanges one byte with the sensor
+    pub(crate) fn exchange(&mut self, byte: u8) -> u8 {
+        match &mut self.current {
+            None => {
+                let register = byte & 0x7f;
+                let read = byte & 0x80 != 0;
+                if self.fault == Some(Fault::Register(register)) {
+                    self.failing = true;
+                }
+                self.current = Some(if read {
+                    Access::Read(register, Vec::new())
+                } else {
+                    Access::Write(register, Vec::new())
+                });
+                // ACCEL_XOUT_H ... GYRO_ZOUT_L
+                if read
+                    && self.sample_on_read
+                    && self.answers()
+                    && (0x3b..=0x48).contains(&register)
+                {
+                    self.sample();
+                }
+                0xff
+            }
+            Some(Access::Read(register, data)) => {
+                let address = Sensor::address(*register, data.len());
+                data.push(0xff);
+                if !self.answers() {
+                    return 0xff;
+                }
+                let value = self.read(address);
+                if let Some(Access::Read(_, data)) = &mut self.current {
+                    *data.last_mut().unwrap() = value;
+                }
+                value
+            }
+            Some(Access::Write(register, data)) => {
+                let address = Sensor::address(*register, data.len());
+                data.push(byte);
+                if self.answers() {
+                    self.write(address, byte);
+                }
+                0xff
+            }
+        }
+    }
+
+    /// The address of the `index` byte of a burst that starts at `register`.
+    ///
+    /// The address auto increments, except for FIFO_R_W.
+    fn address(register: u8, index: usize) -> usize {
+        if register == 0x74 {
+            0x74
+        } else {
+            (register as usize + index) & 0x7f
+        }
+    }
+
+    fn read(&mut self, address: usize) -> u8 {
+        match address {
+            // INT_STATUS is cleared when read
+            0x3a => core::mem::take(&mut self.registers[address]),
+            // FIFO_COUNTH and FIFO_COUNTL
+            0x72 => (self.fifo.len() >> 8) as u8,
+            0x73 => self.fifo.len() as u8,
+            // FIFO_R_W
+            0x74 => self.fifo.pop_front().unwrap_or(0xff),
+            // I2C_MST_STATUS is cleared when read
+            0x36 => core::mem::take(&mut self.registers[address]),
+            // EXT_SENS_DATA_00 ... EXT_SENS_DATA_23
+            0x49..=0x60 => self.external_data(address - 0x49),
+            // ACCEL_XOUT_H ... ACCEL_ZOUT_L and GYRO_XOUT_H ... GYRO_ZOUT_L
+            0x3b..=0x40 | 0x43..=0x48 => self.data_register(address),
+            _ => self.registers[address],
+        }
+    }
+
+    /// Returns a data register, adding the self-test response if the
+    /// self-test bit of the axis is set
+    fn data_register(&self, address: usize) -> u8 {
+        let (config, first) = if address < 0x43 {
+            (self.registers[0x1c], 0x3b)
+        } else {
+            (self.registers[0x1b], 0x43)
+        };
+        let axis = (address - first) / 2;
+        let high = first + axis * 2;
+        let mut value = i16::from_be_bytes([self.registers[high], self.registers[high + 1]]);
+        // XA_ST is bit 7, YA_ST bit 6 and ZA_ST bit 5
+        if config & (0x80 >> axis) != 0 {
+            let sensor = if first == 0x3b { 0 } else { 3 };
+            value = value.wrapping_add(self.self_test_response[sensor + axis]);
+        }
+        value.to_be_bytes()[(address - first) % 2]
+    }
+
+    fn write(&mut self, address: usize, value: u8) {
+        if self.read_only == Some(address as u8) {
+            return;
+        }
+        match address {
+            // I2C_SLV4_DI, I2C_MST_STATUS, INT_STATUS, the data registers,
+            // EXT_SENS_DATA, FIFO_COUNT and WHO_AM_I are read only
+            0x35 | 0x36 | 0x3a..=0x60 | 0x72 | 0x73 | 0x75 => return,
+            // FIFO_R_W writes into the FIFO
+            0x74 => return self.push_fifo(&[value]),
+            _ => {}
+        }
+        self.registers[address] = value;
+        // DEVICE_RESET
+        if address == 0x6b && value & 0x80 != 0 {
+            let who_am_i = self.registers[0x75];
+            self.registers = Sensor::power_on_registers();
+            self.registers[0x75] = who_am_i;
+            // The MPU6000 and the ICM sensors start in sleep mode
+            self.registers[0x6b] = match who_am_i {
+                0x68 | 0xaf => 0x40,
+                0x12 => 0x41,
+                _ => 0x01,
+            };
+        }
+        // FIFO_RST clears itself
+        if address == 0x6a && value & 0x04 != 0 {
+            self.fifo.clear();
+            self.registers[address] &= !0x04;
+        }
+        // I2C_SLV4_EN starts a single transfer
+        if address == 0x34 && value & 0x80 != 0 {
+            self.slv4_transfer();
+        }
+    }
+
+    /// Performs the `I2C_SLV4` transfer on the auxiliary bus
+    fn slv4_transfer(&mut self) {
+        let (slave, register) = (self.registers[0x31], self.registers[0x32] as usize);
+        // I2C_SLV4_EN clears itself
+        self.registers[0x34] &= !0x80;
+        let Some(ak8963) = self.ak8963.as_mut().filter(|_| slave & 0x7f == 0x0c) else {
+            // I2C_SLV4_NACK
+            self.registers[0x36] |= 1 << 4;
+            return;
+        };
+        if slave & 0x80 != 0 {
+            // I2C_SLV4_DI
+            self.registers[0x35] = ak8963[register];
+        } else {
+            ak8963[register] = self.registers[0x33];
+            self.aux_log.push((register as u8, self.registers[0x33]));
+        }
+        // I2C_SLV4_DONE
+        self.registers[0x36] |= 1 << 6;
+    }
+
+    /// Returns the `index` byte read by `I2C_SLV0` from the AK8963
+    fn external_data(&self, index: usize) -> u8 {
+        let (slave, register, ctrl) = (
+            self.registers[0x25],
+            self.registers[0x26] as usize,
+            self.registers[0x27],
+        );
+        match self.ak8963 {
+            Some(ak8963)
+                if slave == 0x80 | 0x0c && ctrl & 0x80 != 0 && index < (ctrl & 0x0f) as usize =>
+            {
+                ak8963[register + index]
+            }
+            _ => 0,
+        }
+    }
+
+    /// Writes a frame into the FIFO, like the sensor does when it
+    /// takes a sample. The FIFO stops when it is full.
+    pub(crate) fn push_fifo(&mut self, frame: &[u8]) {
+        for &byte in frame {
+            if self.fifo.len() == 512 {
+                // FIFO_OFLOW_INT
+                self.registers[0x3a] |= 1 << 4;
+                return;
+            }
+            self.fifo.push_back(byte);
+        }
+    }
+
+    /// Adds samples to the script
+    pub(crate) fn script(&mut self, samples: impl IntoIterator<Item = Sample>) {
+        self.script.extend(samples);
+    }
+
+    /// Takes a sample, like the sensor does at every sample period.
+    ///
+    /// The data registers get the next scripted sample, or keep their
+    /// values if the script is empty. If the FIFO is enabled, the
+    /// values of the sensors selected by `FIFO_EN` are written into
+    /// the FIFO. A sleeping sensor does not take samples.
+    pub(crate) fn sample(&mut self) {
+        // SLEEP
+        if self.registers[0x6b] & 0x40 != 0 {
+            return;
+        }
+        if let Some(sample) = self.script.pop_front() {
+            self.set_sample(sample);
+        }
+        // USER_CTRL FIFO_EN
+        if self.registers[0x6a] & 0x40 != 0 {
+            let fifo_en = self.registers[0x23];
+            let mut frame = Vec::new();
+            // ACCEL
+            if fifo_en & 0x08 != 0 {
+                frame.extend_from_slice(&self.registers[0x3b..0x41]);
+            }
+            // TEMP_OUT
+            if fifo_en & 0x80 != 0 {
+                frame.extend_from_slice(&self.registers[0x41..0x43]);
+            }
+            // GYRO_XOUT, GYRO_YOUT and GYRO_ZOUT
+            for axis in 0..3 {
+                if fifo_en & (0x40 >> axis) != 0 {
+                    frame.extend_from_slice(&self.registers[0x43 + axis * 2..0x45 + axis * 2]);
+                }
+            }
+            self.push_fifo(&frame);
+        }
+        self.registers[0x3a] |= RAW_DATA_RDY_INT;
+    }
+
+    /// Writes the values of `sample` into the data registers
+    pub(crate) fn set_sample(&mut self, sample: Sample) {
+        for (axis, value) in sample.accel.into_iter().enumerate() {
+            self.set_i16(0x3b + axis as u8 * 2, value);
+        }
+        self.set_i16(0x41, sample.temperature);
+        for (axis, value) in sample.gyro.into_iter().enumerate() {
+            self.set_i16(0x43 + axis as u8 * 2, value);
+        }
+    }
+
+    pub(crate) fn transfer(&mut self, read: &mut [u8], write: &[u8]) {
+        for index in 0..read.len().max(write.len()) {
+            let value = self.exchange(write.get(index).copied().unwrap_or(0));
+            if let Some(byte) = read.get_mut(index) {
+                *byte = value;
+            }
+        }
+    }
+
+    fn operation(&mut self, operation: &mut Operation<'_, u8>) {
+        match operation {
+            Operation::Read(read) => self.transfer(read, &[]),
+            Operation::Write(write) => self.transfer(&mut [], write),
+            Operation::Transfer(read, write) => self.transfer(read, write),
+            Operation::TransferInPlace(buf) => {
+                let write = buf.to_vec();
+                self.transfer(buf, &write)
+            }
+            Operation::DelayNs(_) => {}
+        }
+    }
+
+    /// The result of a transfer
+    pub(crate) fn result(&self) -> Result<(), ErrorKind> {
+        if self.failing {
+            Err(ErrorKind::Other)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Sets the value of a 16 bit register pair
+    pub(crate) fn set_i16(&mut self, register: u8, value: i16) {
+        let [high, low] = value.to_be_bytes();
+        self.registers[register as usize] = high;
+        self.registers[register as usize + 1] = low;
+    }
+}
+
+pub(crate) type Shared = Rc<RefCell<Sensor>>;
+
+/// Fake SPI bus connected to the sensor
+pub(crate) struct Bus(pub(crate) Shared);
+
+/// Fake CS pin connected to the sensor
+pub(crate) struct Cs(pub(crate) Shared);
+
+/// Fake SPI device connected to the sensor
+pub(crate) struct Device(pub(crate) Shared);
+
+impl ErrorType for Bus {
+    type Error = ErrorKind;
+}
+
+impl embedded_hal_async::spi::SpiBus for Bus {
+    async fn read(&mut self, words: &mut [u8]) -> Result<(), ErrorKind> {
+        embedded_hal::spi::SpiBus::read(self, words)
+    }
+
+    async fn write(&mut self, words: &[u8]) -> Result<(), ErrorKind> {
+        embedded_hal::spi::SpiBus::write(self, words)
+    }
+
+    async fn transfer(&mut self, read: &mut [u8], write: &[u8]) -> Result<(), ErrorKind> {
+        embedded_hal::spi::SpiBus::transfer(self, read, write)
+    }
+
+    async fn transfer_in_place(&mut self, words: &mut [u8]) -> Result<(), ErrorKind> {
+        embedded_hal::spi::SpiBus::transfer_in_place(self, words)
+    }
+
+    async fn flush(&mut self) -> Result<(), ErrorKind> {
+        Ok(())
+    }
+}
+
+impl embedded_hal::spi::SpiBus for Bus {
+    fn read(&mut self, words: &mut [u8]) -> Result<(), ErrorKind> {
+        self.0.borrow_mut().transfer(words, &[]);
+        self.0.borrow().result()
+    }
+
+    fn write(&mut self, words: &[u8]) -> Result<(), ErrorKind> {
+        self.0.borrow_mut().transfer(&mut [], words);
+        self.0.borrow().result()
+    }
+
+    fn transfer(&mut self, read: &mut [u8], write: &[u8]) -> Result<(), ErrorKind> {
+        self.0.borrow_mut().transfer(read, write);
+        self.0.borrow().result()
+    }
+
+    fn transfer_in_place(&mut self, words: &mut [u8]) -> Result<(), ErrorKind> {
+        let write = words.to_vec();
+        self.0.borrow_mut().transfer(words, &write);
+        self.0.borrow().result()
+    }
+
+    fn flush(&mut self) -> Result<(), ErrorKind> {
+        Ok(())
+    }
+}
+
+impl embedded_hal::digital::ErrorType for Cs {
+    type Error = Infallible;
+}
+
+impl embedded_hal::digital::OutputPin for Cs {
+    fn set_low(&mut self) -> Result<(), Infallible> {
+        self.0.borrow_mut().select();
+        Ok(())
+    }
+
+    fn set_high(&mut self) -> Result<(), Infallible> {
+        self.0.borrow_mut().deselect();
+        Ok(())
+    }
+}
+
+impl ErrorType for Device {
+    type Error = ErrorKind;
+}
+
+impl embedded_hal_async::spi::SpiDevice for Device {
+    async fn transaction(&mut self, operations: &mut [Operation<'_, u8>]) -> Result<(), ErrorKind> {
+        embedded_hal::spi::SpiDevice::transaction(self, operations)
+    }
+}
+
+impl embedded_hal::spi::SpiDevice for Device {
+    fn transaction(&mut self, operations: &mut [Operation<'_, u8>]) -> Result<(), ErrorKind> {
+        let mut sensor = self.0.borrow_mut();
+        sensor.select();
+        for operation in operations {
+            sensor.operation(operation);
+        }
+        sensor.deselect();
+        sensor.result()
+    }
+}
+
+/// Fake I2C bus with the sensor connected at `address`
+pub(crate) struct I2cBus {
+    sensor: Shared,
+    address: u8,
+}
+
+impl I2cBus {
+    pub(crate) fn new(sensor: &Shared, address: Address) -> I2cBus {
+        I2cBus {
+            sensor: sensor.clone(),
+            address: address as u8,
+        }
+    }
+}
+
+impl i2c::ErrorType for I2cBus {
+    type Error = i2c::ErrorKind;
+}
+
+impl embedded_hal_async::i2c::I2c for I2cBus {
+    async fn transaction(
+        &mut self,
+        address: u8,
+        operations: &mut [i2c::Operation<'_>],
+    ) -> Result<(), i2c::ErrorKind> {
+        i2c::I2c::transaction(self, address, operations)
+    }
+}
+
+impl i2c::I2c for I2cBus {
+    fn transaction(
+        &mut self,
+        address: u8,
+        operations: &mut [i2c::Operation<'_>],
+    ) -> Result<(), i2c::ErrorKind> {
+        if address != self.address {
+            return Err(i2c::ErrorKind::NoAcknowledge(
+                i2c::NoAcknowledgeSource::Address,
+            ));
+        }
+        let mut sensor = self.sensor.borrow_mut();
+        sensor.select();
+        for operation in operations {
+            match operation {
+                // The first byte written is the register address, the
+                // sensor does not use a read bit over I2C
+                i2c::Operation::Write(write) => {
+                    for &byte in write.iter() {
+                        let byte = if sensor.current.is_none() {
+                            byte & 0x7f
+                        } else {
+                            byte
+                        };
+                        sensor.exchange(byte);
+                    }
+                }
+                // A read after the register address reads that register
+                i2c::Operation::Read(read) => {
+                    if let Some(Access::Write(register, data)) = &sensor.current
+                        && data.is_empty()
+                    {
+                        let register = *register;
+                        sensor.current = None;
+                        sensor.exchange(0x80 | register);
+                    }
+                    sensor.transfer(read, &[]);
+                }
+            }
+        }
+        sensor.deselect();
+        if sensor.failing {
+            Err(i2c::ErrorKind::Other)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// Fake pin connected to the sensor's INT pin
+///
+/// When the application waits for the pin and no interrupt is
+/// pending, the fake sensor triggers the `event` interrupt, for
+/// instance it takes a new sample.
+///
+/// The pin is active while an enabled `INT_STATUS` bit is set. An
+/// edge that leaves the active level ends the interrupt, like the
+/// pulse of an interrupt that is not latched, and clears the bits.
+pub(crate) struct Int {
+    sensor: Shared,
+    /// The `INT_STATUS` bit set while waiting
+    event: u8,
+    /// The number of events triggered while waiting
+    pub(crate) samples: usize,
+}
+
+impl Int {
+    pub(crate) fn new(sensor: &Shared, event: u8) -> Int {
+        Int {
+            sensor: sensor.clone(),
+            event,
+            samples: 0,
+        }
+    }
+
+    /// Returns the level of the INT pin
+    pub(crate) fn is_high(&self) -> bool {
+        let sensor = self.sensor.borrow();
+        let active = sensor.registers[0x3a] & sensor.registers[0x38] != 0;
+        // ACTL
+        active ^ (sensor.registers[0x37] & 0x80 != 0)
+    }
+
+    fn wait_for(&mut self, high: bool) {
+        if self.is_high() != high {
+            self.sensor.borrow_mut().registers[0x3a] |= self.event;
+            self.samples += 1;
+        }
+        assert_eq!(self.is_high(), high);
+    }
+
+    /// Returns the level of the INT pin while an interrupt is pending
+    fn active_level(&self) -> bool {
+        // ACTL
+        self.sensor.borrow().registers[0x37] & 0x80 == 0
+    }
+
+    /// Ends the pending interrupts, the pin becomes inactive
+    fn end_interrupt(&mut self) {
+        let mut sensor = self.sensor.borrow_mut();
+        sensor.registers[0x3a] &= !sensor.registers[0x38];
+    }
+
+    fn wait_for_edge(&mut self, high: bool) {
+        let active = self.active_level();
+        if high == active {
+            // A pending interrupt ends before the next one
+            if self.is_high() == active {
+                self.end_interrupt();
+            }
+            self.wait_for(high);
+        } else {
+            if self.is_high() != active {
+                self.wait_for(active);
+            }
+            self.end_interrupt();
+            assert_eq!(self.is_high(), high);
+        }
+    }
+}
+
+impl embedded_hal::digital::ErrorType for Int {
+    type Error = Infallible;
+}
+
+impl embedded_hal_async::digital::Wait for Int {
+    async fn wait_for_high(&mut self) -> Result<(), Infallible> {
+        self.wait_for(true);
+        Ok(())
+    }
+
+    async fn wait_for_low(&mut self) -> Result<(), Infallible> {
+        self.wait_for(false);
+        Ok(())
+    }
+
+    async fn wait_for_rising_edge(&mut self) -> Result<(), Infallible> {
+        self.wait_for_edge(true);
+        Ok(())
+    }
+
+    async fn wait_for_falling_edge(&mut self) -> Result<(), Infallible> {
+        self.wait_for_edge(false);
+        Ok(())
+    }
+
+    async fn wait_for_any_edge(&mut self) -> Result<(), Infallible> {
+        let high = self.is_high();
+        self.wait_for_edge(!high);
+        Ok(())
+    }
+}
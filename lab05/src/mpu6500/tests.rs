@@ -0,0 +1,1894 @@
+//! Host tests for the MPU 6500 drivers.
+//!
+//! The tests use the simulated sensor of the [`sim`](crate::mpu6500::sim)
+//! module. The same sensor is used by the SPI Bus, the SPI Device, the I2C
+//! and the blocking drivers.
+
+extern crate std;
+
+use core::cell::RefCell;
+use std::{rc::Rc, vec::Vec};
+
+use embassy_futures::block_on;
+use embedded_hal::{i2c, spi::ErrorKind};
+use embedded_hal_async::digital::Wait;
+
+use crate::mpu6500::{
+    AccelDlpf, AccelScale, Acceleration, Address, AuxSlave, Chip, ClockSource, Config, Error,
+    FifoFrame, FifoRead, FifoSensors, GyroDlpf, GyroScale, I2cMasterClock, InterruptPin,
+    LpAccelOdr, MagCalibration, MagCalibrator, MagMode, MagneticField, Offsets, RawMeasurement,
+    SELF_TEST_SAMPLES, SelfTestAxis, Standby, WakeOnMotion, bus, device, device_blocking, heading,
+    i2c_blocking,
+    sim::{
+        Access, Bus, Cs, Device, Fault, I2cBus, Int, RAW_DATA_RDY_INT, Sample, Sensor, Shared,
+        WOM_INT,
+    },
+    temperature_to_celsius,
+};
+
+/// Readings obtained from a driver, compared between the drivers
+type Readings = (bool, [f32; 3], [f32; 3]);
+
+/// Creates a sensor with known acceleration and gyro values
+fn sensor() -> Shared {
+    let mut sensor = Sensor::new();
+    sensor.set_i16(0x3b, 16384);
+    sensor.set_i16(0x3d, -8192);
+    sensor.set_i16(0x3f, 1000);
+    sensor.set_i16(0x41, 3339);
+    sensor.set_i16(0x43, -32768);
+    sensor.set_i16(0x45, 131);
+    sensor.set_i16(0x47, 32767);
+    Rc::new(RefCell::new(sensor))
+}
+
+fn run_bus(sensor: &Shared) -> Readings {
+    let mut spi = Bus(sensor.clone());
+    let mut mpu6500 = bus::Mpu6500::new(&mut spi, Cs(sensor.clone()));
+    block_on(async {
+        let connected = mpu6500.init().await.is_ok();
+        mpu6500.set_accel_scale(AccelScale::G4).await.unwrap();
+        mpu6500.set_gyro_scale(GyroScale::Gs1000).await.unwrap();
+        let a = mpu6500.read_acceleration().await.unwrap();
+        let g = mpu6500.read_gyro().await.unwrap();
+        (connected, [a.x, a.y, a.z], [g.x, g.y, g.z])
+    })
+}
+
+fn run_device(sensor: &Shared) -> Readings {
+    let mut spi = Device(sensor.clone());
+    let mut mpu6500 = device::Mpu6500::new(&mut spi);
+    block_on(async {
+        let connected = mpu6500.init().await.is_ok();
+        mpu6500.set_accel_scale(AccelScale::G4).await.unwrap();
+        mpu6500.set_gyro_scale(GyroScale::Gs1000).await.unwrap();
+        let a = mpu6500.read_acceleration().await.unwrap();
+        let g = mpu6500.read_gyro().await.unwrap();
+        (connected, [a.x, a.y, a.z], [g.x, g.y, g.z])
+    })
+}
+
+fn run_device_blocking(sensor: &Shared) -> Readings {
+    let mut spi = Device(sensor.clone());
+    let mut mpu6500 = device_blocking::Mpu6500::new(&mut spi);
+    let connected = mpu6500.init().is_ok();
+    mpu6500.set_accel_scale(AccelScale::G4).unwrap();
+    mpu6500.set_gyro_scale(GyroScale::Gs1000).unwrap();
+    let a = mpu6500.read_acceleration().unwrap();
+    let g = mpu6500.read_gyro().unwrap();
+    (connected, [a.x, a.y, a.z], [g.x, g.y, g.z])
+}
+
+fn run_i2c(sensor: &Shared) -> Readings {
+    let mut mpu6500 =
+        crate::mpu6500::i2c::Mpu6500::new(I2cBus::new(sensor, Address::Ad0Low), Address::Ad0Low);
+    block_on(async {
+        let connected = mpu6500.init().await.is_ok();
+        mpu6500.set_accel_scale(AccelScale::G4).await.unwrap();
+        mpu6500.set_gyro_scale(GyroScale::Gs1000).await.unwrap();
+        let a = mpu6500.read_acceleration().await.unwrap();
+        let g = mpu6500.read_gyro().await.unwrap();
+        (connected, [a.x, a.y, a.z], [g.x, g.y, g.z])
+    })
+}
+
+fn run_i2c_blocking(sensor: &Shared) -> Readings {
+    let mut i2c = I2cBus::new(sensor, Address::Ad0High);
+    // A `&mut` reference to the bus is also an I2C bus
+    let mut mpu6500 = i2c_blocking::Mpu6500::new(&mut i2c, Address::Ad0High);
+    let connected = mpu6500.init().is_ok();
+    mpu6500.set_accel_scale(AccelScale::G4).unwrap();
+    mpu6500.set_gyro_scale(GyroScale::Gs1000).unwrap();
+    let a = mpu6500.read_acceleration().unwrap();
+    let g = mpu6500.read_gyro().unwrap();
+    (connected, [a.x, a.y, a.z], [g.x, g.y, g.z])
+}
+
+#[test]
+fn drivers_generate_the_same_register_traffic() {
+    let bus_sensor = sensor();
+    let device_sensor = sensor();
+    let device_blocking_sensor = sensor();
+    let i2c_sensor = sensor();
+    let i2c_blocking_sensor = sensor();
+
+    run_bus(&bus_sensor);
+    run_device(&device_sensor);
+    run_device_blocking(&device_blocking_sensor);
+    run_i2c(&i2c_sensor);
+    run_i2c_blocking(&i2c_blocking_sensor);
+
+    let expected = std::vec![
+        Access::Read(0x75, std::vec![0x70]),
+        Access::Read(0x19, std::vec![0x00; 5]),
+        Access::Read(0x6b, std::vec![0x01, 0x00]),
+        // Only the scale bits change
+        Access::Read(0x1c, std::vec![0x00]),
+        Access::Write(0x1c, std::vec![0b01 << 3]),
+        Access::Read(0x1c, std::vec![0b01 << 3]),
+        Access::Read(0x1b, std::vec![0x00]),
+        Access::Write(0x1b, std::vec![0b10 << 3]),
+        Access::Read(0x1b, std::vec![0b10 << 3]),
+        Access::Read(0x3b, std::vec![0x40, 0x00, 0xe0, 0x00, 0x03, 0xe8]),
+        Access::Read(0x43, std::vec![0x80, 0x00, 0x00, 0x83, 0x7f, 0xff]),
+    ];
+    assert_eq!(bus_sensor.borrow().log, expected);
+    assert_eq!(device_sensor.borrow().log, expected);
+    assert_eq!(device_blocking_sensor.borrow().log, expected);
+    assert_eq!(i2c_sensor.borrow().log, expected);
+    assert_eq!(i2c_blocking_sensor.borrow().log, expected);
+}
+
+#[test]
+fn drivers_return_the_same_values() {
+    let bus = run_bus(&sensor());
+    let device = run_device(&sensor());
+    let device_blocking = run_device_blocking(&sensor());
+    let i2c = run_i2c(&sensor());
+    let i2c_blocking = run_i2c_blocking(&sensor());
+
+    assert!(bus.0);
+    assert_eq!(bus, device);
+    assert_eq!(bus, device_blocking);
+    assert_eq!(bus, i2c);
+    assert_eq!(bus, i2c_blocking);
+}
+
+#[test]
+fn i2c_uses_the_ad0_address() {
+    let sensor = sensor();
+    let mut mpu6500 =
+        i2c_blocking::Mpu6500::new(I2cBus::new(&sensor, Address::Ad0Low), Address::Ad0High);
+    assert!(matches!(
+        mpu6500.init(),
+        Err(Error::Bus(i2c::ErrorKind::NoAcknowledge(_)))
+    ));
+    assert!(sensor.borrow().log.is_empty());
+}
+
+#[test]
+fn set_scale_is_used_by_the_following_reads() {
+    let sensor = sensor();
+    let mut spi = Device(sensor.clone());
+    let mut mpu6500 = device::Mpu6500::new(&mut spi);
+    block_on(async {
+        mpu6500.init().await.unwrap();
+        let before = mpu6500.read_gyro().await.unwrap();
+        mpu6500.set_gyro_scale(GyroScale::Gs2000).await.unwrap();
+        let after = mpu6500.read_gyro().await.unwrap();
+        assert_eq!(mpu6500.gyro_scale(), GyroScale::Gs2000);
+        assert_eq!(before.z, 32767.0 / 131.0);
+        assert_eq!(after.z, 32767.0 / 16.4);
+    });
+}
+
+#[test]
+fn init_reads_the_configured_scales() {
+    let sensor = sensor();
+    sensor.borrow_mut().registers[0x1b] = 0b11 << 3;
+    sensor.borrow_mut().registers[0x1c] = 0b10 << 3;
+    let mut spi = Device(sensor.clone());
+    let mut mpu6500 = device_blocking::Mpu6500::new(&mut spi);
+    mpu6500.init().unwrap();
+    assert_eq!(mpu6500.gyro_scale(), GyroScale::Gs2000);
+    assert_eq!(mpu6500.accel_scale(), AccelScale::G8);
+}
+
+#[test]
+fn init_reports_unexpected_who_am_i() {
+    let sensor = sensor();
+    // 0x68 is the MPU6000, 0x69 is not a supported sensor
+    sensor.borrow_mut().registers[0x75] = 0x69;
+    let mut spi = Device(sensor.clone());
+    let mut mpu6500 = device_blocking::Mpu6500::new(&mut spi);
+    assert!(matches!(mpu6500.init(), Err(Error::UnexpectedWhoAmI(0x69))));
+}
+
+#[test]
+fn init_reports_bus_errors() {
+    let sensor = sensor();
+    sensor.borrow_mut().fault = Some(Fault::Always);
+    let mut spi = Bus(sensor.clone());
+    let mut mpu6500 = bus::Mpu6500::new(&mut spi, Cs(sensor.clone()));
+    assert!(matches!(
+        block_on(mpu6500.init()),
+        Err(Error::Bus(ErrorKind::Other))
+    ));
+}
+
+#[test]
+fn driver_has_to_be_initialised() {
+    let sensor = sensor();
+    let mut spi = Device(sensor.clone());
+    let mut mpu6500 = device_blocking::Mpu6500::new(&mut spi);
+    assert!(matches!(
+        mpu6500.read_acceleration(),
+        Err(Error::NotInitialised)
+    ));
+    assert!(matches!(
+        mpu6500.set_gyro_scale(GyroScale::Gs500),
+        Err(Error::NotInitialised)
+    ));
+    assert!(sensor.borrow().log.is_empty());
+}
+
+#[test]
+fn set_scale_verifies_the_written_value() {
+    let sensor = sensor();
+    sensor.borrow_mut().read_only = Some(0x1c);
+    let mut spi = Device(sensor.clone());
+    let mut mpu6500 = device_blocking::Mpu6500::new(&mut spi);
+    mpu6500.init().unwrap();
+    assert!(matches!(
+        mpu6500.set_accel_scale(AccelScale::G16),
+        Err(Error::InvalidConfiguration)
+    ));
+    assert_eq!(mpu6500.accel_scale(), AccelScale::G2);
+}
+
+#[test]
+fn accel_conversion_matches_the_datasheet() {
+    // Datasheet: ACCEL_FS_SEL sensitivity in LSB / g
+    let scales = [
+        (AccelScale::G2, 16384, 2.0),
+        (AccelScale::G4, 8192, 4.0),
+        (AccelScale::G8, 4096, 8.0),
+        (AccelScale::G16, 2048, 16.0),
+    ];
+    for (scale, lsb_per_g, full_scale) in scales {
+        assert_eq!(scale.sensitivity(), lsb_per_g as f32);
+        assert_eq!(scale.full_scale(), full_scale);
+        assert_eq!(scale.to_g(lsb_per_g), 1.0);
+        assert_eq!(scale.to_g(-lsb_per_g), -1.0);
+        assert_eq!(scale.to_g(i16::MIN), -full_scale);
+        assert!((scale.to_g(i16::MAX) - full_scale).abs() < 0.001);
+    }
+}
+
+#[test]
+fn gyro_conversion_matches_the_datasheet() {
+    // Datasheet: GYRO_FS_SEL sensitivity in LSB / (deg/s)
+    let scales = [
+        (GyroScale::Gs250, 131.0, 250.0),
+        (GyroScale::Gs500, 65.5, 500.0),
+        (GyroScale::Gs1000, 32.8, 1000.0),
+        (GyroScale::Gs2000, 16.4, 2000.0),
+    ];
+    for (scale, lsb_per_deg_s, full_scale) in scales {
+        assert_eq!(scale.sensitivity(), lsb_per_deg_s);
+        assert_eq!(scale.full_scale(), full_scale);
+        // 100 deg/s
+        let raw = (lsb_per_deg_s * 100.0) as i16;
+        assert!((scale.to_deg_s(raw) - 100.0).abs() < 0.01);
+        // The full scale is reached close to the maximum raw value
+        assert!((scale.to_deg_s(i16::MAX) - full_scale).abs() / full_scale < 0.005);
+    }
+}
+
+#[test]
+fn acceleration_is_converted_to_m_s2() {
+    let acceleration = crate::mpu6500::Acceleration {
+        x: 1.0,
+        y: -0.5,
+        z: 0.0,
+    };
+    assert_eq!(acceleration.to_m_s2(), [9.80665, -4.903325, 0.0]);
+}
+
+#[test]
+fn read_raw_returns_one_burst() {
+    let sensor = sensor();
+    let mut spi = Device(sensor.clone());
+    let mut mpu6500 = device_blocking::Mpu6500::new(&mut spi);
+    mpu6500.init().unwrap();
+    sensor.borrow_mut().log.clear();
+
+    let raw = mpu6500.read_raw().unwrap();
+    assert_eq!(
+        raw,
+        RawMeasurement {
+            accel: [16384, -8192, 1000],
+            temperature: 3339,
+            gyro: [-32768, 131, 32767],
+        }
+    );
+    let log = &sensor.borrow().log;
+    assert_eq!(log.len(), 1);
+    assert!(matches!(&log[0], Access::Read(0x3b, data) if data.len() == 14));
+}
+
+#[test]
+fn temperature_conversion_matches_the_datasheet() {
+    assert_eq!(temperature_to_celsius(0), 21.0);
+    assert!((temperature_to_celsius(3339) - 31.0).abs() < 0.01);
+    assert!((temperature_to_celsius(-3339) - 11.0).abs() < 0.01);
+}
+
+#[test]
+fn read_all_converts_one_burst() {
+    let sensor = sensor();
+    let mut spi = Device(sensor.clone());
+    let mut mpu6500 = device::Mpu6500::new(&mut spi);
+    let measurement = block_on(async {
+        mpu6500.init().await.unwrap();
+        mpu6500.set_accel_scale(AccelScale::G4).await.unwrap();
+        sensor.borrow_mut().log.clear();
+        mpu6500.read_all().await.unwrap()
+    });
+
+    assert_eq!(measurement.accel.x, 2.0);
+    assert_eq!(measurement.accel.y, -1.0);
+    assert!((measurement.temperature - 31.0).abs() < 0.01);
+    assert_eq!(measurement.gyro.y, 1.0);
+    let log = &sensor.borrow().log;
+    assert_eq!(log.len(), 1);
+    assert!(matches!(&log[0], Access::Read(0x3b, data) if data.len() == 14));
+}
+
+/// Fake delay that stores the total time waited
+struct Delay(u64);
+
+impl embedded_hal::delay::DelayNs for Delay {
+    fn delay_ns(&mut self, ns: u32) {
+        self.0 += ns as u64;
+    }
+}
+
+#[test]
+fn configure_writes_and_verifies_all_registers() {
+    let sensor = sensor();
+    let mut spi = Device(sensor.clone());
+    let mut mpu6500 = device_blocking::Mpu6500::new(&mut spi);
+    mpu6500.init().unwrap();
+    assert_eq!(mpu6500.config(), Config::default());
+
+    let config = Config::default()
+        .gyro_scale(GyroScale::Gs500)
+        .accel_scale(AccelScale::G8)
+        .gyro_dlpf(GyroDlpf::Hz41)
+        .accel_dlpf(AccelDlpf::Hz1046)
+        .sample_rate_divider(9)
+        .clock_source(ClockSource::Internal)
+        .cycle(true)
+        .standby(Standby {
+            gyro_x: true,
+            gyro_y: true,
+            gyro_z: true,
+            ..Standby::default()
+        });
+    mpu6500.configure(config).unwrap();
+
+    let registers = sensor.borrow().registers;
+    assert_eq!(
+        registers[0x19..=0x1d],
+        [9, 0x40 | 3, 0b01 << 3, 0b10 << 3, 0b1000]
+    );
+    assert_eq!(registers[0x6b..=0x6c], [0b0010_0000, 0b0000_0111]);
+    assert_eq!(mpu6500.config(), config);
+    assert_eq!(mpu6500.read_config().unwrap(), config);
+    assert_eq!(mpu6500.gyro_scale(), GyroScale::Gs500);
+    assert_eq!(mpu6500.accel_scale(), AccelScale::G8);
+}
+
+#[test]
+fn configure_reports_values_that_were_not_stored() {
+    let sensor = sensor();
+    sensor.borrow_mut().read_only = Some(0x1a);
+    let mut spi = Device(sensor.clone());
+    let mut mpu6500 = device_blocking::Mpu6500::new(&mut spi);
+    mpu6500.init().unwrap();
+
+    let config = Config::default()
+        .accel_scale(AccelScale::G4)
+        .gyro_dlpf(GyroDlpf::Hz5);
+    assert!(matches!(
+        mpu6500.configure(config),
+        Err(Error::InvalidConfiguration)
+    ));
+    // The driver uses the configuration stored by the sensor
+    assert_eq!(mpu6500.config().gyro_dlpf, GyroDlpf::Hz250);
+    assert_eq!(mpu6500.accel_scale(), AccelScale::G4);
+}
+
+#[test]
+fn sample_rate_depends_on_the_filter_and_divider() {
+    assert_eq!(Config::default().sample_rate(), 8000.0);
+    // The 8 kHz filters ignore the divider
+    assert_eq!(
+        Config::default().sample_rate_divider(9).sample_rate(),
+        8000.0
+    );
+    assert_eq!(
+        Config::default()
+            .gyro_dlpf(GyroDlpf::Hz3600)
+            .sample_rate_divider(9)
+            .sample_rate(),
+        8000.0
+    );
+    let config = Config::default().gyro_dlpf(GyroDlpf::Hz41);
+    assert_eq!(config.sample_rate(), 1000.0);
+    assert_eq!(config.sample_rate_divider(9).sample_rate(), 100.0);
+    assert_eq!(
+        config.sample_rate_divider(255).sample_rate(),
+        1000.0 / 256.0
+    );
+}
+
+#[test]
+fn mpu6000_always_uses_the_sample_rate_divider() {
+    let config = Config::default().sample_rate_divider(9);
+    assert_eq!(config.chip_sample_rate(Chip::Mpu6000), 800.0);
+    assert_eq!(config.chip_sample_rate(Chip::Icm20602), 8000.0);
+
+    let sensor = chip_sensor(0x68, 0x40);
+    let mut spi = Device(sensor.clone());
+    let mut mpu6500 = device_blocking::Mpu6500::new(&mut spi);
+    mpu6500.init().unwrap();
+    mpu6500.configure(config).unwrap();
+    assert_eq!(mpu6500.sample_rate(), 800.0);
+}
+
+#[test]
+fn reset_follows_the_datasheet_sequence() {
+    let sensor = sensor();
+    let mut spi = Device(sensor.clone());
+    let mut mpu6500 = device_blocking::Mpu6500::new(&mut spi);
+    mpu6500.init().unwrap();
+    mpu6500
+        .configure(Config::default().gyro_scale(GyroScale::Gs2000))
+        .unwrap();
+    sensor.borrow_mut().log.clear();
+
+    let mut delay = Delay(0);
+    mpu6500.reset(&mut delay).unwrap();
+
+    assert_eq!(delay.0, 200_000_000);
+    let log = &sensor.borrow().log;
+    assert_eq!(log[0], Access::Write(0x6b, std::vec![0x80]));
+    assert_eq!(log[1], Access::Write(0x68, std::vec![0b111]));
+    assert_eq!(mpu6500.gyro_scale(), GyroScale::Gs250);
+}
+
+#[test]
+fn setting_a_scale_keeps_the_other_bits() {
+    let sensor = sensor();
+    let mut spi = Device(sensor.clone());
+    let mut mpu6500 = device_blocking::Mpu6500::new(&mut spi);
+    mpu6500.init().unwrap();
+    // The self-test bits and FCHOICE_B
+    sensor.borrow_mut().registers[0x1b] = 0b1110_0011;
+    sensor.borrow_mut().registers[0x1c] = 0b1110_0000;
+
+    mpu6500.set_gyro_scale(GyroScale::Gs1000).unwrap();
+    mpu6500.set_accel_scale(AccelScale::G4).unwrap();
+    assert_eq!(sensor.borrow().registers[0x1b], 0b1111_0011);
+    assert_eq!(sensor.borrow().registers[0x1c], 0b1110_1000);
+    assert_eq!(mpu6500.gyro_scale(), GyroScale::Gs1000);
+    assert_eq!(mpu6500.accel_scale(), AccelScale::G4);
+}
+
+#[test]
+fn reset_runs_the_chip_init_sequence_again() {
+    // The MPU6000 and ICM20608 wake up and disable I2C, the ICM20602
+    // disables I2C in I2C_IF
+    for (who_am_i, user_ctrl, init) in [
+        (0x68, 0x40, [(0x6a, 0x10), (0x6b, 0x01)]),
+        (0xaf, 0x40, [(0x6a, 0x10), (0x6b, 0x01)]),
+        (0x12, 0x41, [(0x70, 0x40), (0x6b, 0x01)]),
+    ] {
+        let sensor = chip_sensor(who_am_i, user_ctrl);
+        let mut spi = Device(sensor.clone());
+        let mut mpu6500 = device_blocking::Mpu6500::new(&mut spi);
+        mpu6500.init().unwrap();
+        sensor.borrow_mut().log.clear();
+
+        mpu6500.reset(&mut Delay(0)).unwrap();
+        assert_eq!(writes(&sensor)[2..], init);
+        assert!(!mpu6500.config().sleep);
+    }
+}
+
+/// Returns a FIFO frame with the accel and gyro values
+fn fifo_frame(index: i16) -> [u8; 12] {
+    let mut frame = [0u8; 12];
+    for (axis, value) in [index, 0, 16384, 131 * index, 0, 0].iter().enumerate() {
+        frame[axis * 2..axis * 2 + 2].copy_from_slice(&value.to_be_bytes());
+    }
+    frame
+}
+
+#[test]
+fn fifo_frames_are_decoded() {
+    let sensor = sensor();
+    let mut spi = Device(sensor.clone());
+    let mut mpu6500 = device_blocking::Mpu6500::new(&mut spi);
+    mpu6500.init().unwrap();
+    let sensors = FifoSensors {
+        accel: true,
+        temperature: false,
+        gyro: true,
+    };
+    mpu6500.enable_fifo(sensors).unwrap();
+    assert_eq!(sensor.borrow().registers[0x23], 0b0111_1000);
+    assert_eq!(sensor.borrow().registers[0x6a] & 0x40, 0x40);
+
+    for index in 0..3 {
+        sensor.borrow_mut().push_fifo(&fifo_frame(index));
+    }
+    sensor.borrow_mut().log.clear();
+    assert_eq!(mpu6500.fifo_count().unwrap(), 36);
+
+    let mut frames = [FifoFrame::default(); 8];
+    let read = mpu6500.read_fifo(&mut frames).unwrap();
+    assert_eq!(
+        read,
+        FifoRead {
+            frames: 3,
+            overflow: false
+        }
+    );
+    for (index, frame) in frames[..3].iter().enumerate() {
+        assert_eq!(frame.accel.unwrap().x, index as f32 / 16384.0);
+        assert_eq!(frame.accel.unwrap().z, 1.0);
+        assert!(frame.temperature.is_none());
+        assert_eq!(frame.gyro.unwrap().x, index as f32);
+    }
+    // The frames are read in a single burst
+    assert!(sensor.borrow().log.contains(&Access::Read(
+        0x74,
+        [fifo_frame(0), fifo_frame(1), fifo_frame(2)].concat()
+    )));
+    assert_eq!(mpu6500.fifo_count().unwrap(), 0);
+}
+
+#[test]
+fn fifo_partial_frames_stay_in_the_fifo() {
+    let sensor = sensor();
+    let mut spi = Device(sensor.clone());
+    let mut mpu6500 = device_blocking::Mpu6500::new(&mut spi);
+    mpu6500.init().unwrap();
+    mpu6500
+        .enable_fifo(FifoSensors {
+            accel: true,
+            temperature: false,
+            gyro: true,
+        })
+        .unwrap();
+
+    sensor.borrow_mut().push_fifo(&fifo_frame(1));
+    sensor.borrow_mut().push_fifo(&fifo_frame(2)[..5]);
+    let mut frames = [FifoFrame::default(); 4];
+    assert_eq!(mpu6500.read_fifo(&mut frames).unwrap().frames, 1);
+    assert_eq!(mpu6500.fifo_count().unwrap(), 5);
+
+    // The rest of the frame arrives
+    sensor.borrow_mut().push_fifo(&fifo_frame(2)[5..]);
+    assert_eq!(mpu6500.read_fifo(&mut frames).unwrap().frames, 1);
+    assert_eq!(frames[0].gyro.unwrap().x, 2.0);
+
+    // The buffer limits the number of frames read
+    for index in 0..3 {
+        sensor.borrow_mut().push_fifo(&fifo_frame(index));
+    }
+    assert_eq!(mpu6500.read_fifo(&mut frames[..2]).unwrap().frames, 2);
+    assert_eq!(mpu6500.fifo_count().unwrap(), 12);
+}
+
+#[test]
+fn fifo_overflow_is_reported_and_recovered() {
+    let sensor = sensor();
+    let mut spi = Device(sensor.clone());
+    let mut mpu6500 = device_blocking::Mpu6500::new(&mut spi);
+    mpu6500.init().unwrap();
+    mpu6500
+        .enable_fifo(FifoSensors {
+            accel: true,
+            temperature: true,
+            gyro: true,
+        })
+        .unwrap();
+
+    // 512 / 14 = 36 complete frames, the 37th frame is incomplete
+    for _ in 0..40 {
+        sensor.borrow_mut().push_fifo(&[0u8; 14]);
+    }
+    let mut frames = [FifoFrame::default(); 64];
+    let read = mpu6500.read_fifo(&mut frames).unwrap();
+    assert_eq!(
+        read,
+        FifoRead {
+            frames: 36,
+            overflow: true
+        }
+    );
+    assert!(frames[0].temperature.is_some());
+    // The FIFO was reset, the incomplete frame is dropped
+    assert_eq!(mpu6500.fifo_count().unwrap(), 0);
+
+    sensor.borrow_mut().push_fifo(&[0u8; 14]);
+    let read = mpu6500.read_fifo(&mut frames).unwrap();
+    assert_eq!(
+        read,
+        FifoRead {
+            frames: 1,
+            overflow: false
+        }
+    );
+}
+
+#[test]
+fn fifo_has_to_be_enabled() {
+    let sensor = sensor();
+    let mut spi = Device(sensor.clone());
+    let mut mpu6500 = device_blocking::Mpu6500::new(&mut spi);
+    mpu6500.init().unwrap();
+    let mut frames = [FifoFrame::default(); 4];
+    assert!(matches!(
+        mpu6500.read_fifo(&mut frames),
+        Err(Error::InvalidConfiguration)
+    ));
+    mpu6500.enable_fifo(FifoSensors::default()).unwrap();
+    assert!(matches!(
+        mpu6500.read_fifo(&mut frames),
+        Err(Error::InvalidConfiguration)
+    ));
+}
+
+#[test]
+fn data_ready_configures_the_int_pin() {
+    let sensor = sensor();
+    let mut spi = Device(sensor.clone());
+    let mut mpu6500 = device_blocking::Mpu6500::new(&mut spi);
+    mpu6500.init().unwrap();
+
+    mpu6500.enable_data_ready(InterruptPin::default()).unwrap();
+    // LATCH_INT_EN and RAW_RDY_EN
+    assert_eq!(sensor.borrow().registers[0x37], 0x20);
+    assert_eq!(sensor.borrow().registers[0x38], 0x01);
+
+    mpu6500
+        .enable_data_ready(InterruptPin {
+            active_low: true,
+            open_drain: true,
+        })
+        .unwrap();
+    assert_eq!(sensor.borrow().registers[0x37], 0xe0);
+
+    mpu6500.disable_data_ready().unwrap();
+    assert_eq!(sensor.borrow().registers[0x38], 0x00);
+}
+
+#[test]
+fn wait_for_data_clears_the_interrupt() {
+    let sensor = sensor();
+    let mut spi = Device(sensor.clone());
+    let mut mpu6500 = device::Mpu6500::new(&mut spi);
+    let mut int = Int::new(&sensor, RAW_DATA_RDY_INT);
+    block_on(async {
+        mpu6500.init().await.unwrap();
+        mpu6500
+            .enable_data_ready(InterruptPin::default())
+            .await
+            .unwrap();
+
+        // The sensor takes a sample
+        mpu6500.wait_for_data(&mut int).await.unwrap();
+        assert_eq!(int.samples, 1);
+        assert_eq!(sensor.borrow().registers[0x3a], 0);
+
+        // A sample that was taken before waiting is not missed
+        sensor.borrow_mut().registers[0x3a] |= 1;
+        mpu6500.wait_for_data(&mut int).await.unwrap();
+        assert_eq!(int.samples, 1);
+
+        mpu6500.wait_for_data(&mut int).await.unwrap();
+        assert_eq!(int.samples, 2);
+    });
+}
+
+#[test]
+fn wait_for_data_uses_the_pin_polarity() {
+    let sensor = sensor();
+    let mut spi = Device(sensor.clone());
+    let mut mpu6500 = device_blocking::Mpu6500::new(&mut spi);
+    let mut int = Int::new(&sensor, RAW_DATA_RDY_INT);
+    mpu6500.init().unwrap();
+    mpu6500
+        .enable_data_ready(InterruptPin {
+            active_low: true,
+            open_drain: false,
+        })
+        .unwrap();
+    block_on(mpu6500.wait_for_data(&mut int)).unwrap();
+    assert_eq!(int.samples, 1);
+    assert!(int.is_high());
+}
+
+#[test]
+fn wait_for_data_requires_the_interrupt() {
+    let sensor = sensor();
+    let mut spi = Device(sensor.clone());
+    let mut mpu6500 = device_blocking::Mpu6500::new(&mut spi);
+    let mut int = Int::new(&sensor, RAW_DATA_RDY_INT);
+    mpu6500.init().unwrap();
+    assert!(matches!(
+        block_on(mpu6500.wait_for_data(&mut int)),
+        Err(Error::InvalidConfiguration)
+    ));
+}
+
+#[test]
+fn the_simulated_int_pin_has_edges() {
+    let sensor = sensor();
+    let mut spi = Device(sensor.clone());
+    let mut mpu6500 = device_blocking::Mpu6500::new(&mut spi);
+    let mut int = Int::new(&sensor, RAW_DATA_RDY_INT);
+    mpu6500.init().unwrap();
+    mpu6500.enable_data_ready(InterruptPin::default()).unwrap();
+    block_on(async {
+        // Every rising edge is a new sample
+        int.wait_for_rising_edge().await.unwrap();
+        assert!(int.is_high());
+        int.wait_for_rising_edge().await.unwrap();
+        assert_eq!(int.samples, 2);
+
+        // The falling edge ends the interrupt
+        int.wait_for_falling_edge().await.unwrap();
+        assert!(!int.is_high());
+        assert_eq!(sensor.borrow().registers[0x3a], 0);
+        int.wait_for_any_edge().await.unwrap();
+        assert!(int.is_high());
+        assert_eq!(int.samples, 3);
+    });
+
+    // An active low pin falls when a sample is taken
+    mpu6500
+        .enable_data_ready(InterruptPin {
+            active_low: true,
+            open_drain: false,
+        })
+        .unwrap();
+    block_on(int.wait_for_falling_edge()).unwrap();
+    assert!(!int.is_high());
+    assert_eq!(int.samples, 4);
+}
+
+#[test]
+fn data_ready_can_be_polled() {
+    let sensor = sensor();
+    let mut spi = Device(sensor.clone());
+    let mut mpu6500 = device_blocking::Mpu6500::new(&mut spi);
+    mpu6500.init().unwrap();
+    assert!(!mpu6500.data_ready().unwrap());
+    sensor.borrow_mut().registers[0x3a] |= 1;
+    assert!(mpu6500.data_ready().unwrap());
+    assert!(!mpu6500.data_ready().unwrap());
+}
+
+#[test]
+fn waiting_for_data_keeps_the_fifo_overflow() {
+    let sensor = sensor();
+    let mut spi = Device(sensor.clone());
+    let mut mpu6500 = device_blocking::Mpu6500::new(&mut spi);
+    let mut int = Int::new(&sensor, RAW_DATA_RDY_INT);
+    mpu6500.init().unwrap();
+    mpu6500
+        .enable_fifo(FifoSensors {
+            accel: true,
+            temperature: false,
+            gyro: false,
+        })
+        .unwrap();
+    mpu6500.enable_data_ready(InterruptPin::default()).unwrap();
+    for _ in 0..100 {
+        sensor.borrow_mut().push_fifo(&[0u8; 6]);
+    }
+    // Reading INT_STATUS clears FIFO_OFLOW_INT
+    block_on(mpu6500.wait_for_data(&mut int)).unwrap();
+    let mut frames = [FifoFrame::default(); 100];
+    let read = mpu6500.read_fifo(&mut frames).unwrap();
+    assert!(read.overflow);
+    assert_eq!(read.frames, 85);
+}
+
+#[test]
+fn wake_on_motion_follows_the_datasheet_sequence() {
+    let sensor = sensor();
+    let mut spi = Device(sensor.clone());
+    let mut mpu6500 = device_blocking::Mpu6500::new(&mut spi);
+    mpu6500.init().unwrap();
+    sensor.borrow_mut().log.clear();
+
+    mpu6500
+        .enable_wake_on_motion(
+            WakeOnMotion {
+                threshold_mg: 100,
+                odr: LpAccelOdr::Hz15_63,
+            },
+            InterruptPin::default(),
+        )
+        .unwrap();
+    let writes: Vec<_> = sensor
+        .borrow()
+        .log
+        .iter()
+        .filter_map(|access| match access {
+            Access::Write(register, data) => Some((*register, data[0])),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(
+        writes,
+        [
+            (0x6b, 0x01),
+            (0x6c, 0x07),
+            (0x1d, 0x01),
+            (0x37, 0x20),
+            (0x38, 0x40),
+            (0x69, 0xc0),
+            (0x1f, 25),
+            (0x1e, 6),
+            (0x6b, 0x21),
+        ]
+    );
+    assert!(mpu6500.config().cycle);
+}
+
+#[test]
+fn wake_on_motion_threshold_saturates() {
+    let wake_on_motion = |threshold_mg| WakeOnMotion {
+        threshold_mg,
+        odr: LpAccelOdr::Hz0_24,
+    };
+    let sensor = sensor();
+    let mut spi = Device(sensor.clone());
+    let mut mpu6500 = device_blocking::Mpu6500::new(&mut spi);
+    mpu6500.init().unwrap();
+    for (threshold_mg, register) in [(0, 0), (7, 1), (1020, 255), (5000, 255)] {
+        mpu6500
+            .enable_wake_on_motion(wake_on_motion(threshold_mg), InterruptPin::default())
+            .unwrap();
+        assert_eq!(sensor.borrow().registers[0x1f], register);
+    }
+}
+
+#[test]
+fn wait_for_motion_ignores_other_interrupts() {
+    let sensor = sensor();
+    let mut spi = Device(sensor.clone());
+    let mut mpu6500 = device_blocking::Mpu6500::new(&mut spi);
+    let mut int = Int::new(&sensor, WOM_INT);
+    mpu6500.init().unwrap();
+    assert!(matches!(
+        block_on(mpu6500.wait_for_motion(&mut int)),
+        Err(Error::InvalidConfiguration)
+    ));
+
+    mpu6500
+        .enable_wake_on_motion(
+            WakeOnMotion {
+                threshold_mg: 40,
+                odr: LpAccelOdr::Hz3_91,
+            },
+            InterruptPin::default(),
+        )
+        .unwrap();
+    // A data ready interrupt that is still pending
+    sensor.borrow_mut().registers[0x3a] = RAW_DATA_RDY_INT;
+    block_on(mpu6500.wait_for_motion(&mut int)).unwrap();
+    assert_eq!(int.samples, 1);
+    assert_eq!(sensor.borrow().registers[0x3a], 0);
+}
+
+#[test]
+fn disabling_wake_on_motion_restores_the_configuration() {
+    let sensor = sensor();
+    let mut spi = Device(sensor.clone());
+    let mut mpu6500 = device_blocking::Mpu6500::new(&mut spi);
+    mpu6500.init().unwrap();
+    let config = Config::default()
+        .accel_scale(AccelScale::G8)
+        .accel_dlpf(AccelDlpf::Hz45)
+        .gyro_dlpf(GyroDlpf::Hz41)
+        .sample_rate_divider(9)
+        .standby(Standby {
+            accel_x: true,
+            ..Standby::default()
+        });
+    mpu6500.configure(config).unwrap();
+    let pin = InterruptPin {
+        active_low: true,
+        open_drain: false,
+    };
+    mpu6500.enable_data_ready(pin).unwrap();
+
+    mpu6500
+        .enable_wake_on_motion(
+            WakeOnMotion {
+                threshold_mg: 40,
+                odr: LpAccelOdr::Hz3_91,
+            },
+            InterruptPin::default(),
+        )
+        .unwrap();
+    // Changing the settings keeps the state saved the first time
+    mpu6500
+        .enable_wake_on_motion(
+            WakeOnMotion {
+                threshold_mg: 80,
+                odr: LpAccelOdr::Hz500,
+            },
+            InterruptPin::default(),
+        )
+        .unwrap();
+    assert_ne!(mpu6500.config(), config);
+
+    mpu6500.disable_wake_on_motion().unwrap();
+    assert_eq!(mpu6500.config(), config);
+    assert_eq!(mpu6500.read_config().unwrap(), config);
+    assert_eq!(sensor.borrow().registers[0x69], 0);
+    assert_eq!(sensor.borrow().registers[0x37], 0xa0);
+    assert_eq!(sensor.borrow().registers[0x38], 0x01);
+
+    // The data ready interrupt works again, with the same polarity
+    let mut int = Int::new(&sensor, RAW_DATA_RDY_INT);
+    block_on(mpu6500.wait_for_data(&mut int)).unwrap();
+    assert_eq!(int.samples, 1);
+    assert!(int.is_high());
+}
+
+/// Creates a sensor that lies flat and still, with the gyro and
+/// accelerometer bias and the accelerometer factory trim
+fn still_sensor() -> Shared {
+    let sensor = sensor();
+    {
+        let mut sensor = sensor.borrow_mut();
+        // 1 deg/s, about -0.5 deg/s and 2 deg/s with the 250 deg/s scale
+        sensor.set_i16(0x43, 131);
+        sensor.set_i16(0x45, -65);
+        sensor.set_i16(0x47, 262);
+        // 10 mg, -20 mg and 1 g + 49 mg with the 2 g scale
+        sensor.set_i16(0x3b, 164);
+        sensor.set_i16(0x3d, -328);
+        sensor.set_i16(0x3f, 16384 + 803);
+        // The factory trim, with the reserved bit set for X and Z
+        sensor.set_i16(0x77, 1000 << 1 | 1);
+        sensor.set_i16(0x7a, -1000 << 1);
+        sensor.set_i16(0x7d, 0x0001);
+    }
+    sensor
+}
+
+#[test]
+fn offsets_keep_the_reserved_accel_bit() {
+    let sensor = still_sensor();
+    let mut spi = Device(sensor.clone());
+    let mut mpu6500 = device_blocking::Mpu6500::new(&mut spi);
+    mpu6500.init().unwrap();
+    assert_eq!(
+        mpu6500.read_offsets().unwrap(),
+        Offsets {
+            gyro: [0, 0, 0],
+            accel: [1000, -1000, 0],
+        }
+    );
+
+    let offsets = Offsets {
+        gyro: [-1, 300, -32768],
+        accel: [-16384, 16383, 5],
+    };
+    mpu6500.write_offsets(offsets).unwrap();
+    assert_eq!(mpu6500.read_offsets().unwrap(), offsets);
+    let registers = sensor.borrow().registers;
+    assert_eq!(registers[0x13..=0x18], [0xff, 0xff, 0x01, 0x2c, 0x80, 0x00]);
+    assert_eq!(registers[0x77..=0x78], [0x80, 0x01]);
+    assert_eq!(registers[0x7a..=0x7b], [0x7f, 0xfe]);
+    assert_eq!(registers[0x7d..=0x7e], [0x00, 0x0b]);
+}
+
+#[test]
+fn calibration_removes_the_bias() {
+    let sensor = still_sensor();
+    let mut spi = Device(sensor.clone());
+    let mut mpu6500 = device_blocking::Mpu6500::new(&mut spi);
+    mpu6500.init().unwrap();
+    mpu6500
+        .configure(
+            Config::default()
+                .gyro_dlpf(GyroDlpf::Hz41)
+                .sample_rate_divider(9),
+        )
+        .unwrap();
+    sensor.borrow_mut().log.clear();
+
+    let mut delay = Delay(0);
+    let gravity = Acceleration {
+        x: 0.0,
+        y: 0.0,
+        z: 1.0,
+    };
+    let offsets = mpu6500.calibrate(10, gravity, &mut delay).unwrap();
+    assert_eq!(
+        offsets,
+        Offsets {
+            // -1 * 32.8, 0.5 * 32.8 and -2 * 32.8
+            gyro: [-33, 16, -66],
+            // The factory trim minus 10 mg, 20 mg and 49 mg in 0.98 mg steps
+            accel: [1000 - 10, -1000 + 20, -50],
+        }
+    );
+    assert_eq!(mpu6500.read_offsets().unwrap(), offsets);
+    // The reserved bits are unchanged
+    let registers = sensor.borrow().registers;
+    assert_eq!(registers[0x78] & 1, 1);
+    assert_eq!(registers[0x7b] & 1, 0);
+    assert_eq!(registers[0x7e] & 1, 1);
+
+    // 10 samples, one every 10 ms at 100 Hz
+    let reads = sensor
+        .borrow()
+        .log
+        .iter()
+        .filter(|access| matches!(access, Access::Read(0x3b, data) if data.len() == 14))
+        .count();
+    assert_eq!(reads, 10);
+    assert_eq!(delay.0, 100_000_000);
+}
+
+#[test]
+fn calibration_requires_samples() {
+    let sensor = still_sensor();
+    let mut spi = Device(sensor.clone());
+    let mut mpu6500 = device_blocking::Mpu6500::new(&mut spi);
+    mpu6500.init().unwrap();
+    let gravity = Acceleration {
+        x: 0.0,
+        y: 0.0,
+        z: 1.0,
+    };
+    assert!(matches!(
+        mpu6500.calibrate(0, gravity, &mut Delay(0)),
+        Err(Error::InvalidConfiguration)
+    ));
+}
+
+/// The factory trim in LSB of the `SELF_TEST` register codes used by the tests
+const TRIM_CODE_1: f32 = 2620.0;
+/// 2620 * 1.01 ^ 99
+const TRIM_CODE_100: f32 = 7016.45;
+
+/// Creates a still sensor whose self-test responses
+/// match the factory trim
+fn self_test_sensor() -> Shared {
+    let sensor = still_sensor();
+    {
+        let mut sensor = sensor.borrow_mut();
+        // SELF_TEST_X_GYRO ... SELF_TEST_Z_GYRO
+        sensor.registers[0x00..=0x02].copy_from_slice(&[100, 1, 100]);
+        // SELF_TEST_X_ACCEL ... SELF_TEST_Z_ACCEL
+        sensor.registers[0x0d..=0x0f].copy_from_slice(&[1, 100, 1]);
+        // A negative response is compared by its magnitude
+        sensor.self_test_response = [2620, 7016, -2620, 7016, 2620, -7016];
+    }
+    sensor
+}
+
+#[test]
+fn self_test_passes_with_the_factory_response() {
+    let sensor = self_test_sensor();
+    let mut spi = Device(sensor.clone());
+    let mut mpu6500 = device_blocking::Mpu6500::new(&mut spi);
+    mpu6500.init().unwrap();
+    let config = Config::default()
+        .gyro_scale(GyroScale::Gs2000)
+        .accel_scale(AccelScale::G16)
+        .sample_rate_divider(4);
+    mpu6500.configure(config).unwrap();
+    sensor.borrow_mut().log.clear();
+
+    let mut delay = Delay(0);
+    let report = mpu6500.self_test(&mut delay).unwrap();
+    assert!(report.passed());
+    let trims = [
+        (report.accel[0], TRIM_CODE_1 / 16384.0),
+        (report.accel[1], TRIM_CODE_100 / 16384.0),
+        (report.accel[2], TRIM_CODE_1 / 16384.0),
+        (report.gyro[0], TRIM_CODE_100 / 131.0),
+        (report.gyro[1], TRIM_CODE_1 / 131.0),
+        (report.gyro[2], TRIM_CODE_100 / 131.0),
+    ];
+    for (axis, trim) in trims {
+        assert!((axis.factory_trim.unwrap() / trim - 1.0).abs() < 1e-4);
+        assert!(axis.deviation.unwrap().abs() < 0.01);
+    }
+    assert!(report.accel[2].response < 0.0);
+
+    // Two series of samples, one with the self-test enabled
+    let reads = sensor
+        .borrow()
+        .log
+        .iter()
+        .filter(|access| matches!(access, Access::Read(0x3b, data) if data.len() == 14))
+        .count();
+    assert_eq!(reads, 2 * SELF_TEST_SAMPLES as usize);
+    assert!(
+        sensor
+            .borrow()
+            .log
+            .contains(&Access::Write(0x1b, std::vec![0xe0]))
+    );
+    assert!(
+        sensor
+            .borrow()
+            .log
+            .contains(&Access::Write(0x1c, std::vec![0xe0]))
+    );
+    // 1 ms per sample and 20 ms for the values to settle three times
+    assert_eq!(delay.0, (2 * SELF_TEST_SAMPLES as u64 + 60) * 1_000_000);
+
+    // The self-test bits are cleared and the configuration is restored
+    assert_eq!(mpu6500.config(), config);
+    assert_eq!(mpu6500.read_config().unwrap(), config);
+    assert_eq!(sensor.borrow().registers[0x1b] & 0xe0, 0);
+    assert_eq!(sensor.borrow().registers[0x1c] & 0xe0, 0);
+}
+
+#[test]
+fn self_test_restores_the_configuration_after_an_error() {
+    let sensor = self_test_sensor();
+    let mut spi = Device(sensor.clone());
+    let mut mpu6500 = device_blocking::Mpu6500::new(&mut spi);
+    mpu6500.init().unwrap();
+    let config = Config::default()
+        .gyro_scale(GyroScale::Gs2000)
+        .accel_scale(AccelScale::G16)
+        .sample_rate_divider(4);
+    mpu6500.configure(config).unwrap();
+
+    // Reading the factory trim fails, after the self-test
+    // configuration was applied
+    sensor.borrow_mut().fault = Some(Fault::Register(0x00));
+    assert!(matches!(
+        mpu6500.self_test(&mut Delay(0)),
+        Err(Error::Bus(_))
+    ));
+    sensor.borrow_mut().fault = None;
+
+    let registers = sensor.borrow().registers;
+    assert_eq!(registers[0x19], 4);
+    assert_eq!(registers[0x1b], 0b11 << 3);
+    assert_eq!(registers[0x1c], 0b11 << 3);
+    assert_eq!(mpu6500.config(), config);
+}
+
+#[test]
+fn self_test_reports_the_failing_axes() {
+    let sensor = self_test_sensor();
+    {
+        let mut sensor = sensor.borrow_mut();
+        // Accel Y is 60% above the factory trim, accel Z has no factory
+        // trim and a 0.3 g response
+        sensor.registers[0x0f] = 0;
+        sensor.self_test_response[1] = (TRIM_CODE_100 * 1.6) as i16;
+        sensor.self_test_response[2] = (0.3 * 16384.0) as i16;
+        // Gyro X is 60% below the factory trim, gyro Z has no factory
+        // trim and a 30 deg/s response
+        sensor.registers[0x02] = 0;
+        sensor.self_test_response[3] = (TRIM_CODE_100 * 0.4) as i16;
+        sensor.self_test_response[5] = 30 * 131;
+    }
+    let mut spi = Device(sensor.clone());
+    let mut mpu6500 = device_blocking::Mpu6500::new(&mut spi);
+    mpu6500.init().unwrap();
+
+    let report = mpu6500.self_test(&mut Delay(0)).unwrap();
+    assert!(!report.passed());
+    let passed = |axes: [SelfTestAxis; 3]| axes.map(|axis| axis.passed);
+    assert_eq!(passed(report.accel), [true, false, true]);
+    assert_eq!(passed(report.gyro), [false, true, false]);
+
+    assert!((report.accel[1].deviation.unwrap() - 60.0).abs() < 0.1);
+    assert!(report.accel[2].factory_trim.is_none());
+    assert!(report.accel[2].deviation.is_none());
+    assert!((report.accel[2].response - 0.3).abs() < 0.001);
+    assert!((report.gyro[0].deviation.unwrap() + 60.0).abs() < 0.1);
+    assert!((report.gyro[2].response - 30.0).abs() < 0.001);
+}
+
+/// Creates a sensor that reports `who_am_i`, in its power on state
+fn chip_sensor(who_am_i: u8, pwr_mgmt_1: u8) -> Shared {
+    let sensor = sensor();
+    sensor.borrow_mut().registers[0x75] = who_am_i;
+    sensor.borrow_mut().registers[0x6b] = pwr_mgmt_1;
+    sensor
+}
+
+/// Returns the register writes logged by the sensor
+fn writes(sensor: &Shared) -> Vec<(u8, u8)> {
+    sensor
+        .borrow()
+        .log
+        .iter()
+        .filter_map(|access| match access {
+            Access::Write(register, data) => Some((*register, data[0])),
+            _ => None,
+        })
+        .collect()
+}
+
+#[test]
+fn probe_detects_the_chip() {
+    for (who_am_i, chip) in [
+        (0x68, Chip::Mpu6000),
+        (0x70, Chip::Mpu6500),
+        (0x71, Chip::Mpu9250),
+        (0x12, Chip::Icm20602),
+        (0xaf, Chip::Icm20608),
+    ] {
+        let sensor = chip_sensor(who_am_i, 0x01);
+        let mut spi = Device(sensor.clone());
+        let mut mpu6500 = device_blocking::Mpu6500::new(&mut spi);
+        assert_eq!(mpu6500.probe().unwrap(), chip);
+        assert_eq!(mpu6500.chip(), chip);
+        assert_eq!(chip.who_am_i(), who_am_i);
+        assert_eq!(Chip::from_who_am_i(who_am_i), Some(chip));
+    }
+}
+
+#[test]
+fn init_runs_the_chip_init_sequence() {
+    // The MPU6500 and MPU9250 start awake, nothing is written
+    for who_am_i in [0x70, 0x71] {
+        let sensor = chip_sensor(who_am_i, 0x01);
+        let mut spi = Device(sensor.clone());
+        let mut mpu6500 = device_blocking::Mpu6500::new(&mut spi);
+        mpu6500.init().unwrap();
+        assert!(writes(&sensor).is_empty());
+    }
+
+    // The MPU6000 and ICM20608 disable I2C in USER_CTRL and wake up
+    for who_am_i in [0x68, 0xaf] {
+        let sensor = chip_sensor(who_am_i, 0x40);
+        let mut spi = Device(sensor.clone());
+        let mut mpu6500 = device_blocking::Mpu6500::new(&mut spi);
+        mpu6500.init().unwrap();
+        assert_eq!(writes(&sensor), [(0x6a, 0x10), (0x6b, 0x01)]);
+        assert!(!mpu6500.config().sleep);
+        assert_eq!(mpu6500.config().clock_source, ClockSource::Auto);
+    }
+
+    // The ICM20602 disables I2C in I2C_IF
+    let sensor = chip_sensor(0x12, 0x41);
+    let mut spi = Device(sensor.clone());
+    let mut mpu6500 = device_blocking::Mpu6500::new(&mut spi);
+    mpu6500.init().unwrap();
+    assert_eq!(writes(&sensor), [(0x70, 0x40), (0x6b, 0x01)]);
+    assert!(!mpu6500.config().sleep);
+}
+
+#[test]
+fn mpu6000_has_no_accel_config2() {
+    let sensor = chip_sensor(0x68, 0x40);
+    // A value that is not a valid A_DLPF_CFG
+    sensor.borrow_mut().registers[0x1d] = 0xaa;
+    let mut spi = Device(sensor.clone());
+    let mut mpu6500 = device_blocking::Mpu6500::new(&mut spi);
+    mpu6500.init().unwrap();
+    assert_eq!(mpu6500.config().accel_dlpf, AccelDlpf::Hz218);
+    sensor.borrow_mut().log.clear();
+
+    // The accelerometer uses the gyro filter, `accel_dlpf` is ignored
+    let config = Config::default()
+        .gyro_dlpf(GyroDlpf::Hz41)
+        .accel_dlpf(AccelDlpf::Hz45);
+    mpu6500.configure(config).unwrap();
+    assert!(
+        writes(&sensor)
+            .iter()
+            .all(|(register, _)| *register != 0x1d)
+    );
+    assert_eq!(sensor.borrow().registers[0x1d], 0xaa);
+    assert_eq!(mpu6500.config().gyro_dlpf, GyroDlpf::Hz41);
+}
+
+#[test]
+fn mpu6000_does_not_support_the_mpu6500_features() {
+    let sensor = chip_sensor(0x68, 0x40);
+    let mut spi = Device(sensor.clone());
+    let mut mpu6500 = device_blocking::Mpu6500::new(&mut spi);
+    mpu6500.init().unwrap();
+    sensor.borrow_mut().log.clear();
+    let wake_on_motion = WakeOnMotion {
+        threshold_mg: 40,
+        odr: LpAccelOdr::Hz3_91,
+    };
+    assert!(matches!(
+        mpu6500.enable_wake_on_motion(wake_on_motion, InterruptPin::default()),
+        Err(Error::Unsupported)
+    ));
+    assert!(matches!(mpu6500.read_offsets(), Err(Error::Unsupported)));
+    assert!(matches!(
+        mpu6500.self_test(&mut Delay(0)),
+        Err(Error::Unsupported)
+    ));
+    assert!(sensor.borrow().log.is_empty());
+}
+
+#[test]
+fn icm20602_has_a_wake_on_motion_threshold_per_axis() {
+    let sensor = chip_sensor(0x12, 0x41);
+    let mut spi = Device(sensor.clone());
+    let mut mpu6500 = device_blocking::Mpu6500::new(&mut spi);
+    mpu6500.init().unwrap();
+    mpu6500
+        .enable_wake_on_motion(
+            WakeOnMotion {
+                threshold_mg: 40,
+                odr: LpAccelOdr::Hz3_91,
+            },
+            InterruptPin::default(),
+        )
+        .unwrap();
+    let registers = sensor.borrow().registers;
+    assert_eq!(registers[0x20..=0x22], [10, 10, 10]);
+    assert_eq!(registers[0x1f], 0);
+}
+
+#[test]
+fn icm_sensors_wake_up_at_the_sample_rate() {
+    for (who_am_i, user_ctrl) in [(0x12, 0x41), (0xaf, 0x40)] {
+        let sensor = chip_sensor(who_am_i, user_ctrl);
+        let mut spi = Device(sensor.clone());
+        let mut mpu6500 = device_blocking::Mpu6500::new(&mut spi);
+        mpu6500.init().unwrap();
+        mpu6500.configure(Config::default()).unwrap();
+        sensor.borrow_mut().log.clear();
+
+        // 1 kHz / (1 + 63), LP_MODE_CFG is not written
+        let wake_on_motion = |odr| WakeOnMotion {
+            threshold_mg: 40,
+            odr,
+        };
+        mpu6500
+            .enable_wake_on_motion(wake_on_motion(LpAccelOdr::Hz15_63), InterruptPin::default())
+            .unwrap();
+        let writes = writes(&sensor);
+        assert!(writes.contains(&(0x19, 63)));
+        assert!(writes.iter().all(|(register, _)| *register != 0x1e));
+        assert_eq!(mpu6500.config().sample_rate_divider, 63);
+
+        mpu6500
+            .enable_wake_on_motion(wake_on_motion(LpAccelOdr::Hz3_91), InterruptPin::default())
+            .unwrap();
+        assert_eq!(sensor.borrow().registers[0x19], 255);
+        mpu6500
+            .enable_wake_on_motion(wake_on_motion(LpAccelOdr::Hz500), InterruptPin::default())
+            .unwrap();
+        assert_eq!(sensor.borrow().registers[0x19], 1);
+
+        // The lower rates are not supported
+        assert!(matches!(
+            mpu6500
+                .enable_wake_on_motion(wake_on_motion(LpAccelOdr::Hz1_95), InterruptPin::default()),
+            Err(Error::InvalidConfiguration)
+        ));
+
+        // The divider is restored
+        mpu6500.disable_wake_on_motion().unwrap();
+        assert_eq!(sensor.borrow().registers[0x19], 0);
+    }
+}
+
+#[test]
+fn i2c_init_keeps_the_i2c_interface_enabled() {
+    for who_am_i in [0x68, 0xaf, 0x12] {
+        let sensor = chip_sensor(who_am_i, 0x40);
+        let mut mpu6500 =
+            i2c_blocking::Mpu6500::new(I2cBus::new(&sensor, Address::Ad0Low), Address::Ad0Low);
+        mpu6500.init().unwrap();
+        assert_eq!(writes(&sensor), [(0x6b, 0x01)]);
+        assert!(!mpu6500.config().sleep);
+    }
+}
+
+/// Creates an MPU9250 with the AK8963 that measures the magnetic field
+/// `(x, y, z)`, in the magnetometer's axes, and the `ASA` values
+fn mpu9250_sensor(field: [i16; 3], asa: [u8; 3]) -> Shared {
+    let sensor = still_sensor();
+    sensor.borrow_mut().registers[0x75] = 0x71;
+    let mut ak8963 = [0u8; 32];
+    // WIA
+    ak8963[0x00] = 0x48;
+    for (axis, value) in field.iter().enumerate() {
+        ak8963[0x03 + 2 * axis..0x05 + 2 * axis].copy_from_slice(&value.to_le_bytes());
+    }
+    ak8963[0x10..0x13].copy_from_slice(&asa);
+    sensor.borrow_mut().ak8963 = Some(ak8963);
+    sensor
+}
+
+#[test]
+fn magnetometer_init_follows_the_ak8963_sequence() {
+    let sensor = mpu9250_sensor([0; 3], [128, 0, 255]);
+    let mut spi = Device(sensor.clone());
+    let mut mpu6500 = device_blocking::Mpu6500::new(&mut spi);
+    mpu6500.init().unwrap();
+    let magnetometer = mpu6500
+        .init_magnetometer(MagMode::Continuous100Hz, &mut Delay(0))
+        .unwrap();
+    assert_eq!(mpu6500.magnetometer(), Some(magnetometer));
+
+    // ASA 128 does not change the value, 0 halves it and 255 adds 50%
+    assert_eq!(magnetometer.adjustment[0], 1.0);
+    assert_eq!(magnetometer.adjustment[1], 0.5);
+    assert!((magnetometer.adjustment[2] - 1.496).abs() < 0.001);
+    assert_eq!(magnetometer.calibration, MagCalibration::default());
+
+    // CNTL2 reset, power down, fuse ROM, power down and the
+    // continuous mode with 16 bits values
+    assert_eq!(
+        sensor.borrow().aux_log,
+        [
+            (0x0b, 0x01),
+            (0x0a, 0x00),
+            (0x0a, 0x0f),
+            (0x0a, 0x00),
+            (0x0a, 0x16)
+        ]
+    );
+    let registers = sensor.borrow().registers;
+    // I2C_MST_EN and a 400 kHz clock
+    assert_eq!(registers[0x6a] & 0x20, 0x20);
+    assert_eq!(registers[0x24], 13);
+    // I2C_SLV0 reads HXL ... ST2 from 0x0c
+    assert_eq!(registers[0x25..=0x27], [0x8c, 0x03, 0x87]);
+}
+
+#[test]
+fn magnetometer_has_to_answer() {
+    let sensor = still_sensor();
+    let mut spi = Device(sensor.clone());
+    let mut mpu6500 = device_blocking::Mpu6500::new(&mut spi);
+    mpu6500.init().unwrap();
+    assert!(matches!(
+        mpu6500.init_magnetometer(MagMode::Continuous8Hz, &mut Delay(0)),
+        Err(Error::AuxI2c)
+    ));
+
+    // Another device answers at the magnetometer's address
+    let sensor = mpu9250_sensor([0; 3], [128; 3]);
+    sensor.borrow_mut().ak8963.as_mut().unwrap()[0] = 0x10;
+    let mut spi = Device(sensor.clone());
+    let mut mpu6500 = device_blocking::Mpu6500::new(&mut spi);
+    mpu6500.init().unwrap();
+    assert!(matches!(
+        mpu6500.init_magnetometer(MagMode::Continuous8Hz, &mut Delay(0)),
+        Err(Error::UnexpectedWhoAmI(0x10))
+    ));
+    assert!(matches!(mpu6500.read_9_axis(), Err(Error::NotInitialised)));
+}
+
+#[test]
+fn nine_axis_measurement_is_one_burst() {
+    // 100 LSB on the magnetometer's X axis, -200 on Y and 300 on Z
+    let sensor = mpu9250_sensor([100, -200, 300], [128, 128, 0]);
+    let mut spi = Device(sensor.clone());
+    let mut mpu6500 = device_blocking::Mpu6500::new(&mut spi);
+    mpu6500.init().unwrap();
+    mpu6500
+        .init_magnetometer(MagMode::Continuous100Hz, &mut Delay(0))
+        .unwrap();
+    sensor.borrow_mut().log.clear();
+
+    let measurement = mpu6500.read_9_axis().unwrap();
+    {
+        let log = &sensor.borrow().log;
+        assert_eq!(log.len(), 1);
+        assert!(matches!(&log[0], Access::Read(0x3b, data) if data.len() == 21));
+    }
+
+    // The X and Y axes are swapped, Z is inverted
+    assert!((measurement.mag.x - -200.0 * 0.15).abs() < 0.001);
+    assert!((measurement.mag.y - 100.0 * 0.15).abs() < 0.001);
+    assert!((measurement.mag.z - -300.0 * 0.5 * 0.15).abs() < 0.001);
+    assert_eq!(
+        measurement.heading,
+        heading(measurement.accel, measurement.mag)
+    );
+    assert_eq!(mpu6500.read_magnetic_field().unwrap(), measurement.mag);
+}
+
+#[test]
+fn magnetic_overflow_is_reported() {
+    let sensor = mpu9250_sensor([0; 3], [128; 3]);
+    let mut spi = Device(sensor.clone());
+    let mut mpu6500 = device_blocking::Mpu6500::new(&mut spi);
+    mpu6500.init().unwrap();
+    mpu6500
+        .init_magnetometer(MagMode::Continuous100Hz, &mut Delay(0))
+        .unwrap();
+    // ST2 HOFL
+    sensor.borrow_mut().ak8963.as_mut().unwrap()[0x09] = 1 << 3;
+    assert!(matches!(
+        mpu6500.read_9_axis(),
+        Err(Error::MagneticOverflow)
+    ));
+}
+
+#[test]
+fn heading_is_tilt_compensated() {
+    let flat = Acceleration {
+        x: 0.0,
+        y: 0.0,
+        z: 1.0,
+    };
+    // The field points north and down (northern hemisphere)
+    let north = MagneticField {
+        x: 20.0,
+        y: 0.0,
+        z: -40.0,
+    };
+    assert!(heading(flat, north).abs() < 0.01);
+
+    // Facing east, the Y axis (left) points north
+    let east = MagneticField {
+        x: 0.0,
+        y: 20.0,
+        z: -40.0,
+    };
+    assert!((heading(flat, east) - 90.0).abs() < 0.01);
+    let west = MagneticField {
+        x: 0.0,
+        y: -20.0,
+        z: -40.0,
+    };
+    assert!((heading(flat, west) - 270.0).abs() < 0.01);
+
+    // Facing east and rolled by 30 deg around the X axis, the
+    // field and the gravity rotate together
+    let (sin, cos) = (0.5f32, 0.75f32.sqrt());
+    let rolled = Acceleration {
+        x: 0.0,
+        y: sin,
+        z: cos,
+    };
+    let rolled_east = MagneticField {
+        x: 0.0,
+        y: 20.0 * cos - 40.0 * sin,
+        z: -20.0 * sin - 40.0 * cos,
+    };
+    assert!((heading(rolled, rolled_east) - 90.0).abs() < 0.01);
+}
+
+#[test]
+fn mag_calibration_removes_hard_and_soft_iron() {
+    // A 50 uT field, offset by (10, -5, 20) uT and scaled
+    // by 1.25 on the X axis, measured on every axis
+    let mut calibrator = MagCalibrator::new();
+    assert!(calibrator.calibration().is_none());
+    for sign in [-1.0, 1.0] {
+        calibrator.update(MagneticField {
+            x: 10.0 + sign * 62.5,
+            y: -5.0,
+            z: 20.0,
+        });
+        calibrator.update(MagneticField {
+            x: 10.0,
+            y: -5.0 + sign * 50.0,
+            z: 20.0,
+        });
+        calibrator.update(MagneticField {
+            x: 10.0,
+            y: -5.0,
+            z: 20.0 + sign * 50.0,
+        });
+    }
+    assert_eq!(calibrator.samples(), 6);
+    let calibration = calibrator.calibration().unwrap();
+    assert_eq!(calibration.hard_iron, [10.0, -5.0, 20.0]);
+
+    let field = calibration.apply(MagneticField {
+        x: 10.0 + 62.5,
+        y: -5.0,
+        z: 20.0,
+    });
+    let z = calibration.apply(MagneticField {
+        x: 10.0,
+        y: -5.0,
+        z: 20.0 + 50.0,
+    });
+    // The axes have the same radius after the calibration
+    assert!((field.x - z.z).abs() < 0.001);
+    assert!(field.y.abs() < 0.001 && field.z.abs() < 0.001);
+}
+
+#[test]
+fn bypass_disables_the_i2c_master() {
+    let sensor = mpu9250_sensor([0; 3], [128; 3]);
+    let mut mpu6500 =
+        i2c_blocking::Mpu6500::new(I2cBus::new(&sensor, Address::Ad0Low), Address::Ad0Low);
+    mpu6500.init().unwrap();
+    mpu6500.enable_data_ready(InterruptPin::default()).unwrap();
+    mpu6500.enable_i2c_master(I2cMasterClock::Khz400).unwrap();
+    mpu6500.set_bypass(true).unwrap();
+    let registers = sensor.borrow().registers;
+    assert_eq!(registers[0x6a] & 0x20, 0);
+    // BYPASS_EN is set, the INT pin configuration is kept
+    assert_eq!(registers[0x37], 0x20 | 0x02);
+
+    mpu6500.set_bypass(false).unwrap();
+    assert_eq!(sensor.borrow().registers[0x37], 0x20);
+}
+
+#[test]
+fn bypass_needs_the_i2c_interface() {
+    let sensor = mpu9250_sensor([0; 3], [128; 3]);
+    let mut spi = Device(sensor.clone());
+    let mut mpu6500 = device_blocking::Mpu6500::new(&mut spi);
+    mpu6500.init().unwrap();
+    mpu6500.enable_i2c_master(I2cMasterClock::Khz400).unwrap();
+    sensor.borrow_mut().log.clear();
+
+    assert!(matches!(mpu6500.set_bypass(true), Err(Error::Unsupported)));
+    assert!(writes(&sensor).is_empty());
+    assert_eq!(sensor.borrow().registers[0x6a] & 0x20, 0x20);
+}
+
+#[test]
+fn slaves_read_into_the_external_data() {
+    let sensor = mpu9250_sensor([0x0201, 0x0403, 0x0605], [128; 3]);
+    let mut spi = Device(sensor.clone());
+    let mut mpu6500 = device_blocking::Mpu6500::new(&mut spi);
+    mpu6500.init().unwrap();
+    mpu6500.enable_i2c_master(I2cMasterClock::Khz400).unwrap();
+    assert_eq!(mpu6500.aux_read(0x0c, 0x00, &mut Delay(0)).unwrap(), 0x48);
+    assert!(matches!(
+        mpu6500.configure_slave_read(AuxSlave::Slv1, 0x0c, 0x03, 16),
+        Err(Error::InvalidConfiguration)
+    ));
+    mpu6500
+        .configure_slave_read(AuxSlave::Slv0, 0x0c, 0x03, 6)
+        .unwrap();
+    let mut data = [0u8; 6];
+    mpu6500.read_external_data(&mut data).unwrap();
+    assert_eq!(data, [1, 2, 3, 4, 5, 6]);
+
+    mpu6500
+        .configure_slave_write(AuxSlave::Slv2, 0x0c, 0x0a, 0x16)
+        .unwrap();
+    assert_eq!(sensor.borrow().registers[0x2b..=0x2d], [0x0c, 0x0a, 0x81]);
+    assert_eq!(sensor.borrow().registers[0x65], 0x16);
+    mpu6500.disable_slave(AuxSlave::Slv2).unwrap();
+    assert_eq!(sensor.borrow().registers[0x2d], 0);
+}
+
+#[test]
+fn icm20602_has_no_auxiliary_i2c() {
+    let sensor = chip_sensor(0x12, 0x41);
+    let mut spi = Device(sensor.clone());
+    let mut mpu6500 = device_blocking::Mpu6500::new(&mut spi);
+    mpu6500.init().unwrap();
+    assert!(matches!(
+        mpu6500.enable_i2c_master(I2cMasterClock::Khz400),
+        Err(Error::Unsupported)
+    ));
+    assert!(matches!(
+        mpu6500.init_magnetometer(MagMode::Continuous8Hz, &mut Delay(0)),
+        Err(Error::Unsupported)
+    ));
+}
+
+#[test]
+fn sim_follows_the_spi_protocol() {
+    use embedded_hal::{
+        digital::OutputPin,
+        spi::{Operation, SpiBus, SpiDevice},
+    };
+
+    let sensor = sensor();
+    let mut device = Device(sensor.clone());
+
+    // The read bit selects a read, the burst auto increments
+    let mut who_am_i = [0u8; 1];
+    device
+        .transaction(&mut [
+            Operation::Write(&[0x80 | 0x75]),
+            Operation::Read(&mut who_am_i),
+        ])
+        .unwrap();
+    assert_eq!(who_am_i, [0x70]);
+    let mut accel = [0u8; 6];
+    device
+        .transaction(&mut [
+            Operation::Write(&[0x80 | 0x3b]),
+            Operation::Read(&mut accel),
+        ])
+        .unwrap();
+    assert_eq!(accel, [0x40, 0x00, 0xe0, 0x00, 0x03, 0xe8]);
+
+    // Without the read bit, the bytes are written into consecutive
+    // registers, the read only registers ignore them
+    device.write(&[0x1b, 0x18, 0x08]).unwrap();
+    device.write(&[0x75, 0x12]).unwrap();
+    let registers = sensor.borrow().registers;
+    assert_eq!(registers[0x1b..=0x1c], [0x18, 0x08]);
+    assert_eq!(registers[0x75], 0x70);
+
+    // The bus transfers the same bytes while CS is LOW
+    let mut bus = Bus(sensor.clone());
+    let mut cs = Cs(sensor.clone());
+    let mut data = [0x80 | 0x1b, 0, 0];
+    cs.set_low().unwrap();
+    bus.transfer_in_place(&mut data).unwrap();
+    cs.set_high().unwrap();
+    assert_eq!(data[1..], [0x18, 0x08]);
+    assert_eq!(
+        sensor.borrow().log.last(),
+        Some(&Access::Read(0x1b, [0x18, 0x08].to_vec()))
+    );
+}
+
+/// Initialises a driver and reads the X acceleration three times
+type ReadThree = fn(&Shared) -> Result<[f32; 3], Error<ErrorKind>>;
+
+fn read_three_bus(sensor: &Shared) -> Result<[f32; 3], Error<ErrorKind>> {
+    let mut spi = Bus(sensor.clone());
+    let mut mpu6500 = bus::Mpu6500::new(&mut spi, Cs(sensor.clone()));
+    block_on(async {
+        mpu6500.init().await?;
+        let mut values = [0f32; 3];
+        for value in &mut values {
+            *value = mpu6500.read_acceleration().await?.x;
+        }
+        Ok(values)
+    })
+}
+
+fn read_three_device(sensor: &Shared) -> Result<[f32; 3], Error<ErrorKind>> {
+    let mut spi = Device(sensor.clone());
+    let mut mpu6500 = device::Mpu6500::new(&mut spi);
+    block_on(async {
+        mpu6500.init().await?;
+        let mut values = [0f32; 3];
+        for value in &mut values {
+            *value = mpu6500.read_acceleration().await?.x;
+        }
+        Ok(values)
+    })
+}
+
+fn read_three_device_blocking(sensor: &Shared) -> Result<[f32; 3], Error<ErrorKind>> {
+    let mut spi = Device(sensor.clone());
+    let mut mpu6500 = device_blocking::Mpu6500::new(&mut spi);
+    mpu6500.init()?;
+    let mut values = [0f32; 3];
+    for value in &mut values {
+        *value = mpu6500.read_acceleration()?.x;
+    }
+    Ok(values)
+}
+
+const SPI_DRIVERS: [(&str, ReadThree); 3] = [
+    ("bus", read_three_bus),
+    ("device", read_three_device),
+    ("device_blocking", read_three_device_blocking),
+];
+
+/// Returns a sample with the X acceleration `x` g (at 2 g)
+fn sample_x(x: f32) -> Sample {
+    Sample {
+        accel: [(x * 16384.0) as i16, 0, 16384],
+        ..Sample::default()
+    }
+}
+
+#[test]
+fn scripted_samples_are_read_in_order() {
+    for (name, read_three) in SPI_DRIVERS {
+        let sensor = Sensor::shared();
+        sensor.borrow_mut().sample_on_read = true;
+        sensor.borrow_mut().script([0.25, -0.5, 1.0].map(sample_x));
+        assert_eq!(read_three(&sensor).unwrap(), [0.25, -0.5, 1.0], "{name}");
+        assert!(sensor.borrow().script.is_empty(), "{name}");
+
+        // The last sample stays in the data registers
+        assert_eq!(read_three(&sensor).unwrap(), [1.0; 3], "{name}");
+    }
+}
+
+#[test]
+fn scripted_samples_fill_the_fifo() {
+    let sensor = Sensor::shared();
+    let mut spi = Device(sensor.clone());
+    let mut mpu6500 = device_blocking::Mpu6500::new(&mut spi);
+    mpu6500.init().unwrap();
+    mpu6500
+        .enable_fifo(FifoSensors {
+            accel: true,
+            temperature: true,
+            gyro: true,
+        })
+        .unwrap();
+
+    sensor.borrow_mut().script((0..5).map(|index| Sample {
+        accel: [index * 1638, 0, 16384],
+        temperature: 0,
+        gyro: [0, 0, -index * 131],
+    }));
+    for _ in 0..5 {
+        sensor.borrow_mut().sample();
+    }
+    assert_eq!(sensor.borrow().registers[0x3a], RAW_DATA_RDY_INT);
+
+    let mut frames = [FifoFrame::default(); 8];
+    assert_eq!(mpu6500.read_fifo(&mut frames).unwrap().frames, 5);
+    for (index, frame) in frames[..5].iter().enumerate() {
+        assert_eq!(frame.accel.unwrap().x, (index * 1638) as f32 / 16384.0);
+        assert_eq!(frame.temperature, Some(temperature_to_celsius(0)));
+        assert_eq!(frame.gyro.unwrap().z, -(index as f32));
+    }
+
+    // A sleeping sensor does not take samples
+    sensor.borrow_mut().registers[0x6b] |= 0x40;
+    sensor.borrow_mut().sample();
+    assert_eq!(mpu6500.fifo_count().unwrap(), 0);
+}
+
+#[test]
+fn injected_faults_are_reported_by_the_spi_drivers() {
+    for (name, read_three) in SPI_DRIVERS {
+        // Count the transactions of a successful run
+        let sensor = Sensor::shared();
+        read_three(&sensor).unwrap();
+        let transactions = sensor.borrow().transactions;
+
+        // The last read fails
+        let sensor = Sensor::shared();
+        sensor.borrow_mut().fault = Some(Fault::After(transactions - 1));
+        assert!(
+            matches!(read_three(&sensor), Err(Error::Bus(ErrorKind::Other))),
+            "{name}"
+        );
+        // The failed transaction did not change the data
+        assert!(
+            matches!(sensor.borrow().log.last(), Some(Access::Read(0x3b, data)) if data.iter().all(|byte| *byte == 0xff))
+        );
+
+        // The reads of the data registers fail, init does not read them
+        let sensor = Sensor::shared();
+        sensor.borrow_mut().fault = Some(Fault::Register(0x3b));
+        assert!(
+            matches!(read_three(&sensor), Err(Error::Bus(ErrorKind::Other))),
+            "{name}"
+        );
+        assert_eq!(sensor.borrow().log.len(), transactions - 2, "{name}");
+
+        // A missing sensor reads as 0xff
+        let sensor = Sensor::shared();
+        sensor.borrow_mut().fault = Some(Fault::Disconnected);
+        assert!(
+            matches!(read_three(&sensor), Err(Error::UnexpectedWhoAmI(0xff))),
+            "{name}"
+        );
+    }
+}
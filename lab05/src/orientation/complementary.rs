@@ -0,0 +1,131 @@
+//! Complementary filter.
+//!
+//! The filter integrates the gyro to get the new angles and then
+//! moves them a little towards the angles given by the accelerometer:
+//!
+//! angle = alpha * (angle + rate * dt) + (1 - alpha) * accel_angle
+//!
+//! The gyro is trusted for fast changes and the accelerometer for
+//! slow changes. `alpha` is computed from the filter's *time constant*
+//! `tau` and the time between the samples, `alpha = tau / (tau + dt)`,
+//! so the filter behaves the same at any sample rate. The accelerometer
+//! corrects a gyro error in about `tau` seconds. A larger time constant
+//! rejects more vibrations, but corrects the drift slower.
+//!
+//! The accelerometer cannot correct the yaw. If the samples have a
+//! magnetic field, the yaw is corrected using the tilt compensated
+//! heading, otherwise it drifts.
+
+use embassy_time::Instant;
+
+use crate::{
+    mpu6500::heading,
+    orientation::{EulerAngles, Filter, Orientation, Sample, accel_angles, elapsed, wrap_degrees},
+};
+
+/// The default time constant in s
+pub const DEFAULT_TIME_CONSTANT: f32 = 0.5;
+
+/// The smallest value of `cos(pitch)` used to convert the gyro rates.
+///
+/// The roll and yaw rates are not defined at a pitch of 90 deg.
+const MIN_COS_PITCH: f32 = 0.001;
+
+/// Complementary orientation filter.
+pub struct ComplementaryFilter {
+    /// The time constant in s
+    time_constant: f32,
+    /// The current angles, `None` before the first sample
+    angles: Option<EulerAngles>,
+    /// The time of the previous sample
+    last: Instant,
+}
+
+impl ComplementaryFilter {
+    /// Creates a new filter with the `time_constant` in s
+    pub fn new(time_constant: f32) -> ComplementaryFilter {
+        ComplementaryFilter {
+            time_constant,
+            angles: None,
+            last: Instant::MIN,
+        }
+    }
+
+    /// Sets the time constant in s
+    pub fn set_time_constant(&mut self, time_constant: f32) {
+        self.time_constant = time_constant;
+    }
+
+    /// Returns the time constant in s
+    pub fn time_constant(&self) -> f32 {
+        self.time_constant
+    }
+}
+
+impl Default for ComplementaryFilter {
+    fn default() -> ComplementaryFilter {
+        ComplementaryFilter::new(DEFAULT_TIME_CONSTANT)
+    }
+}
+
+impl Filter for ComplementaryFilter {
+    fn update(&mut self, sample: Sample) -> Orientation {
+        let mut target = accel_angles(sample.accel);
+        // The heading is clockwise, the yaw is counterclockwise
+        let mag_yaw = sample
+            .mag
+            .map(|mag| wrap_degrees(-heading(sample.accel, mag)));
+        target.yaw = mag_yaw.unwrap_or(0f32);
+
+        let Some(angles) = self.angles else {
+            self.angles = Some(target);
+            self.last = sample.timestamp;
+            return self.orientation();
+        };
+        let dt = elapsed(self.last, sample.timestamp);
+        self.last = sample.timestamp;
+
+        // Convert the rotation rates around the sensor's axes to
+        // roll, pitch and yaw rates.
+        let (sin_roll, cos_roll) = libm::sincosf(angles.roll.to_radians());
+        let (sin_pitch, cos_pitch) = libm::sincosf(angles.pitch.to_radians());
+        let cos_pitch = if cos_pitch.abs() < MIN_COS_PITCH {
+            MIN_COS_PITCH.copysign(cos_pitch)
+        } else {
+            cos_pitch
+        };
+        let tan_pitch = sin_pitch / cos_pitch;
+        let gyro = sample.gyro;
+        let roll_rate = gyro.x + (sin_roll * gyro.y + cos_roll * gyro.z) * tan_pitch;
+        let pitch_rate = cos_roll * gyro.y - sin_roll * gyro.z;
+        let yaw_rate = (sin_roll * gyro.y + cos_roll * gyro.z) / cos_pitch;
+
+        let roll = angles.roll + roll_rate * dt;
+        let pitch = angles.pitch + pitch_rate * dt;
+        let yaw = angles.yaw + yaw_rate * dt;
+
+        // The weight of the accelerometer
+        let sum = self.time_constant + dt;
+        let weight = if sum > 0f32 { dt / sum } else { 1f32 };
+
+        // The roll and the yaw wrap around at 180 deg, the filter
+        // moves them on the shortest way towards the target.
+        self.angles = Some(EulerAngles {
+            roll: wrap_degrees(roll + weight * wrap_degrees(target.roll - roll)),
+            pitch: (pitch + weight * (target.pitch - pitch)).clamp(-90f32, 90f32),
+            yaw: match mag_yaw {
+                Some(mag_yaw) => wrap_degrees(yaw + weight * wrap_degrees(mag_yaw - yaw)),
+                None => wrap_degrees(yaw),
+            },
+        });
+        self.orientation()
+    }
+
+    fn orientation(&self) -> Orientation {
+        Orientation::from_euler(self.angles.unwrap_or_default())
+    }
+
+    fn reset(&mut self) {
+        self.angles = None;
+    }
+}
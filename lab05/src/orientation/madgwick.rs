@@ -0,0 +1,245 @@
+//! Madgwick AHRS filter.
+//!
+//! The filter stores the orientation as a quaternion. For every sample
+//! it integrates the gyro and then takes a gradient descent step that
+//! moves the quaternion towards the orientation in which the measured
+//! acceleration (and magnetic field) match the expected gravity (and
+//! earth's magnetic field).
+//!
+//! The size of the step is `beta`, in rad/s. It is the gyro error that
+//! the filter can correct: a larger value corrects the drift faster,
+//! but lets more of the accelerometer's noise and vibrations through.
+//! Madgwick suggests `beta = sqrt(3 / 4) * gyro_error`.
+//!
+//! The algorithm is the one published by Sebastian Madgwick in
+//! *An efficient orientation filter for inertial and inertial/magnetic
+//! sensor arrays* (2010).
+
+use embassy_time::Instant;
+
+use crate::{
+    mpu6500::{Acceleration, Gyro, MagneticField, heading},
+    orientation::{Filter, Orientation, Quaternion, Sample, accel_angles, elapsed, wrap_degrees},
+};
+
+/// The default gain in rad/s
+pub const DEFAULT_BETA: f32 = 0.1;
+
+/// Madgwick orientation filter.
+pub struct Madgwick {
+    /// The gain in rad/s
+    beta: f32,
+    /// The current orientation, `None` before the first sample
+    quaternion: Option<Quaternion>,
+    /// The time of the previous sample
+    last: Instant,
+}
+
+impl Madgwick {
+    /// Creates a new filter with the gain `beta` in rad/s
+    pub fn new(beta: f32) -> Madgwick {
+        Madgwick {
+            beta,
+            quaternion: None,
+            last: Instant::MIN,
+        }
+    }
+
+    /// Sets the gain in rad/s
+    pub fn set_beta(&mut self, beta: f32) {
+        self.beta = beta;
+    }
+
+    /// Returns the gain in rad/s
+    pub fn beta(&self) -> f32 {
+        self.beta
+    }
+}
+
+impl Default for Madgwick {
+    fn default() -> Madgwick {
+        Madgwick::new(DEFAULT_BETA)
+    }
+}
+
+impl Filter for Madgwick {
+    fn update(&mut self, sample: Sample) -> Orientation {
+        let Some(q) = self.quaternion else {
+            let mut angles = accel_angles(sample.accel);
+            if let Some(mag) = sample.mag {
+                // The heading is clockwise, the yaw is counterclockwise
+                angles.yaw = wrap_degrees(-heading(sample.accel, mag));
+            }
+            self.quaternion = Some(Quaternion::from_euler(angles));
+            self.last = sample.timestamp;
+            return self.orientation();
+        };
+        let dt = elapsed(self.last, sample.timestamp);
+        self.last = sample.timestamp;
+
+        // The rate of change of the quaternion given by the gyro
+        let mut q_dot = gyro_rate(q, sample.gyro);
+
+        let step = match sample.mag {
+            Some(mag) if mag.x != 0f32 || mag.y != 0f32 || mag.z != 0f32 => {
+                marg_step(q, sample.accel, mag)
+            }
+            _ => imu_step(q, sample.accel),
+        };
+        if let Some(step) = step {
+            q_dot = [
+                q_dot[0] - self.beta * step.w,
+                q_dot[1] - self.beta * step.x,
+                q_dot[2] - self.beta * step.y,
+                q_dot[3] - self.beta * step.z,
+            ];
+        }
+
+        let q = Quaternion {
+            w: q.w + q_dot[0] * dt,
+            x: q.x + q_dot[1] * dt,
+            y: q.y + q_dot[2] * dt,
+            z: q.z + q_dot[3] * dt,
+        };
+        self.quaternion = Some(q.normalize());
+        self.orientation()
+    }
+
+    fn orientation(&self) -> Orientation {
+        Orientation::from_quaternion(self.quaternion.unwrap_or_default())
+    }
+
+    fn reset(&mut self) {
+        self.quaternion = None;
+    }
+}
+
+/// Returns the rate of change of the quaternion `q` given by the gyro,
+/// `0.5 * q * (0, gx, gy, gz)`, with the gyro in rad/s.
+fn gyro_rate(q: Quaternion, gyro: Gyro) -> [f32; 4] {
+    let (gx, gy, gz) = (
+        gyro.x.to_radians(),
+        gyro.y.to_radians(),
+        gyro.z.to_radians(),
+    );
+    [
+        0.5 * (-q.x * gx - q.y * gy - q.z * gz),
+        0.5 * (q.w * gx + q.y * gz - q.z * gy),
+        0.5 * (q.w * gy - q.x * gz + q.z * gx),
+        0.5 * (q.w * gz + q.x * gy - q.y * gx),
+    ]
+}
+
+/// Returns the normalized gradient of the gravity error,
+/// `None` if the acceleration is 0 (free fall).
+fn imu_step(q: Quaternion, accel: Acceleration) -> Option<Quaternion> {
+    let [ax, ay, az] = normalize([accel.x, accel.y, accel.z])?;
+    let Quaternion {
+        w: q0,
+        x: q1,
+        y: q2,
+        z: q3,
+    } = q;
+    let (q0q0, q1q1, q2q2, q3q3) = (q0 * q0, q1 * q1, q2 * q2, q3 * q3);
+
+    let s0 = 4.0 * q0 * q2q2 + 2.0 * q2 * ax + 4.0 * q0 * q1q1 - 2.0 * q1 * ay;
+    let s1 = 4.0 * q1 * q3q3 - 2.0 * q3 * ax + 4.0 * q0q0 * q1 - 2.0 * q0 * ay - 4.0 * q1
+        + 8.0 * q1 * q1q1
+        + 8.0 * q1 * q2q2
+        + 4.0 * q1 * az;
+    let s2 = 4.0 * q0q0 * q2 + 2.0 * q0 * ax + 4.0 * q2 * q3q3 - 2.0 * q3 * ay - 4.0 * q2
+        + 8.0 * q2 * q1q1
+        + 8.0 * q2 * q2q2
+        + 4.0 * q2 * az;
+    let s3 = 4.0 * q1q1 * q3 - 2.0 * q1 * ax + 4.0 * q2q2 * q3 - 2.0 * q2 * ay;
+    normalize_step([s0, s1, s2, s3])
+}
+
+/// Returns the normalized gradient of the gravity and magnetic field
+/// error, `None` if the acceleration is 0 (free fall).
+fn marg_step(q: Quaternion, accel: Acceleration, mag: MagneticField) -> Option<Quaternion> {
+    let [ax, ay, az] = normalize([accel.x, accel.y, accel.z])?;
+    let Some([mx, my, mz]) = normalize([mag.x, mag.y, mag.z]) else {
+        return imu_step(q, accel);
+    };
+    let Quaternion {
+        w: q0,
+        x: q1,
+        y: q2,
+        z: q3,
+    } = q;
+    let (q0q0, q0q1, q0q2, q0q3) = (q0 * q0, q0 * q1, q0 * q2, q0 * q3);
+    let (q1q1, q1q2, q1q3) = (q1 * q1, q1 * q2, q1 * q3);
+    let (q2q2, q2q3, q3q3) = (q2 * q2, q2 * q3, q3 * q3);
+
+    // The direction of the earth's magnetic field, it only has
+    // a horizontal (bx) and a vertical (bz) component
+    let hx = mx * q0q0 - 2.0 * q0 * my * q3
+        + 2.0 * q0 * mz * q2
+        + mx * q1q1
+        + 2.0 * q1 * my * q2
+        + 2.0 * q1 * mz * q3
+        - mx * q2q2
+        - mx * q3q3;
+    let hy = 2.0 * q0 * mx * q3 + my * q0q0 - 2.0 * q0 * mz * q1 + 2.0 * q1 * mx * q2 - my * q1q1
+        + my * q2q2
+        + 2.0 * q2 * mz * q3
+        - my * q3q3;
+    let bx2 = libm::sqrtf(hx * hx + hy * hy);
+    let bz2 = -2.0 * q0 * mx * q2 + 2.0 * q0 * my * q1 + mz * q0q0 + 2.0 * q1 * mx * q3 - mz * q1q1
+        + 2.0 * q2 * my * q3
+        - mz * q2q2
+        + mz * q3q3;
+    let (bx4, bz4) = (2.0 * bx2, 2.0 * bz2);
+
+    // The errors between the expected and the measured directions
+    let fx = 2.0 * q1q3 - 2.0 * q0q2 - ax;
+    let fy = 2.0 * q0q1 + 2.0 * q2q3 - ay;
+    let fz = 1.0 - 2.0 * q1q1 - 2.0 * q2q2 - az;
+    let fmx = bx2 * (0.5 - q2q2 - q3q3) + bz2 * (q1q3 - q0q2) - mx;
+    let fmy = bx2 * (q1q2 - q0q3) + bz2 * (q0q1 + q2q3) - my;
+    let fmz = bx2 * (q0q2 + q1q3) + bz2 * (0.5 - q1q1 - q2q2) - mz;
+
+    let s0 = -2.0 * q2 * fx + 2.0 * q1 * fy - bz2 * q2 * fmx
+        + (-bx2 * q3 + bz2 * q1) * fmy
+        + bx2 * q2 * fmz;
+    let s1 = 2.0 * q3 * fx + 2.0 * q0 * fy - 4.0 * q1 * fz
+        + bz2 * q3 * fmx
+        + (bx2 * q2 + bz2 * q0) * fmy
+        + (bx2 * q3 - bz4 * q1) * fmz;
+    let s2 = -2.0 * q0 * fx + 2.0 * q3 * fy - 4.0 * q2 * fz
+        + (-bx4 * q2 - bz2 * q0) * fmx
+        + (bx2 * q1 + bz2 * q3) * fmy
+        + (bx2 * q0 - bz4 * q2) * fmz;
+    let s3 = 2.0 * q1 * fx
+        + 2.0 * q2 * fy
+        + (-bx4 * q3 + bz2 * q1) * fmx
+        + (-bx2 * q0 + bz2 * q2) * fmy
+        + bx2 * q1 * fmz;
+    normalize_step([s0, s1, s2, s3])
+}
+
+/// Returns the vector scaled to a length of 1, `None` if it is 0.
+fn normalize(v: [f32; 3]) -> Option<[f32; 3]> {
+    let norm = libm::sqrtf(v[0] * v[0] + v[1] * v[1] + v[2] * v[2]);
+    if norm == 0f32 {
+        None
+    } else {
+        Some(v.map(|value| value / norm))
+    }
+}
+
+/// Returns the gradient scaled to a length of 1, `None` if it is 0
+/// (the orientation already matches the measurements).
+fn normalize_step(s: [f32; 4]) -> Option<Quaternion> {
+    if s.iter().all(|value| *value == 0f32) {
+        return None;
+    }
+    let step = Quaternion {
+        w: s[0],
+        x: s[1],
+        y: s[2],
+        z: s[3],
+    };
+    Some(step.normalize())
+}
@@ -0,0 +1,253 @@
+//! Orientation estimation from MPU 6500 samples.
+//!
+//! The gyro measures how fast the sensor rotates. Integrating it gives
+//! the orientation, but the small gyro errors add up and the orientation
+//! *drifts*. The accelerometer measures the gravity, which gives the
+//! roll and pitch without drift, but it is disturbed by every movement.
+//!
+//! The filters of this module fuse the two sensors: they integrate the
+//! gyro and slowly pull the result towards the orientation given by the
+//! accelerometer (and the magnetometer, if available, for the yaw):
+//! - [`ComplementaryFilter`] - works directly with roll, pitch and yaw
+//!   angles, simple and cheap
+//! - [`Madgwick`] - the Madgwick AHRS filter, works with a quaternion
+//!   and has no problems when the pitch is close to 90 deg
+//!
+//! Both filters implement the [`Filter`] trait, so an application
+//! can switch between them.
+//!
+//! The angles use the sensor's axes, as printed on the board. A positive
+//! angle is a counterclockwise rotation around the axis, when the axis
+//! points towards the viewer. When the sensor lies flat, with the chip up,
+//! all the angles are 0.
+//!
+//! ```ignore
+//! let mut filter = Madgwick::new(DEFAULT_BETA);
+//! loop {
+//!     let measurement = mpu6500.read_all().await?;
+//!     let orientation = filter.update(Sample::new(measurement, Instant::now()));
+//!     info!("Roll {} Pitch {} Yaw {}", orientation.roll, orientation.pitch, orientation.yaw);
+//! }
+//! ```
+
+mod complementary;
+mod madgwick;
+
+#[cfg(test)]
+mod tests;
+
+use embassy_time::Instant;
+
+use crate::mpu6500::{Acceleration, Gyro, MagneticField, Measurement, NineAxisMeasurement};
+
+pub use complementary::{ComplementaryFilter, DEFAULT_TIME_CONSTANT};
+pub use madgwick::{DEFAULT_BETA, Madgwick};
+
+/// A gyro and accelerometer sample and the time when it was taken.
+#[derive(Copy, Clone, Debug, defmt::Format)]
+pub struct Sample {
+    /// The acceleration in g
+    pub accel: Acceleration,
+    /// The gyro in deg/s
+    pub gyro: Gyro,
+    /// The magnetic field in uT, if a magnetometer is used
+    pub mag: Option<MagneticField>,
+    /// The time when the sample was taken
+    pub timestamp: Instant,
+}
+
+impl Sample {
+    /// Creates a sample from a measurement read with
+    /// `Mpu6500::read_all`
+    pub fn new(measurement: Measurement, timestamp: Instant) -> Sample {
+        Sample {
+            accel: measurement.accel,
+            gyro: measurement.gyro,
+            mag: None,
+            timestamp,
+        }
+    }
+
+    /// Creates a sample from a measurement read with
+    /// `Mpu6500::read_9_axis`
+    pub fn with_mag(measurement: NineAxisMeasurement, timestamp: Instant) -> Sample {
+        Sample {
+            accel: measurement.accel,
+            gyro: measurement.gyro,
+            mag: Some(measurement.mag),
+            timestamp,
+        }
+    }
+}
+
+/// The orientation as roll, pitch and yaw angles, in deg.
+///
+/// The angles are applied in the yaw, pitch, roll order (the
+/// Z, Y, X aerospace sequence).
+#[derive(Copy, Clone, Default, PartialEq, Debug, defmt::Format)]
+pub struct EulerAngles {
+    /// The rotation around the X axis, -180 ... 180 deg
+    pub roll: f32,
+    /// The rotation around the Y axis, -90 ... 90 deg
+    pub pitch: f32,
+    /// The rotation around the Z axis, -180 ... 180 deg
+    pub yaw: f32,
+}
+
+/// The orientation as a unit quaternion.
+///
+/// The quaternion rotates vectors from the sensor's axes
+/// to the earth's axes.
+#[derive(Copy, Clone, PartialEq, Debug, defmt::Format)]
+pub struct Quaternion {
+    pub w: f32,
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+}
+
+impl Default for Quaternion {
+    fn default() -> Quaternion {
+        Quaternion::IDENTITY
+    }
+}
+
+impl Quaternion {
+    /// The quaternion of the sensor lying flat
+    pub const IDENTITY: Quaternion = Quaternion {
+        w: 1f32,
+        x: 0f32,
+        y: 0f32,
+        z: 0f32,
+    };
+
+    /// Creates the quaternion of the roll, pitch and yaw angles.
+    pub fn from_euler(angles: EulerAngles) -> Quaternion {
+        let (sr, cr) = libm::sincosf(angles.roll.to_radians() / 2f32);
+        let (sp, cp) = libm::sincosf(angles.pitch.to_radians() / 2f32);
+        let (sy, cy) = libm::sincosf(angles.yaw.to_radians() / 2f32);
+        Quaternion {
+            w: cr * cp * cy + sr * sp * sy,
+            x: sr * cp * cy - cr * sp * sy,
+            y: cr * sp * cy + sr * cp * sy,
+            z: cr * cp * sy - sr * sp * cy,
+        }
+    }
+
+    /// Returns the roll, pitch and yaw angles of the quaternion.
+    pub fn to_euler(&self) -> EulerAngles {
+        let Quaternion { w, x, y, z } = *self;
+        let roll = libm::atan2f(2f32 * (w * x + y * z), 1f32 - 2f32 * (x * x + y * y));
+        // Rounding errors might push the value slightly outside -1 ... 1
+        let pitch = libm::asinf((2f32 * (w * y - z * x)).clamp(-1f32, 1f32));
+        let yaw = libm::atan2f(2f32 * (w * z + x * y), 1f32 - 2f32 * (y * y + z * z));
+        EulerAngles {
+            roll: roll.to_degrees(),
+            pitch: pitch.to_degrees(),
+            yaw: yaw.to_degrees(),
+        }
+    }
+
+    /// Returns the quaternion scaled to a length of 1.
+    ///
+    /// A zero quaternion is returned unchanged.
+    pub fn normalize(&self) -> Quaternion {
+        let norm =
+            libm::sqrtf(self.w * self.w + self.x * self.x + self.y * self.y + self.z * self.z);
+        if norm == 0f32 {
+            return *self;
+        }
+        Quaternion {
+            w: self.w / norm,
+            x: self.x / norm,
+            y: self.y / norm,
+            z: self.z / norm,
+        }
+    }
+}
+
+/// The orientation computed by a filter.
+#[derive(Copy, Clone, Default, PartialEq, Debug, defmt::Format)]
+pub struct Orientation {
+    /// The rotation around the X axis in deg
+    pub roll: f32,
+    /// The rotation around the Y axis in deg
+    pub pitch: f32,
+    /// The rotation around the Z axis in deg
+    pub yaw: f32,
+    /// The same orientation as a quaternion
+    pub quaternion: Quaternion,
+}
+
+impl Orientation {
+    /// Creates the orientation of a quaternion
+    pub fn from_quaternion(quaternion: Quaternion) -> Orientation {
+        let EulerAngles { roll, pitch, yaw } = quaternion.to_euler();
+        Orientation {
+            roll,
+            pitch,
+            yaw,
+            quaternion,
+        }
+    }
+
+    /// Creates the orientation of the roll, pitch and yaw angles
+    pub fn from_euler(angles: EulerAngles) -> Orientation {
+        Orientation {
+            roll: angles.roll,
+            pitch: angles.pitch,
+            yaw: angles.yaw,
+            quaternion: Quaternion::from_euler(angles),
+        }
+    }
+}
+
+/// An orientation filter.
+pub trait Filter {
+    /// Adds a sample and returns the new orientation
+    ///
+    /// The first sample (after [`Filter::reset`]) sets the roll and the
+    /// pitch using only the acceleration. The following samples are fused
+    /// using the time elapsed since the previous sample.
+    fn update(&mut self, sample: Sample) -> Orientation;
+
+    /// Returns the current orientation
+    fn orientation(&self) -> Orientation;
+
+    /// Forgets the orientation, the next sample starts the filter again
+    fn reset(&mut self);
+}
+
+/// Returns the roll and pitch given by the acceleration, in deg.
+///
+/// The yaw is 0, the gravity does not depend on it.
+pub fn accel_angles(accel: Acceleration) -> EulerAngles {
+    let roll = libm::atan2f(accel.y, accel.z);
+    let pitch = libm::atan2f(-accel.x, libm::sqrtf(accel.y * accel.y + accel.z * accel.z));
+    EulerAngles {
+        roll: roll.to_degrees(),
+        pitch: pitch.to_degrees(),
+        yaw: 0f32,
+    }
+}
+
+/// Returns the time elapsed between two samples in s.
+///
+/// Samples that are not newer than the previous sample
+/// do not move the filter forward.
+fn elapsed(previous: Instant, timestamp: Instant) -> f32 {
+    timestamp
+        .checked_duration_since(previous)
+        .map(|duration| duration.as_micros() as f32 / 1_000_000f32)
+        .unwrap_or(0f32)
+}
+
+/// Wraps an angle in deg to the -180 ... 180 deg interval.
+pub(crate) fn wrap_degrees(angle: f32) -> f32 {
+    let wrapped = libm::remainderf(angle, 360f32);
+    if wrapped <= -180f32 {
+        wrapped + 360f32
+    } else {
+        wrapped
+    }
+}
@@ -0,0 +1,387 @@
+//! Host tests for the orientation filters.
+//!
+//! The tests feed the filters synthetic traces: the sensor is moved
+//! along a known orientation and the samples are computed from it,
+//! with an optional gyro bias and a deterministic noise.
+//!
+//! `assets/traces/tilt.raw` is a trace in the format of a capture from
+//! the board: the `ACCEL_XOUT_H` ... `GYRO_ZOUT_L` registers of every
+//! sample, read at 100 Hz with the ±2 g and ±250 deg/s scales. The
+//! board rests flat for 2 s, rolls to 30 deg in 1 s, pitches to -20 deg
+//! in 1 s and returns flat in 1 s, resting for 3 s after every move.
+//! The gyro has a bias below 1 deg/s and the accelerometer shakes while
+//! the board moves. No capture from the board is available yet, so the
+//! trace is generated from this model; a capture of the same moves can
+//! replace it as long as it keeps the format.
+
+extern crate std;
+
+use std::boxed::Box;
+
+use embassy_time::Instant;
+
+use crate::{
+    mpu6500::{AccelScale, Acceleration, Gyro, GyroScale, MagneticField},
+    orientation::{
+        ComplementaryFilter, DEFAULT_BETA, DEFAULT_TIME_CONSTANT, EulerAngles, Filter, Madgwick,
+        Quaternion, Sample, accel_angles, wrap_degrees,
+    },
+};
+
+/// The time between two samples in ms (100 Hz)
+const PERIOD_MS: u64 = 10;
+
+/// The earth's magnetic field in uT, the X axis points north and
+/// the Z axis up
+const EARTH_FIELD: [f32; 3] = [20f32, 0f32, -40f32];
+
+/// Rotates a vector from the earth's axes to the sensor's axes.
+fn to_sensor(angles: EulerAngles, v: [f32; 3]) -> [f32; 3] {
+    let Quaternion { w, x, y, z } = Quaternion::from_euler(angles);
+    // The transposed rotation matrix of the quaternion
+    [
+        (1f32 - 2f32 * (y * y + z * z)) * v[0]
+            + 2f32 * (x * y + w * z) * v[1]
+            + 2f32 * (x * z - w * y) * v[2],
+        2f32 * (x * y - w * z) * v[0]
+            + (1f32 - 2f32 * (x * x + z * z)) * v[1]
+            + 2f32 * (y * z + w * x) * v[2],
+        2f32 * (x * z + w * y) * v[0]
+            + 2f32 * (y * z - w * x) * v[1]
+            + (1f32 - 2f32 * (x * x + y * y)) * v[2],
+    ]
+}
+
+/// A deterministic pseudo random generator that returns
+/// values between -1 and 1
+struct Noise(u32);
+
+impl Noise {
+    fn next(&mut self) -> f32 {
+        self.0 = self.0.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+        (self.0 >> 8) as f32 / (1u32 << 23) as f32 - 1f32
+    }
+}
+
+/// Builds the sample of the sensor at rest in the `angles` orientation,
+/// rotating with `gyro` deg/s around its axes.
+fn sample(index: u64, angles: EulerAngles, gyro: [f32; 3], mag: bool) -> Sample {
+    let [ax, ay, az] = to_sensor(angles, [0f32, 0f32, 1f32]);
+    let [mx, my, mz] = to_sensor(angles, EARTH_FIELD);
+    Sample {
+        accel: Acceleration {
+            x: ax,
+            y: ay,
+            z: az,
+        },
+        gyro: Gyro {
+            x: gyro[0],
+            y: gyro[1],
+            z: gyro[2],
+        },
+        mag: mag.then_some(MagneticField {
+            x: mx,
+            y: my,
+            z: mz,
+        }),
+        timestamp: Instant::from_millis(index * PERIOD_MS),
+    }
+}
+
+fn angles(roll: f32, pitch: f32, yaw: f32) -> EulerAngles {
+    EulerAngles { roll, pitch, yaw }
+}
+
+/// Checks that two angles in deg differ less than `tolerance`.
+fn assert_angle(name: &str, actual: f32, expected: f32, tolerance: f32) {
+    assert!(
+        wrap_degrees(actual - expected).abs() < tolerance,
+        "{name}: {actual} deg is not {expected} +/- {tolerance} deg"
+    );
+}
+
+/// Runs a filter with samples of the sensor in the same orientation
+/// for `count` samples, starting with the sample `start`.
+fn hold(filter: &mut dyn Filter, start: u64, count: u64, angles: EulerAngles, mag: bool) {
+    for index in start..start + count {
+        filter.update(sample(index, angles, [0f32; 3], mag));
+    }
+}
+
+/// The tilt trace, see the module documentation
+const TILT_TRACE: &[u8] = include_bytes!("../../assets/traces/tilt.raw");
+
+/// Decodes the sample `index` of the tilt trace.
+fn tilt_trace_sample(index: usize) -> Sample {
+    let bytes = &TILT_TRACE[index * 14..(index + 1) * 14];
+    let raw = |register: usize| i16::from_be_bytes([bytes[register * 2], bytes[register * 2 + 1]]);
+    Sample {
+        accel: Acceleration {
+            x: AccelScale::G2.to_g(raw(0)),
+            y: AccelScale::G2.to_g(raw(1)),
+            z: AccelScale::G2.to_g(raw(2)),
+        },
+        // The register 3 holds the temperature
+        gyro: Gyro {
+            x: GyroScale::Gs250.to_deg_s(raw(4)),
+            y: GyroScale::Gs250.to_deg_s(raw(5)),
+            z: GyroScale::Gs250.to_deg_s(raw(6)),
+        },
+        mag: None,
+        timestamp: Instant::from_millis(index as u64 * PERIOD_MS),
+    }
+}
+
+/// Returns both filters, with the default gains
+fn filters() -> [(&'static str, Box<dyn Filter>); 2] {
+    [
+        ("complementary", Box::new(ComplementaryFilter::default())),
+        ("madgwick", Box::new(Madgwick::default())),
+    ]
+}
+
+#[test]
+fn quaternion_and_euler_angles_round_trip() {
+    for expected in [
+        angles(0f32, 0f32, 0f32),
+        angles(30f32, -20f32, 45f32),
+        angles(-150f32, 60f32, -170f32),
+        angles(10f32, -85f32, 90f32),
+    ] {
+        let actual = Quaternion::from_euler(expected).to_euler();
+        assert_angle("quaternion", actual.roll, expected.roll, 0.01);
+        assert_angle("quaternion", actual.pitch, expected.pitch, 0.01);
+        assert_angle("quaternion", actual.yaw, expected.yaw, 0.01);
+    }
+    assert_eq!(
+        Quaternion::from_euler(angles(0f32, 0f32, 0f32)),
+        Quaternion::IDENTITY
+    );
+}
+
+#[test]
+fn accel_angles_give_roll_and_pitch() {
+    let tilt = angles(30f32, -20f32, 70f32);
+    let [x, y, z] = to_sensor(tilt, [0f32, 0f32, 1f32]);
+    let actual = accel_angles(Acceleration { x, y, z });
+    assert_angle("accel", actual.roll, 30f32, 0.01);
+    assert_angle("accel", actual.pitch, -20f32, 0.01);
+    assert_eq!(actual.yaw, 0f32);
+}
+
+#[test]
+fn first_sample_sets_the_orientation() {
+    for (name, mut filter) in filters() {
+        let orientation = filter.update(sample(0, angles(30f32, -20f32, 0f32), [0f32; 3], false));
+        assert_angle(name, orientation.roll, 30f32, 0.1);
+        assert_angle(name, orientation.pitch, -20f32, 0.1);
+        assert_angle(name, orientation.yaw, 0f32, 0.1);
+        assert_eq!(filter.orientation(), orientation, "{name}");
+
+        // With a magnetometer, the yaw is set too
+        filter.reset();
+        let orientation = filter.update(sample(0, angles(10f32, 5f32, 60f32), [0f32; 3], true));
+        assert_angle(name, orientation.roll, 10f32, 0.1);
+        assert_angle(name, orientation.pitch, 5f32, 0.1);
+        assert_angle(name, orientation.yaw, 60f32, 0.1);
+    }
+}
+
+#[test]
+fn static_tilt_converges_to_the_accelerometer() {
+    let tilt = angles(25f32, -15f32, 0f32);
+    for (name, mut filter) in filters() {
+        // The filter starts flat and the sensor is tilted
+        // without the gyro seeing it
+        hold(filter.as_mut(), 0, 1, angles(0f32, 0f32, 0f32), false);
+        hold(filter.as_mut(), 1, 1000, tilt, false);
+        let orientation = filter.orientation();
+        assert_angle(name, orientation.roll, tilt.roll, 0.5);
+        assert_angle(name, orientation.pitch, tilt.pitch, 0.5);
+        assert!(
+            orientation.yaw.abs() < 5f32,
+            "{name} yaw {}",
+            orientation.yaw
+        );
+    }
+}
+
+#[test]
+fn gyro_is_integrated_into_the_yaw() {
+    for (name, mut filter) in filters() {
+        // 90 deg/s around Z for 1 s
+        for index in 0..=100 {
+            let yaw = 0.9 * index as f32;
+            filter.update(sample(
+                index,
+                angles(0f32, 0f32, yaw),
+                [0f32, 0f32, 90f32],
+                false,
+            ));
+        }
+        let orientation = filter.orientation();
+        assert_angle(name, orientation.yaw, 90f32, 1f32);
+        assert_angle(name, orientation.roll, 0f32, 0.5);
+        assert_angle(name, orientation.pitch, 0f32, 0.5);
+    }
+}
+
+#[test]
+fn roll_rotation_is_tracked() {
+    for (name, mut filter) in filters() {
+        // 45 deg/s around X for 1 s
+        for index in 0..=100 {
+            let roll = 0.45 * index as f32;
+            filter.update(sample(
+                index,
+                angles(roll, 0f32, 0f32),
+                [45f32, 0f32, 0f32],
+                false,
+            ));
+        }
+        let orientation = filter.orientation();
+        assert_angle(name, orientation.roll, 45f32, 1f32);
+        assert_angle(name, orientation.pitch, 0f32, 1f32);
+    }
+}
+
+#[test]
+fn gyro_bias_is_corrected_by_the_accelerometer() {
+    // A 2 deg/s bias on X would add up to 40 deg in 20 s
+    let bias = [2f32, 0f32, 0f32];
+    for (name, mut filter) in filters() {
+        for index in 0..2000 {
+            filter.update(sample(index, angles(0f32, 0f32, 0f32), bias, false));
+        }
+        let orientation = filter.orientation();
+        assert_angle(name, orientation.roll, 0f32, 1.5);
+        assert_angle(name, orientation.pitch, 0f32, 0.5);
+    }
+}
+
+#[test]
+fn noise_stays_bounded() {
+    let tilt = angles(-20f32, 35f32, 0f32);
+    for (name, mut filter) in filters() {
+        let mut noise = Noise(0x1234_5678);
+        for index in 0..1000 {
+            let mut sample = sample(index, tilt, [0.5, -0.3, 0.2], false);
+            sample.accel.x += 0.05 * noise.next();
+            sample.accel.y += 0.05 * noise.next();
+            sample.accel.z += 0.05 * noise.next();
+            sample.gyro.x += noise.next();
+            sample.gyro.y += noise.next();
+            sample.gyro.z += noise.next();
+            let orientation = filter.update(sample);
+            if index > 300 {
+                assert_angle(name, orientation.roll, tilt.roll, 3f32);
+                assert_angle(name, orientation.pitch, tilt.pitch, 3f32);
+            }
+        }
+    }
+}
+
+#[test]
+fn magnetometer_corrects_the_yaw() {
+    let position = angles(10f32, -5f32, 60f32);
+    for (name, mut filter) in filters() {
+        // The first sample has no magnetic field, the filter starts with
+        // a yaw of 0 and the magnetometer pulls it to 60 deg
+        filter.update(sample(0, position, [0f32; 3], false));
+        assert_angle(name, filter.orientation().yaw, 0f32, 0.1);
+        hold(filter.as_mut(), 1, 3000, position, true);
+        let orientation = filter.orientation();
+        assert_angle(name, orientation.yaw, 60f32, 1f32);
+        assert_angle(name, orientation.roll, 10f32, 1f32);
+        assert_angle(name, orientation.pitch, -5f32, 1f32);
+    }
+}
+
+#[test]
+fn samples_without_elapsed_time_do_not_move_the_filter() {
+    for (_, mut filter) in filters() {
+        filter.update(sample(10, angles(0f32, 0f32, 0f32), [0f32; 3], false));
+        let before = filter.orientation();
+        // Same timestamp and an older timestamp
+        filter.update(sample(
+            10,
+            angles(0f32, 0f32, 0f32),
+            [0f32, 0f32, 500f32],
+            false,
+        ));
+        filter.update(sample(
+            5,
+            angles(0f32, 0f32, 0f32),
+            [0f32, 0f32, 500f32],
+            false,
+        ));
+        assert_eq!(filter.orientation(), before);
+    }
+}
+
+#[test]
+fn reset_starts_the_filter_again() {
+    for (name, mut filter) in filters() {
+        hold(filter.as_mut(), 0, 100, angles(0f32, 0f32, 0f32), false);
+        filter.reset();
+        let orientation = filter.update(sample(100, angles(-40f32, 20f32, 0f32), [0f32; 3], false));
+        assert_angle(name, orientation.roll, -40f32, 0.1);
+        assert_angle(name, orientation.pitch, 20f32, 0.1);
+    }
+}
+
+#[test]
+fn gains_are_tunable() {
+    let mut complementary = ComplementaryFilter::default();
+    assert_eq!(complementary.time_constant(), DEFAULT_TIME_CONSTANT);
+    complementary.set_time_constant(2f32);
+    assert_eq!(complementary.time_constant(), 2f32);
+
+    let mut madgwick = Madgwick::default();
+    assert_eq!(madgwick.beta(), DEFAULT_BETA);
+    madgwick.set_beta(0.5);
+    assert_eq!(madgwick.beta(), 0.5);
+
+    // A larger time constant corrects slower
+    let tilt = angles(30f32, 0f32, 0f32);
+    let mut fast = ComplementaryFilter::new(0.2);
+    let mut slow = ComplementaryFilter::new(2f32);
+    for filter in [&mut fast as &mut dyn Filter, &mut slow] {
+        hold(filter, 0, 1, angles(0f32, 0f32, 0f32), false);
+        hold(filter, 1, 50, tilt, false);
+    }
+    assert!(fast.orientation().roll > slow.orientation().roll);
+
+    // A larger beta corrects faster
+    let mut fast = Madgwick::new(1f32);
+    let mut slow = Madgwick::new(0.05);
+    for filter in [&mut fast as &mut dyn Filter, &mut slow] {
+        hold(filter, 0, 1, angles(0f32, 0f32, 0f32), false);
+        hold(filter, 1, 50, tilt, false);
+    }
+    assert!(fast.orientation().roll > slow.orientation().roll);
+}
+
+#[test]
+fn tilt_trace_is_tracked() {
+    assert_eq!(TILT_TRACE.len(), 1400 * 14);
+    // The last sample of every rest and the orientation during it
+    let rests = [
+        (199, angles(0f32, 0f32, 0f32)),
+        (599, angles(30f32, 0f32, 0f32)),
+        (999, angles(30f32, -20f32, 0f32)),
+        (1399, angles(0f32, 0f32, 0f32)),
+    ];
+    for (name, mut filter) in filters() {
+        let mut index = 0;
+        for (last, expected) in rests {
+            while index <= last {
+                filter.update(tilt_trace_sample(index));
+                index += 1;
+            }
+            let orientation = filter.orientation();
+            assert_angle(name, orientation.roll, expected.roll, 1.5);
+            assert_angle(name, orientation.pitch, expected.pitch, 1.5);
+        }
+    }
+}
@@ -0,0 +1,179 @@
+//! The step counter.
+
+use embassy_time::{Duration, Instant};
+
+use crate::{
+    low_pass::LowPass,
+    mpu6500::Acceleration,
+    pedometer::{Config, Steps},
+};
+
+/// The time constant of the low pass filter that smooths
+/// the magnitude (about 3 Hz)
+const SMOOTHING_TIME_CONSTANT: Duration = Duration::from_millis(50);
+
+/// The time constant of the low pass filter that
+/// estimates the gravity
+const GRAVITY_TIME_CONSTANT: Duration = Duration::from_secs(1);
+
+/// The weight of a new peak in the average peak height
+const PEAK_AVERAGE_WEIGHT: f32 = 0.25;
+
+/// The weight of a new interval in the average step interval
+const INTERVAL_AVERAGE_WEIGHT: f32 = 0.3;
+
+/// Counts steps in acceleration samples.
+pub struct StepCounter {
+    config: Config,
+    /// Smooths the magnitude
+    smoothing: LowPass<1>,
+    /// Estimates the gravity
+    gravity: LowPass<1>,
+    /// The highest value and its time since the signal
+    /// rose above the threshold
+    peak: Option<(f32, Instant)>,
+    /// The average height of the recent peaks, 0 if there
+    /// are no recent steps
+    average_peak: f32,
+    /// The time of the last step
+    last_step: Option<Instant>,
+    /// The average time between the recent steps in s
+    average_interval: f32,
+    /// The steps that wait for the counter to start counting
+    pending: u32,
+    /// Whether the counter counts (the person walks)
+    walking: bool,
+    /// The steps counted so far
+    count: u32,
+}
+
+impl StepCounter {
+    /// Creates a new step counter
+    pub fn new(config: Config) -> StepCounter {
+        StepCounter {
+            config,
+            smoothing: LowPass::new(SMOOTHING_TIME_CONSTANT),
+            gravity: LowPass::new(GRAVITY_TIME_CONSTANT),
+            peak: None,
+            average_peak: 0f32,
+            last_step: None,
+            average_interval: 0f32,
+            pending: 0,
+            walking: false,
+            count: 0,
+        }
+    }
+
+    /// Returns the configuration
+    pub fn config(&self) -> Config {
+        self.config
+    }
+
+    /// Returns the steps counted so far
+    pub fn steps(&self) -> Steps {
+        Steps {
+            count: self.count,
+            cadence: if self.walking && self.average_interval > 0f32 {
+                60f32 / self.average_interval
+            } else {
+                0f32
+            },
+            distance: self.count as f32 * self.config.step_length,
+        }
+    }
+
+    /// Sets the step count back to 0
+    pub fn reset(&mut self) {
+        *self = StepCounter::new(self.config);
+    }
+
+    /// Adds a sample, returns `true` if the [`Steps`] changed.
+    ///
+    /// The samples have to be added in the order in which they
+    /// were taken.
+    pub fn update(&mut self, accel: Acceleration, timestamp: Instant) -> bool {
+        let magnitude = libm::sqrtf(accel.x * accel.x + accel.y * accel.y + accel.z * accel.z);
+        let signal = self.filter(magnitude, timestamp);
+        let before = self.steps();
+
+        // The person stopped walking
+        if let Some(last) = self.last_step
+            && timestamp.saturating_duration_since(last) > self.config.max_step_interval
+        {
+            self.stop();
+        }
+
+        let threshold = self
+            .config
+            .min_threshold
+            .max(self.config.threshold_factor * self.average_peak);
+        if signal > threshold {
+            self.peak = match self.peak {
+                Some((height, time)) if height >= signal => Some((height, time)),
+                _ => Some((signal, timestamp)),
+            };
+        } else if let Some((height, time)) = self.peak.take() {
+            self.step(height, time);
+        }
+
+        self.steps() != before
+    }
+}
+
+/// Private API
+impl StepCounter {
+    /// Smooths the magnitude and removes the gravity
+    fn filter(&mut self, magnitude: f32, timestamp: Instant) -> f32 {
+        let [smooth] = self.smoothing.update([magnitude], timestamp);
+        let [gravity] = self.gravity.update([magnitude], timestamp);
+        smooth - gravity
+    }
+
+    /// Handles a peak of `height` at `time`
+    fn step(&mut self, height: f32, time: Instant) {
+        let interval = match self.last_step {
+            // The same step
+            Some(last) if time.saturating_duration_since(last) < self.config.min_step_interval => {
+                return;
+            }
+            Some(last) => {
+                Some(time.saturating_duration_since(last).as_micros() as f32 / 1_000_000f32)
+            }
+            None => None,
+        };
+        self.last_step = Some(time);
+        self.average_peak = if self.average_peak == 0f32 {
+            height
+        } else {
+            self.average_peak + PEAK_AVERAGE_WEIGHT * (height - self.average_peak)
+        };
+        if let Some(interval) = interval {
+            self.average_interval = if self.average_interval == 0f32 {
+                interval
+            } else {
+                self.average_interval + INTERVAL_AVERAGE_WEIGHT * (interval - self.average_interval)
+            };
+        }
+
+        if self.walking {
+            self.count += 1;
+        } else {
+            self.pending += 1;
+            if self.pending >= u32::from(self.config.start_steps) {
+                self.count += self.pending;
+                self.pending = 0;
+                self.walking = true;
+            }
+        }
+    }
+
+    /// Forgets the steps that were not counted and
+    /// the recent steps' statistics
+    fn stop(&mut self) {
+        self.last_step = None;
+        self.average_peak = 0f32;
+        self.average_interval = 0f32;
+        self.pending = 0;
+        self.walking = false;
+    }
+}
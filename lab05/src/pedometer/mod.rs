@@ -0,0 +1,164 @@
+//! Step counting from MPU 6500 acceleration samples.
+//!
+//! Every step produces a peak of the acceleration's magnitude, when the
+//! heel hits the ground. The [`StepCounter`]:
+//! 1. computes the magnitude of the acceleration, so that the steps are
+//!    counted no matter how the board is held
+//! 2. smooths the magnitude and removes the gravity using two low pass
+//!    filters
+//! 3. looks for peaks above a threshold that adapts to how strong the
+//!    recent steps were, so that both slow walking and running work
+//! 4. counts the peaks that are at least `min_step_interval` apart
+//!
+//! A few isolated peaks are not counted. The counter starts counting
+//! only after `start_steps` regular steps, and then adds them all.
+//!
+//! The counter also estimates the cadence (steps per minute) and
+//! the distance walked, using a fixed step length.
+//!
+//! The [`run`] function consumes samples from an embassy channel and
+//! publishes the [`Steps`] into an embassy watch, so that it can run in
+//! its own task, while other tasks read the sensor and display the
+//! steps.
+//!
+//! ```ignore
+//! static SAMPLES: Channel<ThreadModeRawMutex, (Acceleration, Instant), 16> = Channel::new();
+//! static STEPS: Watch<ThreadModeRawMutex, Steps, 1> = Watch::new();
+//!
+//! #[embassy_executor::task]
+//! async fn pedometer() {
+//!     let mut counter = StepCounter::new(Config::default());
+//!     run(&mut counter, SAMPLES.receiver(), STEPS.sender()).await
+//! }
+//! ```
+
+mod counter;
+
+#[cfg(test)]
+mod tests;
+
+use embassy_sync::{blocking_mutex::raw::RawMutex, channel::Receiver, watch::Sender};
+use embassy_time::{Duration, Instant};
+
+use crate::mpu6500::Acceleration;
+
+pub use counter::StepCounter;
+
+/// The steps counted so far.
+#[derive(Copy, Clone, Default, PartialEq, Debug, defmt::Format)]
+pub struct Steps {
+    /// The number of steps
+    pub count: u32,
+    /// The number of steps per minute, 0 if the
+    /// person does not walk
+    pub cadence: f32,
+    /// The distance walked in m
+    pub distance: f32,
+}
+
+/// The step counter configuration.
+///
+/// The accelerations are in g.
+#[derive(Copy, Clone, PartialEq, Debug, defmt::Format)]
+pub struct Config {
+    /// The smallest peak of the acceleration that can be a step
+    pub min_threshold: f32,
+    /// The threshold is this part of the average height of
+    /// the recent steps' peaks, from 0 to 1
+    pub threshold_factor: f32,
+    /// The shortest time between two steps, closer peaks
+    /// are part of the same step
+    pub min_step_interval: Duration,
+    /// The longest time between two steps, a longer pause
+    /// means that the person stopped walking
+    pub max_step_interval: Duration,
+    /// The number of regular steps after which the counter
+    /// starts counting
+    pub start_steps: u8,
+    /// The length of a step in m
+    pub step_length: f32,
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config {
+            min_threshold: 0.08,
+            threshold_factor: 0.5,
+            min_step_interval: Duration::from_millis(250),
+            max_step_interval: Duration::from_millis(2000),
+            start_steps: 4,
+            step_length: 0.7,
+        }
+    }
+}
+
+/// Builder functions
+impl Config {
+    /// Sets the smallest peak that can be a step
+    pub fn min_threshold(self, value: f32) -> Config {
+        Config {
+            min_threshold: value,
+            ..self
+        }
+    }
+
+    /// Sets the part of the recent peaks' height used as threshold
+    pub fn threshold_factor(self, value: f32) -> Config {
+        Config {
+            threshold_factor: value,
+            ..self
+        }
+    }
+
+    /// Sets the shortest time between two steps
+    pub fn min_step_interval(self, value: Duration) -> Config {
+        Config {
+            min_step_interval: value,
+            ..self
+        }
+    }
+
+    /// Sets the longest time between two steps
+    pub fn max_step_interval(self, value: Duration) -> Config {
+        Config {
+            max_step_interval: value,
+            ..self
+        }
+    }
+
+    /// Sets the number of regular steps after which the
+    /// counter starts counting
+    pub fn start_steps(self, value: u8) -> Config {
+        Config {
+            start_steps: value,
+            ..self
+        }
+    }
+
+    /// Sets the length of a step in m
+    pub fn step_length(self, value: f32) -> Config {
+        Config {
+            step_length: value,
+            ..self
+        }
+    }
+}
+
+/// Counts the steps of the samples received from `samples` and
+/// sends the new [`Steps`] to `steps` every time they change.
+///
+/// The samples are the acceleration and the time when it was read.
+/// The function never returns, it is meant to run in its own task.
+pub async fn run<M: RawMutex, const N: usize, const W: usize>(
+    counter: &mut StepCounter,
+    samples: Receiver<'_, M, (Acceleration, Instant), N>,
+    steps: Sender<'_, M, Steps, W>,
+) -> ! {
+    steps.send(counter.steps());
+    loop {
+        let (accel, timestamp) = samples.receive().await;
+        if counter.update(accel, timestamp) {
+            steps.send(counter.steps());
+        }
+    }
+}
@@ -0,0 +1,277 @@
+//! Host tests for the step counter.
+//!
+//! Most tests replay deterministic synthetic walks sampled at 100 Hz.
+//! Every step is a peak of the vertical acceleration followed by a
+//! smaller dip, with a side to side sway and a pseudo random noise
+//! added. The board is held tilted, so the steps are spread over all
+//! the axes.
+//!
+//! `assets/traces/walk.raw` is a trace in the format of a capture from
+//! the board: the `ACCEL_XOUT_H` ... `ACCEL_ZOUT_L` registers of every
+//! sample, read at 100 Hz with the ±4 g scale. It holds 2 s of standing,
+//! 60 steps with a varying cadence and strength, while the hand sways,
+//! and 2 s of standing. No capture of a real walk is available yet, so
+//! the trace is generated from this model; a capture of a real walk
+//! can replace it as long as it keeps the format and the step count.
+
+extern crate std;
+
+use std::vec::Vec;
+
+use embassy_time::{Duration, Instant};
+
+use crate::{
+    mpu6500::{AccelScale, Acceleration},
+    pedometer::{Config, StepCounter, Steps},
+};
+
+/// The time between two samples in ms
+const PERIOD_MS: u64 = 10;
+
+/// The direction of the gravity in the sensor's axes, the board
+/// is held tilted
+const UP: [f32; 3] = [0.36, 0.48, 0.8];
+
+/// A synthetic walk.
+struct Walk {
+    counter: StepCounter,
+    /// The time of the next sample in ms
+    time: u64,
+    /// The state of the noise generator
+    noise: u32,
+    /// The steps after every sample
+    history: Vec<Steps>,
+}
+
+impl Walk {
+    fn new(config: Config) -> Walk {
+        Walk {
+            counter: StepCounter::new(config),
+            time: 0,
+            noise: 0x9e37_79b9,
+            history: Vec::new(),
+        }
+    }
+
+    /// Returns a value between -`amplitude` and `amplitude`
+    fn noise(&mut self, amplitude: f32) -> f32 {
+        self.noise = self
+            .noise
+            .wrapping_mul(1_664_525)
+            .wrapping_add(1_013_904_223);
+        ((self.noise >> 8) as f32 / (1u32 << 23) as f32 - 1f32) * amplitude
+    }
+
+    /// Adds a sample with the vertical acceleration `vertical`
+    /// and the side acceleration `side`
+    fn sample(&mut self, vertical: f32, side: f32) {
+        let accel = Acceleration {
+            x: UP[0] * (1f32 + vertical) + side + self.noise(0.02),
+            y: UP[1] * (1f32 + vertical) + self.noise(0.02),
+            z: UP[2] * (1f32 + vertical) - side * 0.45 + self.noise(0.02),
+        };
+        self.counter.update(accel, Instant::from_millis(self.time));
+        self.history.push(self.counter.steps());
+        self.time += PERIOD_MS;
+    }
+
+    /// Walks `steps` steps at `frequency` steps per second, with
+    /// peaks of `amplitude` g
+    fn walk(&mut self, steps: u32, frequency: f32, amplitude: f32) {
+        let samples = (steps as f32 / frequency * 1000f32 / PERIOD_MS as f32) as u32;
+        for index in 0..samples {
+            let t = index as f32 * PERIOD_MS as f32 / 1000f32;
+            // Every step starts and ends between two peaks
+            let phase = 2f32 * core::f32::consts::PI * frequency * t + core::f32::consts::PI;
+            // The heel strike peak and the weaker push off
+            let vertical = amplitude * (0.6 * libm::cosf(phase) + 0.4 * libm::cosf(2f32 * phase));
+            // The body sways left and right once every two steps
+            let side = 0.1 * libm::sinf(phase / 2f32);
+            self.sample(vertical, side);
+        }
+    }
+
+    /// Stands still for `ms`
+    fn stand(&mut self, ms: u64) {
+        for _ in 0..ms / PERIOD_MS {
+            self.sample(0f32, 0f32);
+        }
+    }
+
+    fn steps(&self) -> Steps {
+        self.counter.steps()
+    }
+}
+
+#[test]
+fn walking_steps_are_counted() {
+    let mut walk = Walk::new(Config::default());
+    walk.stand(1000);
+    walk.walk(100, 1.8, 0.3);
+    walk.stand(1000);
+    assert_eq!(walk.steps().count, 100);
+}
+
+/// The walk trace, see the module documentation
+const WALK_TRACE: &[u8] = include_bytes!("../../assets/traces/walk.raw");
+
+/// The steps in the walk trace
+const WALK_TRACE_STEPS: u32 = 60;
+
+#[test]
+fn walk_trace_steps_are_counted() {
+    let mut counter = StepCounter::new(Config::default());
+    for (index, sample) in WALK_TRACE.chunks_exact(6).enumerate() {
+        let [x, y, z] = [0, 2, 4].map(|offset| {
+            AccelScale::G4.to_g(i16::from_be_bytes([sample[offset], sample[offset + 1]]))
+        });
+        counter.update(
+            Acceleration { x, y, z },
+            Instant::from_millis(index as u64 * PERIOD_MS),
+        );
+    }
+    assert_eq!(counter.steps().count, WALK_TRACE_STEPS);
+}
+
+#[test]
+fn slow_walking_and_running_are_counted() {
+    // Slow walking has weak peaks
+    let mut walk = Walk::new(Config::default());
+    walk.stand(1000);
+    walk.walk(40, 1.2, 0.15);
+    assert_eq!(walk.steps().count, 40);
+
+    // Running has fast and strong peaks
+    let mut walk = Walk::new(Config::default());
+    walk.stand(1000);
+    walk.walk(60, 2.8, 1.2);
+    assert_eq!(walk.steps().count, 60);
+}
+
+/// Walks 30 strong steps with a second, weaker peak between them,
+/// like a heavy push off, and returns the steps counted during the
+/// last 20 steps, after the threshold had time to adapt
+fn walk_with_push_off(config: Config) -> u32 {
+    let mut walk = Walk::new(config);
+    walk.stand(1000);
+    let frequency = 1.6;
+    let samples_per_step = 100f32 / frequency;
+    let mut counted = 0;
+    for index in 0..(30f32 * samples_per_step) as u32 {
+        if index == (10f32 * samples_per_step) as u32 {
+            counted = walk.steps().count;
+        }
+        let phase = 2f32 * core::f32::consts::PI * frequency * index as f32 * 0.01;
+        let step = libm::cosf(phase);
+        let push_off = libm::cosf(phase - core::f32::consts::PI);
+        let vertical = 1.2 * step.max(0f32).powi(4) + 0.6 * push_off.max(0f32).powi(2);
+        walk.sample(vertical, 0f32);
+    }
+    walk.steps().count - counted
+}
+
+#[test]
+fn threshold_adapts_to_the_steps() {
+    assert_eq!(walk_with_push_off(Config::default()), 20);
+    // The fixed minimum threshold counts both peaks
+    assert_eq!(
+        walk_with_push_off(Config::default().threshold_factor(0f32)),
+        40
+    );
+}
+
+#[test]
+fn standing_still_counts_nothing() {
+    let mut walk = Walk::new(Config::default());
+    walk.stand(10_000);
+    assert_eq!(walk.steps(), Steps::default());
+    assert!(walk.history.iter().all(|steps| *steps == Steps::default()));
+}
+
+#[test]
+fn isolated_bumps_are_not_steps() {
+    let mut walk = Walk::new(Config::default());
+    walk.stand(1000);
+    // Three bumps, far from each other
+    for _ in 0..3 {
+        walk.walk(1, 2f32, 0.5);
+        walk.stand(3000);
+    }
+    assert_eq!(walk.steps().count, 0);
+}
+
+#[test]
+fn counting_restarts_after_a_pause() {
+    let mut walk = Walk::new(Config::default());
+    walk.stand(1000);
+    walk.walk(20, 1.8, 0.3);
+    walk.stand(3000);
+    assert_eq!(walk.steps().count, 20);
+    assert_eq!(walk.steps().cadence, 0f32);
+
+    walk.walk(20, 1.8, 0.3);
+    assert_eq!(walk.steps().count, 40);
+}
+
+#[test]
+fn cadence_and_distance_are_estimated() {
+    let mut walk = Walk::new(Config::default());
+    walk.stand(1000);
+    walk.walk(50, 1.8, 0.3);
+    let steps = walk.steps();
+    assert!(
+        (steps.cadence - 108f32).abs() < 2f32,
+        "cadence {}",
+        steps.cadence
+    );
+    assert_eq!(steps.distance, steps.count as f32 * 0.7);
+
+    let mut walk = Walk::new(Config::default().step_length(0.5));
+    walk.stand(1000);
+    walk.walk(50, 1.8, 0.3);
+    assert_eq!(walk.steps().distance, walk.steps().count as f32 * 0.5);
+}
+
+#[test]
+fn count_only_grows_while_walking() {
+    let mut walk = Walk::new(Config::default());
+    walk.stand(1000);
+    walk.walk(30, 1.8, 0.3);
+    let counts: Vec<u32> = walk.history.iter().map(|steps| steps.count).collect();
+    assert!(counts.windows(2).all(|pair| pair[0] <= pair[1]));
+    // The first steps are added together, when the counter starts
+    assert!(counts.contains(&0) && counts.contains(&4) && !counts.contains(&1));
+}
+
+#[test]
+fn configuration_changes_the_counting() {
+    // Counting starts with the first step
+    let mut walk = Walk::new(Config::default().start_steps(1));
+    walk.stand(1000);
+    walk.walk(1, 2f32, 0.5);
+    walk.stand(3000);
+    assert_eq!(walk.steps().count, 1);
+
+    // A high minimum threshold ignores slow walking
+    let mut walk = Walk::new(Config::default().min_threshold(0.5));
+    walk.stand(1000);
+    walk.walk(40, 1.2, 0.15);
+    assert_eq!(walk.steps().count, 0);
+
+    // Steps closer than the minimum interval are the same step
+    let config = Config::default().min_step_interval(Duration::from_millis(700));
+    let mut walk = Walk::new(config);
+    walk.stand(1000);
+    walk.walk(40, 2f32, 0.3);
+    assert_eq!(walk.steps().count, 20);
+}
+
+#[test]
+fn reset_clears_the_count() {
+    let mut walk = Walk::new(Config::default());
+    walk.stand(1000);
+    walk.walk(20, 1.8, 0.3);
+    walk.counter.reset();
+    assert_eq!(walk.steps(), Steps::default());
+    assert_eq!(walk.counter.config(), Config::default());
+}
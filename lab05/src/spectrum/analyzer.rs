@@ -0,0 +1,226 @@
+//! The spectrum analyzer.
+
+use crate::{
+    mpu6500::{self, Acceleration},
+    spectrum::{Analysis, Axis, AxisAnalysis, DOMINANT_PEAKS, Peak, Window, fft},
+};
+
+/// Computes the spectrum of `N` acceleration samples.
+///
+/// `N` has to be a power of two. The frequency resolution is
+/// `sample_rate / N`, a larger buffer separates closer frequencies,
+/// but takes longer to fill.
+pub struct SpectrumAnalyzer<const N: usize> {
+    sample_rate: f32,
+    window: Window,
+    /// The samples of every axis, used by the FFT as the
+    /// real part of the signal
+    samples: [[f32; N]; 3],
+    /// The number of samples in the buffer
+    len: usize,
+    /// The amplitudes of the frequencies of every axis in g,
+    /// used by the FFT as the imaginary part of the signal
+    spectrum: [[f32; N]; 3],
+}
+
+impl<const N: usize> SpectrumAnalyzer<N> {
+    /// Creates a new analyzer for samples taken at
+    /// `sample_rate` Hz
+    pub fn new(sample_rate: f32, window: Window) -> SpectrumAnalyzer<N> {
+        const {
+            assert!(
+                N.is_power_of_two() && N >= 4,
+                "the buffer size has to be a power of two of at least 4"
+            )
+        };
+        SpectrumAnalyzer {
+            sample_rate,
+            window,
+            samples: [[0f32; N]; 3],
+            len: 0,
+            spectrum: [[0f32; N]; 3],
+        }
+    }
+
+    /// Creates a new analyzer for the sample rate
+    /// of the sensor's configuration.
+    ///
+    /// The data registers and the FIFO are updated at the sample rate,
+    /// which depends on the gyroscope's low pass filter and on the sample
+    /// rate divider. If the sensor is configured again, the analyzer has
+    /// to be created again.
+    pub fn from_config(config: &mpu6500::Config, window: Window) -> SpectrumAnalyzer<N> {
+        SpectrumAnalyzer::new(config.sample_rate(), window)
+    }
+
+    /// Returns the sample rate in Hz
+    pub fn sample_rate(&self) -> f32 {
+        self.sample_rate
+    }
+
+    /// Returns the window
+    pub fn window(&self) -> Window {
+        self.window
+    }
+
+    /// Returns the frequency resolution, the distance between
+    /// two bins of the spectrum, in Hz
+    pub fn resolution(&self) -> f32 {
+        self.sample_rate / N as f32
+    }
+
+    /// Returns the frequency of the spectrum's `bin` in Hz
+    pub fn frequency(&self, bin: usize) -> f32 {
+        bin as f32 * self.resolution()
+    }
+
+    /// Returns the number of samples in the buffer
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns `true` if the buffer has no samples
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns `true` if the buffer has `N` samples
+    /// and can be analyzed
+    pub fn is_full(&self) -> bool {
+        self.len == N
+    }
+
+    /// Removes the samples from the buffer.
+    ///
+    /// Use this if samples were lost, the spectrum of a buffer
+    /// with gaps is wrong.
+    pub fn clear(&mut self) {
+        self.len = 0;
+    }
+
+    /// Adds a sample to the buffer, returns `true` if the
+    /// buffer is full.
+    ///
+    /// The sample is ignored if the buffer is already full.
+    pub fn push(&mut self, accel: Acceleration) -> bool {
+        if self.len < N {
+            self.samples[Axis::X as usize][self.len] = accel.x;
+            self.samples[Axis::Y as usize][self.len] = accel.y;
+            self.samples[Axis::Z as usize][self.len] = accel.z;
+            self.len += 1;
+        }
+        self.is_full()
+    }
+
+    /// Analyzes the buffer, returns `None` if the buffer
+    /// is not full.
+    ///
+    /// The buffer is emptied, the next analysis starts with
+    /// the next sample.
+    pub fn analyze(&mut self) -> Option<Analysis> {
+        if !self.is_full() {
+            return None;
+        }
+        self.len = 0;
+        Some(Analysis {
+            x: self.analyze_axis(Axis::X),
+            y: self.analyze_axis(Axis::Y),
+            z: self.analyze_axis(Axis::Z),
+        })
+    }
+
+    /// Returns the amplitudes in g of the frequencies from 0 to half
+    /// of the sample rate (`N / 2` bins) of the last analysis
+    pub fn spectrum(&self, axis: Axis) -> &[f32] {
+        &self.spectrum[axis as usize][..N / 2]
+    }
+
+    /// Groups the spectrum of `axis` in `bars.len()` bars of equal
+    /// width, for displaying it. Every bar is the highest
+    /// amplitude of its bins.
+    pub fn bars(&self, axis: Axis, bars: &mut [f32]) {
+        let spectrum = self.spectrum(axis);
+        let count = bars.len();
+        for (index, bar) in bars.iter_mut().enumerate() {
+            let start = index * spectrum.len() / count;
+            let end = ((index + 1) * spectrum.len() / count).max(start + 1);
+            *bar = spectrum[start..end].iter().copied().fold(0f32, f32::max);
+        }
+    }
+}
+
+/// Private API
+impl<const N: usize> SpectrumAnalyzer<N> {
+    /// Computes the spectrum of `axis` and finds its peaks
+    fn analyze_axis(&mut self, axis: Axis) -> AxisAnalysis {
+        let re = &mut self.samples[axis as usize];
+        let im = &mut self.spectrum[axis as usize];
+
+        // Remove the mean, otherwise its leakage hides
+        // the low frequencies
+        let mean = re.iter().sum::<f32>() / N as f32;
+        let mut power = 0f32;
+        let mut gain = 0f32;
+        for (index, (re, im)) in re.iter_mut().zip(im.iter_mut()).enumerate() {
+            *re -= mean;
+            power += *re * *re;
+            let coefficient = self.window.coefficient(index, N);
+            gain += coefficient;
+            *re *= coefficient;
+            *im = 0f32;
+        }
+        let rms = libm::sqrtf(power / N as f32);
+
+        fft(re, im);
+
+        // The amplitude of a sine is split between the positive and
+        // the negative frequency, and the window lowers it by `gain`
+        let scale = 2f32 / gain.max(f32::EPSILON);
+        for bin in 0..=N / 2 {
+            im[bin] = libm::sqrtf(re[bin] * re[bin] + im[bin] * im[bin]) * scale;
+        }
+        im[0] /= 2f32;
+        im[N / 2] /= 2f32;
+
+        AxisAnalysis {
+            mean,
+            rms,
+            peaks: self.peaks(axis),
+        }
+    }
+
+    /// Returns the highest local maxima of the spectrum of `axis`
+    fn peaks(&self, axis: Axis) -> [Option<Peak>; DOMINANT_PEAKS] {
+        let spectrum = &self.spectrum[axis as usize];
+        let mut peaks = [None::<Peak>; DOMINANT_PEAKS];
+        for bin in 1..N / 2 {
+            let (before, value, after) = (spectrum[bin - 1], spectrum[bin], spectrum[bin + 1]);
+            if value <= before || value < after || value <= f32::EPSILON {
+                continue;
+            }
+
+            // The frequency is usually between two bins, fit a
+            // parabola through the peak and its neighbours
+            let curvature = before - 2f32 * value + after;
+            let offset = if curvature < 0f32 {
+                0.5 * (before - after) / curvature
+            } else {
+                0f32
+            };
+            let peak = Peak {
+                frequency: (bin as f32 + offset) * self.resolution(),
+                amplitude: value - 0.25 * (before - after) * offset,
+            };
+
+            // Keep the peaks sorted, the strongest first
+            if let Some(position) = peaks
+                .iter()
+                .position(|other| other.is_none_or(|other| other.amplitude < peak.amplitude))
+            {
+                peaks[position..].rotate_right(1);
+                peaks[position] = Some(peak);
+            }
+        }
+        peaks
+    }
+}
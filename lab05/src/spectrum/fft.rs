@@ -0,0 +1,46 @@
+//! Fixed size radix-2 FFT.
+
+use core::f32::consts::PI;
+
+/// Computes the discrete Fourier transform of the complex signal
+/// `re + j * im`, in place.
+///
+/// `N` has to be a power of two. The function does not scale the
+/// result, the value of bin `k` is `sum(x[n] * e^(-j*2*pi*k*n/N))`.
+pub fn fft<const N: usize>(re: &mut [f32; N], im: &mut [f32; N]) {
+    const { assert!(N.is_power_of_two(), "the FFT size has to be a power of two") };
+    if N < 2 {
+        return;
+    }
+
+    // Reorder the samples in the bit reversed order of their index
+    let bits = N.trailing_zeros();
+    for i in 0..N {
+        let j = i.reverse_bits() >> (usize::BITS - bits);
+        if j > i {
+            re.swap(i, j);
+            im.swap(i, j);
+        }
+    }
+
+    // Combine the transforms of length `half` into
+    // transforms of length `len`
+    let mut len = 2;
+    while len <= N {
+        let half = len / 2;
+        let angle = -2f32 * PI / len as f32;
+        for k in 0..half {
+            let (sin, cos) = libm::sincosf(angle * k as f32);
+            for start in (0..N).step_by(len) {
+                let (a, b) = (start + k, start + k + half);
+                let tr = re[b] * cos - im[b] * sin;
+                let ti = re[b] * sin + im[b] * cos;
+                re[b] = re[a] - tr;
+                im[b] = im[a] - ti;
+                re[a] += tr;
+                im[a] += ti;
+            }
+        }
+        len *= 2;
+    }
+}
@@ -0,0 +1,124 @@
+//! Vibration spectrum analysis of MPU 6500 acceleration samples.
+//!
+//! The [`SpectrumAnalyzer`] buffers `N` acceleration samples and
+//! computes their spectrum using an FFT. For every axis it reports
+//! the RMS of the vibration and the dominant frequencies. The buffers
+//! are part of the analyzer, nothing is allocated.
+//!
+//! Before the FFT, the samples are multiplied by a [`Window`]. The FFT
+//! assumes that the buffer repeats forever, a vibration that does not
+//! fit an integer number of times in the buffer *leaks* into the
+//! neighbouring frequencies. The window fades the buffer's edges
+//! in and out, which reduces the leakage.
+//!
+//! The frequencies are only correct if the samples are taken at the
+//! analyzer's sample rate, without gaps. Use
+//! [`SpectrumAnalyzer::from_config`] to take the sample rate from the
+//! sensor's configuration and read the samples from the FIFO (or when
+//! the data ready interrupt fires), never with a timer. If samples
+//! are lost (the FIFO overflows), [`SpectrumAnalyzer::clear`] the
+//! buffer and start again.
+//!
+//! The analyzer sees frequencies up to half of the sample rate. The
+//! sensor's low pass filter should remove the higher frequencies,
+//! otherwise they appear as false lower frequencies (aliasing).
+//!
+//! ```ignore
+//! let mut analyzer = SpectrumAnalyzer::<256>::from_config(&mpu6500.config(), Window::Hann);
+//! for frame in frames {
+//!     if analyzer.push(frame.accel.unwrap()) {
+//!         let analysis = analyzer.analyze().unwrap();
+//!         info!("Z: {} Hz", analysis.z.peaks[0]);
+//!     }
+//! }
+//! ```
+
+mod analyzer;
+mod fft;
+
+#[cfg(test)]
+mod tests;
+
+pub use analyzer::SpectrumAnalyzer;
+pub use fft::fft;
+
+/// The number of dominant frequencies reported for every axis
+pub const DOMINANT_PEAKS: usize = 3;
+
+/// The window applied to the samples before the FFT.
+#[derive(Copy, Clone, Default, PartialEq, Debug, defmt::Format)]
+pub enum Window {
+    /// No window, the best frequency resolution, but the
+    /// most leakage
+    Rectangular,
+    /// The Hann window, a good choice for most signals
+    #[default]
+    Hann,
+    /// The Hamming window, less leakage close to the peak,
+    /// more far from it
+    Hamming,
+}
+
+impl Window {
+    /// Returns the window's value for the sample `index`
+    /// of a buffer of `len` samples.
+    pub fn coefficient(&self, index: usize, len: usize) -> f32 {
+        let cos = || {
+            libm::cosf(2f32 * core::f32::consts::PI * index as f32 / (len as f32 - 1f32).max(1f32))
+        };
+        match self {
+            Window::Rectangular => 1f32,
+            Window::Hann => 0.5 - 0.5 * cos(),
+            Window::Hamming => 0.54 - 0.46 * cos(),
+        }
+    }
+}
+
+/// The acceleration axes.
+#[derive(Copy, Clone, PartialEq, Debug, defmt::Format)]
+pub enum Axis {
+    X = 0,
+    Y = 1,
+    Z = 2,
+}
+
+/// A dominant frequency of the spectrum.
+#[derive(Copy, Clone, PartialEq, Debug, defmt::Format)]
+pub struct Peak {
+    /// The frequency in Hz, interpolated between the FFT bins
+    pub frequency: f32,
+    /// The amplitude of the vibration in g
+    pub amplitude: f32,
+}
+
+/// The analysis of an axis.
+#[derive(Copy, Clone, PartialEq, Debug, defmt::Format)]
+pub struct AxisAnalysis {
+    /// The average acceleration in g, the gravity
+    /// and the sensor's offset
+    pub mean: f32,
+    /// The RMS of the vibration (without the mean) in g
+    pub rms: f32,
+    /// The dominant frequencies, the strongest first, `None`
+    /// if the spectrum has fewer peaks
+    pub peaks: [Option<Peak>; DOMINANT_PEAKS],
+}
+
+/// The analysis of a buffer of samples.
+#[derive(Copy, Clone, PartialEq, Debug, defmt::Format)]
+pub struct Analysis {
+    pub x: AxisAnalysis,
+    pub y: AxisAnalysis,
+    pub z: AxisAnalysis,
+}
+
+impl Analysis {
+    /// Returns the analysis of `axis`
+    pub fn axis(&self, axis: Axis) -> &AxisAnalysis {
+        match axis {
+            Axis::X => &self.x,
+            Axis::Y => &self.y,
+            Axis::Z => &self.z,
+        }
+    }
+}
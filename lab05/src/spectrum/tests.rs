@@ -0,0 +1,265 @@
+//! Host tests for the spectrum analyzer.
+//!
+//! The tests analyze synthetic vibrations, sums of sines with a known
+//! frequency and amplitude, and check that the analyzer finds them.
+
+extern crate std;
+
+use core::f32::consts::PI;
+
+use crate::{
+    mpu6500::{AccelDlpf, Acceleration, Config, GyroDlpf},
+    spectrum::{Axis, SpectrumAnalyzer, Window, fft},
+};
+
+/// The sample rate of the synthetic vibrations in Hz
+const SAMPLE_RATE: f32 = 200f32;
+
+/// The acceleration of a board lying still
+const STILL: Acceleration = Acceleration {
+    x: 0f32,
+    y: 0f32,
+    z: 1f32,
+};
+
+/// Fills the analyzer with samples of `signal` on the Z axis, the
+/// X and Y axes vibrate at 10 Hz and 30 Hz
+fn fill<const N: usize>(analyzer: &mut SpectrumAnalyzer<N>, signal: impl Fn(f32) -> f32) {
+    for index in 0..N {
+        let t = index as f32 / analyzer.sample_rate();
+        let full = analyzer.push(Acceleration {
+            x: 0.1 * libm::sinf(2f32 * PI * 10f32 * t),
+            y: 0.3 * libm::sinf(2f32 * PI * 30f32 * t),
+            z: 1f32 + signal(t),
+        });
+        assert_eq!(full, index == N - 1);
+    }
+}
+
+/// Returns a sine of `frequency` Hz and `amplitude` g
+fn sine(frequency: f32, amplitude: f32) -> impl Fn(f32) -> f32 {
+    move |t| amplitude * libm::sinf(2f32 * PI * frequency * t)
+}
+
+fn assert_close(name: &str, actual: f32, expected: f32, tolerance: f32) {
+    assert!(
+        (actual - expected).abs() <= tolerance,
+        "{name}: {actual}, expected {expected}"
+    );
+}
+
+#[test]
+fn fft_of_an_impulse_is_flat() {
+    let mut re = [0f32; 16];
+    let mut im = [0f32; 16];
+    re[0] = 1f32;
+    fft(&mut re, &mut im);
+    for bin in 0..16 {
+        assert_close("re", re[bin], 1f32, 1e-6);
+        assert_close("im", im[bin], 0f32, 1e-6);
+    }
+}
+
+#[test]
+fn fft_matches_the_definition() {
+    let mut re = [0f32; 32];
+    let mut im = [0f32; 32];
+    for (index, (re, im)) in re.iter_mut().zip(im.iter_mut()).enumerate() {
+        *re = libm::sinf(index as f32 * 0.7) + 0.5;
+        *im = libm::cosf(index as f32 * 1.3);
+    }
+    let (input_re, input_im) = (re, im);
+    fft(&mut re, &mut im);
+
+    for bin in 0..32 {
+        let (mut expected_re, mut expected_im) = (0f32, 0f32);
+        for n in 0..32 {
+            let (sin, cos) = libm::sincosf(-2f32 * PI * (bin * n) as f32 / 32f32);
+            expected_re += input_re[n] * cos - input_im[n] * sin;
+            expected_im += input_re[n] * sin + input_im[n] * cos;
+        }
+        assert_close("re", re[bin], expected_re, 1e-3);
+        assert_close("im", im[bin], expected_im, 1e-3);
+    }
+}
+
+#[test]
+fn analysis_needs_a_full_buffer() {
+    let mut analyzer = SpectrumAnalyzer::<64>::new(SAMPLE_RATE, Window::Hann);
+    assert!(analyzer.analyze().is_none());
+    for _ in 0..63 {
+        assert!(!analyzer.push(STILL));
+    }
+    assert!(analyzer.analyze().is_none());
+    assert!(analyzer.push(STILL));
+    assert!(analyzer.analyze().is_some());
+    assert!(analyzer.is_empty());
+
+    analyzer.push(STILL);
+    analyzer.clear();
+    assert!(analyzer.is_empty());
+}
+
+#[test]
+fn dominant_frequency_on_a_bin() {
+    // 25 Hz is bin 32 of 256 samples at 200 Hz
+    for window in [Window::Rectangular, Window::Hann, Window::Hamming] {
+        let mut analyzer = SpectrumAnalyzer::<256>::new(SAMPLE_RATE, window);
+        fill(&mut analyzer, sine(25f32, 0.5));
+        let analysis = analyzer.analyze().unwrap();
+
+        let peak = analysis.z.peaks[0].unwrap();
+        assert_close("frequency", peak.frequency, 25f32, 0.05);
+        assert_close("amplitude", peak.amplitude, 0.5, 0.01);
+        assert_close("bin", analyzer.spectrum(Axis::Z)[32], 0.5, 0.01);
+        assert_close("mean", analysis.z.mean, 1f32, 1e-3);
+    }
+}
+
+#[test]
+fn dominant_frequency_between_bins() {
+    // 17.3 Hz is between bins 22 and 23
+    let mut analyzer = SpectrumAnalyzer::<256>::new(SAMPLE_RATE, Window::Hann);
+    fill(&mut analyzer, sine(17.3, 0.5));
+    let analysis = analyzer.analyze().unwrap();
+
+    let peak = analysis.z.peaks[0].unwrap();
+    assert_close("frequency", peak.frequency, 17.3, 0.2);
+    assert_close("amplitude", peak.amplitude, 0.5, 0.05);
+}
+
+#[test]
+fn every_axis_is_analyzed() {
+    let mut analyzer = SpectrumAnalyzer::<256>::new(SAMPLE_RATE, Window::Hann);
+    fill(&mut analyzer, sine(60f32, 0.2));
+    let analysis = analyzer.analyze().unwrap();
+
+    for (axis, frequency, amplitude) in [
+        (Axis::X, 10f32, 0.1),
+        (Axis::Y, 30f32, 0.3),
+        (Axis::Z, 60f32, 0.2),
+    ] {
+        let peak = analysis.axis(axis).peaks[0].unwrap();
+        assert_close("frequency", peak.frequency, frequency, 0.2);
+        assert_close("amplitude", peak.amplitude, amplitude, 0.02);
+    }
+}
+
+#[test]
+fn rms_of_a_sine() {
+    let mut analyzer = SpectrumAnalyzer::<256>::new(SAMPLE_RATE, Window::Hann);
+    fill(&mut analyzer, sine(25f32, 0.5));
+    let analysis = analyzer.analyze().unwrap();
+
+    // The RMS does not include the gravity
+    assert_close("z", analysis.z.rms, 0.5 / libm::sqrtf(2f32), 1e-3);
+    assert_close("x", analysis.x.rms, 0.1 / libm::sqrtf(2f32), 1e-3);
+}
+
+#[test]
+fn peaks_are_sorted_by_amplitude() {
+    let mut analyzer = SpectrumAnalyzer::<256>::new(SAMPLE_RATE, Window::Hann);
+    fill(&mut analyzer, |t| {
+        sine(12.5, 0.1)(t) + sine(50f32, 0.4)(t) + sine(75f32, 0.25)(t)
+    });
+    let peaks = analyzer.analyze().unwrap().z.peaks.map(Option::unwrap);
+
+    assert_close("first", peaks[0].frequency, 50f32, 0.05);
+    assert_close("second", peaks[1].frequency, 75f32, 0.05);
+    assert_close("third", peaks[2].frequency, 12.5, 0.05);
+    assert_close("first", peaks[0].amplitude, 0.4, 0.01);
+    assert_close("second", peaks[1].amplitude, 0.25, 0.01);
+    assert_close("third", peaks[2].amplitude, 0.1, 0.01);
+}
+
+#[test]
+fn constant_acceleration_has_no_peaks() {
+    let mut analyzer = SpectrumAnalyzer::<64>::new(SAMPLE_RATE, Window::Hann);
+    for _ in 0..64 {
+        analyzer.push(STILL);
+    }
+    let analysis = analyzer.analyze().unwrap();
+    assert_eq!(analysis.z.peaks, [None; 3]);
+    assert_close("rms", analysis.z.rms, 0f32, 1e-6);
+    assert_close("mean", analysis.z.mean, 1f32, 1e-6);
+}
+
+#[test]
+fn window_reduces_leakage() {
+    // The amplitude far from an off bin vibration
+    let leakage = |window| {
+        let mut analyzer = SpectrumAnalyzer::<256>::new(SAMPLE_RATE, window);
+        fill(&mut analyzer, sine(17.3, 0.5));
+        analyzer.analyze().unwrap();
+        analyzer.spectrum(Axis::Z)[60..]
+            .iter()
+            .copied()
+            .fold(0f32, f32::max)
+    };
+    let rectangular = leakage(Window::Rectangular);
+    assert!(leakage(Window::Hann) < rectangular / 10f32);
+    // Hamming leaks more than Hann far from the peak
+    assert!(leakage(Window::Hamming) < rectangular / 2f32);
+}
+
+#[test]
+fn sample_rate_follows_the_configuration() {
+    let config = Config::default()
+        .gyro_dlpf(GyroDlpf::Hz41)
+        .sample_rate_divider(9);
+    let analyzer = SpectrumAnalyzer::<256>::from_config(&config, Window::Hann);
+    assert_eq!(analyzer.sample_rate(), 100f32);
+    assert_eq!(analyzer.resolution(), 100f32 / 256f32);
+    assert_eq!(analyzer.frequency(128), 50f32);
+
+    // The 250 Hz gyroscope filter samples at 8 kHz, the
+    // divider is ignored
+    let config = Config::default()
+        .gyro_dlpf(GyroDlpf::Hz250)
+        .accel_dlpf(AccelDlpf::Hz21)
+        .sample_rate_divider(9);
+    let analyzer = SpectrumAnalyzer::<256>::from_config(&config, Window::Hann);
+    assert_eq!(analyzer.sample_rate(), 8000f32);
+
+    // The frames follow the divider, even without
+    // the accelerometer's filter
+    let config = Config::default()
+        .gyro_dlpf(GyroDlpf::Hz41)
+        .accel_dlpf(AccelDlpf::Hz1046)
+        .sample_rate_divider(9);
+    let analyzer = SpectrumAnalyzer::<256>::from_config(&config, Window::Hann);
+    assert_eq!(analyzer.sample_rate(), 100f32);
+
+    // The same samples at a different rate are a different frequency
+    let config = Config::default()
+        .gyro_dlpf(GyroDlpf::Hz92)
+        .sample_rate_divider(4);
+    let mut analyzer = SpectrumAnalyzer::<256>::from_config(&config, Window::Hann);
+    fill(&mut analyzer, sine(25f32, 0.5));
+    let peak = analyzer.analyze().unwrap().z.peaks[0].unwrap();
+    assert_close("frequency", peak.frequency, 25f32, 0.05);
+}
+
+#[test]
+fn bars_group_the_spectrum() {
+    let mut analyzer = SpectrumAnalyzer::<64>::new(SAMPLE_RATE, Window::Rectangular);
+    // 50 Hz is bin 16 of 32
+    fill(&mut analyzer, sine(50f32, 0.5));
+    analyzer.analyze().unwrap();
+
+    let mut bars = [0f32; 8];
+    analyzer.bars(Axis::Z, &mut bars);
+    for (index, bar) in bars.iter().enumerate() {
+        if index == 4 {
+            assert_close("peak bar", *bar, 0.5, 1e-3);
+        } else {
+            assert!(*bar < 1e-3, "bar {index}: {bar}");
+        }
+    }
+
+    // More bars than bins repeat the bins
+    let mut bars = [0f32; 64];
+    analyzer.bars(Axis::Z, &mut bars);
+    assert_close("bar", bars[32], 0.5, 1e-3);
+    assert_close("bar", bars[33], 0.5, 1e-3);
+}
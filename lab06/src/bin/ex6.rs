@@ -0,0 +1,93 @@
+#![no_std]
+#![no_main]
+
+use defmt::{error, info, warn};
+use defmt_rtt as _;
+use embassy_embedded_hal::shared_bus::asynch::i2c::I2cDevice;
+use embassy_executor::Spawner;
+use embassy_stm32::{
+    bind_interrupts,
+    i2c::{self, I2c},
+    peripherals,
+};
+use embassy_sync::{blocking_mutex::raw::ThreadModeRawMutex, mutex::Mutex};
+use embassy_time::Timer;
+use embedded_hal_async::i2c::I2c as _;
+use panic_probe as _;
+
+// We use the MPU6500 driver from lab05, it also speaks I2C.
+use lab05::mpu6500::{Address, i2c::Mpu6500};
+
+// For I2C to work, we need to bind the interrupts to the correct handlers.
+bind_interrupts!(struct Irqs {
+    I2C1_EV => i2c::EventInterruptHandler<peripherals::I2C1>;
+    I2C1_ER => i2c::ErrorInterruptHandler<peripherals::I2C1>;
+});
+
+/// I2C device address for the BMP390 (A0 connected to low).
+const BMP390_ADDR: u8 = 0x76;
+
+/// Register address of the BMP390 `CHIP_ID` register.
+const REGISTER_CHIP_ID: u8 = 0x00;
+
+#[embassy_executor::main]
+async fn main(_spawner: Spawner) {
+    // Initialize the device peripherals
+    let peripherals = embassy_stm32::init(Default::default());
+    info!("Device started");
+
+    // I2C definition, I2C1 is connected to PB6 (SCL) and PB7 (SDA).
+    let i2c = I2c::new(
+        peripherals.I2C1,
+        peripherals.PB6,
+        peripherals.PB7,
+        Irqs,
+        peripherals.GPDMA1_CH0,
+        peripherals.GPDMA1_CH1,
+        Default::default(),
+    );
+
+    // Create a Mutex so that we can safely share the I2C bus between devices.
+    //
+    // Due to the Mutex, only one device will have access to the bus at a time.
+    let i2c_mutex = Mutex::<ThreadModeRawMutex, _>::new(i2c);
+
+    // Create an I2C device for the MPU6500 driver and one for the BMP390.
+    // Each device uses its own address on the same bus.
+    let mut bmp390 = I2cDevice::new(&i2c_mutex);
+
+    // The AD0 pin of the MPU6500 is connected to low, its address is 0x68.
+    let mut mpu6500 = Mpu6500::new(I2cDevice::new(&i2c_mutex), Address::Ad0Low);
+
+    if let Err(error) = mpu6500.init().await {
+        error!("MPU6500 sensor is not available: {}", error);
+        return;
+    }
+    info!("MPU6500 sensor is {}", mpu6500.chip());
+
+    loop {
+        // Read the BMP390 chip ID, the bus is free between the
+        // transfers of the MPU6500 driver.
+        let mut chip_id = [0u8; 1];
+        match bmp390
+            .write_read(BMP390_ADDR, &[REGISTER_CHIP_ID], &mut chip_id)
+            .await
+        {
+            Ok(()) => info!("BMP390 chip ID: 0x{:02x}", chip_id[0]),
+            Err(e) => warn!("Failed to read from BMP390: {:?}", e),
+        }
+
+        match mpu6500.read_all().await {
+            Ok(measurement) => {
+                let [ax, ay, az] = measurement.accel.to_m_s2();
+                let gyro = measurement.gyro;
+                info!("Accel (m/s²): X={}, Y={}, Z={}", ax, ay, az);
+                info!("Gyro  (°/s):  X={}, Y={}, Z={}", gyro.x, gyro.y, gyro.z);
+                info!("Temperature:  {} °C", measurement.temperature);
+            }
+            Err(e) => warn!("Failed to read from MPU6500: {:?}", e),
+        }
+
+        Timer::after_millis(500).await;
+    }
+}